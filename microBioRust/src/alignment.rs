@@ -0,0 +1,1016 @@
+//! # Multiple sequence alignments
+//!
+//! An ```Alignment``` holds aligned sequences of equal length as ```fasta::Record```s, with column access, gap counting and gappy column removal,
+//! and the Stockholm ```#=GF```, ```#=GS```, ```#=GR``` and ```#=GC``` annotations (the conservation line of a Clustal file is kept as the ```clustal_consensus``` column annotation).
+//! ```Alignment::sequences``` gives the rows for the seqmetrics distance functions, e.g. ```hamming_matrix```.
+//!
+//! The reader recognises Clustal, Stockholm, PHYLIP (interleaved or sequential, relaxed names) and aligned FASTA from the first line of the file,
+//! or reads the format given with ```Reader::format```. Stockholm and PHYLIP files can hold several alignments, ```alignments()``` returns each.
+//! The writer writes any of these formats, compressed input is read transparently.
+//!
+//!```rust
+//! use microBioRust::alignment::{Format, Reader, Writer};
+//!
+//! let text = concat!(
+//!     "CLUSTAL W multiple sequence alignment\n\n",
+//!     "b3304      MDKKSARIRR\n",
+//!     "Q8ZJ89     MDKK-ARLRR\n",
+//!     "           ****.**:**\n",
+//! );
+//! let alignment = Reader::new(text.as_bytes()).read_alignment().unwrap().unwrap();
+//! assert_eq!(alignment.ids(), vec!["b3304", "Q8ZJ89"]);
+//! assert_eq!(alignment.width(), 10);
+//! assert_eq!(alignment.column(4).unwrap(), "S-");
+//! assert_eq!(alignment.ungapped(1).unwrap(), "MDKKARLRR");
+//!
+//! let mut writer = Writer::new(Vec::new()).format(Format::PhylipSequential);
+//! writer.write_alignment(&alignment).unwrap();
+//! assert_eq!(
+//!     String::from_utf8(writer.into_inner()).unwrap(),
+//!     " 2 10\nb3304      MDKKSARIRR\nQ8ZJ89     MDKK-ARLRR\n"
+//! );
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::fasta::{split_header, write_wrapped, Record, DEFAULT_LINE_WIDTH};
+use anyhow::{anyhow, Context};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+///the column annotation holding the conservation line of a Clustal file
+pub const CLUSTAL_CONSENSUS: &str = "clustal_consensus";
+
+//names shorter than this are padded in PHYLIP files, as strict PHYLIP readers expect
+const PHYLIP_NAME_WIDTH: usize = 10;
+
+///true for the gap characters ```-``` and ```.```
+pub fn is_gap(residue: u8) -> bool {
+    residue == b'-' || residue == b'.'
+}
+
+///the formats read and written
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Clustal,
+    Stockholm,
+    ///interleaved PHYLIP, read as interleaved or sequential
+    Phylip,
+    ///sequential PHYLIP, read as interleaved or sequential
+    PhylipSequential,
+    Fasta,
+}
+
+impl Format {
+    ///the format of a file from its first non-blank line
+    pub fn detect(line: &str) -> Option<Self> {
+        let line = line.trim_start();
+        let mut numbers = line.split_whitespace().take(2);
+        if line.starts_with("# STOCKHOLM") {
+            Some(Format::Stockholm)
+        } else if line.starts_with('>') {
+            Some(Format::Fasta)
+        } else if ["CLUSTAL", "MUSCLE", "PROBCONS"]
+            .iter()
+            .any(|name| line.starts_with(name))
+        {
+            Some(Format::Clustal)
+        } else if numbers.all(|n| n.parse::<usize>().is_ok()) && !line.is_empty() {
+            Some(Format::Phylip)
+        } else {
+            None
+        }
+    }
+}
+
+///aligned sequences of equal length with their annotations
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Alignment {
+    pub records: Vec<Record>,
+    ///```#=GF``` lines, tag and text, in file order
+    pub file_annotations: Vec<(String, String)>,
+    ///```#=GS``` lines, sequence id, tag and text
+    pub sequence_annotations: Vec<(String, String, String)>,
+    ///```#=GR``` lines, sequence id, tag and one character per column
+    pub residue_annotations: Vec<(String, String, String)>,
+    ///```#=GC``` lines, tag and one character per column
+    pub column_annotations: Vec<(String, String)>,
+}
+
+impl Alignment {
+    pub fn new() -> Self {
+        Alignment::default()
+    }
+    ///an alignment of the records, which must all have the same length
+    pub fn from_records(records: Vec<Record>) -> Result<Self, anyhow::Error> {
+        let mut alignment = Alignment::new();
+        for record in records {
+            alignment.push(record)?;
+        }
+        Ok(alignment)
+    }
+    ///adds a row, an error is returned when it is not ASCII or its length differs from the rows already there
+    pub fn push(&mut self, record: Record) -> Result<(), anyhow::Error> {
+        if !record.seq.is_ascii() {
+            return Err(anyhow!("{} has non-ASCII residues", record.id));
+        }
+        if let Some(first) = self.records.first() {
+            if first.seq.len() != record.seq.len() {
+                return Err(anyhow!(
+                    "{} has {} columns, {} has {}",
+                    record.id,
+                    record.seq.len(),
+                    first.id,
+                    first.seq.len()
+                ));
+            }
+        }
+        self.records.push(record);
+        Ok(())
+    }
+    ///the number of sequences
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+    ///the number of columns
+    pub fn width(&self) -> usize {
+        self.records.first().map(|r| r.seq.len()).unwrap_or(0)
+    }
+    pub fn ids(&self) -> Vec<String> {
+        self.records.iter().map(|r| r.id.clone()).collect()
+    }
+    ///the aligned rows, gaps included, as taken by ```seqmetrics::hamming::hamming_matrix```
+    pub fn sequences(&self) -> Vec<String> {
+        self.records.iter().map(|r| r.seq.clone()).collect()
+    }
+    ///the row with the given id
+    pub fn get(&self, id: &str) -> Option<&Record> {
+        self.records.iter().find(|r| r.id == id)
+    }
+    ///the residues of a column, counted from 0, one per row
+    pub fn column(&self, index: usize) -> Option<String> {
+        if index >= self.width() {
+            return None;
+        }
+        Some(
+            self.records
+                .iter()
+                .map(|r| r.seq.as_bytes()[index] as char)
+                .collect(),
+        )
+    }
+    ///the share of rows with a gap in a column
+    pub fn gap_fraction(&self, index: usize) -> Option<f64> {
+        let column = self.column(index)?;
+        let gaps = column.bytes().filter(|r| is_gap(*r)).count();
+        Some(gaps as f64 / self.len() as f64)
+    }
+    ///the row without its gaps, counted from 0
+    pub fn ungapped(&self, index: usize) -> Option<String> {
+        self.records
+            .get(index)
+            .map(|r| r.seq.chars().filter(|c| !is_gap(*c as u8)).collect())
+    }
+    ///a copy keeping the columns with at most the given share of gaps, per column annotations are cut to match
+    pub fn remove_gappy_columns(&self, max_gap_fraction: f64) -> Alignment {
+        let keep: Vec<usize> = (0..self.width())
+            .filter(|i| self.gap_fraction(*i).unwrap_or(0.0) <= max_gap_fraction)
+            .collect();
+        let cut = |text: &str| -> String {
+            let bytes = text.as_bytes();
+            keep.iter()
+                .filter_map(|i| bytes.get(*i).map(|b| *b as char))
+                .collect()
+        };
+        Alignment {
+            records: self
+                .records
+                .iter()
+                .map(|r| Record {
+                    id: r.id.clone(),
+                    description: r.description.clone(),
+                    seq: cut(&r.seq),
+                })
+                .collect(),
+            file_annotations: self.file_annotations.clone(),
+            sequence_annotations: self.sequence_annotations.clone(),
+            residue_annotations: self
+                .residue_annotations
+                .iter()
+                .map(|(id, tag, text)| (id.clone(), tag.clone(), cut(text)))
+                .collect(),
+            column_annotations: self
+                .column_annotations
+                .iter()
+                .map(|(tag, text)| (tag.clone(), cut(text)))
+                .collect(),
+        }
+    }
+    ///the ```#=GC``` annotation with the given tag, e.g. ```SS_cons``` or ```clustal_consensus```
+    pub fn column_annotation(&self, tag: &str) -> Option<&str> {
+        self.column_annotations
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, text)| text.as_str())
+    }
+}
+
+//appends to the per column text of a tag, adding the tag when new
+fn append_annotation(annotations: &mut Vec<(String, String)>, tag: &str, text: &str) {
+    match annotations.iter_mut().find(|(t, _)| t == tag) {
+        Some((_, existing)) => existing.push_str(text),
+        None => annotations.push((tag.to_string(), text.to_string())),
+    }
+}
+
+//the residues of a line, spaces between groups of residues are dropped
+fn residues(text: &str) -> String {
+    text.chars().filter(|c| !c.is_whitespace()).collect()
+}
+
+//true for the line starting the next PHYLIP data set, the sequence and column counts
+fn is_phylip_header(line: &str) -> bool {
+    let numbers: Vec<&str> = line.split_whitespace().collect();
+    numbers.len() >= 2 && numbers[..2].iter().all(|n| n.parse::<usize>().is_ok())
+}
+
+///an iterator over the alignments of a file
+#[derive(Debug)]
+pub struct Alignments<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Alignments<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Alignment, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_alignment() {
+            Ok(Some(alignment)) => Some(Ok(alignment)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+    peeked: Option<String>,
+    format: Option<Format>,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read alignments from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read alignment from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new alignment reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+            peeked: None,
+            format: None,
+        }
+    }
+    ///reads the given format instead of recognising it from the first line
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+    //the next line without its line ending, None at the end of the file
+    fn next_line(&mut self) -> Result<Option<String>, ParseError> {
+        if let Some(line) = self.peeked.take() {
+            return Ok(Some(line));
+        }
+        self.line_buffer.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: String::new(),
+                line: String::new(),
+                source,
+            })?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.line_number += 1;
+        Ok(Some(
+            self.line_buffer.trim_end_matches(['\n', '\r']).to_string(),
+        ))
+    }
+    //looks at the next line without consuming it
+    fn peek_line(&mut self) -> Result<Option<&str>, ParseError> {
+        if self.peeked.is_none() {
+            self.peeked = self.next_line()?;
+        }
+        Ok(self.peeked.as_deref())
+    }
+    //the error for a line of the file
+    fn malformed(&self, record_id: &str, line: &str, message: String) -> ParseError {
+        ParseError::MalformedLine {
+            line_number: self.line_number,
+            record_id: record_id.to_string(),
+            line: line.to_string(),
+            message,
+        }
+    }
+    ///reads the next alignment, returns None at the end of the file
+    pub fn read_alignment(&mut self) -> Result<Option<Alignment>, ParseError> {
+        let first = loop {
+            match self.peek_line()? {
+                None => return Ok(None),
+                Some(line) if line.trim().is_empty() => {
+                    self.peeked = None;
+                }
+                Some(line) => break line.to_string(),
+            }
+        };
+        let format = match self.format.or_else(|| Format::detect(&first)) {
+            Some(format) => format,
+            None => {
+                return Err(self.malformed(
+                    "",
+                    &first,
+                    "not a Clustal, Stockholm, PHYLIP or FASTA alignment".to_string(),
+                ))
+            }
+        };
+        let alignment = match format {
+            Format::Clustal => self.read_clustal()?,
+            Format::Stockholm => self.read_stockholm()?,
+            Format::Phylip | Format::PhylipSequential => self.read_phylip()?,
+            Format::Fasta => self.read_fasta()?,
+        };
+        Ok(Some(alignment))
+    }
+    //adds the rows in file order, checking that they have the same length
+    fn finish(&self, rows: Vec<Record>, mut alignment: Alignment) -> Result<Alignment, ParseError> {
+        for record in rows {
+            let id = record.id.clone();
+            alignment
+                .push(record)
+                .map_err(|e| self.malformed(&id, "", e.to_string()))?;
+        }
+        Ok(alignment)
+    }
+    fn read_fasta(&mut self) -> Result<Alignment, ParseError> {
+        let mut rows: Vec<Record> = Vec::new();
+        while let Some(line) = self.next_line()? {
+            if let Some(header) = line.strip_prefix('>') {
+                let (id, description) = split_header(header);
+                rows.push(Record {
+                    id,
+                    description,
+                    seq: String::new(),
+                });
+            } else if line.starts_with(';') || line.trim().is_empty() {
+                continue;
+            } else {
+                match rows.last_mut() {
+                    Some(record) => record.seq.push_str(&residues(&line)),
+                    None => {
+                        return Err(self.malformed(
+                            "",
+                            &line,
+                            "sequence before the first header".to_string(),
+                        ))
+                    }
+                }
+            }
+        }
+        self.finish(rows, Alignment::new())
+    }
+    fn read_clustal(&mut self) -> Result<Alignment, ParseError> {
+        //the CLUSTAL line
+        self.next_line()?;
+        let mut rows: Vec<Record> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        let mut consensus = String::new();
+        //where the residues of the current block start, in the line and in the alignment
+        let mut residue_offset = 0;
+        let mut block_start = 0;
+        while let Some(line) = self.next_line()? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                //the conservation line under a block
+                let width = rows.first().map(|r| r.seq.len()).unwrap_or(0) - block_start;
+                let chunk: String = line
+                    .chars()
+                    .chain(std::iter::repeat(' '))
+                    .skip(residue_offset)
+                    .take(width)
+                    .collect();
+                consensus.push_str(&" ".repeat(block_start.saturating_sub(consensus.len())));
+                consensus.push_str(&chunk);
+                continue;
+            }
+            let mut fields = line.split_whitespace();
+            let id = fields.next().unwrap_or_default().to_string();
+            let text = fields.next().ok_or_else(|| {
+                self.malformed(&id, &line, "sequence line without residues".to_string())
+            })?;
+            let row = match index.get(&id) {
+                Some(row) => *row,
+                None => {
+                    index.insert(id.clone(), rows.len());
+                    rows.push(Record {
+                        id: id.clone(),
+                        description: None,
+                        seq: String::new(),
+                    });
+                    rows.len() - 1
+                }
+            };
+            if row == 0 {
+                block_start = rows[0].seq.len();
+                //searched after the id, which may itself contain the residues
+                let id_end = line.len() - line.trim_start().len() + id.len();
+                residue_offset = id_end + line[id_end..].find(text).unwrap_or(0);
+            }
+            rows[row].seq.push_str(text);
+        }
+        let mut alignment = Alignment::new();
+        if !consensus.trim().is_empty() {
+            let width = rows.first().map(|r| r.seq.len()).unwrap_or(0);
+            consensus.push_str(&" ".repeat(width.saturating_sub(consensus.len())));
+            alignment
+                .column_annotations
+                .push((CLUSTAL_CONSENSUS.to_string(), consensus));
+        }
+        self.finish(rows, alignment)
+    }
+    fn read_stockholm(&mut self) -> Result<Alignment, ParseError> {
+        //the # STOCKHOLM 1.0 line
+        self.next_line()?;
+        let mut alignment = Alignment::new();
+        let mut rows: Vec<Record> = Vec::new();
+        let mut index: HashMap<String, usize> = HashMap::new();
+        while let Some(line) = self.next_line()? {
+            if line.trim() == "//" {
+                break;
+            }
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(markup) = line.strip_prefix("#=") {
+                let mut fields = markup.splitn(2, char::is_whitespace);
+                let kind = fields.next().unwrap_or_default();
+                let rest = fields.next().unwrap_or_default().trim_start();
+                let (first, after_first) = rest
+                    .split_once(char::is_whitespace)
+                    .map(|(a, b)| (a, b.trim_start()))
+                    .unwrap_or((rest, ""));
+                let (second, after_second) = after_first
+                    .split_once(char::is_whitespace)
+                    .map(|(a, b)| (a, b.trim_start()))
+                    .unwrap_or((after_first, ""));
+                match kind {
+                    "GF" => alignment
+                        .file_annotations
+                        .push((first.to_string(), after_first.trim_end().to_string())),
+                    "GC" => append_annotation(
+                        &mut alignment.column_annotations,
+                        first,
+                        after_first.trim(),
+                    ),
+                    "GS" => alignment.sequence_annotations.push((
+                        first.to_string(),
+                        second.to_string(),
+                        after_second.trim_end().to_string(),
+                    )),
+                    "GR" => {
+                        match alignment
+                            .residue_annotations
+                            .iter_mut()
+                            .find(|(id, tag, _)| id == first && tag == second)
+                        {
+                            Some((_, _, text)) => text.push_str(after_second.trim()),
+                            None => alignment.residue_annotations.push((
+                                first.to_string(),
+                                second.to_string(),
+                                after_second.trim().to_string(),
+                            )),
+                        }
+                    }
+                    _ => {
+                        return Err(self.malformed("", &line, format!("unknown markup #={}", kind)))
+                    }
+                }
+                continue;
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            let (id, text) = line.split_once(char::is_whitespace).ok_or_else(|| {
+                self.malformed("", &line, "sequence line without residues".to_string())
+            })?;
+            match index.get(id) {
+                Some(row) => rows[*row].seq.push_str(&residues(text)),
+                None => {
+                    index.insert(id.to_string(), rows.len());
+                    rows.push(Record {
+                        id: id.to_string(),
+                        description: None,
+                        seq: residues(text),
+                    });
+                }
+            }
+        }
+        self.finish(rows, alignment)
+    }
+    fn read_phylip(&mut self) -> Result<Alignment, ParseError> {
+        let header = self.next_line()?.unwrap_or_default();
+        let counts: Vec<usize> = header
+            .split_whitespace()
+            .take(2)
+            .filter_map(|n| n.parse().ok())
+            .collect();
+        let (taxa, columns) = match counts.as_slice() {
+            [taxa, columns] => (*taxa, *columns),
+            _ => {
+                return Err(self.malformed(
+                    "",
+                    &header,
+                    "expected the numbers of sequences and columns".to_string(),
+                ))
+            }
+        };
+        //the lines of this data set, up to the header of the next one
+        let mut lines: Vec<String> = Vec::new();
+        while let Some(line) = self.peek_line()? {
+            if is_phylip_header(line) {
+                break;
+            }
+            let line = line.to_string();
+            self.peeked = None;
+            if !line.trim().is_empty() {
+                lines.push(line);
+            }
+        }
+        let named = |line: &str| -> (String, String) {
+            let line = line.trim_start();
+            match line.split_once(char::is_whitespace) {
+                Some((name, text)) => (name.to_string(), residues(text)),
+                None => (line.to_string(), String::new()),
+            }
+        };
+        //sequential, each name line followed by as many lines as its sequence takes
+        let mut sequential: Vec<Record> = Vec::new();
+        let mut remaining = lines.iter();
+        'taxa: for _ in 0..taxa {
+            let Some(line) = remaining.next() else {
+                break;
+            };
+            let (id, mut seq) = named(line);
+            while seq.len() < columns {
+                match remaining.next() {
+                    Some(line) => seq.push_str(&residues(line)),
+                    None => break 'taxa,
+                }
+            }
+            sequential.push(Record {
+                id,
+                description: None,
+                seq,
+            });
+        }
+        let rows = match sequential.len() == taxa
+            && remaining.next().is_none()
+            && sequential.iter().all(|r| r.seq.len() == columns)
+        {
+            true => sequential,
+            false => {
+                if taxa == 0 {
+                    return Err(self.malformed(
+                        "",
+                        &header,
+                        format!("{} lines after a header of 0 sequences", lines.len()),
+                    ));
+                }
+                //interleaved, the first block names the sequences and later blocks follow in the same order
+                let mut rows: Vec<Record> = lines
+                    .iter()
+                    .take(taxa)
+                    .map(|line| {
+                        let (id, seq) = named(line);
+                        Record {
+                            id,
+                            description: None,
+                            seq,
+                        }
+                    })
+                    .collect();
+                for (i, line) in lines.iter().enumerate().skip(taxa) {
+                    rows[i % taxa].seq.push_str(&residues(line));
+                }
+                if let Some(short) = rows.iter().find(|r| r.seq.len() != columns) {
+                    return Err(self.malformed(
+                        &short.id,
+                        &header,
+                        format!("{} columns instead of {}", short.seq.len(), columns),
+                    ));
+                }
+                rows
+            }
+        };
+        if rows.len() != taxa {
+            return Err(self.malformed(
+                "",
+                &header,
+                format!("{} sequences instead of {}", rows.len(), taxa),
+            ));
+        }
+        self.finish(rows, Alignment::new())
+    }
+    ///return an iterator over the alignments of the file
+    pub fn alignments(self) -> Alignments<B> {
+        Alignments {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+///writes alignments in one of the formats, FASTA by default
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    writer: W,
+    format: Format,
+    line_width: usize,
+}
+
+impl Writer<io::BufWriter<fs::File>> {
+    /// Write alignments to the given file path, replacing the file.
+    pub fn to_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::create(&path)
+            .map(|file| Writer::new(io::BufWriter::new(file)))
+            .with_context(|| format!("Failed to write alignment to {:#?}", path))
+    }
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer,
+            format: Format::Fasta,
+            line_width: DEFAULT_LINE_WIDTH,
+        }
+    }
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+    ///sets the number of columns per line or block, 0 writes each row on a single line (Stockholm is always written on single lines)
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+    //the column ranges of the blocks of an interleaved format
+    fn blocks(&self, width: usize) -> Vec<std::ops::Range<usize>> {
+        let step = match self.line_width {
+            0 => width.max(1),
+            line_width => line_width,
+        };
+        (0..width.max(1))
+            .step_by(step)
+            .map(|start| start..(start + step).min(width))
+            .collect()
+    }
+    pub fn write_alignment(&mut self, alignment: &Alignment) -> io::Result<()> {
+        match self.format {
+            Format::Fasta => {
+                for record in alignment.records.iter() {
+                    writeln!(self.writer, ">{}", record.header())?;
+                    write_wrapped(&mut self.writer, &record.seq, self.line_width)?;
+                }
+            }
+            Format::Clustal => {
+                writeln!(self.writer, "CLUSTAL W multiple sequence alignment\n")?;
+                let name_width = alignment
+                    .records
+                    .iter()
+                    .map(|r| r.id.len())
+                    .max()
+                    .unwrap_or(0)
+                    + 6;
+                let consensus = alignment.column_annotation(CLUSTAL_CONSENSUS);
+                for block in self.blocks(alignment.width()) {
+                    writeln!(self.writer)?;
+                    for record in alignment.records.iter() {
+                        writeln!(
+                            self.writer,
+                            "{:<name_width$}{}",
+                            record.id,
+                            &record.seq[block.clone()]
+                        )?;
+                    }
+                    if let Some(consensus) = consensus {
+                        let line = format!(
+                            "{:<name_width$}{}",
+                            "",
+                            consensus.get(block).unwrap_or_default()
+                        );
+                        writeln!(self.writer, "{}", line.trim_end())?;
+                    }
+                }
+            }
+            Format::Stockholm => {
+                writeln!(self.writer, "# STOCKHOLM 1.0")?;
+                for (tag, text) in alignment.file_annotations.iter() {
+                    writeln!(self.writer, "#=GF {} {}", tag, text)?;
+                }
+                for (id, tag, text) in alignment.sequence_annotations.iter() {
+                    writeln!(self.writer, "#=GS {} {} {}", id, tag, text)?;
+                }
+                let name_width = alignment
+                    .records
+                    .iter()
+                    .map(|r| r.id.len())
+                    .chain(
+                        alignment
+                            .residue_annotations
+                            .iter()
+                            .map(|(id, tag, _)| id.len() + tag.len() + 6),
+                    )
+                    .chain(
+                        alignment
+                            .column_annotations
+                            .iter()
+                            .map(|(tag, _)| tag.len() + 5),
+                    )
+                    .max()
+                    .unwrap_or(0)
+                    + 1;
+                for record in alignment.records.iter() {
+                    writeln!(self.writer, "{:<name_width$}{}", record.id, record.seq)?;
+                    for (id, tag, text) in alignment.residue_annotations.iter() {
+                        if *id == record.id {
+                            let name = format!("#=GR {} {}", id, tag);
+                            writeln!(self.writer, "{:<name_width$}{}", name, text)?;
+                        }
+                    }
+                }
+                for (tag, text) in alignment.column_annotations.iter() {
+                    if tag != CLUSTAL_CONSENSUS {
+                        let name = format!("#=GC {}", tag);
+                        writeln!(self.writer, "{:<name_width$}{}", name, text)?;
+                    }
+                }
+                writeln!(self.writer, "//")?;
+            }
+            Format::Phylip | Format::PhylipSequential => {
+                writeln!(self.writer, " {} {}", alignment.len(), alignment.width())?;
+                let name_width = alignment
+                    .records
+                    .iter()
+                    .map(|r| r.id.len() + 1)
+                    .max()
+                    .unwrap_or(0)
+                    .max(PHYLIP_NAME_WIDTH + 1);
+                if self.format == Format::PhylipSequential {
+                    for record in alignment.records.iter() {
+                        writeln!(self.writer, "{:<name_width$}{}", record.id, record.seq)?;
+                    }
+                    return Ok(());
+                }
+                for (i, block) in self.blocks(alignment.width()).into_iter().enumerate() {
+                    if i > 0 {
+                        writeln!(self.writer)?;
+                    }
+                    for record in alignment.records.iter() {
+                        let name = match i {
+                            0 => record.id.as_str(),
+                            _ => "",
+                        };
+                        writeln!(
+                            self.writer,
+                            "{:<name_width$}{}",
+                            name,
+                            &record.seq[block.clone()]
+                        )?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CLUSTAL: &str = concat!(
+        "CLUSTAL W (1.83) multiple sequence alignment\n",
+        "\n",
+        "\n",
+        "kleb_rpoB       MVYSYTEKKRIRKDFG 16\n",
+        "ecoli_rpoB      MVYSYTEKKRIRKDFG 16\n",
+        "salm_rpoB       MVYSYTEKKRLRKDFG 16\n",
+        "                **********:*****\n",
+        "\n",
+        "kleb_rpoB       KRPQVLDV-PY 26\n",
+        "ecoli_rpoB      KRPQVLDVPYL 27\n",
+        "salm_rpoB       KRPQ-LDVPYL 26\n",
+        "                ****\n",
+    );
+
+    const STOCKHOLM: &str = concat!(
+        "# STOCKHOLM 1.0\n",
+        "#=GF ID   tRNA\n",
+        "#=GF DE   transfer RNA\n",
+        "#=GS seq1 AC P00001\n",
+        "\n",
+        "seq1          GCGGAUUUAG\n",
+        "#=GR seq1 SS  <<<<..>>>>\n",
+        "seq2          GCGGA-UUAG\n",
+        "#=GC SS_cons  <<<<..>>>>\n",
+        "\n",
+        "seq1          CUC\n",
+        "#=GR seq1 SS  ...\n",
+        "seq2          CU-\n",
+        "#=GC SS_cons  ...\n",
+        "//\n",
+        "# STOCKHOLM 1.0\n",
+        "seqA  AC-T\n",
+        "seqB  ACGT\n",
+        "//\n",
+    );
+
+    #[test]
+    fn test_read_clustal() {
+        let alignment = Reader::new(CLUSTAL.as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            alignment.ids(),
+            vec!["kleb_rpoB", "ecoli_rpoB", "salm_rpoB"]
+        );
+        assert_eq!(alignment.records[0].seq, "MVYSYTEKKRIRKDFGKRPQVLDV-PY");
+        assert_eq!(alignment.width(), 27);
+        assert_eq!(alignment.column(10).unwrap(), "IIL");
+        assert_eq!(
+            alignment.column_annotation(CLUSTAL_CONSENSUS).unwrap(),
+            "**********:*********       "
+        );
+        assert_eq!(alignment.gap_fraction(20), Some(1.0 / 3.0));
+        let trimmed = alignment.remove_gappy_columns(0.0);
+        assert_eq!(trimmed.width(), 25);
+        assert_eq!(
+            trimmed.column_annotation(CLUSTAL_CONSENSUS).unwrap().len(),
+            25
+        );
+        assert!(alignment.column(27).is_none());
+        //residues that also occur in the id
+        let alignment = Reader::new("CLUSTAL W\n\nAAA   AA\nBBB   AC\n      * \n".as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        assert_eq!(alignment.column_annotation(CLUSTAL_CONSENSUS), Some("* "));
+        let row = Record {
+            id: "seq1".to_string(),
+            description: None,
+            seq: "AC\u{e9}".to_string(),
+        };
+        assert!(Alignment::from_records(vec![row]).is_err());
+    }
+    #[test]
+    fn test_read_stockholm() {
+        let alignments: Vec<Alignment> = Reader::new(STOCKHOLM.as_bytes())
+            .alignments()
+            .map(|a| a.unwrap())
+            .collect();
+        assert_eq!(alignments.len(), 2);
+        let first = &alignments[0];
+        assert_eq!(first.sequences(), vec!["GCGGAUUUAGCUC", "GCGGA-UUAGCU-"]);
+        assert_eq!(first.column_annotation("SS_cons"), Some("<<<<..>>>>..."));
+        assert_eq!(
+            first.file_annotations[1],
+            ("DE".to_string(), "transfer RNA".to_string())
+        );
+        assert_eq!(
+            first.sequence_annotations[0],
+            ("seq1".to_string(), "AC".to_string(), "P00001".to_string())
+        );
+        assert_eq!(first.residue_annotations[0].2, "<<<<..>>>>...");
+        assert_eq!(alignments[1].ids(), vec!["seqA", "seqB"]);
+        //written and read back
+        let mut writer = Writer::new(Vec::new()).format(Format::Stockholm);
+        writer.write_alignment(first).unwrap();
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        let again = Reader::new(written.as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        assert_eq!(&again, first);
+    }
+    #[test]
+    fn test_phylip() {
+        let interleaved = " 3 14\nkleb_rpoB  MVYSYTEKKR\necoli_rpoB MVYSYTEKKR\nsalm_rpoB  MVYSYTEKKR\n\n           IRK-\n           IRKD\n           LRKD\n";
+        let sequential = " 3 14\nkleb_rpoB  MVYSYTEKKR\nIRK-\necoli_rpoB MVYSYTEKKRIRKD\nsalm_rpoB  MVYSYTE KKRLRKD\n";
+        let first = Reader::new(interleaved.as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        let second = Reader::new(sequential.as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        assert_eq!(first, second);
+        assert_eq!(first.records[2].seq, "MVYSYTEKKRLRKD");
+        let mut writer = Writer::new(Vec::new())
+            .format(Format::Phylip)
+            .line_width(10);
+        writer.write_alignment(&first).unwrap();
+        assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), interleaved);
+        //bootstrap replicates hold several data sets
+        let replicates = format!("{}{}", interleaved, interleaved);
+        assert_eq!(Reader::new(replicates.as_bytes()).alignments().count(), 2);
+        match Reader::new(" 3 15\nkleb_rpoB  MVY\n".as_bytes()).read_alignment() {
+            Err(ParseError::MalformedLine { record_id, .. }) => assert_eq!(record_id, "kleb_rpoB"),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+        assert!(Reader::new(" 0 5\nfoo ACGTA\n".as_bytes())
+            .format(Format::Phylip)
+            .read_alignment()
+            .is_err());
+    }
+    #[test]
+    fn test_fasta_and_clustal_writer() {
+        let text = ">seq1 first\nAC-GT\n>seq2\nACCGT\n";
+        let alignment = Reader::new(text.as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        assert_eq!(alignment.records[0].description.as_deref(), Some("first"));
+        let mut writer = Writer::new(Vec::new());
+        writer.write_alignment(&alignment).unwrap();
+        assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), text);
+        let clustal = Reader::new(CLUSTAL.as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        let mut writer = Writer::new(Vec::new()).format(Format::Clustal);
+        writer.write_alignment(&clustal).unwrap();
+        let written = String::from_utf8(writer.into_inner()).unwrap();
+        let again = Reader::new(written.as_bytes())
+            .read_alignment()
+            .unwrap()
+            .unwrap();
+        assert_eq!(again, clustal);
+        match Reader::new(">seq1\nACGT\n>seq2\nACG\n".as_bytes()).read_alignment() {
+            Err(ParseError::MalformedLine { record_id, .. }) => assert_eq!(record_id, "seq2"),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+    }
+}
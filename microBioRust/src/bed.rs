@@ -0,0 +1,553 @@
+//! # BED intervals
+//!
+//! BED3 to BED12 lines are read into ```Record```s with 0-based, half-open coordinates as in the file, columns past the twelfth are kept in ```extra```.
+//! ```track```, ```browser``` and ```#``` lines are skipped. ```Record::location``` gives the 1-based ```Location``` of an interval (a join of its blocks),
+//! and ```Record::from_location``` makes an interval of a feature location, a joined feature becoming one block per part.
+//!
+//! ```gbk::bed_write``` and ```embl::bed_write``` save the CDS of records as BED6 or BED12 named by locus tag,
+//! and ```add_features``` attaches the intervals of a BED file to a ```gbk::Record``` as features of a given type.
+//!
+//!```rust
+//! use microBioRust::bed::Reader;
+//!
+//! let text = concat!(
+//!     "track name=cds\n",
+//!     "NC_000913\t9\t363\tb3304\t0\t-\n",
+//!     "NC_000913\t100\t400\tspliced\t500\t+\t100\t400\t0\t2\t50,100,\t0,200,\n",
+//! );
+//! let records: Vec<_> = Reader::new(text.as_bytes()).records().map(|r| r.unwrap()).collect();
+//! assert_eq!((records[0].start, records[0].end), (9, 363));
+//! assert_eq!(records[0].location(913, false).to_string(), "complement(10..363)");
+//! assert_eq!(records[1].location(913, false).to_string(), "join(101..150,301..400)");
+//! assert_eq!(records[1].to_line(6), "NC_000913\t100\t400\tspliced\t500\t+");
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk;
+use crate::gff::location_parts;
+use crate::location::{Location, Position};
+use crate::qualifiers::Qualifiers;
+use crate::record::RangeValue;
+use crate::translation::{genetic_code, DEFAULT_TABLE};
+use anyhow::{anyhow, Context};
+use std::{fmt, io, path::Path};
+
+///a line of a BED file, coordinates count from 0 and ```end``` is not included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub chrom: String,
+    pub start: u32,
+    pub end: u32,
+    pub name: Option<String>,
+    pub score: Option<f64>,
+    ///1, -1, or None for ```.```
+    pub strand: Option<i8>,
+    pub thick_start: Option<u32>,
+    pub thick_end: Option<u32>,
+    pub item_rgb: Option<String>,
+    ///(start relative to ```start```, size) of each block, empty when the line has no blocks
+    pub blocks: Vec<(u32, u32)>,
+    ///columns after the twelfth
+    pub extra: Vec<String>,
+    ///the number of BED columns the line had, 3 to 12
+    pub columns: usize,
+}
+
+impl Record {
+    ///an interval without the optional columns
+    pub fn new(chrom: &str, start: u32, end: u32) -> Self {
+        Record {
+            chrom: chrom.to_string(),
+            start,
+            end,
+            name: None,
+            score: None,
+            strand: None,
+            thick_start: None,
+            thick_end: None,
+            item_rgb: None,
+            blocks: Vec::new(),
+            extra: Vec::new(),
+            columns: 3,
+        }
+    }
+    ///reads a BED line, columns are split on tabs, or on whitespace when the line has no tab
+    pub fn from_line(line: &str) -> Result<Self, anyhow::Error> {
+        let columns: Vec<&str> = match line.contains('\t') {
+            true => line.split('\t').collect(),
+            false => line.split_whitespace().collect(),
+        };
+        if columns.len() < 3 {
+            return Err(anyhow!(
+                "expected at least 3 columns, found {}",
+                columns.len()
+            ));
+        }
+        let number = |index: usize, name: &str| -> Result<u32, anyhow::Error> {
+            columns[index]
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| anyhow!("bad {} {:?}: {}", name, columns[index], e))
+        };
+        let list = |index: usize, name: &str| -> Result<Vec<u32>, anyhow::Error> {
+            columns[index]
+                .trim()
+                .split(',')
+                .filter(|v| !v.is_empty())
+                .map(|v| {
+                    v.parse::<u32>()
+                        .map_err(|e| anyhow!("bad {} {:?}: {}", name, columns[index], e))
+                })
+                .collect()
+        };
+        let optional = |index: usize| columns.get(index).map(|c| c.trim()).filter(|c| *c != ".");
+        let mut record = Record::new(columns[0], number(1, "chromStart")?, number(2, "chromEnd")?);
+        if record.end < record.start {
+            return Err(anyhow!(
+                "chromEnd {} is before chromStart {}",
+                record.end,
+                record.start
+            ));
+        }
+        record.columns = columns.len().min(12);
+        record.name = optional(3).map(|n| n.to_string());
+        record.score = match optional(4) {
+            Some(score) => Some(
+                score
+                    .parse::<f64>()
+                    .map_err(|e| anyhow!("bad score {:?}: {}", score, e))?,
+            ),
+            None => None,
+        };
+        record.strand = match optional(5) {
+            Some("+") => Some(1),
+            Some("-") => Some(-1),
+            None => None,
+            Some(strand) => return Err(anyhow!("bad strand {:?}", strand)),
+        };
+        if columns.len() >= 8 {
+            record.thick_start = Some(number(6, "thickStart")?);
+            record.thick_end = Some(number(7, "thickEnd")?);
+        }
+        record.item_rgb = optional(8).map(|c| c.to_string());
+        if columns.len() >= 12 {
+            let count = number(9, "blockCount")? as usize;
+            let sizes = list(10, "blockSizes")?;
+            let starts = list(11, "blockStarts")?;
+            if sizes.len() != count || starts.len() != count {
+                return Err(anyhow!(
+                    "blockCount {} does not match {} blockSizes and {} blockStarts",
+                    count,
+                    sizes.len(),
+                    starts.len()
+                ));
+            }
+            record.blocks = starts.into_iter().zip(sizes).collect();
+            let span = record.end - record.start;
+            match (record.blocks.first(), record.blocks.last()) {
+                (Some((first, _)), Some((last, size))) if *first != 0 || last + size != span => {
+                    return Err(anyhow!(
+                        "the blocks must start at chromStart and end at chromEnd"
+                    ))
+                }
+                _ => (),
+            }
+            if record.blocks.windows(2).any(|w| w[0].0 + w[0].1 > w[1].0) {
+                return Err(anyhow!("the blocks overlap or are out of order"));
+            }
+        } else if columns.len() > 9 {
+            return Err(anyhow!(
+                "blockCount, blockSizes and blockStarts must be given together"
+            ));
+        }
+        record.extra = columns.iter().skip(12).map(|c| c.to_string()).collect();
+        Ok(record)
+    }
+    ///the interval of a feature location, one block per part, a part running over the origin of a circular record ends past the record length
+    pub fn from_location(chrom: &str, location: &Location, length: u32, circular: bool) -> Self {
+        let parts = location_parts(location, length, circular, None);
+        let start = parts.iter().map(|p| p.0).min().unwrap_or(1) - 1;
+        let end = parts.iter().map(|p| p.1).max().unwrap_or(0);
+        let mut record = Record::new(chrom, start, end.max(start));
+        record.strand = match location.strand() {
+            -1 => Some(-1),
+            _ => Some(1),
+        };
+        record.blocks = parts
+            .iter()
+            .map(|(s, e, _)| (s - 1 - start, e + 1 - s))
+            .collect();
+        record.columns = 6;
+        record
+    }
+    ///the blocks of the interval, a single block over the whole interval when the line has none
+    pub fn block_list(&self) -> Vec<(u32, u32)> {
+        match self.blocks.is_empty() {
+            true => vec![(0, self.end - self.start)],
+            false => self.blocks.clone(),
+        }
+    }
+    ///the 1-based location of the interval, a join of its blocks in biological order
+    ///positions past the length of a circular record are wrapped to its start
+    pub fn location(&self, length: u32, circular: bool) -> Location {
+        let strand = self.strand.unwrap_or(1);
+        let wrap = |v: u32| match circular && length > 0 && v > length {
+            true => v - length,
+            false => v,
+        };
+        let mut parts: Vec<Location> = self
+            .block_list()
+            .iter()
+            .map(|(block_start, size)| Location::Range {
+                start: Position::Exact(wrap(self.start + block_start + 1)),
+                end: Position::Exact(wrap(self.start + block_start + size)),
+                strand,
+            })
+            .collect();
+        if strand == -1 {
+            parts.reverse();
+        }
+        match parts.len() {
+            1 => parts.remove(0),
+            _ => Location::Join(parts),
+        }
+    }
+    ///the line with the given number of BED columns (3 to 12) and the extra columns, missing values are written as in UCSC files (score 0, strand ```.```)
+    pub fn to_line(&self, columns: usize) -> String {
+        let blocks = self.block_list();
+        let list = |values: Vec<u32>| values.iter().map(|v| format!("{},", v)).collect::<String>();
+        let fields = [
+            self.chrom.clone(),
+            self.start.to_string(),
+            self.end.to_string(),
+            self.name.clone().unwrap_or(".".to_string()),
+            self.score.unwrap_or(0.0).to_string(),
+            match self.strand {
+                Some(1) => "+".to_string(),
+                Some(-1) => "-".to_string(),
+                _ => ".".to_string(),
+            },
+            self.thick_start.unwrap_or(self.start).to_string(),
+            self.thick_end.unwrap_or(self.end).to_string(),
+            self.item_rgb.clone().unwrap_or("0".to_string()),
+            blocks.len().to_string(),
+            list(blocks.iter().map(|(_, size)| *size).collect()),
+            list(blocks.iter().map(|(start, _)| *start).collect()),
+        ];
+        let mut line = fields[..columns.clamp(3, 12)].join("\t");
+        for extra in self.extra.iter() {
+            line.push('\t');
+            line.push_str(extra);
+        }
+        line
+    }
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_line(self.columns))
+    }
+}
+
+///attaches the intervals on a record (matching its name, accession or version) to it as features of the given type, returning how many were added
+///the name of an interval becomes the locus tag, a CDS is extracted from the record sequence and translated with table 11
+///empty intervals (start equal to end) cover no base and are skipped
+pub fn add_features(
+    record: &mut gbk::Record,
+    intervals: &[Record],
+    feature_type: &str,
+) -> Result<usize, anyhow::Error> {
+    let names: Vec<String> = [record.id.clone(), record.header.version.clone()]
+        .into_iter()
+        .chain(record.header.accessions.iter().cloned())
+        .filter(|name| !name.is_empty())
+        .collect();
+    let length = record.sequence.len().max(record.length as usize) as u32;
+    let circular = record.is_circular();
+    let mut added = 0;
+    for interval in intervals
+        .iter()
+        .filter(|i| names.contains(&i.chrom) && i.end > i.start)
+    {
+        if interval.end > length && !circular {
+            return Err(anyhow!(
+                "interval {}..{} runs past the end of {} ({} bp)",
+                interval.start,
+                interval.end,
+                record.id,
+                length
+            ));
+        }
+        let location = interval.location(length, circular);
+        let count = record
+            .features_of(feature_type)
+            .map_or(0, |f| f.attributes.len())
+            + 1;
+        let mut qualifiers = Qualifiers::new();
+        if let Some(name) = &interval.name {
+            qualifiers.push("locus_tag", Some(name.clone()));
+        }
+        let features = record.features_mut(feature_type);
+        let mut key = interval
+            .name
+            .clone()
+            .unwrap_or(format!("{}_{}", feature_type, count));
+        if features.attributes.contains_key(&key) {
+            key = format!("{}_{}", key, count);
+        }
+        let strand = interval.strand.unwrap_or(1);
+        features
+            .set_counter(key.clone())
+            .set_start(RangeValue::Exact(interval.start + 1))
+            .set_stop(RangeValue::Exact(interval.end))
+            .set_gene(String::new())
+            .set_product(String::new())
+            .set_strand(strand)
+            .set_location(location.clone())
+            .set_qualifiers(qualifiers);
+        added += 1;
+        if feature_type != "CDS" {
+            continue;
+        }
+        features.set_codon_start(1);
+        if record.sequence.is_empty() {
+            continue;
+        }
+        let cds_char = location.extract(&record.sequence);
+        let code = genetic_code(DEFAULT_TABLE).ok_or(anyhow!("no translation table"))?;
+        let prot_seq = code.translate_cds(&cds_char, true);
+        let faa = prot_seq.split('*').next().unwrap_or_default().to_string();
+        record
+            .seq_features
+            .set_counter(key)
+            .set_start(RangeValue::Exact(interval.start + 1))
+            .set_stop(RangeValue::Exact(interval.end - 1))
+            .set_sequence_ffn(cds_char)
+            .set_sequence_faa(faa)
+            .set_codon_start(0)
+            .set_strand(strand);
+    }
+    Ok(added)
+}
+
+///an iterator over the intervals of a file
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Records<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read BED from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read BED from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new BED reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+        }
+    }
+    ///reads the next interval, returns None at the end of the file
+    pub fn read_record(&mut self) -> Result<Option<Record>, ParseError> {
+        loop {
+            self.line_buffer.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.line_buffer)
+                .map_err(|source| ParseError::Io {
+                    line_number: self.line_number + 1,
+                    record_id: String::new(),
+                    line: String::new(),
+                    source,
+                })?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty()
+                || line.starts_with('#')
+                || line.starts_with("track")
+                || line.starts_with("browser")
+            {
+                continue;
+            }
+            let record = Record::from_line(line).map_err(|e| ParseError::MalformedLine {
+                line_number: self.line_number,
+                record_id: line
+                    .split_whitespace()
+                    .next()
+                    .unwrap_or_default()
+                    .to_string(),
+                line: line.to_string(),
+                message: e.to_string(),
+            })?;
+            return Ok(Some(record));
+        }
+    }
+    ///return an iterator over the intervals of the file
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbk::Reader as GbkReader;
+
+    #[test]
+    fn test_read_bed() {
+        let text = concat!(
+            "browser position NC_000913:1-913\n",
+            "# intervals\n",
+            "NC_000913\t0\t50\n",
+            "NC_000913 10 20 peak1 7.5 .\n",
+            "NC_000913\t100\t400\tx\t0\t-\t120\t380\t255,0,0\t3\t10,20,30,\t0,100,270\tkeep\n",
+            "NC_000913\t5\t4\n",
+        );
+        let mut reader = Reader::new(text.as_bytes());
+        let bed3 = reader.read_record().unwrap().unwrap();
+        assert_eq!(
+            (bed3.columns, bed3.to_string()),
+            (3, "NC_000913\t0\t50".to_string())
+        );
+        let bed6 = reader.read_record().unwrap().unwrap();
+        assert_eq!(bed6.name.as_deref(), Some("peak1"));
+        assert_eq!((bed6.score, bed6.strand), (Some(7.5), None));
+        assert_eq!(bed6.to_line(6), "NC_000913\t10\t20\tpeak1\t7.5\t.");
+        let bed12 = reader.read_record().unwrap().unwrap();
+        assert_eq!(bed12.blocks, vec![(0, 10), (100, 20), (270, 30)]);
+        assert_eq!((bed12.thick_start, bed12.thick_end), (Some(120), Some(380)));
+        assert_eq!(bed12.extra, vec!["keep"]);
+        assert_eq!(
+            bed12.location(913, false).to_string(),
+            "complement(join(101..110,201..220,371..400))"
+        );
+        assert_eq!(
+            bed12.to_string(),
+            "NC_000913\t100\t400\tx\t0\t-\t120\t380\t255,0,0\t3\t10,20,30,\t0,100,270,\tkeep"
+        );
+        //the location of an interval gives the interval back
+        let location = bed12.location(913, false);
+        let again = Record::from_location("NC_000913", &location, 913, false);
+        assert_eq!(
+            (again.start, again.end, again.blocks.clone()),
+            (100, 400, bed12.blocks.clone())
+        );
+        match reader.read_record() {
+            Err(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                ..
+            }) => assert_eq!((line_number, record_id.as_str()), (6, "NC_000913")),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+        for bad in [
+            "c\t0\t10\tn\t0\t+\t0\t10\t0\t2\t5,5,\t0,4,",
+            "c\t0\t10\tn\t0\t+\t0\t10\t0\t2\t5,4,\t0,5,",
+            "c\t0\t10\tn\t0\t*",
+        ] {
+            assert!(Record::from_line(bad).is_err(), "{}", bad);
+        }
+    }
+    #[test]
+    fn test_bed_round_trip() {
+        let records: Vec<gbk::Record> = GbkReader::from_file("K12_ribo.gbk")
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let output = std::env::temp_dir().join("microbiorust_test_bed_write.bed");
+        let _ = std::fs::remove_file(&output);
+        gbk::bed_write(records.clone(), output.to_str().unwrap(), 12).expect("failed to write bed");
+        let content = std::fs::read_to_string(&output).expect("failed to read bed");
+        std::fs::remove_file(&output).ok();
+        assert_eq!(
+            content,
+            concat!(
+                "NC_000913\t9\t363\tb3304\t0\t-\t9\t363\t0\t1\t354,\t0,\n",
+                "NC_000913\t372\t906\tb3305\t0\t-\t372\t906\t0\t1\t534,\t0,\n",
+            )
+        );
+        //the intervals attached to a record without features give the same CDS
+        let intervals: Vec<Record> = Reader::new(content.as_bytes())
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let mut record = records[0].clone();
+        record.cds = Default::default();
+        record.seq_features = Default::default();
+        assert_eq!(add_features(&mut record, &intervals, "CDS").unwrap(), 2);
+        for locus_tag in ["b3304", "b3305"] {
+            assert_eq!(
+                record.cds.get_location(locus_tag),
+                records[0].cds.get_location(locus_tag)
+            );
+            assert_eq!(
+                record.seq_features.get_sequence_faa(locus_tag),
+                records[0].seq_features.get_sequence_faa(locus_tag)
+            );
+        }
+        let empty = vec![Record::new("NC_000913", 0, 0)];
+        assert_eq!(add_features(&mut record, &empty, "CDS").unwrap(), 0);
+        let elsewhere = vec![Record::new("plasmid", 0, 10)];
+        assert_eq!(
+            add_features(&mut record, &elsewhere, "misc_feature").unwrap(),
+            0
+        );
+    }
+}
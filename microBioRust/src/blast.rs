@@ -0,0 +1,534 @@
+//! # BLAST tabular output
+//!
+//! Reads the tabular output of BLAST (```-outfmt 6``` and ```-outfmt 7```), DIAMOND and MMseqs2 into typed ```Hit```s.
+//! The columns default to the twelve standard ones, a custom column list such as ```-outfmt "6 qseqid sseqid pident length qlen slen"``` is given with ```Reader::fields```,
+//! and the ```# Fields:``` comment line of outfmt 7 sets the columns as the file is read. MMseqs2 column names (query, target, fident, bits...) are understood too.
+//!
+//! ```best_hits``` keeps the best scoring hit of each query, ```Hit::query_coverage``` gives the share of the query in an alignment
+//! and ```join_hits``` joins hits to the CDS of a ```gbk::Record``` by locus tag, as written by ```gbk_to_faa``` (```>record|locus_tag```) or by protein_id.
+//!
+//!```rust
+//! use microBioRust::blast::{best_hits, Reader};
+//!
+//! let text = concat!(
+//!     "# BLASTP 2.15.0+\n",
+//!     "# Fields: query acc.ver, subject acc.ver, % identity, alignment length, mismatches, gap opens, q. start, q. end, s. start, s. end, evalue, bit score, query length\n",
+//!     "b3304\tP0C018.1\t99.15\t117\t1\t0\t1\t117\t1\t117\t1.2e-80\t235\t117\n",
+//!     "b3304\tQ8ZJ89.1\t95.73\t117\t5\t0\t1\t117\t1\t117\t3.4e-78\t228\t117\n",
+//! );
+//! let hits: Vec<_> = Reader::new(text.as_bytes()).hits().map(|h| h.unwrap()).collect();
+//! assert_eq!(hits.len(), 2);
+//! assert_eq!(hits[0].subject_id, "P0C018.1");
+//! assert_eq!(hits[0].query_coverage(), Some(100.0));
+//! let best = best_hits(hits);
+//! assert_eq!(best.len(), 1);
+//! assert_eq!(best[0].bit_score, 235.0);
+//!```
+//!
+//! BLAST XML (```-outfmt 5```) is read by the ```xml``` submodule.
+
+pub mod xml;
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk::Record;
+use anyhow::{anyhow, Context};
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    path::Path,
+};
+
+///the columns of ```-outfmt 6``` when no columns are given, also written as ```std```
+pub const DEFAULT_FIELDS: [&str; 12] = [
+    "qseqid", "sseqid", "pident", "length", "mismatch", "gapopen", "qstart", "qend", "sstart",
+    "send", "evalue", "bitscore",
+];
+
+///the column specifiers named by the descriptions of an outfmt 7 ```# Fields:``` line
+const FIELD_DESCRIPTIONS: [(&str, &str); 33] = [
+    ("query id", "qseqid"),
+    ("query gi", "qgi"),
+    ("query acc.", "qacc"),
+    ("query acc.ver", "qaccver"),
+    ("query length", "qlen"),
+    ("subject id", "sseqid"),
+    ("subject ids", "sallseqid"),
+    ("subject gi", "sgi"),
+    ("subject acc.", "sacc"),
+    ("subject acc.ver", "saccver"),
+    ("subject length", "slen"),
+    ("q. start", "qstart"),
+    ("q. end", "qend"),
+    ("s. start", "sstart"),
+    ("s. end", "send"),
+    ("query seq", "qseq"),
+    ("subject seq", "sseq"),
+    ("evalue", "evalue"),
+    ("bit score", "bitscore"),
+    ("score", "score"),
+    ("alignment length", "length"),
+    ("% identity", "pident"),
+    ("identical", "nident"),
+    ("mismatches", "mismatch"),
+    ("positives", "positive"),
+    ("gap opens", "gapopen"),
+    ("gaps", "gaps"),
+    ("% positives", "ppos"),
+    ("query/sbjct frames", "frames"),
+    ("subject tax ids", "staxids"),
+    ("subject title", "stitle"),
+    ("% query coverage per subject", "qcovs"),
+    ("% query coverage per hsp", "qcovhsp"),
+];
+
+///the BLAST specifier for a column name, MMseqs2 names are mapped onto the BLAST ones
+pub fn field_name(name: &str) -> String {
+    let name = name.trim();
+    if let Some((_, field)) = FIELD_DESCRIPTIONS.iter().find(|(d, _)| *d == name) {
+        return field.to_string();
+    }
+    match name {
+        "query" => "qseqid",
+        "target" => "sseqid",
+        "alnlen" => "length",
+        "tstart" => "sstart",
+        "tend" => "send",
+        "bits" => "bitscore",
+        "tlen" => "slen",
+        "raw" => "score",
+        other => other,
+    }
+    .to_string()
+}
+
+///reads a column list such as ```6 qseqid sseqid pident``` or ```std qlen slen```, the leading format number is optional
+pub fn parse_fields(spec: &str) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    for field in spec
+        .trim_matches(|c| c == '"' || c == '\'')
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|f| !f.is_empty())
+    {
+        match field {
+            "6" | "7" | "8" | "10" if fields.is_empty() => (),
+            "std" => fields.extend(DEFAULT_FIELDS.iter().map(|f| f.to_string())),
+            other => fields.push(field_name(other)),
+        }
+    }
+    fields
+}
+
+///a line of tabular output, an alignment (HSP) between a query and a subject
+///columns without a field of their own are kept in ```extra``` by their specifier
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hit {
+    pub query_id: String,
+    pub subject_id: String,
+    pub percent_identity: f64,
+    pub alignment_length: u32,
+    pub mismatches: u32,
+    pub gap_opens: u32,
+    pub query_start: u32,
+    pub query_end: u32,
+    pub subject_start: u32,
+    pub subject_end: u32,
+    pub evalue: f64,
+    pub bit_score: f64,
+    pub query_length: Option<u32>,
+    pub subject_length: Option<u32>,
+    pub extra: Vec<(String, String)>,
+}
+
+impl Hit {
+    ///reads a tab separated line with the given columns, the query and subject ids are required
+    pub fn from_columns(fields: &[String], line: &str) -> Result<Self, anyhow::Error> {
+        let values: Vec<&str> = line.split('\t').collect();
+        if values.len() != fields.len() {
+            return Err(anyhow!(
+                "{} columns where {} were expected",
+                values.len(),
+                fields.len()
+            ));
+        }
+        let mut hit = Hit::default();
+        for (field, value) in fields.iter().zip(values) {
+            let value = value.trim();
+            let number = || -> Result<u32, anyhow::Error> {
+                value
+                    .parse::<u32>()
+                    .map_err(|e| anyhow!("bad {} {:?}: {}", field, value, e))
+            };
+            let float = || -> Result<f64, anyhow::Error> {
+                value
+                    .parse::<f64>()
+                    .map_err(|e| anyhow!("bad {} {:?}: {}", field, value, e))
+            };
+            match field.as_str() {
+                "qseqid" => hit.query_id = value.to_string(),
+                "sseqid" => hit.subject_id = value.to_string(),
+                //an accession is the id when the id column is left out
+                "qaccver" | "qacc" if hit.query_id.is_empty() => hit.query_id = value.to_string(),
+                "saccver" | "sacc" if hit.subject_id.is_empty() => {
+                    hit.subject_id = value.to_string()
+                }
+                "pident" => hit.percent_identity = float()?,
+                //MMseqs2 gives the identity as a fraction
+                "fident" => hit.percent_identity = float()? * 100.0,
+                "length" => hit.alignment_length = number()?,
+                "mismatch" => hit.mismatches = number()?,
+                "gapopen" => hit.gap_opens = number()?,
+                "qstart" => hit.query_start = number()?,
+                "qend" => hit.query_end = number()?,
+                "sstart" => hit.subject_start = number()?,
+                "send" => hit.subject_end = number()?,
+                "evalue" => hit.evalue = float()?,
+                "bitscore" => hit.bit_score = float()?,
+                "qlen" => hit.query_length = Some(number()?),
+                "slen" => hit.subject_length = Some(number()?),
+                _ => hit.extra.push((field.to_string(), value.to_string())),
+            }
+        }
+        if hit.query_id.is_empty() || hit.subject_id.is_empty() {
+            return Err(anyhow!("no query or subject id"));
+        }
+        Ok(hit)
+    }
+    ///the value of a column without a field of its own, e.g. ```stitle``` or ```staxids```
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.extra
+            .iter()
+            .find(|(f, _)| f == field)
+            .map(|(_, v)| v.as_str())
+    }
+    ///the percentage of the query covered by this alignment, needs the qlen column
+    pub fn query_coverage(&self) -> Option<f64> {
+        let length = self.query_length.filter(|l| *l > 0)?;
+        let aligned = self.query_start.abs_diff(self.query_end) + 1;
+        Some(aligned as f64 * 100.0 / length as f64)
+    }
+    ///the percentage of the subject covered by this alignment, needs the slen column
+    pub fn subject_coverage(&self) -> Option<f64> {
+        let length = self.subject_length.filter(|l| *l > 0)?;
+        let aligned = self.subject_start.abs_diff(self.subject_end) + 1;
+        Some(aligned as f64 * 100.0 / length as f64)
+    }
+    ///-1 when the subject is aligned on its reverse strand (sstart above send), otherwise 1
+    pub fn subject_strand(&self) -> i8 {
+        match self.subject_start > self.subject_end {
+            true => -1,
+            false => 1,
+        }
+    }
+    //orders hits by bit score, then by the lower evalue
+    fn better_than(&self, other: &Hit) -> bool {
+        self.bit_score > other.bit_score
+            || (self.bit_score == other.bit_score && self.evalue < other.evalue)
+    }
+}
+
+///keeps the hit with the highest bit score (the lowest evalue on a tie) of each query, in the order the queries first appear
+pub fn best_hits<I: IntoIterator<Item = Hit>>(hits: I) -> Vec<Hit> {
+    let mut best: Vec<Hit> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    for hit in hits {
+        match index.get(&hit.query_id) {
+            Some(&i) => {
+                if hit.better_than(&best[i]) {
+                    best[i] = hit;
+                }
+            }
+            None => {
+                index.insert(hit.query_id.clone(), best.len());
+                best.push(hit);
+            }
+        }
+    }
+    best
+}
+
+///the percentage of the query covered by any of the alignments, overlapping alignments are counted once (as qcovs)
+///the alignments should share a query, the query length is taken from the first with a qlen column
+pub fn merged_query_coverage(hits: &[Hit]) -> Option<f64> {
+    let length = hits
+        .iter()
+        .find_map(|h| h.query_length)
+        .filter(|l| *l > 0)?;
+    let mut spans: Vec<(u32, u32)> = hits
+        .iter()
+        .map(|h| {
+            (
+                h.query_start.min(h.query_end),
+                h.query_start.max(h.query_end),
+            )
+        })
+        .collect();
+    spans.sort();
+    let mut covered = 0;
+    let mut current: Option<(u32, u32)> = None;
+    for (start, end) in spans {
+        current = match current {
+            Some((s, e)) if start <= e + 1 => Some((s, e.max(end))),
+            Some((s, e)) => {
+                covered += e - s + 1;
+                Some((start, end))
+            }
+            None => Some((start, end)),
+        };
+    }
+    if let Some((s, e)) = current {
+        covered += e - s + 1;
+    }
+    Some(covered as f64 * 100.0 / length as f64)
+}
+
+//the locus tag of the CDS an id names, the id can be the locus tag, end with |locus_tag or be the protein_id
+pub(crate) fn cds_locus_tag(record: &Record, id: &str) -> Option<String> {
+    let last = id.rsplit('|').find(|part| !part.is_empty()).unwrap_or(id);
+    for candidate in [id, last] {
+        if record.cds.attributes.contains_key(candidate) {
+            return Some(candidate.to_string());
+        }
+    }
+    let unversioned = |v: &str| v.split('.').next().unwrap_or(v).to_string();
+    record
+        .cds
+        .attributes
+        .keys()
+        .find(|locus_tag| {
+            record
+                .cds
+                .get_qualifiers(locus_tag)
+                .and_then(|q| q.protein_id())
+                .is_some_and(|protein_id| {
+                    protein_id == last || unversioned(protein_id) == unversioned(last)
+                })
+        })
+        .cloned()
+}
+
+///groups the hits by the locus tag of the CDS they belong to, matching the query id first and the subject id second
+///hits that name no CDS of the record are left out
+pub fn join_hits<'a>(record: &Record, hits: &'a [Hit]) -> BTreeMap<String, Vec<&'a Hit>> {
+    let mut joined: BTreeMap<String, Vec<&'a Hit>> = BTreeMap::new();
+    for hit in hits {
+        if let Some(locus_tag) =
+            cds_locus_tag(record, &hit.query_id).or_else(|| cds_locus_tag(record, &hit.subject_id))
+        {
+            joined.entry(locus_tag).or_default().push(hit);
+        }
+    }
+    joined
+}
+
+///an iterator over the hits of a file
+#[derive(Debug)]
+pub struct Hits<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Hits<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Hit, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_hit() {
+            Ok(Some(hit)) => Some(Ok(hit)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+    fields: Vec<String>,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read BLAST tabular output from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read BLAST output from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new BLAST tabular reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+            fields: DEFAULT_FIELDS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+    ///sets the columns of the file as given to ```-outfmt```, e.g. ```"6 qseqid sseqid pident length qlen slen evalue bitscore"```
+    pub fn fields(mut self, spec: &str) -> Self {
+        self.fields = parse_fields(spec);
+        self
+    }
+    ///reads the next hit, skipping comment lines, returns None at the end of the file
+    pub fn read_hit(&mut self) -> Result<Option<Hit>, ParseError> {
+        loop {
+            self.line_buffer.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.line_buffer)
+                .map_err(|source| ParseError::Io {
+                    line_number: self.line_number + 1,
+                    record_id: String::new(),
+                    line: String::new(),
+                    source,
+                })?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(comment) = line.strip_prefix('#') {
+                //outfmt 7 names the columns before the hits of each query
+                if let Some(fields) = comment.trim().strip_prefix("Fields:") {
+                    self.fields = fields.split(',').map(field_name).collect();
+                }
+                continue;
+            }
+            let hit =
+                Hit::from_columns(&self.fields, line).map_err(|e| ParseError::MalformedLine {
+                    line_number: self.line_number,
+                    record_id: line.split('\t').next().unwrap_or_default().to_string(),
+                    line: line.to_string(),
+                    message: e.to_string(),
+                })?;
+            return Ok(Some(hit));
+        }
+    }
+    ///return an iterator over the hits of the file
+    pub fn hits(self) -> Hits<B> {
+        Hits {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genbank;
+
+    #[test]
+    fn test_read_outfmt6() {
+        let text = "b3304\tP0C018.1\t99.15\t117\t1\t0\t1\t117\t1\t117\t1.2e-80\t235\n\
+                    b3305\tP0AG55.1\t100.000\t177\t0\t0\t1\t177\t177\t1\t5e-120\t350\n";
+        let hits: Vec<Hit> = Reader::new(text.as_bytes())
+            .hits()
+            .map(|h| h.unwrap())
+            .collect();
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].percent_identity, 99.15);
+        assert_eq!(hits[0].evalue, 1.2e-80);
+        assert_eq!(hits[1].subject_strand(), -1);
+        assert_eq!(hits[1].query_coverage(), None);
+        let mut records = Reader::new("b3304\tP0C018.1\t99.15\n".as_bytes()).hits();
+        match records.next() {
+            Some(Err(ParseError::MalformedLine { line_number, .. })) => assert_eq!(line_number, 1),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_custom_fields() {
+        assert_eq!(parse_fields("6 std qlen").len(), 13);
+        //DIAMOND with a custom column list
+        let text = "b3304\tP0C018\t99.1\t117\t60\t120\t1e-80\tRplR ribosomal protein\n";
+        let hits: Vec<Hit> = Reader::new(text.as_bytes())
+            .fields("6 qseqid sseqid pident length qlen slen evalue stitle")
+            .hits()
+            .map(|h| h.unwrap())
+            .collect();
+        assert_eq!(hits[0].query_length, Some(60));
+        assert_eq!(hits[0].get("stitle"), Some("RplR ribosomal protein"));
+        //MMseqs2 convertalis columns
+        let text = "b3304\tP0C018\t0.991\t117\t1\t0\t1\t117\t1\t117\t1.0E-80\t235\t117\t117\n";
+        let hits: Vec<Hit> = Reader::new(text.as_bytes())
+            .fields("query,target,fident,alnlen,mismatch,gapopen,qstart,qend,tstart,tend,evalue,bits,qlen,tlen")
+            .hits()
+            .map(|h| h.unwrap())
+            .collect();
+        assert!((hits[0].percent_identity - 99.1).abs() < 1e-9);
+        assert_eq!(hits[0].subject_length, Some(117));
+        assert_eq!(hits[0].subject_coverage(), Some(100.0));
+    }
+    #[test]
+    fn test_best_hits_and_coverage() {
+        let hit = |query: &str, start: u32, end: u32, bits: f64| Hit {
+            query_id: query.to_string(),
+            subject_id: "s".to_string(),
+            query_start: start,
+            query_end: end,
+            query_length: Some(200),
+            bit_score: bits,
+            ..Default::default()
+        };
+        let hits = vec![
+            hit("q1", 1, 50, 80.0),
+            hit("q2", 1, 200, 300.0),
+            hit("q1", 41, 100, 120.0),
+        ];
+        let best = best_hits(hits.clone());
+        assert_eq!(best.len(), 2);
+        assert_eq!(
+            (best[0].query_id.as_str(), best[0].bit_score),
+            ("q1", 120.0)
+        );
+        assert_eq!(best[1].query_coverage(), Some(100.0));
+        assert_eq!(
+            merged_query_coverage(&[hits[0].clone(), hits[2].clone()]),
+            Some(50.0)
+        );
+    }
+    #[test]
+    fn test_join_hits() {
+        let records = genbank!("K12_ribo.gbk");
+        let text = "NC_000913|b3304\tP0C018.1\t100\t117\t0\t0\t1\t117\t1\t117\t1e-80\t235\n\
+                    unrelated\tb3305\t100\t177\t0\t0\t1\t177\t1\t177\t1e-120\t350\n\
+                    unrelated\tnothing\t100\t177\t0\t0\t1\t177\t1\t177\t1e-120\t350\n";
+        let hits: Vec<Hit> = Reader::new(text.as_bytes())
+            .hits()
+            .map(|h| h.unwrap())
+            .collect();
+        let joined = join_hits(&records[0], &hits);
+        assert_eq!(joined.len(), 2);
+        assert_eq!(joined["b3304"][0].subject_id, "P0C018.1");
+        assert_eq!(joined["b3305"][0].query_id, "unrelated");
+    }
+}
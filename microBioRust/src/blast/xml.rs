@@ -0,0 +1,573 @@
+//! # BLAST XML output
+//!
+//! A streaming reader for BLAST XML (```-outfmt 5```), each ```<Iteration>``` (one per query) is read and returned before the next is looked at,
+//! so large result files are never held in memory. An ```Iteration``` holds its ```Hit```s and each hit its ```Hsp```s with the scores,
+//! identities, positives, gaps, coordinates and the query, hit and midline alignment strings.
+//! ```Iteration::tabular_hits``` gives the HSPs as tabular ```blast::Hit```s for ```best_hits``` and ```join_hits```.
+//!
+//!```rust
+//! use microBioRust::blast::xml::Reader;
+//!
+//! let text = "<?xml version=\"1.0\"?>
+//! <BlastOutput>
+//!   <BlastOutput_program>blastp</BlastOutput_program>
+//!   <BlastOutput_db>ribosomal.faa</BlastOutput_db>
+//!   <BlastOutput_iterations>
+//!     <Iteration>
+//!       <Iteration_iter-num>1</Iteration_iter-num>
+//!       <Iteration_query-def>b3304 rplR</Iteration_query-def>
+//!       <Iteration_query-len>10</Iteration_query-len>
+//!       <Iteration_hits>
+//!         <Hit>
+//!           <Hit_num>1</Hit_num>
+//!           <Hit_id>P0C018.1</Hit_id>
+//!           <Hit_len>117</Hit_len>
+//!           <Hit_hsps>
+//!             <Hsp>
+//!               <Hsp_bit-score>20.4</Hsp_bit-score>
+//!               <Hsp_evalue>1e-05</Hsp_evalue>
+//!               <Hsp_query-from>1</Hsp_query-from>
+//!               <Hsp_query-to>10</Hsp_query-to>
+//!               <Hsp_hit-from>1</Hsp_hit-from>
+//!               <Hsp_hit-to>10</Hsp_hit-to>
+//!               <Hsp_identity>9</Hsp_identity>
+//!               <Hsp_positive>10</Hsp_positive>
+//!               <Hsp_gaps>0</Hsp_gaps>
+//!               <Hsp_align-len>10</Hsp_align-len>
+//!               <Hsp_qseq>MDKKSARIRR</Hsp_qseq>
+//!               <Hsp_hseq>MDKKSSRIRR</Hsp_hseq>
+//!               <Hsp_midline>MDKKS+RIRR</Hsp_midline>
+//!             </Hsp>
+//!           </Hit_hsps>
+//!         </Hit>
+//!       </Iteration_hits>
+//!     </Iteration>
+//!   </BlastOutput_iterations>
+//! </BlastOutput>";
+//! let mut reader = Reader::new(text.as_bytes());
+//! let iteration = reader.read_iteration().unwrap().unwrap();
+//! assert_eq!(reader.header().program, "blastp");
+//! assert_eq!(iteration.query_id(), "b3304");
+//! let hsp = &iteration.hits[0].hsps[0];
+//! assert_eq!((hsp.identity, hsp.positive), (9, 10));
+//! assert_eq!(hsp.midline, "MDKKS+RIRR");
+//! assert!(reader.read_iteration().unwrap().is_none());
+//!```
+
+use crate::blast::Hit as TabularHit;
+use crate::compression::open;
+use crate::error::ParseError;
+use anyhow::Context;
+use quick_xml::events::Event;
+use std::{io, path::Path};
+
+///the details of the search given once at the top of the file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct BlastOutput {
+    pub program: String,
+    pub version: String,
+    pub reference: String,
+    pub db: String,
+    pub query_id: String,
+    pub query_def: String,
+    pub query_len: u32,
+    ///the search parameters without their ```Parameters_``` prefix, e.g. ("expect", "10")
+    pub parameters: Vec<(String, String)>,
+}
+
+///a high scoring pair, one alignment between the query and a hit
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hsp {
+    pub num: u32,
+    pub bit_score: f64,
+    pub score: f64,
+    pub evalue: f64,
+    pub query_from: u32,
+    pub query_to: u32,
+    pub hit_from: u32,
+    pub hit_to: u32,
+    pub query_frame: i8,
+    pub hit_frame: i8,
+    pub identity: u32,
+    pub positive: u32,
+    pub gaps: u32,
+    pub align_len: u32,
+    pub qseq: String,
+    pub hseq: String,
+    pub midline: String,
+}
+
+impl Hsp {
+    ///the percentage of identical positions in the alignment
+    pub fn percent_identity(&self) -> f64 {
+        match self.align_len {
+            0 => 0.0,
+            length => self.identity as f64 * 100.0 / length as f64,
+        }
+    }
+    ///the number of mismatched positions, aligned positions that are neither identical nor gaps
+    pub fn mismatches(&self) -> u32 {
+        self.align_len.saturating_sub(self.identity + self.gaps)
+    }
+    ///the number of gap openings, counted from the alignment strings
+    pub fn gap_opens(&self) -> u32 {
+        let opens = |seq: &str| {
+            seq.as_bytes()
+                .split(|b| *b != b'-')
+                .filter(|run| !run.is_empty())
+                .count() as u32
+        };
+        opens(&self.qseq) + opens(&self.hseq)
+    }
+}
+
+///a database sequence with one or more HSPs against the query
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Hit {
+    pub num: u32,
+    pub id: String,
+    pub def: String,
+    pub accession: String,
+    pub len: u32,
+    pub hsps: Vec<Hsp>,
+}
+
+///the search of one query, with its hits in the order given by BLAST
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Iteration {
+    pub iter_num: u32,
+    pub query_id: String,
+    pub query_def: String,
+    pub query_len: u32,
+    pub hits: Vec<Hit>,
+    ///e.g. ```No hits found```
+    pub message: String,
+}
+
+impl Iteration {
+    ///the query name as given on the command line, the first word of the query definition (BLAST+ gives ```Query_1``` as the query id)
+    pub fn query_id(&self) -> &str {
+        match self.query_def.split_whitespace().next() {
+            Some(name) => name,
+            None => &self.query_id,
+        }
+    }
+    ///every HSP as a tabular hit, in the columns of ```-outfmt "6 std qlen slen"```
+    pub fn tabular_hits(&self) -> Vec<TabularHit> {
+        let mut hits = Vec::new();
+        for hit in self.hits.iter() {
+            for hsp in hit.hsps.iter() {
+                hits.push(TabularHit {
+                    query_id: self.query_id().to_string(),
+                    subject_id: hit.id.clone(),
+                    percent_identity: hsp.percent_identity(),
+                    alignment_length: hsp.align_len,
+                    mismatches: hsp.mismatches(),
+                    gap_opens: hsp.gap_opens(),
+                    query_start: hsp.query_from,
+                    query_end: hsp.query_to,
+                    subject_start: hsp.hit_from,
+                    subject_end: hsp.hit_to,
+                    evalue: hsp.evalue,
+                    bit_score: hsp.bit_score,
+                    query_length: Some(self.query_len),
+                    subject_length: Some(hit.len),
+                    extra: Vec::new(),
+                });
+            }
+        }
+        hits
+    }
+}
+
+//counts the lines passed to the XML parser so errors can give a line number
+#[derive(Debug)]
+struct LineCounter<B> {
+    inner: B,
+    line_number: usize,
+}
+
+impl<B: io::BufRead> io::Read for LineCounter<B> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.line_number += buf[..read].iter().filter(|b| **b == b'\n').count();
+        Ok(read)
+    }
+}
+
+impl<B: io::BufRead> io::BufRead for LineCounter<B> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+    fn consume(&mut self, amt: usize) {
+        if let Ok(buffer) = self.inner.fill_buf() {
+            let end = amt.min(buffer.len());
+            self.line_number += buffer[..end].iter().filter(|b| **b == b'\n').count();
+        }
+        self.inner.consume(amt)
+    }
+}
+
+///an iterator over the iterations (queries) of a file
+#[derive(Debug)]
+pub struct Iterations<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Iterations<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Iteration, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_iteration() {
+            Ok(Some(iteration)) => Some(Ok(iteration)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///streaming reader for the file
+pub struct Reader<B> {
+    reader: quick_xml::Reader<LineCounter<B>>,
+    buffer: Vec<u8>,
+    text: String,
+    header: BlastOutput,
+}
+
+impl<B> std::fmt::Debug for Reader<B> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Reader")
+            .field("header", &self.header)
+            .finish()
+    }
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read BLAST XML from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read BLAST XML from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new BLAST XML reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: quick_xml::Reader::from_reader(LineCounter {
+                inner: bufreader,
+                line_number: 0,
+            }),
+            buffer: Vec::new(),
+            text: String::new(),
+            header: BlastOutput::default(),
+        }
+    }
+    ///the program, database and parameters of the search, filled in once the first iteration has been read
+    pub fn header(&self) -> &BlastOutput {
+        &self.header
+    }
+    //the error for a bad element or value, the line is the one the parser has reached
+    fn error(&self, record_id: &str, line: &str, message: String) -> ParseError {
+        ParseError::MalformedLine {
+            line_number: self.reader.get_ref().line_number + 1,
+            record_id: record_id.to_string(),
+            line: line.to_string(),
+            message,
+        }
+    }
+    ///reads the next iteration, returns None at the end of the file
+    pub fn read_iteration(&mut self) -> Result<Option<Iteration>, ParseError> {
+        let mut iteration: Option<Iteration> = None;
+        let mut hit: Option<Hit> = None;
+        let mut hsp: Option<Hsp> = None;
+        loop {
+            self.buffer.clear();
+            let event = self
+                .reader
+                .read_event_into(&mut self.buffer)
+                .map_err(|e| e.to_string());
+            let name = match event {
+                Err(message) => {
+                    let query = iteration.as_ref().map(|i| i.query_id().to_string());
+                    return Err(self.error(&query.unwrap_or_default(), "", message));
+                }
+                Ok(Event::Eof) => return Ok(None),
+                Ok(Event::Start(start)) => {
+                    self.text.clear();
+                    match start.name().as_ref() {
+                        b"Iteration" => iteration = Some(Iteration::default()),
+                        b"Hit" => hit = Some(Hit::default()),
+                        b"Hsp" => hsp = Some(Hsp::default()),
+                        _ => (),
+                    }
+                    continue;
+                }
+                Ok(Event::Text(text)) => {
+                    let text = text.unescape().map_err(|e| e.to_string());
+                    match text {
+                        Ok(text) => self.text.push_str(&text),
+                        Err(message) => return Err(self.error("", "", message)),
+                    }
+                    continue;
+                }
+                Ok(Event::CData(text)) => {
+                    self.text.push_str(&String::from_utf8_lossy(&text));
+                    continue;
+                }
+                Ok(Event::End(end)) => String::from_utf8_lossy(end.name().as_ref()).to_string(),
+                Ok(_) => continue,
+            };
+            let text = std::mem::take(&mut self.text);
+            let query = iteration
+                .as_ref()
+                .map(|i| i.query_id().to_string())
+                .unwrap_or_default();
+            let number = |text: &str| -> Result<u32, ParseError> {
+                text.trim()
+                    .parse::<u32>()
+                    .map_err(|e| self.error(&query, &format!("<{}>{}", name, text), e.to_string()))
+            };
+            let float = |text: &str| -> Result<f64, ParseError> {
+                text.trim()
+                    .parse::<f64>()
+                    .map_err(|e| self.error(&query, &format!("<{}>{}", name, text), e.to_string()))
+            };
+            let frame = |text: &str| -> Result<i8, ParseError> {
+                text.trim()
+                    .parse::<i8>()
+                    .map_err(|e| self.error(&query, &format!("<{}>{}", name, text), e.to_string()))
+            };
+            if name.starts_with("Hsp_") {
+                let Some(hsp) = hsp.as_mut() else { continue };
+                match name.as_str() {
+                    "Hsp_num" => hsp.num = number(&text)?,
+                    "Hsp_bit-score" => hsp.bit_score = float(&text)?,
+                    "Hsp_score" => hsp.score = float(&text)?,
+                    "Hsp_evalue" => hsp.evalue = float(&text)?,
+                    "Hsp_query-from" => hsp.query_from = number(&text)?,
+                    "Hsp_query-to" => hsp.query_to = number(&text)?,
+                    "Hsp_hit-from" => hsp.hit_from = number(&text)?,
+                    "Hsp_hit-to" => hsp.hit_to = number(&text)?,
+                    "Hsp_query-frame" => hsp.query_frame = frame(&text)?,
+                    "Hsp_hit-frame" => hsp.hit_frame = frame(&text)?,
+                    "Hsp_identity" => hsp.identity = number(&text)?,
+                    "Hsp_positive" => hsp.positive = number(&text)?,
+                    "Hsp_gaps" => hsp.gaps = number(&text)?,
+                    "Hsp_align-len" => hsp.align_len = number(&text)?,
+                    "Hsp_qseq" => hsp.qseq = text,
+                    "Hsp_hseq" => hsp.hseq = text,
+                    "Hsp_midline" => hsp.midline = text,
+                    _ => (),
+                }
+                continue;
+            }
+            match name.as_str() {
+                "Hsp" => {
+                    if let (Some(hit), Some(hsp)) = (hit.as_mut(), hsp.take()) {
+                        hit.hsps.push(hsp);
+                    }
+                }
+                "Hit_num" | "Hit_id" | "Hit_def" | "Hit_accession" | "Hit_len" => {
+                    if let Some(hit) = hit.as_mut() {
+                        match name.as_str() {
+                            "Hit_num" => hit.num = number(&text)?,
+                            "Hit_id" => hit.id = text,
+                            "Hit_def" => hit.def = text,
+                            "Hit_accession" => hit.accession = text,
+                            _ => hit.len = number(&text)?,
+                        }
+                    }
+                }
+                "Hit" => {
+                    if let (Some(iteration), Some(hit)) = (iteration.as_mut(), hit.take()) {
+                        iteration.hits.push(hit);
+                    }
+                }
+                "Iteration_iter-num"
+                | "Iteration_query-ID"
+                | "Iteration_query-def"
+                | "Iteration_query-len"
+                | "Iteration_message" => {
+                    if let Some(iteration) = iteration.as_mut() {
+                        match name.as_str() {
+                            "Iteration_iter-num" => iteration.iter_num = number(&text)?,
+                            "Iteration_query-ID" => iteration.query_id = text,
+                            "Iteration_query-def" => iteration.query_def = text,
+                            "Iteration_query-len" => iteration.query_len = number(&text)?,
+                            _ => iteration.message = text,
+                        }
+                    }
+                }
+                "Iteration" => {
+                    if let Some(iteration) = iteration.take() {
+                        return Ok(Some(iteration));
+                    }
+                }
+                "BlastOutput_program" => self.header.program = text,
+                "BlastOutput_version" => self.header.version = text,
+                "BlastOutput_reference" => self.header.reference = text,
+                "BlastOutput_db" => self.header.db = text,
+                "BlastOutput_query-ID" => self.header.query_id = text,
+                "BlastOutput_query-def" => self.header.query_def = text,
+                "BlastOutput_query-len" => self.header.query_len = number(&text)?,
+                parameter if parameter.starts_with("Parameters_") => self.header.parameters.push((
+                    parameter["Parameters_".len()..].to_string(),
+                    text.trim().to_string(),
+                )),
+                _ => (),
+            }
+        }
+    }
+    ///return an iterator over the iterations of the file
+    pub fn iterations(self) -> Iterations<B> {
+        Iterations {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE BlastOutput PUBLIC "-//NCBI//NCBI BlastOutput/EN" "http://www.ncbi.nlm.nih.gov/dtd/NCBI_BlastOutput.dtd">
+<BlastOutput>
+  <BlastOutput_program>blastp</BlastOutput_program>
+  <BlastOutput_version>BLASTP 2.15.0+</BlastOutput_version>
+  <BlastOutput_db>ribosomal.faa</BlastOutput_db>
+  <BlastOutput_query-ID>Query_1</BlastOutput_query-ID>
+  <BlastOutput_query-def>b3304 50S ribosomal subunit protein L18</BlastOutput_query-def>
+  <BlastOutput_query-len>12</BlastOutput_query-len>
+  <BlastOutput_param>
+    <Parameters>
+      <Parameters_matrix>BLOSUM62</Parameters_matrix>
+      <Parameters_expect>10</Parameters_expect>
+    </Parameters>
+  </BlastOutput_param>
+  <BlastOutput_iterations>
+    <Iteration>
+      <Iteration_iter-num>1</Iteration_iter-num>
+      <Iteration_query-ID>Query_1</Iteration_query-ID>
+      <Iteration_query-def>b3304 50S ribosomal subunit protein L18</Iteration_query-def>
+      <Iteration_query-len>12</Iteration_query-len>
+      <Iteration_hits>
+        <Hit>
+          <Hit_num>1</Hit_num>
+          <Hit_id>sp|P0C018|RL18_ECOLI</Hit_id>
+          <Hit_def>50S ribosomal protein L18 &amp; others</Hit_def>
+          <Hit_accession>P0C018</Hit_accession>
+          <Hit_len>117</Hit_len>
+          <Hit_hsps>
+            <Hsp>
+              <Hsp_num>1</Hsp_num>
+              <Hsp_bit-score>25.4</Hsp_bit-score>
+              <Hsp_score>54</Hsp_score>
+              <Hsp_evalue>2.5e-06</Hsp_evalue>
+              <Hsp_query-from>1</Hsp_query-from>
+              <Hsp_query-to>12</Hsp_query-to>
+              <Hsp_hit-from>1</Hsp_hit-from>
+              <Hsp_hit-to>11</Hsp_hit-to>
+              <Hsp_query-frame>0</Hsp_query-frame>
+              <Hsp_hit-frame>0</Hsp_hit-frame>
+              <Hsp_identity>10</Hsp_identity>
+              <Hsp_positive>11</Hsp_positive>
+              <Hsp_gaps>1</Hsp_gaps>
+              <Hsp_align-len>12</Hsp_align-len>
+              <Hsp_qseq>MDKKSARIRRAT</Hsp_qseq>
+              <Hsp_hseq>MDKKS-RIRRVT</Hsp_hseq>
+              <Hsp_midline>MDKKS RIRR T</Hsp_midline>
+            </Hsp>
+          </Hit_hsps>
+        </Hit>
+      </Iteration_hits>
+    </Iteration>
+    <Iteration>
+      <Iteration_iter-num>2</Iteration_iter-num>
+      <Iteration_query-ID>Query_2</Iteration_query-ID>
+      <Iteration_query-def>b3305</Iteration_query-def>
+      <Iteration_query-len>177</Iteration_query-len>
+      <Iteration_hits>
+      </Iteration_hits>
+      <Iteration_message>No hits found</Iteration_message>
+    </Iteration>
+  </BlastOutput_iterations>
+</BlastOutput>
+"#;
+
+    #[test]
+    fn test_read_blast_xml() {
+        let mut reader = Reader::new(XML.as_bytes());
+        let first = reader.read_iteration().unwrap().unwrap();
+        assert_eq!(reader.header().db, "ribosomal.faa");
+        assert_eq!(
+            reader.header().parameters,
+            vec![
+                ("matrix".to_string(), "BLOSUM62".to_string()),
+                ("expect".to_string(), "10".to_string())
+            ]
+        );
+        assert_eq!(first.query_id(), "b3304");
+        assert_eq!(first.hits[0].def, "50S ribosomal protein L18 & others");
+        let hsp = &first.hits[0].hsps[0];
+        assert_eq!(hsp.evalue, 2.5e-06);
+        assert_eq!(hsp.gaps, 1);
+        //the midline keeps its spaces
+        assert_eq!(hsp.midline, "MDKKS RIRR T");
+        assert_eq!((hsp.mismatches(), hsp.gap_opens()), (1, 1));
+        let tabular = first.tabular_hits();
+        assert_eq!(tabular[0].subject_id, "sp|P0C018|RL18_ECOLI");
+        assert_eq!(tabular[0].query_coverage(), Some(100.0));
+        let second = reader.read_iteration().unwrap().unwrap();
+        assert!(second.hits.is_empty());
+        assert_eq!(second.message, "No hits found");
+        assert!(reader.read_iteration().unwrap().is_none());
+    }
+    #[test]
+    fn test_bad_value() {
+        let text = XML.replace(
+            "<Hsp_identity>10</Hsp_identity>",
+            "<Hsp_identity>ten</Hsp_identity>",
+        );
+        let mut iterations = Reader::new(text.as_bytes()).iterations();
+        match iterations.next() {
+            Some(Err(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                line,
+                ..
+            })) => {
+                assert_eq!(line_number, 41);
+                assert_eq!(record_id, "b3304");
+                assert_eq!(line, "<Hsp_identity>ten");
+            }
+            other => panic!("expected a malformed value, got {:?}", other),
+        }
+        assert!(iterations.next().is_none());
+    }
+}
@@ -0,0 +1,175 @@
+//! # Compressed input
+//!
+//! The readers open files through ```compression::open```, which looks at the first bytes of the file and decompresses gzip, BGZF,
+//! bzip2, xz and zstd files as they are read, so ```.gbk.gz```, ```.gbff.gz``` or ```.embl.bz2``` files need no unpacking first.
+//! Each codec is a cargo feature (```gzip```, ```bzip2```, ```xz```, ```zstd```), all on by default. A file compressed with a codec
+//! that was left out gives an ```io::ErrorKind::Unsupported``` error naming the feature.
+//!
+//!```rust
+//! use microBioRust::compression::Compression;
+//!
+//! assert_eq!(Compression::detect(b"LOCUS       NC_000913"), Compression::None);
+//! assert_eq!(Compression::detect(&[0x1f, 0x8b, 0x08, 0x00]), Compression::Gzip);
+//! assert_eq!(Compression::detect(b"BZh91AY&SY"), Compression::Bzip2);
+//!```
+
+use std::{
+    fs,
+    io::{self, BufRead},
+    path::Path,
+};
+
+///the compression of a file, found from its magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    ///blocked gzip as written by bgzip and samtools, read as multi-member gzip
+    Bgzf,
+    Bzip2,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    ///the compression of data starting with these bytes, BGZF needs the first 14 bytes to be told from plain gzip
+    pub fn detect(bytes: &[u8]) -> Self {
+        match bytes {
+            [0x1f, 0x8b, 0x08, flags, ..]
+                if flags & 0x04 != 0 && bytes.get(12..14) == Some(b"BC") =>
+            {
+                Compression::Bgzf
+            }
+            [0x1f, 0x8b, ..] => Compression::Gzip,
+            [b'B', b'Z', b'h', ..] => Compression::Bzip2,
+            [0xfd, b'7', b'z', b'X', b'Z', 0x00, ..] => Compression::Xz,
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+    ///the cargo feature reading this compression
+    pub fn feature(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip | Compression::Bgzf => Some("gzip"),
+            Compression::Bzip2 => Some("bzip2"),
+            Compression::Xz => Some("xz"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+}
+
+///wraps a reader in the decoder of its compression, uncompressed data is passed through
+pub fn decompress<R: BufRead + 'static>(mut reader: R) -> io::Result<Box<dyn io::Read>> {
+    let compression = Compression::detect(reader.fill_buf()?);
+    match compression {
+        Compression::None => Ok(Box::new(reader)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip | Compression::Bgzf => {
+            Ok(Box::new(flate2::bufread::MultiGzDecoder::new(reader)))
+        }
+        #[cfg(feature = "bzip2")]
+        Compression::Bzip2 => Ok(Box::new(bzip2::bufread::MultiBzDecoder::new(reader))),
+        #[cfg(feature = "xz")]
+        Compression::Xz => Ok(Box::new(xz2::bufread::XzDecoder::new_multi_decoder(reader))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => Ok(Box::new(zstd::stream::read::Decoder::with_buffer(reader)?)),
+        #[allow(unreachable_patterns)]
+        other => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!(
+                "input is {:?} compressed, build microBioRust with the {:?} feature to read it",
+                other,
+                other.feature().unwrap_or_default()
+            ),
+        )),
+    }
+}
+
+///opens a file for reading, decompressing it if needed
+pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Box<dyn io::Read>> {
+    decompress(io::BufReader::new(fs::File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn read_all(data: Vec<u8>) -> String {
+        let mut text = String::new();
+        decompress(io::Cursor::new(data))
+            .unwrap()
+            .read_to_string(&mut text)
+            .unwrap();
+        text
+    }
+
+    #[test]
+    fn test_detect() {
+        let bgzf = [
+            0x1f, 0x8b, 0x08, 0x04, 0, 0, 0, 0, 0, 0xff, 0x06, 0, b'B', b'C', 0x02, 0,
+        ];
+        assert_eq!(Compression::detect(&bgzf), Compression::Bgzf);
+        assert_eq!(Compression::detect(&bgzf[..4]), Compression::Gzip);
+        assert_eq!(
+            Compression::detect(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]),
+            Compression::Xz
+        );
+        assert_eq!(
+            Compression::detect(&[0x28, 0xb5, 0x2f, 0xfd]),
+            Compression::Zstd
+        );
+        assert_eq!(Compression::detect(b""), Compression::None);
+        assert_eq!(read_all(b"ID   AM236082;".to_vec()), "ID   AM236082;");
+    }
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        use flate2::{write::GzEncoder, GzBuilder};
+        use std::io::Write;
+        //two members, as written by concatenating gzip files or by bgzip
+        let mut data = Vec::new();
+        for part in ["LOCUS ", "test\n"] {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(part.as_bytes()).unwrap();
+            data.extend(encoder.finish().unwrap());
+        }
+        assert_eq!(Compression::detect(&data), Compression::Gzip);
+        assert_eq!(read_all(data), "LOCUS test\n");
+        let mut encoder = GzBuilder::new()
+            .extra(vec![b'B', b'C', 2, 0, 0, 0])
+            .write(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"LOCUS bgzf\n").unwrap();
+        let data = encoder.finish().unwrap();
+        assert_eq!(Compression::detect(&data), Compression::Bgzf);
+        assert_eq!(read_all(data), "LOCUS bgzf\n");
+    }
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_bzip2() {
+        use std::io::Write;
+        let mut encoder = bzip2::write::BzEncoder::new(Vec::new(), bzip2::Compression::default());
+        encoder.write_all(b"ID   bzip2\n").unwrap();
+        let data = encoder.finish().unwrap();
+        assert_eq!(Compression::detect(&data), Compression::Bzip2);
+        assert_eq!(read_all(data), "ID   bzip2\n");
+    }
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_xz() {
+        use std::io::Write;
+        let mut encoder = xz2::write::XzEncoder::new(Vec::new(), 6);
+        encoder.write_all(b"ID   xz\n").unwrap();
+        let data = encoder.finish().unwrap();
+        assert_eq!(Compression::detect(&data), Compression::Xz);
+        assert_eq!(read_all(data), "ID   xz\n");
+    }
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd() {
+        let data = zstd::encode_all(&b"ID   zstd\n"[..], 0).unwrap();
+        assert_eq!(Compression::detect(&data), Compression::Zstd);
+        assert_eq!(read_all(data), "ID   zstd\n");
+    }
+}
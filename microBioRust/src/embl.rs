@@ -18,6 +18,7 @@
 //! 2. Features, ```FeatureAttributes```, construct(enum) of counter (locus tag), gene (if present), product, codon start, strand, start, stop [of cds/gene]
 //! 3. Sequence features, ```SequenceAttributes```, construct(enum) of counter (locus tag), sequence_ffn (DNA gene sequence) sequence_faa (protein translation), strand, codon start, start, stop [cds/gene]
 //! 4. The DNA sequence of the whole record (or contig)
+//! 5. Every other feature of the feature table (gene, rRNA, tRNA, ncRNA, repeat_region, mobile_element, misc_feature...), ```features```, a map of feature type to ```FeatureAttributeBuilder``` keyed by locus tag (or feature type and counter such as tRNA_3 where there is no locus tag)
 //!
 //!  Example to extract and print all the protein sequence fasta, example using getters (or get_ functionality), simplified embl! macro
 //!
@@ -168,7 +169,7 @@
 //!
 //! The boolean true/false describes whether the DNA sequence should be included in the GFF3 file
 //!
-//! To write into embl format requires embl_write(seq_region, record_vec, filename), no true or false since embl format will include the DNA sequence
+//! To write into embl format requires embl_write(record_vec, filename), no true or false since embl format will include the DNA sequence, or ```Writer::write_record``` to stream records
 //!
 //!
 //! ```rust
@@ -263,17 +264,23 @@
 //!```
 //!
 
-use anyhow::{anyhow, Context};
-use bio::alphabets::dna::revcomp;
+use crate::bed::Record as BedRecord;
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gff::{fasta_entry, location_parts, unique_id};
+use crate::gtf::{Transcript, RNA_FEATURES};
+use crate::location::{format_location, location_continues, parse_location};
+use crate::qualifiers::{format_qualifier, gff3_attributes, gff3_escape, QualifierLines};
+use crate::translation::{compare_translation, genetic_code_or_default, DEFAULT_TABLE};
+use anyhow::Context;
 use chrono::prelude::*;
 use lazy_static::lazy_static;
 use paste::paste;
-use protein_translate::translate;
 use regex::Regex;
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     convert::{AsRef, TryInto},
-    fs::{self, File, OpenOptions},
+    fs::{File, OpenOptions},
     io::{self, Write},
     path::Path,
     str,
@@ -282,11 +289,6 @@ use std::{
 
 // Compile regexes once at module load time for massive performance improvement
 lazy_static! {
-    /// Regex for parsing location coordinates (e.g., "1..1000")
-    /// Used in source and CDS coordinate extraction
-    static ref LOCATION_REGEX: Regex = Regex::new(r"([0-9]+)[[:punct:]]+([0-9]+)")
-        .expect("Failed to compile LOCATION_REGEX");
-
     /// Regex for cleaning odd punctuation and Greek letters from product names
     /// Replaces problematic characters like unclosed quotes, biochemical symbols
     static ref PUNCTUATION_REGEX: Regex = Regex::new(r"[/?()',`]|[α-ωΑ-Ω]")
@@ -300,22 +302,28 @@ use crate::{create_builder, create_getters};
 #[macro_export]
 macro_rules! embl {
     ($filename:expr) => {{
-        use std::fs::File;
-        use std::io::BufReader;
-        let file = File::open($filename)
-            .unwrap_or_else(|e| panic!("Could not open file {}: {}", $filename, e));
-        let mut reader = $crate::embl::Reader::new(file);
-        let mut vec = Vec::new();
-        for rec in reader.records() {
-            match rec {
-                Ok(r) => {
-                    println!("this is r {:?}", &r);
-                    vec.push(r);
-                }
-                Err(e) => panic!("Error reading record: {:?}", e),
-            }
+        match $crate::try_embl!($filename) {
+            Ok(records) => records,
+            Err(e) => panic!("Error reading record: {}", e),
+        }
+    }};
+}
+
+///reads every record of a EMBL file, returning the first ```ParseError``` instead of panicking
+#[macro_export]
+macro_rules! try_embl {
+    ($filename:expr) => {{
+        match $crate::compression::open($filename) {
+            Ok(file) => $crate::embl::Reader::new(file)
+                .records()
+                .collect::<Result<Vec<$crate::embl::Record>, $crate::error::ParseError>>(),
+            Err(source) => Err($crate::error::ParseError::Io {
+                line_number: 0,
+                record_id: String::new(),
+                line: String::new(),
+                source,
+            }),
         }
-        vec
     }};
 }
 
@@ -348,11 +356,10 @@ impl<B> Iterator for Records<B>
 where
     B: io::BufRead,
 {
-    type Item = Result<Record, anyhow::Error>;
+    type Item = Result<Record, ParseError>;
 
-    fn next(&mut self) -> Option<Result<Record, anyhow::Error>> {
+    fn next(&mut self) -> Option<Self::Item> {
         if self.error_has_occurred {
-            println!("error was encountered in iteration");
             None
         } else {
             let mut record = Record::new();
@@ -365,9 +372,8 @@ where
                     }
                 }
                 Err(err) => {
-                    //println!("we encountered an error {:?}", &err);
                     self.error_has_occurred = true;
-                    Some(Err(anyhow!("next record read error {:?}", err)))
+                    Some(Err(err))
                 }
             }
         }
@@ -375,7 +381,7 @@ where
 }
 
 pub trait EmblRead {
-    fn read(&mut self, record: &mut Record) -> Result<Record, anyhow::Error>;
+    fn read(&mut self, record: &mut Record) -> Result<Record, ParseError>;
 }
 
 ///per line reader for the file
@@ -383,12 +389,15 @@ pub trait EmblRead {
 pub struct Reader<B> {
     reader: B,
     line_buffer: String,
+    line_number: usize,
+    validate: bool,
+    prefer_annotated: bool,
 }
 
-impl Reader<io::BufReader<fs::File>> {
-    /// Read Embl from given file path in given format.
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read Embl from given file path, decompressing gzip, BGZF, bzip2, xz or zstd files in given format.
     pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
-        fs::File::open(&path)
+        open(&path)
             .map(Reader::new)
             .with_context(|| format!("Failed to read Embl from {:#?}", path))
     }
@@ -403,6 +412,9 @@ where
         Reader {
             reader: io::BufReader::new(reader),
             line_buffer: String::new(),
+            line_number: 0,
+            validate: false,
+            prefer_annotated: false,
         }
     }
 }
@@ -415,8 +427,37 @@ where
         Reader {
             reader: bufreader,
             line_buffer: String::new(),
+            line_number: 0,
+            validate: false,
+            prefer_annotated: false,
         }
     }
+    ///compares the protein translated from each CDS with its /translation, differences are kept in ```Record::translation_issues``` by locus tag
+    pub fn validate_translations(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+    ///fills ```SequenceFaa``` with the annotated /translation of a CDS when it has one, rather than the computed protein
+    pub fn prefer_annotated_translation(mut self, prefer_annotated: bool) -> Self {
+        self.prefer_annotated = prefer_annotated;
+        self
+    }
+    //reads the next line into the line buffer, counting the lines for the error messages
+    fn next_line(&mut self, record_id: &str) -> Result<usize, ParseError> {
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: record_id.to_string(),
+                line: String::new(),
+                source,
+            })?;
+        if read > 0 {
+            self.line_number += 1;
+        }
+        Ok(read)
+    }
     //return an iterator over the records of the genbank file
     pub fn records(self) -> Records<B> {
         Records {
@@ -434,7 +475,7 @@ where
     #[allow(unused_mut)]
     #[allow(unused_variables)]
     #[allow(unused_assignments)]
-    fn read(&mut self, record: &mut Record) -> Result<Record, anyhow::Error> {
+    fn read(&mut self, record: &mut Record) -> Result<Record, ParseError> {
         record.rec_clear();
         //println!("reading new record");
         //initialise variables with capacity hints for better performance
@@ -442,7 +483,6 @@ where
         let mut source_map = SourceAttributeBuilder::new();
         let mut cds = FeatureAttributeBuilder::new();
         let mut seq_features = SequenceAttributeBuilder::new();
-        let mut cds_counter: i32 = 0;
         let mut source_counter: i32 = 0;
         let mut prev_end: u32 = 0;
         let mut organism = String::with_capacity(64);
@@ -450,12 +490,12 @@ where
         let mut strain = String::with_capacity(64);
         let mut source_name = String::with_capacity(32);
         let mut type_material = String::with_capacity(64);
-        let mut theend: u32 = 0;
-        let mut thestart: u32 = 0;
         let mut db_xref = String::with_capacity(32);
+        let mut first_source = String::new();
+        let mut feature_counter: BTreeMap<String, i32> = BTreeMap::new();
         //check if there are any more lines, if not return the record as is
         if self.line_buffer.is_empty() {
-            self.reader.read_line(&mut self.line_buffer)?;
+            self.next_line(&record.id)?;
             if self.line_buffer.is_empty() {
                 return Ok(record.to_owned());
             }
@@ -473,7 +513,7 @@ where
                 header_iter.next();
                 record.id = header_iter
                     .next()
-                    .ok_or_else(|| anyhow::anyhow!("missing record id"))? // Get &str or error
+                    .ok_or_else(|| missing_locus(self.line_number, &record.id, &self.line_buffer))?
                     .to_string();
                 if record.id.ends_with(";") {
                     record.id.pop();
@@ -488,176 +528,205 @@ where
                 header_iter.next();
                 let lens = header_iter
                     .next()
-                    .ok_or_else(|| anyhow::anyhow!("missing record length"))? // Get &str or error
+                    .ok_or_else(|| missing_locus(self.line_number, &record.id, &self.line_buffer))?
                     .to_string();
                 //println!("just before length {:?}", &lens);
-                record.length = lens.trim().parse::<u32>()?;
-                self.line_buffer.clear();
+                record.length = lens
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| missing_locus(self.line_number, &record.id, &self.line_buffer))?;
+                record.header = RecordHeader::from_id_line(&self.line_buffer);
+                record.topology = Topology::from_locus_line(&self.line_buffer);
+                //collect the header lines up to the feature table
+                let mut header_lines: Vec<String> = Vec::new();
+                loop {
+                    self.line_buffer.clear();
+                    self.next_line(&record.id)?;
+                    if self.line_buffer.is_empty()
+                        || self.line_buffer.starts_with("FH")
+                        || self.line_buffer.starts_with("FT")
+                        || self.line_buffer.starts_with("SQ")
+                        || self.line_buffer.starts_with("//")
+                    {
+                        break;
+                    }
+                    header_lines.push(self.line_buffer.trim_end().to_string());
+                }
+                record.header.parse_embl_lines(&header_lines);
+            }
+            //a feature table or sequence without a ID line above it
+            if record.id.is_empty()
+                && (feature_key(&self.line_buffer).is_some() || self.line_buffer.starts_with("SQ"))
+            {
+                return Err(missing_locus(
+                    self.line_number,
+                    &record.id,
+                    &self.line_buffer,
+                ));
             }
             //collect the source fields and populate the source_map and source_attributes
-            if self.line_buffer.starts_with("FT   source") {
-                // Use pre-compiled regex from lazy_static for 10-50x performance improvement
-                let location = LOCATION_REGEX
-                    .captures(&self.line_buffer)
-                    .ok_or_else(|| anyhow::anyhow!("missing location"))?;
-                let start = &location[1];
-                let end = &location[2];
-                thestart = start.trim().parse::<u32>()?;
+            if feature_key(&self.line_buffer) == Some("source") {
+                //the location follows the feature key and can continue over several lines
+                let mut location_text = self.line_buffer[2..].trim_start()["source".len()..]
+                    .trim()
+                    .to_string();
+                let feature_line = self.line_number;
                 source_counter += 1;
                 source_name = format!("source_{}_{}", record.id, source_counter).to_string();
-                thestart += prev_end;
-                theend = end.trim().parse::<u32>()? + prev_end;
-                //println!("so the start and end are {:?} {:?}", &thestart, &theend);
+                let mut qualifier_lines = QualifierLines::new();
                 loop {
                     self.line_buffer.clear();
-                    self.reader.read_line(&mut self.line_buffer)?;
-                    if self.line_buffer.starts_with("FT   CDS") {
+                    self.next_line(&record.id)?;
+                    //the source ends at the next feature key or at the end of the feature table
+                    if feature_key(&self.line_buffer).is_some()
+                        || !self.line_buffer.starts_with("FT ")
+                    {
+                        let location = parse_location(&location_text).map_err(|e| {
+                            ParseError::BadLocation {
+                                line_number: feature_line,
+                                record_id: record.id.clone(),
+                                line: location_text.clone(),
+                                message: e.to_string(),
+                            }
+                        })?;
+                        let (start, stop) = record.bounds(&location);
+                        let qualifiers = qualifier_lines.finish();
+                        organism = qualifiers.get("organism").unwrap_or_default().to_string();
+                        mol_type = qualifiers.get("mol_type").unwrap_or_default().to_string();
+                        strain = qualifiers.get("strain").unwrap_or_default().to_string();
+                        type_material = qualifiers
+                            .get("type_material")
+                            .unwrap_or_default()
+                            .to_string();
+                        db_xref = qualifiers.get("db_xref").unwrap_or_default().to_string();
                         //println!("this source name {:?} start {:?} end {:?} organism {:?} mol_type {:?} strain {:?} type_material {:?} db_xref {:?}", &source_name,&thestart, &theend, &organism, &mol_type, &strain, &type_material, &db_xref);
+                        if first_source.is_empty() {
+                            first_source = source_name.clone();
+                        }
                         record
                             .source_map
                             .set_counter(source_name.to_string())
-                            .set_start(RangeValue::Exact(thestart))
-                            .set_stop(RangeValue::Exact(theend))
+                            .set_start(start)
+                            .set_stop(stop)
                             .set_organism(organism.clone())
                             .set_mol_type(mol_type.clone())
                             .set_strain(strain.clone())
                             // culture_collection.clone()
                             .set_type_material(type_material.clone())
-                            .set_db_xref(db_xref.clone());
+                            .set_db_xref(db_xref.clone())
+                            .set_qualifiers(qualifiers);
                         continue 'outer;
                     }
-                    if self.line_buffer.contains("/organism") {
-                        let org: Vec<&str> = self.line_buffer.split('\"').collect();
-                        organism = org[1].to_string();
-                    }
-                    if self.line_buffer.contains("/mol_type") {
-                        let mol: Vec<&str> = self.line_buffer.split('\"').collect();
-                        mol_type = mol[1].to_string();
-                    }
-                    if self.line_buffer.contains("/strain") {
-                        let stra: Vec<&str> = self.line_buffer.split('\"').collect();
-                        strain = stra[1].to_string();
-                    }
-                    //    if self.line_buffer.contains("/culture_collection") {
-                    //        let cc: Vec<&str> = self.line_buffer.split('\"').collect();
-                    //		culture_collection = cc[1].to_string();
-                    //		}
-                    if self.line_buffer.contains("/type_material") {
-                        let mat: Vec<&str> = self.line_buffer.split('\"').collect();
-                        type_material = mat[1].to_string();
-                    }
-                    if self.line_buffer.contains("/db_xref") {
-                        let db: Vec<&str> = self.line_buffer.split('\"').collect();
-                        db_xref = db[1].to_string();
+                    if location_continues(&location_text) {
+                        location_text.push_str(self.line_buffer[2..].trim());
+                        continue;
                     }
+                    //every source qualifier is kept (organism, mol_type, strain, plasmid, country...)
+                    qualifier_lines.push_line(&self.line_buffer[2..]);
                 }
             }
-            //populate the FeatureAttributes and the coding sequence annotation
-            if self.line_buffer.starts_with("FT   CDS") {
-                let mut startiter: Vec<_> = Vec::new();
-                let mut enditer: Vec<_> = Vec::new();
-                let mut thestart: u32 = 0;
-                let mut thend: u32 = 0;
-                let mut joined: bool = false;
-                //gather the feature coordinates
-                let joined = self.line_buffer.contains("join");
-                // Use pre-compiled regex from lazy_static for 10-50x performance improvement
-                //let matches: Vec<&regex::Captures> = LOCATION_REGEX.captures_iter(&self.line_buffer).collect();
-                for cap in LOCATION_REGEX.captures_iter(&self.line_buffer) {
-                    cds_counter += 1;
-                    thestart = cap[1]
-                        .parse()
-                        .expect("failed to match and parse numerical start");
-                    theend = cap[2]
-                        .parse()
-                        .expect("failed to match and parse numerical end");
-                    startiter.push(thestart);
-                    enditer.push(theend);
-                }
-                let mut gene = String::new();
-                let mut product = String::new();
-                let strand: i8 = if self.line_buffer.contains("complement") {
-                    -1
-                } else {
-                    1
-                };
-                let mut locus_tag = String::new();
-                let mut codon_start: u8 = 1;
+            //populate the FeatureAttributes for the coding sequences (CDS) and every other feature type in the feature table (gene, rRNA, tRNA, mobile_element...)
+            if let Some(key) = feature_key(&self.line_buffer) {
+                let feature_type = key.to_string();
+                let feature_count = feature_counter.entry(feature_type.clone()).or_insert(0);
+                *feature_count += 1;
+                let feature_count = *feature_count;
+                let feature_line = self.line_number;
+                //the location follows the feature key and can continue over several lines
+                let mut location_text = self.line_buffer[2..].trim_start()[feature_type.len()..]
+                    .trim()
+                    .to_string();
+                let mut qualifier_lines = QualifierLines::new();
                 //loop to populate the feature attributes, when complete it calls to the outer loop directly to prevent reading a new line into self.line_buffer
                 loop {
                     self.line_buffer.clear();
-                    self.reader.read_line(&mut self.line_buffer)?;
-                    if self.line_buffer.contains("/locus_tag=") {
-                        let loctag: Vec<&str> = self.line_buffer.split('\"').collect();
-                        locus_tag = loctag[1].to_string();
-                        //println!("designated locus tag {:?}", &locus_tag);
-                    }
-                    if self.line_buffer.contains("/codon_start") {
-                        let codstart: Vec<&str> = self.line_buffer.split('=').collect();
-                        let valstart = codstart[1].trim().parse::<u8>()?;
-                        codon_start = valstart;
-                        //println!("designated codon start {:?} {:?}", &codon_start, &locus_tag);
-                    }
-                    if self.line_buffer.contains("/gene=") {
-                        let gen: Vec<&str> = self.line_buffer.split('\"').collect();
-                        gene = gen[1].to_string();
-                        //println!("gene designated {:?} {:?}", &gene, &locus_tag);
-                    }
-                    if self.line_buffer.contains("/product") {
-                        let prod: Vec<&str> = self.line_buffer.split('\"').collect();
-                        product = substitute_odd_punctuation(prod[1].to_string())?;
-                        //println!("designated product {:?} {:?}", &product, &locus_tag);
-                    }
-                    if self.line_buffer.starts_with("FT   CDS")
-                        || self.line_buffer.starts_with("SQ   Sequence")
-                        || self.line_buffer.starts_with("FT   intron")
-                        || self.line_buffer.starts_with("FT   exon")
-                        || self.line_buffer.starts_with("     misc_feature")
+                    self.next_line(&record.id)?;
+                    //the feature ends at the next feature key or at the end of the FEATURES table (XX, SQ)
+                    if feature_key(&self.line_buffer).is_some()
+                        || !self.line_buffer.starts_with("FT ")
                     {
+                        let qualifiers = qualifier_lines.finish();
+                        let mut locus_tag = qualifiers.locus_tag().unwrap_or_default().to_string();
+                        let gene = qualifiers.gene().unwrap_or_default().to_string();
+                        let product = substitute_odd_punctuation(
+                            qualifiers.product().unwrap_or_default().to_string(),
+                        )
+                        .map_err(|e| ParseError::BadQualifier {
+                            line_number: feature_line,
+                            record_id: record.id.clone(),
+                            line: format!("/product={:?}", qualifiers.product()),
+                            message: e.to_string(),
+                        })?;
+                        let codon_start = match qualifiers.get("codon_start") {
+                            Some(value) => value.trim().parse::<u8>().map_err(|e| {
+                                ParseError::BadQualifier {
+                                    line_number: feature_line,
+                                    record_id: record.id.clone(),
+                                    line: format!("/codon_start={}", value),
+                                    message: e.to_string(),
+                                }
+                            })?,
+                            None => 1,
+                        };
+                        let location = parse_location(&location_text).map_err(|e| {
+                            ParseError::BadLocation {
+                                line_number: feature_line,
+                                record_id: record.id.clone(),
+                                line: location_text.clone(),
+                                message: e.to_string(),
+                            }
+                        })?;
+                        let (start, stop) = record.bounds(&location);
                         if locus_tag.is_empty() {
-                            locus_tag = format!("CDS_{}", cds_counter).to_string();
+                            locus_tag = format!("{}_{}", feature_type, feature_count).to_string();
                         }
-                        if joined {
-                            //println!("currently the start is {:?} and the stop is {:?}", &startiter, &enditer);
-                            for (i, m) in startiter.iter().enumerate() {
-                                let loc_tag = format!("{}_{}", locus_tag.clone(), i);
-                                //check we may need to add or subtract one to m
-                                record
-                                    .cds
-                                    .set_counter(loc_tag)
-                                    .set_start(RangeValue::Exact(*m))
-                                    .set_stop(RangeValue::Exact(enditer[i]))
-                                    .set_gene(gene.to_string())
-                                    .set_product(product.to_string())
-                                    .set_codon_start(codon_start)
-                                    .set_strand(strand);
-                            }
-                            continue 'outer;
-                        } else {
-                            record
-                                .cds
-                                .set_counter(locus_tag.clone())
-                                .set_start(RangeValue::Exact(thestart))
-                                .set_stop(RangeValue::Exact(theend))
-                                .set_gene(gene.to_string())
-                                .set_product(product.to_string())
-                                .set_codon_start(codon_start)
-                                .set_strand(strand);
-                            continue 'outer;
+                        let features = record.features_mut(&feature_type);
+                        //features such as repeated misc_feature lines can share a locus tag, keep each one under its own key
+                        if features.attributes.contains_key(&locus_tag) {
+                            locus_tag = format!("{}_{}", locus_tag, feature_count).to_string();
                         }
+                        //joined features are kept whole, the parts are available from the location
+                        features
+                            .set_counter(locus_tag.clone())
+                            .set_start(start)
+                            .set_stop(stop)
+                            .set_gene(gene.to_string())
+                            .set_product(product.to_string())
+                            .set_strand(location.strand())
+                            .set_location(location)
+                            .set_qualifiers(qualifiers);
+                        if feature_type == "CDS" {
+                            features.set_codon_start(codon_start);
+                        }
+                        continue 'outer;
+                    }
+                    if location_continues(&location_text) {
+                        location_text.push_str(self.line_buffer[2..].trim());
+                        continue;
                     }
+                    //keep every qualifier in file order, values can continue over several FT lines
+                    qualifier_lines.push_line(&self.line_buffer[2..]);
                 }
             }
             //check if we have reached the DNA sequence section and populate the record sequences field if so.  Returns the record on finding end of record mark
             if self.line_buffer.starts_with("SQ   Sequence") {
                 //println!("we have reached the sequence");
                 let mut sequences = String::new();
+                let mut last_line = self.line_buffer.trim_end().to_string();
                 let result_seq = loop {
                     self.line_buffer.clear();
-                    self.reader.read_line(&mut self.line_buffer)?;
+                    self.next_line(&record.id)?;
                     if self.line_buffer.starts_with("//") {
                         break sequences;
+                    } else if self.line_buffer.is_empty() {
+                        //the file ends before the end of record mark
+                        return Err(ParseError::TruncatedSequence {
+                            line_number: self.line_number,
+                            record_id: record.id.clone(),
+                            line: last_line,
+                        });
                     } else {
+                        last_line = self.line_buffer.trim_end().to_string();
                         let s: Vec<&str> = self.line_buffer.split_whitespace().collect();
                         let sequence = if s.len() > 1 {
                             s[0..s.len() - 1].join("")
@@ -668,7 +737,18 @@ where
                     }
                 };
                 record.sequence = result_seq.to_string();
+                if (record.sequence.len() as u64) < record.length as u64 {
+                    return Err(ParseError::TruncatedSequence {
+                        line_number: self.line_number,
+                        record_id: record.id.clone(),
+                        line: last_line,
+                    });
+                }
                 //println!("this is record sequence {:?}", &record.sequence);
+                //a record can hold several source features, the first one spans the record
+                if !first_source.is_empty() {
+                    record.source_map.set_source_name(first_source.clone());
+                }
                 let mut iterablecount: u32 = 0;
                 //Fields are completed and populated for the FeatureAttributes, collect and populate the SequenceAttributes fields
                 for (key, val) in record.cds.iter_sorted() {
@@ -708,82 +788,74 @@ where
                             _ => (),
                         }
                     }
-                    let sta = a.map(|o| o as usize).ok_or(anyhow!("No value for start"))?;
-                    let sto = b.map(|t| t as usize).ok_or(anyhow!("No value for stop"))? - 1;
-                    let stra = c.map(|u| u).ok_or(anyhow!("No value for strand"))?;
-                    let cod = d
-                        .map(|v| v as usize - 1)
-                        .ok_or(anyhow!("No value for strand"))?;
-
-                    let star = sta.try_into()?;
-                    let stow = sto.try_into()?;
-                    let codd = cod.try_into()?;
-                    let mut sliced_sequence: &str = "";
-                    //collects the DNA sequence and translations on the correct strand
-                    if stra == -1 {
-                        if cod > 1 {
-                            println!(
-                                "reverse strand coding start more than one {:?}",
-                                &iterablecount
-                            );
-                            if sto < record.sequence.len() {
-                                sliced_sequence = &record.sequence[sta + cod..sto + 1];
-                            } else {
-                                sliced_sequence = &record.sequence[sta + cod..sto];
-                            }
-                        } else {
-                            println!("record sta {:?} sto {:?} cod {:?} stra {:?} record.seq length {:?}", &sta, &sto, &cod, &stra, &record.sequence.len());
-                            println!(
-                                "sliced sta {:?} sliced sto {:?} record.id {:?}",
-                                sta, sto, &record.id
-                            );
-                            println!(
-                                "iterable count is {:?} reverse strand codon start one",
-                                &iterablecount
-                            );
-                            println!("this is the sequence len {:?}", &record.sequence.len());
-                            if sto < record.sequence.len() {
-                                sliced_sequence = &record.sequence[sta..sto + 1];
-                            } else {
-                                sliced_sequence = &record.sequence[sta..sto];
-                            }
-                            println!("iterable count after is {:?}", &iterablecount);
-                        }
-                        let cds_char = sliced_sequence;
-                        let prot_seq = translate(&revcomp(cds_char.as_bytes()));
-                        let parts: Vec<&str> = prot_seq.split('*').collect();
-                        println!("this is the prot_seq {:?}", &prot_seq);
+                    let bad_location = |message: &str| ParseError::BadLocation {
+                        line_number: self.line_number,
+                        record_id: record.id.clone(),
+                        line: key.to_string(),
+                        message: message.to_string(),
+                    };
+                    let star = a.ok_or(bad_location("no value for start"))?;
+                    let stow = b
+                        .ok_or(bad_location("no value for stop"))?
+                        .saturating_sub(1);
+                    let stra = c.ok_or(bad_location("no value for strand"))?;
+                    let codd = d
+                        .map(|v| v.saturating_sub(1))
+                        .ok_or(bad_location("no value for codon start"))?;
+                    let cod = codd as usize;
+                    let location = match record.cds.get_location(key) {
+                        Some(value) => value.clone(),
+                        None => Location::span(
+                            &RangeValue::Exact(star),
+                            &RangeValue::Exact(stow + 1),
+                            stra,
+                        ),
+                    };
+                    //collects the DNA sequence of the parts in biological order on the coding strand, joined features are spliced before translation
+                    let cds_char = location.extract(&record.sequence);
+                    //the table comes from /transl_table, an alternative start codon is read as Met unless the 5' end is partial
+                    let table = record
+                        .cds
+                        .get_qualifiers(key)
+                        .and_then(|q| q.transl_table())
+                        .unwrap_or(DEFAULT_TABLE);
+                    let (code, unknown_table) = genetic_code_or_default(table);
+                    if let Some(issue) = unknown_table {
                         record
-                            .seq_features
-                            .set_counter(key.to_string())
-                            .set_start(RangeValue::Exact(star))
-                            .set_stop(RangeValue::Exact(stow))
-                            .set_sequence_ffn(cds_char.to_string())
-                            .set_sequence_faa(parts[0].to_string())
-                            .set_codon_start(codd)
-                            .set_strand(stra);
-                    } else {
-                        if cod > 1 {
-                            //println!("forward strand codon value more than one cnt {:?}", &iterablecount);
-                            sliced_sequence = &record.sequence[sta + cod - 1..sto];
-                        } else {
-                            //println!("forward strand codon value one cnt {:?} start {:?} {:?} {:?}", &iterablecount, &sta, &sto, &record.sequence.len());
-                            sliced_sequence = &record.sequence[sta - 1..sto];
+                            .translation_issues
+                            .entry(key.to_string())
+                            .or_default()
+                            .push(issue);
+                    }
+                    let prot_seq = code.translate_cds(
+                        cds_char.get(cod..).unwrap_or_default(),
+                        cod == 0 && !location.partial_start(),
+                    );
+                    let parts: Vec<&str> = prot_seq.split('*').collect();
+                    let annotated = record.cds.get_qualifiers(key).and_then(|q| q.translation());
+                    if let (true, Some(annotated)) = (self.validate, annotated) {
+                        let issues = compare_translation(&prot_seq, annotated);
+                        if !issues.is_empty() {
+                            record
+                                .translation_issues
+                                .entry(key.to_string())
+                                .or_default()
+                                .extend(issues);
                         }
-                        let cds_char = sliced_sequence;
-                        let prot_seq = translate(cds_char.as_bytes());
-                        let parts: Vec<&str> = prot_seq.split('*').collect();
-                        //println!("this is on parts {:?}", &parts);
-                        record
-                            .seq_features
-                            .set_counter(key.to_string())
-                            .set_start(RangeValue::Exact(star))
-                            .set_stop(RangeValue::Exact(stow))
-                            .set_sequence_ffn(cds_char.to_string())
-                            .set_sequence_faa(parts[0].to_string())
-                            .set_codon_start(codd)
-                            .set_strand(stra);
                     }
+                    let faa = match (self.prefer_annotated, annotated) {
+                        (true, Some(annotated)) => annotated,
+                        _ => parts[0],
+                    };
+                    record
+                        .seq_features
+                        .set_counter(key.to_string())
+                        .set_start(RangeValue::Exact(star))
+                        .set_stop(RangeValue::Exact(stow))
+                        .set_sequence_ffn(cds_char.to_string())
+                        .set_sequence_faa(faa.to_string())
+                        .set_codon_start(codd)
+                        .set_strand(stra);
                 }
                 //return the record when completed
                 //println!("record seq features {:?}", &record.seq_features);
@@ -791,13 +863,17 @@ where
             }
             //clear the line buffer and read the next to continue back to the outer loop
             self.line_buffer.clear();
-            self.reader.read_line(&mut self.line_buffer)?;
+            self.next_line(&record.id)?;
         }
         Ok(record.to_owned())
     }
 }
 
+pub use crate::header::{RecordHeader, Reference, Topology};
+pub use crate::location::Location;
+pub use crate::qualifiers::Qualifiers;
 pub use crate::record::RangeValue;
+pub use crate::translation::TranslationIssue;
 
 ///stores the details of the source features in genbank (contigs)
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
@@ -810,6 +886,7 @@ pub enum SourceAttributes {
     CultureCollection { value: String },
     TypeMaterial { value: String },
     DbXref { value: String },
+    Qualifiers { value: Qualifiers },
 }
 
 //macro for creating the getters
@@ -824,7 +901,8 @@ create_getters!(
     Strain { value: String },
     // CultureCollection { value: String},
     TypeMaterial { value: String },
-    DbXref { value: String }
+    DbXref { value: String },
+    Qualifiers { value: Qualifiers }
 );
 
 ///builder for the source information on a per record basis
@@ -857,6 +935,29 @@ impl SourceAttributeBuilder {
     pub fn get_source_attributes(&self, key: &str) -> Option<&HashSet<SourceAttributes>> {
         self.source_attributes.get(key)
     }
+
+    // Method to collect the qualifiers of a source, records made with the builder fall back to the organism, mol_type, strain, type_material and db_xref fields
+    pub fn source_qualifiers(&self, key: &str) -> Qualifiers {
+        if let Some(qualifiers) = self.get_qualifiers(key) {
+            if !qualifiers.is_empty() {
+                return qualifiers.clone();
+            }
+        }
+        let mut qualifiers = Qualifiers::new();
+        let fields = [
+            ("organism", self.get_organism(key)),
+            ("mol_type", self.get_mol_type(key)),
+            ("strain", self.get_strain(key)),
+            ("type_material", self.get_type_material(key)),
+            ("db_xref", self.get_db_xref(key)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                qualifiers.push(name, Some(value.to_string()));
+            }
+        }
+        qualifiers
+    }
 }
 
 create_builder!(
@@ -871,7 +972,8 @@ create_builder!(
     Strain { value: String },
     // CultureCollection { value: String},
     TypeMaterial { value: String },
-    DbXref { value: String }
+    DbXref { value: String },
+    Qualifiers { value: Qualifiers }
 );
 
 ///attributes for each feature, cds or gene
@@ -883,7 +985,8 @@ pub enum FeatureAttributes {
     Product { value: String },
     CodonStart { value: u8 },
     Strand { value: i8 },
-    //   ec_number { value: String }
+    Location { value: Location },
+    Qualifiers { value: Qualifiers },
 }
 
 create_getters!(
@@ -895,7 +998,9 @@ create_getters!(
     Gene { value: String },
     Product { value: String },
     CodonStart { value: u8 },
-    Strand { value: i8 }
+    Strand { value: i8 },
+    Location { value: Location },
+    Qualifiers { value: Qualifiers }
 );
 
 ///builder for the feature information on a per coding sequence (CDS) basis
@@ -915,9 +1020,44 @@ create_builder!(
     Gene { value: String },
     Product { value: String },
     CodonStart { value: u8 },
-    Strand { value: i8 }
+    Strand { value: i8 },
+    Location { value: Location },
+    Qualifiers { value: Qualifiers }
 );
 
+impl FeatureAttributeBuilder {
+    ///collects the qualifiers of a feature, records made with the builder fall back to the locus_tag, codon_start, gene and product fields
+    pub fn feature_qualifiers(&self, feature_type: &str, locus_tag: &str) -> Qualifiers {
+        if let Some(qualifiers) = self.get_qualifiers(locus_tag) {
+            if !qualifiers.is_empty() {
+                return qualifiers.clone();
+            }
+        }
+        let mut qualifiers = Qualifiers::new();
+        if !is_generated_key(feature_type, locus_tag) {
+            qualifiers.push("locus_tag", Some(locus_tag.to_string()));
+        }
+        if let Some(codon_start) = self.get_codon_start(locus_tag) {
+            qualifiers.push("codon_start", Some(codon_start.to_string()));
+        }
+        if let Some(gene) = self.get_gene(locus_tag) {
+            if !gene.is_empty() && gene != "unknown" {
+                qualifiers.push("gene", Some(gene.to_string()));
+            }
+        }
+        match self.get_product(locus_tag) {
+            Some(product) if !product.is_empty() => {
+                qualifiers.push("product", Some(product.to_string()))
+            }
+            None if feature_type == "CDS" => {
+                qualifiers.push("product", Some("unknown product".to_string()))
+            }
+            _ => (),
+        }
+        qualifiers
+    }
+}
+
 ///stores the sequences of the coding sequences (genes) and proteins. Also stores start, stop, codon_start and strand information
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum SequenceAttributes {
@@ -961,6 +1101,24 @@ create_builder!(
     Strand { value: i8 }
 );
 
+///returns the feature key (source, gene, CDS, rRNA, tRNA, mobile_element...) when the FT line opens a new feature in the feature table
+///feature keys start at column 6, qualifiers and continuation lines are indented to column 22
+pub fn feature_key(line: &str) -> Option<&str> {
+    match line.strip_prefix("FT   ") {
+        Some(rest) if !rest.starts_with(' ') => rest.split_whitespace().next(),
+        _ => None,
+    }
+}
+
+//the error for a ID line that cannot be read, or for a record without one
+fn missing_locus(line_number: usize, record_id: &str, line: &str) -> ParseError {
+    ParseError::MissingLocus {
+        line_number,
+        record_id: record_id.to_string(),
+        line: line.trim_end().to_string(),
+    }
+}
+
 ///product lines can contain difficult to parse punctuation such as biochemical symbols like unclosed single quotes, superscripts, single and double brackets etc.
 ///here we substitute these for an underscore
 pub fn substitute_odd_punctuation(input: String) -> Result<String, anyhow::Error> {
@@ -980,10 +1138,12 @@ pub struct GFFInner {
     locus_tag: String,
     gene: String,
     // Inference: String,
-    // Parent: String,
     //   db_xref: String,
     product: String,
+    ///the ID of the parent feature, e.g. the gene of a CDS
+    parent: String,
     // is_circular: bool,
+    qualifiers: Qualifiers,
 }
 
 impl GFFInner {
@@ -993,7 +1153,6 @@ impl GFFInner {
         locus_tag: String,
         gene: String,
         //   Inference: String,
-        //    Parent: String,
         //    db_xref: String,
         product: String,
     ) -> Self {
@@ -1003,8 +1162,20 @@ impl GFFInner {
             locus_tag,
             gene,
             product,
+            parent: String::new(),
+            qualifiers: Qualifiers::new(),
         }
     }
+    ///links the feature to its parent, written as the Parent attribute
+    pub fn set_parent(&mut self, parent: String) -> &mut Self {
+        self.parent = parent;
+        self
+    }
+    ///adds the remaining qualifiers of the feature (db_xref, note, protein_id...) to field9
+    pub fn set_qualifiers(&mut self, qualifiers: Qualifiers) -> &mut Self {
+        self.qualifiers = qualifiers;
+        self
+    }
 }
 
 ///The main GFF3 construct
@@ -1015,13 +1186,15 @@ pub struct GFFOuter<'a> {
     type_val: String,
     start: u32,
     end: u32,
-    score: f64,
+    score: Option<f64>,
     strand: String,
-    phase: u8,
+    ///the phase of a CDS line, other features have none
+    phase: Option<u8>,
     attributes: &'a GFFInner,
 }
 
 impl<'a> GFFOuter<'a> {
+    ///a line with a score and phase, a score of 0 is written as '.' as is the phase of any feature other than a CDS
     pub fn new(
         seqid: String,
         source: String,
@@ -1033,6 +1206,8 @@ impl<'a> GFFOuter<'a> {
         phase: u8,
         attributes: &'a GFFInner,
     ) -> Self {
+        let score = if score == 0.0 { None } else { Some(score) };
+        let phase = if type_val == "CDS" { Some(phase) } else { None };
         GFFOuter {
             seqid,
             source,
@@ -1045,67 +1220,76 @@ impl<'a> GFFOuter<'a> {
             attributes,
         }
     }
+    ///sets the score, None is written as '.'
+    pub fn set_score(&mut self, score: Option<f64>) -> &mut Self {
+        self.score = score;
+        self
+    }
+    ///sets the phase, None is written as '.'
+    pub fn set_phase(&mut self, phase: Option<u8>) -> &mut Self {
+        self.phase = phase;
+        self
+    }
+    ///the column 9 attributes, the reserved ID, Name and Parent tags lead and every value is escaped
     pub fn field9_attributes_build(&self) -> String {
         let mut full_field9 = Vec::new();
-        if !self.attributes.id.is_empty() {
-            full_field9.push(format!("id={}", self.attributes.id));
-        }
-        if !self.attributes.name.is_empty() {
-            full_field9.push(format!("name={}", self.attributes.name));
-        }
-        if !self.attributes.gene.is_empty() {
-            full_field9.push(format!("gene={}", self.attributes.gene));
-        }
-        //    if !self.attributes.Inference.is_empty() {
-        //       full_field9.push(format!("inference={}",self.attributes.Inference));
-        //	  }
-        if !self.attributes.locus_tag.is_empty() {
-            full_field9.push(format!("locus_tag={}", self.attributes.locus_tag));
-        }
-        if !self.attributes.product.is_empty() {
-            full_field9.push(format!("product={}", self.attributes.product));
+        for (tag, value) in [
+            ("ID", &self.attributes.id),
+            ("Name", &self.attributes.name),
+            ("Parent", &self.attributes.parent),
+            ("gene", &self.attributes.gene),
+            ("locus_tag", &self.attributes.locus_tag),
+            ("product", &self.attributes.product),
+        ] {
+            if !value.is_empty() {
+                full_field9.push(format!("{}={}", tag, gff3_escape(value)));
+            }
         }
-        //    if !self.attributes.Parent.is_empty() {
-        //       full_field9.push(format!("Parent={}",self.attributes.Parent));
-        //	  }
-        //       if !self.attributes.db_xref.is_empty() {
-        //          full_field9.push(format!("db_xref={}",self.attributes.db_xref));
-        //	  }
+        //the remaining qualifiers (db_xref, note, protein_id, pseudo...), the phase column already holds the codon start
+        full_field9.extend(gff3_attributes(
+            &self.attributes.qualifiers,
+            &["locus_tag", "gene", "product", "codon_start", "translation"],
+        ));
         full_field9.join(";")
     }
+    ///the nine tab separated columns of the line, a missing score, strand or phase is written as '.'
+    pub fn gff_line(&self) -> String {
+        let field = |value: &str| match value.is_empty() {
+            true => ".".to_string(),
+            false => gff3_escape(value),
+        };
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            field(&self.seqid),
+            field(&self.source),
+            field(&self.type_val),
+            self.start,
+            self.end,
+            self.score.map_or(".".to_string(), |s| s.to_string()),
+            field(&self.strand),
+            self.phase.map_or(".".to_string(), |p| p.to_string()),
+            match self.field9_attributes_build() {
+                attributes if attributes.is_empty() => ".".to_string(),
+                attributes => attributes,
+            }
+        )
+    }
 }
 
+///indentation of the qualifier column when writing the gbk FEATURES table
+pub const QUALIFIER_INDENT: &str = "                     ";
+
 ///formats the translation string which can be mulitple lines, for embl
 pub fn format_translation(translation: &str) -> String {
-    //create method to add the protein sequence into the translation qualifier with correct line lengths
-    let mut formatted = String::new();
     let cleaned_translation = translation.replace("\n", "");
-    formatted.push_str("                     /translation=\"");
-    let line_length: usize = 60;
-    let final_num = line_length - 15;
-    formatted.push_str(&format!("{}\n", &cleaned_translation[0..final_num]));
-    for i in (47..translation.len()).step_by(60) {
-        let end = i + 60 - 1;
-        let valid_end = if end >= translation.len() {
-            &cleaned_translation.len() - 1
-        } else {
-            end
-        };
-        formatted.push_str(&format!(
-            "                     {}",
-            &cleaned_translation[i..valid_end]
-        ));
-        println!(
-            "cleaned translation leng is {:?}",
-            &cleaned_translation[i..valid_end].len()
-        );
-        if cleaned_translation[i..valid_end].len() < 59 {
-            formatted.push('\"');
-        } else {
-            formatted.push('\n');
-        }
-    }
-    formatted
+    format_qualifier(QUALIFIER_INDENT, "translation", Some(&cleaned_translation))
+}
+
+///checks whether a feature key was made up by the reader (e.g. tRNA_3) because the feature had no locus tag
+pub fn is_generated_key(feature_type: &str, key: &str) -> bool {
+    key.strip_prefix(feature_type)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .is_some_and(|n| !n.is_empty() && n.split('_').all(|p| p.parse::<u32>().is_ok()))
 }
 
 ///writes the DNA sequence in gbk format with numbering
@@ -1126,7 +1310,7 @@ pub fn write_gbk_format_sequence(sequence: &str, file: &mut File) -> io::Result<
         formatted.push('\n');
         index += 60;
     }
-    writeln!(file, "{:>6}", &formatted)?;
+    write!(file, "{:>6}", &formatted)?;
     writeln!(file, "//")?;
     Ok(())
 }
@@ -1155,31 +1339,20 @@ pub fn gbk_write(
             Some(value) => value.to_string(),
             None => "Unknown".to_string(),
         };
-        let mol_type = match &record_vec[i].source_map.get_mol_type(key) {
-            Some(value) => value.to_string(),
-            None => "Unknown".to_string(),
-        };
-        let type_material = match &record_vec[i].source_map.get_type_material(key) {
-            Some(value) => value.to_string(),
-            None => "Unknown".to_string(),
-        };
-        let db_xref = match &record_vec[i].source_map.get_db_xref(key) {
-            Some(value) => value.to_string(),
-            None => "Unknown".to_string(),
+        let source_start = match &record_vec[i].source_map.get_start(key) {
+            Some(value) => (*value).clone(),
+            None => RangeValue::Exact(1),
         };
         let source_stop = match &record_vec[i].source_map.get_stop(key) {
-            Some(value) => value.get_value(),
-            None => {
-                println!("stop value not found");
-                None
-            }
-            .expect("stop value not received"),
+            Some(value) => (*value).clone(),
+            None => RangeValue::Exact(record_vec[i].sequence.len() as u32),
         };
         writeln!(
             file,
-            "LOCUS       {}             {} bp    DNA     linear CON {}",
+            "LOCUS       {}             {} bp    DNA     {} CON {}",
             &key,
             &record_vec[i].sequence.len(),
+            &record_vec[i].topology,
             &formatted_date
         )?;
         writeln!(file, "DEFINITION  {} {}.", &organism, &strain)?;
@@ -1189,89 +1362,71 @@ pub fn gbk_write(
         writeln!(file, "  ORGANISM  {} {}", &organism, &strain)?;
         //write lines for the source
         writeln!(file, "FEATURES             Location/Qualifiers")?;
-        writeln!(file, "     source          1..{}", &source_stop)?;
-        writeln!(file, "                     /organism=\"{}\"", &strain)?;
-        writeln!(file, "                     /mol_type=\"{}\"", &mol_type)?;
-        writeln!(file, "                     /strain=\"{}\"", &strain)?;
-        if type_material != *"Unknown".to_string() {
-            writeln!(
-                file,
-                "                     /type_material=\"{}\"",
-                &type_material
-            )?;
+        let source_location = Location::span(&source_start, &source_stop, 1);
+        writeln!(
+            file,
+            "{}",
+            format_location("     source          ", QUALIFIER_INDENT, &source_location)
+        )?;
+        for (name, value) in record_vec[i].source_map.source_qualifiers(key).iter() {
+            writeln!(file, "{}", format_qualifier(QUALIFIER_INDENT, name, value))?;
         }
-        writeln!(file, "                     /db_xref=\"{}\"", &db_xref)?;
-        //write lines for each CDS
-        for locus_tag in record_vec[i].cds.attributes.keys() {
-            let start = match &record_vec[i].cds.get_start(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("start value not found");
-                    None
-                }
-                .expect("start value not received"),
+        //write lines for each feature, gene lines are made up from the CDS when the record holds no gene features
+        let write_genes = !record_vec[i].features.contains_key("gene");
+        for (feature_type, locus_tag) in record_vec[i].ordered_features() {
+            let features = match record_vec[i].features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
             };
-            let stop = match &record_vec[i].cds.get_stop(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("stop value not found");
-                    None
+            let (start, stop) = match (features.get_start(locus_tag), features.get_stop(locus_tag))
+            {
+                (Some(start), Some(stop)) => (start.get_value(), stop.get_value()),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} {} has no start or stop", feature_type, locus_tag),
+                    ))
                 }
-                .expect("stop value not received"),
             };
-            let product = match &record_vec[i].cds.get_product(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown product".to_string(),
-            };
-            let strand = match &record_vec[i].cds.get_strand(locus_tag) {
-                Some(value) => **value,
-                None => 0,
-            };
-            let codon_start = match &record_vec[i].cds.get_codon_start(locus_tag) {
-                Some(value) => **value,
+            let strand = match features.get_strand(locus_tag) {
+                Some(value) => *value,
                 None => 0,
             };
-            let gene = match &record_vec[i].cds.get_gene(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown".to_string(),
+            let qualifiers = features.feature_qualifiers(feature_type, locus_tag);
+            let location = match features.get_location(locus_tag) {
+                Some(value) => value.clone(),
+                None => Location::span(&RangeValue::Exact(start), &RangeValue::Exact(stop), strand),
             };
-            let translation = match &record_vec[i].seq_features.get_sequence_faa(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown".to_string(),
-            };
-            if strand == 1 {
-                writeln!(file, "     gene            {}..{}", &start, &stop)?;
-            } else {
+            if feature_type == "CDS" && write_genes {
+                //the gene follows the CDS location, so a CDS over the origin or a join stays one
                 writeln!(
                     file,
-                    "     gene            complement({}..{})",
-                    &start, &stop
-                )?;
-            }
-            writeln!(file, "                     /locus_tag=\"{}\"", &locus_tag)?;
-            if strand == 1 {
-                writeln!(file, "     CDS             {}..{}", &start, &stop)?;
-            } else {
-                writeln!(
-                    file,
-                    "     CDS             complement({}..{})",
-                    &start, &stop
+                    "{}",
+                    format_location("     gene            ", QUALIFIER_INDENT, &location)
                 )?;
+                //a key made up by the reader or builder is not a locus tag
+                if let Some(tag) = qualifiers.locus_tag() {
+                    writeln!(
+                        file,
+                        "{}",
+                        format_qualifier(QUALIFIER_INDENT, "locus_tag", Some(tag))
+                    )?;
+                }
             }
-            writeln!(file, "                     /locus_tag=\"{}\"", &locus_tag)?;
+            let first_prefix = format!("     {:<16}", feature_type);
             writeln!(
                 file,
-                "                     /codon_start=\"{}\"",
-                &codon_start
+                "{}",
+                format_location(&first_prefix, QUALIFIER_INDENT, &location)
             )?;
-            if gene != "unknown" {
-                writeln!(file, "                     /gene=\"{}\"", &gene)?;
+            for (name, value) in qualifiers.iter() {
+                writeln!(file, "{}", format_qualifier(QUALIFIER_INDENT, name, value))?;
             }
-            if translation != "unknown" {
-                let formatted_translation = format_translation(&translation);
-                writeln!(file, "{}", &formatted_translation)?;
+            if feature_type == "CDS" && qualifiers.translation().is_none() {
+                if let Some(translation) = record_vec[i].seq_features.get_sequence_faa(locus_tag) {
+                    writeln!(file, "{}", format_translation(translation))?;
+                }
             }
-            writeln!(file, "                     /product=\"{}\"", &product)?;
         }
         write_gbk_format_sequence(&record_vec[i].sequence, &mut file)?;
     }
@@ -1279,12 +1434,11 @@ pub fn gbk_write(
 }
 
 ///saves the parsed data in gff3 format
-//writes a gff3 file from a genbank
-#[allow(unused_assignments)]
-#[allow(unused_variables)]
+//writes a gff3 file from a embl, each record is a sequence region named by its id with coordinates counted from its own first base
+//the source feature of each record is found by its own source name, the keys of seq_region stand in for records without one
 pub fn gff_write(
     seq_region: BTreeMap<String, (u32, u32)>,
-    mut record_vec: Vec<Record>,
+    record_vec: Vec<Record>,
     filename: &str,
     dna: bool,
 ) -> io::Result<()> {
@@ -1296,118 +1450,505 @@ pub fn gff_write(
     if file.metadata()?.len() == 0 {
         writeln!(file, "##gff-version 3")?;
     }
-    let mut full_seq = String::new();
-    let mut prev_end: u32 = 0;
-    //println!("this is the full seq_region {:?}", &seq_region);
-    for (k, v) in seq_region.iter() {
-        writeln!(file, "##sequence-region\t{}\t{}\t{}", &k, v.0, v.1)?;
+    //the seqid, source name, length and record of each sequence region
+    //the source name is taken from each record, the seq_region key in the same place is only used when it has none
+    let regions: Vec<(String, String, u32, &Record)> = record_vec
+        .iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let source_name = record
+                .source_map
+                .get_source_name()
+                .or_else(|| seq_region.keys().nth(i))
+                .cloned()
+                .unwrap_or_default();
+            let seqid = match record.id.is_empty() {
+                true => source_name.clone(),
+                false => record.id.clone(),
+            };
+            let length = match record.sequence.is_empty() {
+                true => record.length,
+                false => record.sequence.len() as u32,
+            };
+            (seqid, source_name, length, record)
+        })
+        .collect();
+    for (seqid, _, length, _) in regions.iter() {
+        writeln!(
+            file,
+            "##sequence-region {} 1 {}",
+            gff3_escape(seqid),
+            length
+        )?;
     }
-    for ((source_name, (seq_start, seq_end)), record) in seq_region.iter().zip(record_vec.drain(..))
-    {
-        if dna {
-            full_seq.push_str(&record.sequence);
+    let mut used_ids: HashSet<String> = HashSet::new();
+    for (seqid, source_name, length, record) in regions.iter() {
+        let (length, circular) = (*length, record.is_circular());
+        //the source feature is written as the region line of the sequence
+        let mut source_qualifiers = record.source_map.source_qualifiers(source_name);
+        if circular {
+            source_qualifiers.push("Is_circular", Some("true".to_string()));
         }
-        for locus_tag in record.cds.attributes.keys() {
-            let start = match record.cds.get_start(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("start value not found");
-                    None
-                }
-                .expect("start value not received"),
+        let source_start = record
+            .source_map
+            .get_start(source_name)
+            .map_or(1, |v| v.get_value());
+        let source_stop = record
+            .source_map
+            .get_stop(source_name)
+            .map_or(length, |v| v.get_value());
+        let region_id = format!("{}:{}..{}", seqid, source_start, source_stop);
+        let mut region = GFFInner::new(
+            unique_id(&region_id, &mut used_ids),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+        );
+        region.set_qualifiers(source_qualifiers);
+        let region_line = GFFOuter::new(
+            seqid.clone(),
+            ".".to_string(),
+            "region".to_string(),
+            source_start,
+            source_stop,
+            0.0,
+            "+".to_string(),
+            0,
+            &region,
+        );
+        writeln!(file, "{}", region_line.gff_line())?;
+        //the IDs of the gene features by locus tag, the CDS and RNAs of a gene name it as their Parent
+        let mut gene_ids: HashMap<String, String> = HashMap::new();
+        if let Some(genes) = record.features_of("gene") {
+            for key in genes.attributes.keys() {
+                let locus_tag = genes
+                    .get_qualifiers(key)
+                    .and_then(|q| q.locus_tag())
+                    .unwrap_or(key);
+                let gene_id = unique_id(&format!("gene-{}", locus_tag), &mut used_ids);
+                gene_ids.insert(key.to_string(), gene_id);
+            }
+        }
+        //records without gene features get a gene line made up for each CDS
+        let write_genes = !record.features.contains_key("gene");
+        for (feature_type, key) in record.ordered_features() {
+            let features = match record.features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
             };
-            let stop = match record.cds.get_stop(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("stop value not found");
-                    None
+            let (start, stop) = match (features.get_start(key), features.get_stop(key)) {
+                (Some(start), Some(stop)) => (start.get_value(), stop.get_value()),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} {} has no start or stop", feature_type, key),
+                    ))
                 }
-                .expect("stop value not received"),
             };
-            let gene = match record.cds.get_gene(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown".to_string(),
+            let strand = match features.get_strand(key) {
+                Some(1) => "+",
+                Some(-1) => "-",
+                _ => ".",
+            };
+            let qualifiers = features.feature_qualifiers(feature_type, key);
+            let locus_tag = qualifiers.locus_tag().unwrap_or_default().to_string();
+            let gene = qualifiers.gene().unwrap_or_default().to_string();
+            let product = qualifiers.product().unwrap_or_default().to_string();
+            let name = match gene.is_empty() {
+                true => locus_tag.clone(),
+                false => gene.clone(),
             };
-            let product = match record.cds.get_product(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown product".to_string(),
+            let tag = match locus_tag.is_empty() {
+                true => key,
+                false => &locus_tag,
             };
-            let strand = match record.cds.get_strand(locus_tag) {
-                Some(valu) => match valu {
-                    1 => "+".to_string(),
-                    -1 => "-".to_string(),
-                    _ => {
-                        println!(
-                            "unexpected strand value {} for locus_tag {}",
-                            valu, &locus_tag
+            let mut parent = String::new();
+            let id = match feature_type {
+                "gene" => match gene_ids.get(key) {
+                    Some(gene_id) => gene_id.clone(),
+                    None => unique_id(&format!("gene-{}", tag), &mut used_ids),
+                },
+                _ => {
+                    if feature_type == "CDS" && write_genes {
+                        parent = unique_id(&format!("gene-{}", tag), &mut used_ids);
+                        let gene_line = GFFInner::new(
+                            parent.clone(),
+                            name.clone(),
+                            locus_tag.clone(),
+                            gene.clone(),
+                            String::new(),
+                        );
+                        let gene_outer = GFFOuter::new(
+                            seqid.clone(),
+                            ".".to_string(),
+                            "gene".to_string(),
+                            start,
+                            stop,
+                            0.0,
+                            strand.to_string(),
+                            0,
+                            &gene_line,
                         );
-                        "unknownstrand".to_string()
+                        writeln!(file, "{}", gene_outer.gff_line())?;
+                    } else if let Some(gene_id) = gene_ids.get(tag) {
+                        parent = gene_id.clone();
                     }
-                },
-                None => "unknownvalue".to_string(),
+                    let prefix = feature_type.to_lowercase();
+                    unique_id(&format!("{}-{}", prefix, tag), &mut used_ids)
+                }
             };
-            let phase = match record.cds.get_codon_start(locus_tag) {
-                Some(valuer) => match valuer {
-                    1 => 0,
-                    2 => 1,
-                    3 => 2,
-                    _ => {
-                        println!(
-                            "unexpected phase value {} in the bagging area for locus_tag {}",
-                            valuer, &locus_tag
-                        );
-                        1
+            let codon_start = match feature_type {
+                "CDS" => Some(features.get_codon_start(key).copied().unwrap_or(1)),
+                _ => None,
+            };
+            let location = match features.get_location(key) {
+                Some(value) => value.clone(),
+                None => Location::span(
+                    &RangeValue::Exact(start),
+                    &RangeValue::Exact(stop),
+                    features.get_strand(key).copied().unwrap_or(1),
+                ),
+            };
+            //a joined feature is written as one line per part, the lines share the ID
+            let mut parts = location_parts(&location, length, circular, codon_start);
+            if parts.is_empty() {
+                parts.push((start, stop, codon_start.map(|c| c - 1)));
+            }
+            let mut gff_inner = GFFInner::new(id, name, locus_tag.clone(), gene, product);
+            gff_inner
+                .set_parent(parent)
+                .set_qualifiers(qualifiers.clone());
+            for (part_start, part_end, phase) in parts {
+                let mut gff_outer = GFFOuter::new(
+                    seqid.clone(),
+                    ".".to_string(),
+                    feature_type.to_string(),
+                    part_start,
+                    part_end,
+                    0.0,
+                    strand.to_string(),
+                    0,
+                    &gff_inner,
+                );
+                gff_outer.set_phase(phase);
+                writeln!(file, "{}", gff_outer.gff_line())?;
+            }
+        }
+    }
+    //one FASTA entry per sequence region
+    if dna {
+        writeln!(file, "##FASTA")?;
+        for (seqid, _, _, record) in regions.iter() {
+            writeln!(file, "{}", fasta_entry(seqid, &record.sequence))?;
+        }
+    }
+    Ok(())
+}
+
+///saves the coding sequences and RNA genes in GTF 2.2 format
+//the gene_id of each transcript is its locus tag, the transcript_id is made from the feature type and the gene_id
+pub fn gtf_write(record_vec: Vec<Record>, filename: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .append(true) // Enable appending to the file
+        .create(true) // Create the file if it doesn't exist
+        .open(filename)?;
+    let mut used_ids: HashSet<String> = HashSet::new();
+    for record in record_vec.iter() {
+        let length = match record.sequence.is_empty() {
+            true => record.length,
+            false => record.sequence.len() as u32,
+        };
+        for (feature_type, key) in record.ordered_features() {
+            if feature_type != "CDS" && !RNA_FEATURES.contains(&feature_type) {
+                continue;
+            }
+            let features = match record.features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
+            };
+            let location = match features.get_location(key) {
+                Some(value) => value.clone(),
+                None => match (features.get_start(key), features.get_stop(key)) {
+                    (Some(start), Some(stop)) => {
+                        Location::span(start, stop, features.get_strand(key).copied().unwrap_or(1))
                     }
+                    _ => continue,
                 },
-                None => 1,
             };
-            let gff_inner = GFFInner::new(
-                locus_tag.to_string(),
-                source_name.clone(),
-                locus_tag.to_string(),
-                gene,
-                //  &record.cds.get_Inference(&locus_tag),
-                //  &record.cds.get_Parent(&locus_tag),
-                //   db_xref,
-                product,
-            );
-            let gff_outer = GFFOuter::new(
-                source_name.clone(),
-                ".".to_string(),
-                "CDS".to_string(),
-                start + prev_end,
-                stop + prev_end,
-                0.0,
-                strand,
-                phase,
-                &gff_inner,
+            let qualifiers = features.feature_qualifiers(feature_type, key);
+            let gene_id = qualifiers.locus_tag().unwrap_or(key).to_string();
+            let transcript_id = unique_id(
+                &format!("{}-{}", feature_type.to_lowercase(), gene_id),
+                &mut used_ids,
             );
-            let field9_attributes = gff_outer.field9_attributes_build();
-            //println!("{}\t{}\t{}\t{:?}\t{:?}\t{}\t{}\t{}\t{}", gff_outer.seqid, gff_outer.source, gff_outer.type_val, gff_outer.start, gff_outer.end, gff_outer.score, gff_outer.strand, gff_outer.phase, field9_attributes);
-            writeln!(
-                file,
-                "{}\t{}\t{}\t{:?}\t{:?}\t{}\t{}\t{}\t{}",
-                gff_outer.seqid,
-                gff_outer.source,
-                gff_outer.type_val,
-                gff_outer.start,
-                gff_outer.end,
-                gff_outer.score,
-                gff_outer.strand,
-                gff_outer.phase,
-                field9_attributes
-            )?;
+            let mut attributes: Vec<(String, String)> = Vec::new();
+            if let Some(gene) = qualifiers.gene() {
+                attributes.push(("gene_name".to_string(), gene.to_string()));
+            }
+            let (biotype, codon_start) = match feature_type {
+                "CDS" => (
+                    "protein_coding",
+                    Some(features.get_codon_start(key).copied().unwrap_or(1)),
+                ),
+                _ => (feature_type, None),
+            };
+            attributes.push(("gene_biotype".to_string(), biotype.to_string()));
+            if let Some(product) = qualifiers.product() {
+                attributes.push(("product".to_string(), product.to_string()));
+            }
+            let transcript = Transcript {
+                seqid: record.id.clone(),
+                gene_id,
+                transcript_id,
+                location,
+                codon_start,
+                attributes,
+            };
+            for line in transcript.gtf_lines(length, record.is_circular()) {
+                writeln!(file, "{}", line)?;
+            }
         }
-        prev_end = *seq_end;
     }
-    if dna {
-        writeln!(file, "##FASTA")?;
-        //writeln!(file, ">{}\n",&filename.to_string())?;
-        writeln!(file, "{}", full_seq)?;
+    Ok(())
+}
+
+///saves the coding sequences in BED format, BED6 or BED12 with one block per part of a joined CDS
+//coordinates are 0-based and half-open, each CDS is named by its locus tag with a score of 0
+pub fn bed_write(record_vec: Vec<Record>, filename: &str, columns: usize) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .append(true) // Enable appending to the file
+        .create(true) // Create the file if it doesn't exist
+        .open(filename)?;
+    for record in record_vec.iter() {
+        let length = match record.sequence.is_empty() {
+            true => record.length,
+            false => record.sequence.len() as u32,
+        };
+        for (feature_type, key) in record.ordered_features() {
+            if feature_type != "CDS" {
+                continue;
+            }
+            let location = match record.cds.get_location(key) {
+                Some(value) => value.clone(),
+                None => match (record.cds.get_start(key), record.cds.get_stop(key)) {
+                    (Some(start), Some(stop)) => Location::span(
+                        start,
+                        stop,
+                        record.cds.get_strand(key).copied().unwrap_or(1),
+                    ),
+                    _ => continue,
+                },
+            };
+            let qualifiers = record.cds.feature_qualifiers(feature_type, key);
+            let mut interval =
+                BedRecord::from_location(&record.id, &location, length, record.is_circular());
+            interval.name = Some(qualifiers.locus_tag().unwrap_or(key).to_string());
+            interval.score = Some(0.0);
+            writeln!(file, "{}", interval.to_line(columns))?;
+        }
     }
     Ok(())
 }
 
+///indentation of the qualifier column of EMBL feature table lines
+pub const FT_INDENT: &str = "FT                   ";
+
+///the parts of a record written to an EMBL file, made from an EMBL or a GenBank record
+#[derive(Debug, Default, Clone)]
+pub struct EmblEntry {
+    pub name: String,
+    pub header: RecordHeader,
+    pub topology: Topology,
+    ///every feature in the order written, as feature key, location and qualifiers
+    pub features: Vec<(String, Location, Qualifiers)>,
+    pub sequence: String,
+}
+
+impl EmblEntry {
+    ///formats the entry from its ID line to the closing ```//```, with a trailing newline
+    pub fn embl_lines(&self) -> String {
+        let mut header = self.header.clone();
+        //a record built with the setters has no header, it is made up from the name and the source feature
+        if header.accessions.is_empty() {
+            header.accessions.push(self.name.clone());
+        }
+        if header.organism.is_empty() {
+            header.organism = self
+                .features
+                .iter()
+                .find(|(key, _, _)| key == "source")
+                .and_then(|(_, _, qualifiers)| qualifiers.get("organism"))
+                .unwrap_or("unidentified")
+                .to_string();
+        }
+        let mut lines: Vec<String> = vec![
+            header.embl_id_line(&self.name, self.sequence.len(), self.topology),
+            "XX".to_string(),
+            header.embl_lines(),
+            "FH   Key             Location/Qualifiers".to_string(),
+            "FH".to_string(),
+        ];
+        for (key, location, qualifiers) in self.features.iter() {
+            let first_prefix = format!("FT   {:<16}", key);
+            lines.push(format_location(&first_prefix, FT_INDENT, location));
+            for (name, value) in qualifiers.iter() {
+                lines.push(format_qualifier(FT_INDENT, name, value));
+            }
+        }
+        lines.push("XX".to_string());
+        lines.push(embl_format_sequence(&self.sequence));
+        lines.push("//\n".to_string());
+        lines.join("\n")
+    }
+}
+
+///formats the SQ block of an EMBL record, the base counts line and lowercase sequence lines of 60 bases numbered at the right
+pub fn embl_format_sequence(sequence: &str) -> String {
+    let cleaned: Vec<u8> = sequence
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .map(|b| b.to_ascii_lowercase())
+        .collect();
+    let count = |base: u8| cleaned.iter().filter(|b| **b == base).count();
+    let (a, c, g, t) = (count(b'a'), count(b'c'), count(b'g'), count(b't'));
+    let mut lines = vec![format!(
+        "SQ   Sequence {} BP; {} A; {} C; {} G; {} T; {} other;",
+        cleaned.len(),
+        a,
+        c,
+        g,
+        t,
+        cleaned.len() - a - c - g - t
+    )];
+    for (i, chunk) in cleaned.chunks(60).enumerate() {
+        let groups: Vec<String> = chunk
+            .chunks(10)
+            .map(|group| String::from_utf8_lossy(group).to_string())
+            .collect();
+        lines.push(format!(
+            "     {:<65}{:>10}",
+            groups.join(" "),
+            i * 60 + chunk.len()
+        ));
+    }
+    lines.join("\n")
+}
+
+impl From<&Record> for EmblEntry {
+    fn from(record: &Record) -> Self {
+        let mut features: Vec<(String, Location, Qualifiers)> = Vec::new();
+        //the source features come first, ordered by their start
+        let mut sources: Vec<(u32, &String)> = record
+            .source_map
+            .source_attributes
+            .keys()
+            .map(|key| {
+                let start = record
+                    .source_map
+                    .get_start(key)
+                    .map_or(1, |v| v.get_value());
+                (start, key)
+            })
+            .collect();
+        sources.sort();
+        for (_, key) in sources {
+            let start = record
+                .source_map
+                .get_start(key)
+                .cloned()
+                .unwrap_or(RangeValue::Exact(1));
+            let stop = match record.source_map.get_stop(key) {
+                Some(value) => value.clone(),
+                None => RangeValue::Exact(record.sequence.len() as u32),
+            };
+            features.push((
+                "source".to_string(),
+                Location::span(&start, &stop, 1),
+                record.source_map.source_qualifiers(key),
+            ));
+        }
+        for (feature_type, locus_tag) in record.ordered_features() {
+            let feature = match record.features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
+            };
+            let location = match feature.get_location(locus_tag) {
+                Some(value) => value.clone(),
+                None => match (feature.get_start(locus_tag), feature.get_stop(locus_tag)) {
+                    (Some(start), Some(stop)) => Location::span(
+                        start,
+                        stop,
+                        feature.get_strand(locus_tag).copied().unwrap_or(1),
+                    ),
+                    _ => continue,
+                },
+            };
+            let mut qualifiers = feature.feature_qualifiers(feature_type, locus_tag);
+            if feature_type == "CDS" && qualifiers.translation().is_none() {
+                if let Some(translation) = record.seq_features.get_sequence_faa(locus_tag) {
+                    qualifiers.push("translation", Some(translation.replace('\n', "")));
+                }
+            }
+            features.push((feature_type.to_string(), location, qualifiers));
+        }
+        EmblEntry {
+            name: record.id.clone(),
+            header: record.header.clone(),
+            topology: record.topology,
+            features,
+            sequence: record.sequence.clone(),
+        }
+    }
+}
+
+///writes records as EMBL flat files
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    writer: W,
+}
+
+impl Writer<io::BufWriter<File>> {
+    /// Write EMBL to the given file path, replacing the file.
+    pub fn to_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        File::create(&path)
+            .map(|file| Writer::new(io::BufWriter::new(file)))
+            .with_context(|| format!("Failed to write EMBL to {:#?}", path))
+    }
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Writer { writer }
+    }
+    pub fn write_entry(&mut self, entry: &EmblEntry) -> io::Result<()> {
+        write!(self.writer, "{}", entry.embl_lines())
+    }
+    pub fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        self.write_entry(&EmblEntry::from(record))
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+///saves the parsed data in embl format
+//writes a embl or multi-embl file, the header, every feature and the sequence of each record are written back
+pub fn embl_write(record_vec: Vec<Record>, filename: &str) -> io::Result<()> {
+    let file = OpenOptions::new()
+        .append(true) // Enable appending to the file
+        .create(true) // Create the file if it doesn't exist
+        .open(filename)?;
+    let mut writer = Writer::new(io::BufWriter::new(file));
+    for record in record_vec.iter() {
+        writer.write_record(record)?;
+    }
+    writer.flush()
+}
+
 ///internal record containing data from a single source or contig.  Has multiple features.
 //sets up a record
 #[derive(Debug, Clone)]
@@ -1421,6 +1962,11 @@ pub struct Record {
     pub cds: FeatureAttributeBuilder,
     pub source_map: SourceAttributeBuilder,
     pub seq_features: SequenceAttributeBuilder,
+    pub features: BTreeMap<String, FeatureAttributeBuilder>,
+    pub header: RecordHeader,
+    pub topology: Topology,
+    ///differences between the computed and annotated proteins by locus tag, filled by a reader validating translations, and CDS with an unknown /transl_table
+    pub translation_issues: BTreeMap<String, Vec<TranslationIssue>>,
 }
 
 impl Record {
@@ -1436,6 +1982,10 @@ impl Record {
             source_map: SourceAttributeBuilder::new(),
             cds: FeatureAttributeBuilder::new(),
             seq_features: SequenceAttributeBuilder::new(),
+            features: BTreeMap::new(),
+            header: RecordHeader::new(),
+            topology: Topology::Linear,
+            translation_issues: BTreeMap::new(),
         }
     }
     pub fn is_empty(&mut self) -> bool {
@@ -1465,6 +2015,19 @@ impl Record {
     pub fn strand(&mut self) -> i32 {
         self.strand
     }
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+    pub fn is_circular(&self) -> bool {
+        self.topology == Topology::Circular
+    }
+    ///the start and stop stored for a location, a feature running over the origin of a circular record ends past the record length
+    fn bounds(&self, location: &Location) -> (RangeValue, RangeValue) {
+        match self.is_circular() {
+            true => location.circular_bounds(self.length),
+            false => (location.start_value(), location.end_value()),
+        }
+    }
     pub fn cds(&mut self) -> FeatureAttributeBuilder {
         self.cds.clone()
     }
@@ -1474,6 +2037,49 @@ impl Record {
     pub fn seq_features(&mut self) -> SequenceAttributeBuilder {
         self.seq_features.clone()
     }
+    ///the feature types present in the record, CDS first followed by gene, rRNA, tRNA, mobile_element etc. in alphabetical order
+    pub fn feature_types(&self) -> Vec<&str> {
+        let mut types = Vec::new();
+        if !self.cds.attributes.is_empty() {
+            types.push("CDS");
+        }
+        types.extend(self.features.keys().map(|k| k.as_str()));
+        types
+    }
+    ///the features of a given type, CDS are held in the cds field and all other types in features
+    pub fn features_of(&self, feature_type: &str) -> Option<&FeatureAttributeBuilder> {
+        if feature_type == "CDS" {
+            Some(&self.cds)
+        } else {
+            self.features.get(feature_type)
+        }
+    }
+    ///mutable access to the features of a given type, creating an empty builder for a new feature type
+    pub fn features_mut(&mut self, feature_type: &str) -> &mut FeatureAttributeBuilder {
+        if feature_type == "CDS" {
+            &mut self.cds
+        } else {
+            self.features.entry(feature_type.to_string()).or_default()
+        }
+    }
+    ///every (feature type, locus tag) pair ordered by start coordinate, a gene is placed ahead of the CDS or RNA it encloses
+    pub fn ordered_features(&self) -> Vec<(&str, &str)> {
+        let mut ordered: Vec<(u32, u8, &str, &str)> = Vec::new();
+        for feature_type in self.feature_types() {
+            if let Some(features) = self.features_of(feature_type) {
+                for locus_tag in features.attributes.keys() {
+                    let start = features
+                        .get_start(locus_tag)
+                        .map(|v| v.get_value())
+                        .unwrap_or(0);
+                    let rank = if feature_type == "gene" { 0 } else { 1 };
+                    ordered.push((start, rank, feature_type, locus_tag));
+                }
+            }
+        }
+        ordered.sort();
+        ordered.into_iter().map(|(_, _, t, k)| (t, k)).collect()
+    }
     fn rec_clear(&mut self) {
         self.id.clear();
         self.length = 0;
@@ -1484,6 +2090,10 @@ impl Record {
         self.source_map = SourceAttributeBuilder::new();
         self.cds = FeatureAttributeBuilder::new();
         self.seq_features = SequenceAttributeBuilder::new();
+        self.features = BTreeMap::new();
+        self.header = RecordHeader::new();
+        self.topology = Topology::Linear;
+        self.translation_issues = BTreeMap::new();
     }
 }
 
@@ -1603,8 +2213,332 @@ mod tests {
         if let Some(record) = records.first() {
             if let Some((key, vals)) = record.cds.attributes.first_key_value() {
                 assert_eq!(key, &"pRL80001".to_string());
-                assert_eq!(record.seq_features.get_sequence_faa(&key), Some(&"VENPAQLQKAIHKLIAAHARDLSGALHEHRVKLYPPEARKTLRSFSSIEAAKLIGVNDGYLRHLSLEGKGPQPEIGNNNRRSYSVETIQALREYLDENGKGDRRYSPRRSGREHLQVITAVNFKGGSGKTTTAAHLAQYLALNGYRVLAIDLDPQASMSALHGFQPEFDVGDNETLYGAVRYDEERRPLKDIIKKTYFANLDLVPGNLELMEFEHDTAKVLGSNDRKNIFFTRMDDAIASVADDYDVVVVDCPPQLGFLTISALCAATAVLVTVHPQMLDVMSMCQFLLMTSELLSVVADAGGSMNYDWMRYLVTRYEPGDGPQNQMVSFMRTMFGDHVLNHPMLKSTAISDAGITKQTLYEVSRDQFTRATYDRAMESLDNVNSEIEQLIQSSWGRK".to_string()));
+                assert_eq!(record.seq_features.get_sequence_faa(&key), Some(&"MENPAQLQKAIHKLIAAHARDLSGALHEHRVKLYPPEARKTLRSFSSIEAAKLIGVNDGYLRHLSLEGKGPQPEIGNNNRRSYSVETIQALREYLDENGKGDRRYSPRRSGREHLQVITAVNFKGGSGKTTTAAHLAQYLALNGYRVLAIDLDPQASMSALHGFQPEFDVGDNETLYGAVRYDEERRPLKDIIKKTYFANLDLVPGNLELMEFEHDTAKVLGSNDRKNIFFTRMDDAIASVADDYDVVVVDCPPQLGFLTISALCAATAVLVTVHPQMLDVMSMCQFLLMTSELLSVVADAGGSMNYDWMRYLVTRYEPGDGPQNQMVSFMRTMFGDHVLNHPMLKSTAISDAGITKQTLYEVSRDQFTRATYDRAMESLDNVNSEIEQLIQSSWGRK".to_string()));
             }
         }
     }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_all_feature_types() {
+        let file_embl = "example.embl";
+        let records = embl!(&file_embl);
+        let record = records.first().expect("no record parsed");
+        assert_eq!(record.feature_types(), vec!["CDS", "misc_feature"]);
+        let misc = record
+            .features_of("misc_feature")
+            .expect("missing misc_feature");
+        assert_eq!(
+            misc.get_stop("misc_feature_1").map(|v| v.get_value()),
+            Some(6666)
+        );
+        assert!(is_generated_key("misc_feature", "misc_feature_1"));
+        assert_eq!(record.cds.attributes.len(), 4);
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_qualifiers() {
+        let file_embl = "example.embl";
+        let records = embl!(&file_embl);
+        let record = records.first().expect("no record parsed");
+        let qualifiers = record
+            .cds
+            .get_qualifiers("pRL80001")
+            .expect("missing qualifiers");
+        assert_eq!(qualifiers.protein_id(), Some("CAK02801.1"));
+        assert_eq!(qualifiers.transl_table(), Some(11));
+        assert_eq!(qualifiers.db_xrefs().len(), 8);
+        assert_eq!(qualifiers.db_xrefs()[2], "GOA:Q1M9K5");
+        let misc = record
+            .features_of("misc_feature")
+            .and_then(|f| f.get_qualifiers("misc_feature_1"))
+            .expect("missing misc_feature qualifiers");
+        assert_eq!(misc.get("colour"), Some("12"));
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_multiline_qualifiers() {
+        let file_embl = "example.embl";
+        let records = embl!(&file_embl);
+        let record = records.first().expect("no record parsed");
+        let qualifiers = record
+            .cds
+            .get_qualifiers("pRL80001")
+            .expect("missing qualifiers");
+        let translation = qualifiers.translation().expect("missing translation");
+        assert!(translation.starts_with("MENPAQLQKAIHKLIAAHARDLSGALHEHRVKLYPPEARKTLRSFSSIEAAK"));
+        assert!(translation.ends_with("SEIEQLIQSSWGRK"));
+        //pRL80001 starts with the alternative start codon GTG, read as Met with table 11
+        assert_eq!(
+            record.seq_features.get_sequence_faa("pRL80001"),
+            Some(&translation.to_string())
+        );
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_locations() {
+        let file_embl = "example.embl";
+        let records = embl!(&file_embl);
+        let record = records.first().expect("no record parsed");
+        assert_eq!(
+            record.source_map.get_stop("source_AM236082_1"),
+            Some(&RangeValue::GreaterThan(6666))
+        );
+        let location = record
+            .cds
+            .get_location("pRL80001")
+            .expect("missing location");
+        assert_eq!(location.to_string(), "1..1197");
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_validate_translations() {
+        let mut records = Reader::from_file("example.embl")
+            .expect("failed to open embl")
+            .validate_translations(true)
+            .records();
+        let record = records.next().expect("no record").expect("failed to parse");
+        assert!(record.translation_issues.is_empty());
+        //with the standard code the GTG start of pRL80001 is read as valine
+        let content = std::fs::read_to_string("example.embl")
+            .expect("failed to read embl")
+            .replacen("/transl_table=11", "/transl_table=1", 1);
+        let mut records = Reader::new(content.as_bytes())
+            .validate_translations(true)
+            .records();
+        let record = records.next().expect("no record").expect("failed to parse");
+        assert_eq!(
+            record.translation_issues.get("pRL80001"),
+            Some(&vec![TranslationIssue::FirstCodonDifference {
+                computed: 'V',
+                annotated: 'M'
+            }])
+        );
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_errors() {
+        let content = std::fs::read_to_string("example.embl").expect("failed to read embl");
+        let first_error = |text: &str| {
+            Reader::new(text.as_bytes())
+                .records()
+                .find_map(|r| r.err())
+                .expect("no error returned")
+        };
+        let bad_location = content.replacen(
+            "FT   CDS             1..1197",
+            "FT   CDS             1..x197",
+            1,
+        );
+        let error = first_error(&bad_location);
+        assert!(matches!(error, ParseError::BadLocation { .. }));
+        assert_eq!((error.line_number(), error.record_id()), (76, "AM236082"));
+        //an unknown /transl_table keeps the record, the CDS is translated with table 11 and reported
+        let bad_table = content.replacen("/transl_table=11", "/transl_table=99", 1);
+        let record = Reader::new(bad_table.as_bytes())
+            .records()
+            .next()
+            .expect("no record returned")
+            .expect("unknown table failed the record");
+        assert_eq!(
+            record.translation_issues.get("pRL80001"),
+            Some(&vec![TranslationIssue::UnknownTable { table: 99 }])
+        );
+        assert!(record.seq_features.get_sequence_faa("pRL80001").is_some());
+        let truncated: String = content
+            .lines()
+            .take(200)
+            .map(|l| format!("{}\n", l))
+            .collect();
+        assert!(matches!(
+            first_error(&truncated),
+            ParseError::TruncatedSequence { .. }
+        ));
+        assert_eq!(try_embl!("example.embl").expect("failed to parse").len(), 1);
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_gff_write() {
+        let records = embl!("example.embl");
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        seq_region.insert("source_AM236082_1".to_string(), (1, 6666));
+        let output = std::env::temp_dir().join("microbiorust_test_embl_gff_write.gff");
+        let _ = std::fs::remove_file(&output);
+        gff_write(seq_region, records, output.to_str().unwrap(), true)
+            .expect("failed to write gff");
+        let content = std::fs::read_to_string(&output).expect("failed to read gff");
+        std::fs::remove_file(&output).ok();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines[1], "##sequence-region AM236082 1 6666");
+        assert!(lines[2].starts_with("AM236082\t.\tregion\t1\t6666\t.\t+\t.\tID=AM236082:1..6666"));
+        assert!(lines[3]
+            .starts_with("AM236082\t.\tgene\t1\t1197\t.\t+\t.\tID=gene-pRL80001;Name=repAp8;"));
+        assert!(lines[4].starts_with(
+            "AM236082\t.\tCDS\t1\t1197\t.\t+\t0\tID=cds-pRL80001;Name=repAp8;Parent=gene-pRL80001;"
+        ));
+        let fasta = lines
+            .iter()
+            .position(|l| *l == "##FASTA")
+            .expect("no FASTA section");
+        assert_eq!(lines[fasta + 1], ">AM236082");
+        assert_eq!(lines[fasta + 2].len(), 60);
+    }
+    #[test]
+    fn test_gff_write_unsorted_ids() {
+        //a copy named ZZ000001 comes first, both regions keep the source qualifiers of their record
+        let records = embl!("example.embl");
+        let mut copy = records[0].clone();
+        copy.id = "ZZ000001".to_string();
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        seq_region.insert("source_AM236082_1".to_string(), (1, 6666));
+        let output = std::env::temp_dir().join("microbiorust_test_embl_gff_write_unsorted.gff");
+        let _ = std::fs::remove_file(&output);
+        gff_write(
+            seq_region,
+            vec![copy, records[0].clone()],
+            output.to_str().unwrap(),
+            false,
+        )
+        .expect("failed to write gff");
+        let content = std::fs::read_to_string(&output).expect("failed to read gff");
+        std::fs::remove_file(&output).ok();
+        for seqid in ["ZZ000001", "AM236082"] {
+            let region = content
+                .lines()
+                .find(|l| l.starts_with(&format!("{}\t.\tregion\t", seqid)))
+                .expect("no region line");
+            assert!(
+                region.contains("organism=Rhizobium leguminosarum bv. viciae 3841"),
+                "{}",
+                region
+            );
+        }
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_gtf_write() {
+        let records = embl!("example.embl");
+        let output = std::env::temp_dir().join("microbiorust_test_embl_gtf_write.gtf");
+        let _ = std::fs::remove_file(&output);
+        gtf_write(records.clone(), output.to_str().unwrap()).expect("failed to write gtf");
+        let content = std::fs::read_to_string(&output).expect("failed to read gtf");
+        std::fs::remove_file(&output).ok();
+        assert!(content.contains("AM236082\t.\tstart_codon\t1\t3\t.\t+\t0\t"));
+        assert!(content.contains("AM236082\t.\tstop_codon\t1195\t1197\t.\t+\t0\t"));
+        let gtf_records: Vec<crate::gbk::Record> = crate::gtf::Reader::new(content.as_bytes())
+            .records()
+            .map(|r| r.expect("failed to read gtf"))
+            .collect();
+        assert_eq!(gtf_records.len(), 1);
+        for locus_tag in records[0].cds.attributes.keys() {
+            assert_eq!(
+                gtf_records[0].cds.get_location(locus_tag),
+                records[0].cds.get_location(locus_tag)
+            );
+        }
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_embl_write() {
+        let records = embl!("example.embl");
+        let mut writer = Writer::new(Vec::new());
+        for record in records.iter() {
+            writer.write_record(record).expect("failed to write embl");
+        }
+        let content = String::from_utf8(writer.into_inner()).expect("embl is not utf8");
+        let original = std::fs::read_to_string("example.embl").expect("failed to read embl");
+        assert!(content.lines().all(|line| line.len() <= 80));
+        assert!(content.starts_with(
+            "ID   AM236082; SV 1; linear; genomic DNA; STD; PRO; 6666 BP.\nXX\nAC   AM236082;\n"
+        ));
+        //the feature table and the sequence are written as they were read
+        let section = |text: &str, codes: &[&str]| -> Vec<String> {
+            text.lines()
+                .filter(|line| codes.iter().any(|code| line.starts_with(code)))
+                .map(|line| line.trim_end().to_string())
+                .collect()
+        };
+        assert_eq!(
+            section(&content, &["FH", "FT", "SQ", "     ", "//"]),
+            section(&original, &["FH", "FT", "SQ", "     ", "//"])
+        );
+        //author and journal lines are joined when read and wrapped again when written
+        assert_eq!(
+            section(&content, &["DT", "DE", "KW", "OS", "OC", "RN", "RP", "RT"]),
+            section(&original, &["DT", "DE", "KW", "OS", "OC", "RN", "RP", "RT"])
+        );
+        let written: Vec<Record> = Reader::new(content.as_bytes())
+            .records()
+            .map(|r| r.expect("failed to read written embl"))
+            .collect();
+        assert_eq!(written.len(), records.len());
+        assert_eq!(written[0].header, records[0].header);
+        assert_eq!(written[0].sequence, records[0].sequence);
+        assert_eq!(written[0].ordered_features(), records[0].ordered_features());
+        assert_eq!(
+            written[0].seq_features.get_sequence_faa("pRL80001"),
+            records[0].seq_features.get_sequence_faa("pRL80001")
+        );
+    }
+    #[cfg(feature = "bzip2")]
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_compressed_input() {
+        use std::io::Write;
+        let plain = embl!("example.embl");
+        let path = std::env::temp_dir().join("microbiorust_test_example.embl.bz2");
+        let mut encoder = bzip2::write::BzEncoder::new(
+            File::create(&path).unwrap(),
+            bzip2::Compression::default(),
+        );
+        encoder
+            .write_all(&std::fs::read("example.embl").unwrap())
+            .unwrap();
+        encoder.finish().unwrap();
+        let records = embl!(&path);
+        std::fs::remove_file(&path).ok();
+        assert_eq!(records.len(), plain.len());
+        assert_eq!(records[0].id, plain[0].id);
+        assert_eq!(records[0].sequence, plain[0].sequence);
+        assert_eq!(records[0].cds.attributes, plain[0].cds.attributes);
+    }
 }
@@ -0,0 +1,106 @@
+//! # Errors of the readers
+//!
+//! The GenBank, EMBL and GFF3 readers return a ```ParseError``` giving the line number, the id of the record being read and the offending line,
+//! so a bad file can be fixed without hunting for the problem.
+//!
+//!```rust
+//! use microBioRust::gbk::Reader;
+//! use microBioRust::error::ParseError;
+//!
+//! let text = "LOCUS       test   12 bp    DNA     linear   BCT 01-JAN-2025\nFEATURES             Location/Qualifiers\n     CDS             join(1..3,\n//\n";
+//! let mut records = Reader::new(text.as_bytes()).records();
+//! match records.next() {
+//!     Some(Err(ParseError::BadLocation { line_number, record_id, .. })) => {
+//!         assert_eq!((line_number, record_id.as_str()), (3, "test"));
+//!     }
+//!     other => panic!("expected a bad location, got {:?}", other),
+//! }
+//!```
+
+use std::io;
+use thiserror::Error;
+
+///the ways a record can fail to parse, line numbers count from 1 and the line is given without its line ending
+#[derive(Debug, Error)]
+pub enum ParseError {
+    ///a record without a usable LOCUS (GenBank) or ID (EMBL) line
+    #[error("line {line_number}: missing or incomplete LOCUS/ID line for record {record_id:?}: {line:?}")]
+    MissingLocus {
+        line_number: usize,
+        record_id: String,
+        line: String,
+    },
+    ///a feature location that does not follow the INSDC location grammar
+    #[error("line {line_number} of record {record_id}: bad location {line:?}: {message}")]
+    BadLocation {
+        line_number: usize,
+        record_id: String,
+        line: String,
+        message: String,
+    },
+    ///a qualifier with a value that cannot be used, such as a non-numeric /codon_start or an unknown /transl_table
+    #[error("line {line_number} of record {record_id}: bad qualifier {line:?}: {message}")]
+    BadQualifier {
+        line_number: usize,
+        record_id: String,
+        line: String,
+        message: String,
+    },
+    ///the file ends inside the sequence of a record, or the sequence is shorter than the record length
+    #[error("line {line_number} of record {record_id}: truncated sequence {line:?}")]
+    TruncatedSequence {
+        line_number: usize,
+        record_id: String,
+        line: String,
+    },
+    ///a line without the layout of its format, such as a GFF3 feature line without nine tab separated columns
+    #[error("line {line_number} of record {record_id:?}: {message}: {line:?}")]
+    MalformedLine {
+        line_number: usize,
+        record_id: String,
+        line: String,
+        message: String,
+    },
+    ///the file could not be read
+    #[error("line {line_number} of record {record_id:?}: {source}")]
+    Io {
+        line_number: usize,
+        record_id: String,
+        line: String,
+        #[source]
+        source: io::Error,
+    },
+}
+
+impl ParseError {
+    pub fn line_number(&self) -> usize {
+        match self {
+            ParseError::MissingLocus { line_number, .. }
+            | ParseError::BadLocation { line_number, .. }
+            | ParseError::BadQualifier { line_number, .. }
+            | ParseError::TruncatedSequence { line_number, .. }
+            | ParseError::MalformedLine { line_number, .. }
+            | ParseError::Io { line_number, .. } => *line_number,
+        }
+    }
+    pub fn record_id(&self) -> &str {
+        match self {
+            ParseError::MissingLocus { record_id, .. }
+            | ParseError::BadLocation { record_id, .. }
+            | ParseError::BadQualifier { record_id, .. }
+            | ParseError::TruncatedSequence { record_id, .. }
+            | ParseError::MalformedLine { record_id, .. }
+            | ParseError::Io { record_id, .. } => record_id,
+        }
+    }
+    pub fn line(&self) -> &str {
+        match self {
+            ParseError::MissingLocus { line, .. }
+            | ParseError::BadLocation { line, .. }
+            | ParseError::BadQualifier { line, .. }
+            | ParseError::TruncatedSequence { line, .. }
+            | ParseError::MalformedLine { line, .. }
+            | ParseError::Io { line, .. } => line,
+        }
+    }
+}
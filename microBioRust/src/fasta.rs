@@ -0,0 +1,319 @@
+//! # FASTA reading and writing
+//!
+//! A streaming reader and writer for FASTA, the ```>id description``` header line is split at the first whitespace and both parts are kept.
+//! Files given to ```Reader::from_file``` can be compressed, see the ```compression``` module, they are recognised by their first bytes rather than the file name.
+//! The writer wraps the sequence at 60 characters by default, ```line_width(0)``` writes each sequence on one line.
+//!
+//!```rust
+//! use microBioRust::fasta::{Reader, Record, Writer};
+//!
+//! let text = ">b3304 rplR 50S ribosomal subunit protein L18\nMDKKSARIRR\nATRARRKLQE\n>b3305\nMSRVAKAPVV\n";
+//! let records: Vec<Record> = Reader::new(text.as_bytes())
+//!     .records()
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(records[0].id, "b3304");
+//! assert_eq!(records[0].description.as_deref(), Some("rplR 50S ribosomal subunit protein L18"));
+//! assert_eq!(records[0].seq, "MDKKSARIRRATRARRKLQE");
+//!
+//! let mut writer = Writer::new(Vec::new()).line_width(10);
+//! for record in records.iter() {
+//!     writer.write_record(record).unwrap();
+//! }
+//! assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), text);
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use anyhow::Context;
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+///the line width of sequences written by the FASTA and FASTQ writers
+pub const DEFAULT_LINE_WIDTH: usize = 60;
+
+//splits a header line after its '>' or '@' into the id and the description
+pub(crate) fn split_header(header: &str) -> (String, Option<String>) {
+    match header.trim().split_once(char::is_whitespace) {
+        Some((id, description)) => (id.to_string(), Some(description.trim().to_string())),
+        None => (header.trim().to_string(), None),
+    }
+}
+
+//writes a sequence over lines of at most width characters, a width of 0 writes it on one line
+pub(crate) fn write_wrapped<W: Write>(writer: &mut W, seq: &str, width: usize) -> io::Result<()> {
+    if width == 0 || seq.is_empty() {
+        return writeln!(writer, "{}", seq);
+    }
+    for chunk in seq.as_bytes().chunks(width) {
+        writer.write_all(chunk)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+///a FASTA entry, the sequence is held without line breaks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    pub id: String,
+    pub description: Option<String>,
+    pub seq: String,
+}
+
+impl Record {
+    pub fn new(id: &str, description: Option<&str>, seq: &str) -> Self {
+        Record {
+            id: id.to_string(),
+            description: description.map(|d| d.to_string()),
+            seq: seq.to_string(),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.seq.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.seq.is_empty()
+    }
+    ///the header line without its '>'
+    pub fn header(&self) -> String {
+        match &self.description {
+            Some(description) => format!("{} {}", self.id, description),
+            None => self.id.clone(),
+        }
+    }
+}
+
+///an iterator over the entries of a FASTA file
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Records<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug, Default)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+    //the header of the next entry, read at the end of the previous one
+    next_header: Option<String>,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read FASTA from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read FASTA from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new FASTA reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+            next_header: None,
+        }
+    }
+    fn next_line(&mut self, record_id: &str) -> Result<usize, ParseError> {
+        self.line_buffer.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: record_id.to_string(),
+                line: String::new(),
+                source,
+            })?;
+        if read > 0 {
+            self.line_number += 1;
+        }
+        Ok(read)
+    }
+    ///reads the next entry, returns None at the end of the file
+    pub fn read(&mut self) -> Result<Option<Record>, ParseError> {
+        //the first header, blank lines and ';' comments may come before it
+        while self.next_header.is_none() {
+            if self.next_line("")? == 0 {
+                return Ok(None);
+            }
+            let line = self.line_buffer.trim_end();
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+            match line.strip_prefix('>') {
+                Some(header) => self.next_header = Some(header.to_string()),
+                None => {
+                    return Err(ParseError::MalformedLine {
+                        line_number: self.line_number,
+                        record_id: String::new(),
+                        line: line.to_string(),
+                        message: "sequence before the first '>' header".to_string(),
+                    })
+                }
+            }
+        }
+        let header = self.next_header.take().unwrap_or_default();
+        let (id, description) = split_header(&header);
+        let mut seq = String::new();
+        while self.next_line(&id)? > 0 {
+            let line = self.line_buffer.trim_end();
+            if let Some(header) = line.strip_prefix('>') {
+                self.next_header = Some(header.to_string());
+                break;
+            }
+            seq.extend(line.chars().filter(|c| !c.is_whitespace()));
+        }
+        Ok(Some(Record {
+            id,
+            description,
+            seq,
+        }))
+    }
+    ///return an iterator over the entries of the file
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+///writes FASTA entries with the sequence wrapped over lines
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    writer: W,
+    line_width: usize,
+}
+
+impl Writer<io::BufWriter<fs::File>> {
+    /// Write FASTA to the given file path, replacing the file.
+    pub fn to_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::create(&path)
+            .map(|file| Writer::new(io::BufWriter::new(file)))
+            .with_context(|| format!("Failed to write FASTA to {:#?}", path))
+    }
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer,
+            line_width: DEFAULT_LINE_WIDTH,
+        }
+    }
+    ///sets the number of sequence characters per line, 0 writes each sequence on a single line
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+    pub fn write(&mut self, id: &str, description: Option<&str>, seq: &str) -> io::Result<()> {
+        match description {
+            Some(description) => writeln!(self.writer, ">{} {}", id, description)?,
+            None => writeln!(self.writer, ">{}", id)?,
+        }
+        write_wrapped(&mut self.writer, seq, self.line_width)
+    }
+    pub fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        self.write(&record.id, record.description.as_deref(), &record.seq)
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_fasta() {
+        let text = "; a comment\n\n>seq1\nACGT\nAC GT\n\n>seq2 second entry\n>seq3\nTTTT\n";
+        let records: Vec<Record> = Reader::new(text.as_bytes())
+            .records()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0], Record::new("seq1", None, "ACGTACGT"));
+        assert_eq!(records[1], Record::new("seq2", Some("second entry"), ""));
+        assert_eq!(records[2].header(), "seq3");
+        match Reader::new("ACGT\n>seq1\n".as_bytes()).records().next() {
+            Some(Err(ParseError::MalformedLine { line_number, .. })) => assert_eq!(line_number, 1),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_write_fasta() {
+        let record = Record::new("seq1", Some("a test"), "ACGTACGTAC");
+        let mut writer = Writer::new(Vec::new()).line_width(4);
+        writer.write_record(&record).unwrap();
+        writer.write("seq2", None, "").unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(text, ">seq1 a test\nACGT\nACGT\nAC\n>seq2\n\n");
+        let mut writer = Writer::new(Vec::new()).line_width(0);
+        writer.write_record(&record).unwrap();
+        assert_eq!(writer.into_inner(), b">seq1 a test\nACGTACGTAC\n");
+    }
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_read_gzip_fasta() {
+        let path = std::env::temp_dir().join("microbiorust_test_fasta.fa.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            fs::File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(b">seq1 gzip\nACGT\n").unwrap();
+        encoder.finish().unwrap();
+        let records: Vec<Record> = Reader::from_file(&path)
+            .unwrap()
+            .records()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(records, vec![Record::new("seq1", Some("gzip"), "ACGT")]);
+    }
+}
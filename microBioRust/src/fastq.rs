@@ -0,0 +1,437 @@
+//! # FASTQ reading and writing
+//!
+//! A streaming reader and writer for FASTQ, the ```@id description``` header is split like a FASTA header and the quality string is kept as read.
+//! ```Record::qualities``` decodes it to Phred scores for Sanger/Illumina 1.8+ (Phred+33) or older Illumina (Phred+64) files.
+//! Sequence and quality may be wrapped over several lines, ```Reader::from_file``` reads compressed files too.
+//!
+//!```rust
+//! use microBioRust::fastq::{QualityEncoding, Reader, Record, Writer};
+//!
+//! let text = "@read1 sample=A\nACGTN\n+\nII5+!\n";
+//! let records: Vec<Record> = Reader::new(text.as_bytes())
+//!     .records()
+//!     .collect::<Result<_, _>>()
+//!     .unwrap();
+//! assert_eq!(records[0].description.as_deref(), Some("sample=A"));
+//! assert_eq!(records[0].qualities(QualityEncoding::Phred33).unwrap(), vec![40, 40, 20, 10, 0]);
+//!
+//! let mut writer = Writer::new(Vec::new());
+//! writer.write_record(&records[0]).unwrap();
+//! assert_eq!(String::from_utf8(writer.into_inner()).unwrap(), text);
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::fasta::{split_header, write_wrapped};
+use anyhow::{anyhow, Context};
+use std::{
+    fs,
+    io::{self, Write},
+    path::Path,
+};
+
+///the offset of the quality characters from the Phred score
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum QualityEncoding {
+    ///Sanger and Illumina 1.8+, '!' is 0
+    #[default]
+    Phred33,
+    ///Illumina 1.3 to 1.7, '@' is 0
+    Phred64,
+}
+
+impl QualityEncoding {
+    pub fn offset(&self) -> u8 {
+        match self {
+            QualityEncoding::Phred33 => 33,
+            QualityEncoding::Phred64 => 64,
+        }
+    }
+    ///the Phred scores of a quality string
+    pub fn decode(&self, quality: &str) -> Result<Vec<u8>, anyhow::Error> {
+        let offset = self.offset();
+        quality
+            .bytes()
+            .map(|q| match q {
+                b'!'..=b'~' if q >= offset => Ok(q - offset),
+                _ => Err(anyhow!(
+                    "quality character {:?} is out of range for {:?}",
+                    q as char,
+                    self
+                )),
+            })
+            .collect()
+    }
+    ///the quality string of Phred scores, scores too high for a printable character are capped
+    pub fn encode(&self, scores: &[u8]) -> String {
+        let offset = self.offset();
+        scores
+            .iter()
+            .map(|score| score.saturating_add(offset).min(b'~') as char)
+            .collect()
+    }
+}
+
+///a FASTQ entry, sequence and quality are held without line breaks
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Record {
+    pub id: String,
+    pub description: Option<String>,
+    pub seq: String,
+    pub quality: String,
+}
+
+impl Record {
+    pub fn new(id: &str, description: Option<&str>, seq: &str, quality: &str) -> Self {
+        Record {
+            id: id.to_string(),
+            description: description.map(|d| d.to_string()),
+            seq: seq.to_string(),
+            quality: quality.to_string(),
+        }
+    }
+    ///a record from Phred scores, written with the given encoding
+    pub fn from_scores(
+        id: &str,
+        description: Option<&str>,
+        seq: &str,
+        scores: &[u8],
+        encoding: QualityEncoding,
+    ) -> Self {
+        Record::new(id, description, seq, &encoding.encode(scores))
+    }
+    pub fn len(&self) -> usize {
+        self.seq.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.seq.is_empty()
+    }
+    ///the Phred score of each base
+    pub fn qualities(&self, encoding: QualityEncoding) -> Result<Vec<u8>, anyhow::Error> {
+        encoding.decode(&self.quality)
+    }
+    ///the mean Phred score, None for an empty read
+    pub fn mean_quality(&self, encoding: QualityEncoding) -> Result<Option<f64>, anyhow::Error> {
+        let scores = self.qualities(encoding)?;
+        Ok(match scores.is_empty() {
+            true => None,
+            false => Some(scores.iter().map(|s| *s as f64).sum::<f64>() / scores.len() as f64),
+        })
+    }
+    ///the read as a FASTA entry, dropping the qualities
+    pub fn to_fasta(&self) -> crate::fasta::Record {
+        crate::fasta::Record {
+            id: self.id.clone(),
+            description: self.description.clone(),
+            seq: self.seq.clone(),
+        }
+    }
+}
+
+///an iterator over the entries of a FASTQ file
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Records<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug, Default)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+    encoding: Option<QualityEncoding>,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read FASTQ from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read FASTQ from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new FASTQ reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+            encoding: None,
+        }
+    }
+    ///checks every quality character against the encoding while reading, a read out of range is a ```BadQualifier``` error
+    pub fn encoding(mut self, encoding: QualityEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+    fn next_line(&mut self, record_id: &str) -> Result<usize, ParseError> {
+        self.line_buffer.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: record_id.to_string(),
+                line: String::new(),
+                source,
+            })?;
+        if read > 0 {
+            self.line_number += 1;
+        }
+        Ok(read)
+    }
+    fn malformed(&self, record_id: &str, message: &str) -> ParseError {
+        ParseError::MalformedLine {
+            line_number: self.line_number,
+            record_id: record_id.to_string(),
+            line: self.line_buffer.trim_end().to_string(),
+            message: message.to_string(),
+        }
+    }
+    ///reads the next entry, returns None at the end of the file
+    pub fn read(&mut self) -> Result<Option<Record>, ParseError> {
+        loop {
+            if self.next_line("")? == 0 {
+                return Ok(None);
+            }
+            if !self.line_buffer.trim().is_empty() {
+                break;
+            }
+        }
+        let header = match self.line_buffer.trim_end().strip_prefix('@') {
+            Some(header) => header.to_string(),
+            None => return Err(self.malformed("", "expected a '@' header")),
+        };
+        let (id, description) = split_header(&header);
+        //the sequence runs to the '+' line, the quality is as long as the sequence
+        let mut seq = String::new();
+        loop {
+            if self.next_line(&id)? == 0 {
+                return Err(ParseError::TruncatedSequence {
+                    line_number: self.line_number,
+                    record_id: id,
+                    line: String::new(),
+                });
+            }
+            let line = self.line_buffer.trim_end();
+            if line.starts_with('+') {
+                break;
+            }
+            seq.push_str(line);
+        }
+        let mut quality = String::new();
+        while quality.len() < seq.len() {
+            if self.next_line(&id)? == 0 {
+                return Err(ParseError::TruncatedSequence {
+                    line_number: self.line_number,
+                    record_id: id,
+                    line: String::new(),
+                });
+            }
+            quality.push_str(self.line_buffer.trim_end());
+        }
+        if quality.len() != seq.len() {
+            return Err(self.malformed(&id, "quality is longer than the sequence"));
+        }
+        if let Some(encoding) = self.encoding {
+            encoding
+                .decode(&quality)
+                .map_err(|e| ParseError::BadQualifier {
+                    line_number: self.line_number,
+                    record_id: id.clone(),
+                    line: self.line_buffer.trim_end().to_string(),
+                    message: e.to_string(),
+                })?;
+        }
+        Ok(Some(Record {
+            id,
+            description,
+            seq,
+            quality,
+        }))
+    }
+    ///return an iterator over the entries of the file
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+///writes FASTQ entries, unwrapped unless a line width is set
+#[derive(Debug)]
+pub struct Writer<W: Write> {
+    writer: W,
+    line_width: usize,
+}
+
+impl Writer<io::BufWriter<fs::File>> {
+    /// Write FASTQ to the given file path, replacing the file.
+    pub fn to_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        fs::File::create(&path)
+            .map(|file| Writer::new(io::BufWriter::new(file)))
+            .with_context(|| format!("Failed to write FASTQ to {:#?}", path))
+    }
+}
+
+impl<W: Write> Writer<W> {
+    pub fn new(writer: W) -> Self {
+        Writer {
+            writer,
+            line_width: 0,
+        }
+    }
+    ///sets the number of sequence and quality characters per line, 0 (the default) writes each on a single line
+    pub fn line_width(mut self, line_width: usize) -> Self {
+        self.line_width = line_width;
+        self
+    }
+    pub fn write(
+        &mut self,
+        id: &str,
+        description: Option<&str>,
+        seq: &str,
+        quality: &str,
+    ) -> io::Result<()> {
+        if seq.len() != quality.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{}: sequence and quality differ in length", id),
+            ));
+        }
+        match description {
+            Some(description) => writeln!(self.writer, "@{} {}", id, description)?,
+            None => writeln!(self.writer, "@{}", id)?,
+        }
+        write_wrapped(&mut self.writer, seq, self.line_width)?;
+        writeln!(self.writer, "+")?;
+        write_wrapped(&mut self.writer, quality, self.line_width)
+    }
+    pub fn write_record(&mut self, record: &Record) -> io::Result<()> {
+        self.write(
+            &record.id,
+            record.description.as_deref(),
+            &record.seq,
+            &record.quality,
+        )
+    }
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_quality_encoding() {
+        assert_eq!(
+            QualityEncoding::Phred33.decode("!+5I").unwrap(),
+            vec![0, 10, 20, 40]
+        );
+        assert_eq!(
+            QualityEncoding::Phred64.decode("@JTh").unwrap(),
+            vec![0, 10, 20, 40]
+        );
+        assert!(QualityEncoding::Phred64.decode("!").is_err());
+        assert_eq!(QualityEncoding::Phred64.encode(&[0, 10, 20, 40]), "@JTh");
+        let record = Record::from_scores(
+            "r1",
+            None,
+            "ACGT",
+            &[0, 10, 20, 40],
+            QualityEncoding::Phred33,
+        );
+        assert_eq!(record.quality, "!+5I");
+        assert_eq!(
+            record.mean_quality(QualityEncoding::Phred33).unwrap(),
+            Some(17.5)
+        );
+    }
+    #[test]
+    fn test_read_fastq() {
+        //the quality of the second read is wrapped and starts with '@'
+        let text = "@r1 first\nACGT\nAC\n+r1\nIIII\nII\n\n@r2\nACG\n+\n@@I\n";
+        let records: Vec<Record> = Reader::new(text.as_bytes())
+            .records()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(
+            records[0],
+            Record::new("r1", Some("first"), "ACGTAC", "IIIIII")
+        );
+        assert_eq!(records[1], Record::new("r2", None, "ACG", "@@I"));
+        match Reader::new("@r1\nACGT\n+\nII\n".as_bytes())
+            .records()
+            .next()
+        {
+            Some(Err(ParseError::TruncatedSequence { record_id, .. })) => {
+                assert_eq!(record_id, "r1")
+            }
+            other => panic!("expected a truncated read, got {:?}", other),
+        }
+        let bad = Reader::new("@r1\nAC\n+\n!I\n".as_bytes())
+            .encoding(QualityEncoding::Phred64)
+            .records()
+            .next();
+        assert!(matches!(bad, Some(Err(ParseError::BadQualifier { .. }))));
+    }
+    #[test]
+    fn test_write_fastq() {
+        let record = Record::new("r1", Some("first"), "ACGTAC", "IIIII5");
+        let mut writer = Writer::new(Vec::new()).line_width(4);
+        writer.write_record(&record).unwrap();
+        let text = String::from_utf8(writer.into_inner()).unwrap();
+        assert_eq!(text, "@r1 first\nACGT\nAC\n+\nIIII\nI5\n");
+        let records: Vec<Record> = Reader::new(text.as_bytes())
+            .records()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(records, vec![record]);
+        let mut writer = Writer::new(Vec::new());
+        assert!(writer.write("r2", None, "ACGT", "II").is_err());
+    }
+}
@@ -19,6 +19,7 @@
 //! 2. Features, ```FeatureAttributes```, construct(enum) of counter (locus tag), gene (if present), product, codon start, strand, start, stop [of cds/gene]
 //! 3. Sequence features, ```SequenceAttributes```, construct(enum) of counter (locus tag), sequence_ffn (DNA gene sequence) sequence_faa (protein translation), strand, codon start, start, stop [cds/gene]
 //! 4. The DNA sequence of the whole record (or contig)
+//! 5. Every other feature of the feature table (gene, rRNA, tRNA, ncRNA, repeat_region, mobile_element, misc_feature...), ```features```, a map of feature type to ```FeatureAttributeBuilder``` keyed by locus tag (or feature type and counter such as tRNA_3 where there is no locus tag)
 //!
 //!  Example to extract and print all the protein sequence fasta, example using getters or get_ functionality
 //!
@@ -275,18 +276,25 @@
 //!```
 //!
 
+use crate::bed::Record as BedRecord;
+use crate::compression::open;
+use crate::embl::{EmblEntry, Writer as EmblWriter};
+use crate::error::ParseError;
+use crate::gff::{fasta_entry, location_parts, unique_id};
+use crate::gtf::{Transcript, RNA_FEATURES};
+use crate::location::{format_location, location_continues, parse_location};
+use crate::qualifiers::{format_qualifier, gff3_attributes, gff3_escape, QualifierLines};
+use crate::translation::{compare_translation, genetic_code_or_default, DEFAULT_TABLE};
 use anyhow::{anyhow, Context};
-use bio::alphabets::dna::revcomp;
 use chrono::prelude::*;
 use itertools::Itertools;
 use lazy_static::lazy_static;
 use paste::paste;
-use protein_translate::translate;
 use regex::Regex;
 use std::{
-    collections::{BTreeMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     convert::{AsRef, TryInto},
-    fs::{self, File, OpenOptions},
+    fs::{File, OpenOptions},
     io::{self, Write},
     path::Path,
     str,
@@ -295,11 +303,6 @@ use std::{
 
 // Compile regexes once at module load time for massive performance improvement
 lazy_static! {
-    /// Regex for parsing location coordinates (e.g., "1..1000")
-    /// Used in source and CDS coordinate extraction
-    static ref LOCATION_REGEX: Regex = Regex::new(r"([0-9]+)[[:punct:]]+([0-9]+)")
-        .expect("Failed to compile LOCATION_REGEX");
-
     /// Regex for cleaning odd punctuation and Greek letters from product names
     /// Replaces problematic characters like unclosed quotes, biochemical symbols
     static ref PUNCTUATION_REGEX: Regex = Regex::new(r"[/?()',`]|[α-ωΑ-Ω]")
@@ -376,6 +379,12 @@ macro_rules! create_builder {
 	    pub fn build(self) -> BTreeMap<String, HashSet<$enum_name>> {
 	        self.$attributes
             }
+	    // function to remove the attributes of a key that fail the predicate, e.g. before setting a new start and stop
+	    pub fn retain_attributes(&mut self, key: &str, keep: impl FnMut(&$enum_name) -> bool) {
+	        if let Some(attributes) = self.$attributes.get_mut(key) {
+		    attributes.retain(keep);
+		    }
+	    }
 	    // function to iterate immutably through the BTreeMap as required
 	    pub fn iter_sorted(&'_ self) -> std::collections::btree_map::Iter<'_, String, HashSet<$enum_name>> {
 	        self.$attributes.iter()
@@ -394,22 +403,28 @@ macro_rules! create_builder {
 #[macro_export]
 macro_rules! genbank {
     ($filename:expr) => {{
-        use std::fs::File;
-        use std::io::BufReader;
-        let file = File::open($filename)
-            .unwrap_or_else(|e| panic!("Could not open file {}: {}", $filename, e));
-        let mut reader = $crate::gbk::Reader::new(file);
-        let mut vec = Vec::new();
-        for rec in reader.records() {
-            match rec {
-                Ok(r) => {
-                    //println!("this is r {:?}", &r);
-                    vec.push(r);
-                }
-                Err(e) => panic!("Error reading record: {:?}", e),
-            }
+        match $crate::try_genbank!($filename) {
+            Ok(records) => records,
+            Err(e) => panic!("Error reading record: {}", e),
+        }
+    }};
+}
+
+///reads every record of a GenBank file, returning the first ```ParseError``` instead of panicking
+#[macro_export]
+macro_rules! try_genbank {
+    ($filename:expr) => {{
+        match $crate::compression::open($filename) {
+            Ok(file) => $crate::gbk::Reader::new(file)
+                .records()
+                .collect::<Result<Vec<$crate::gbk::Record>, $crate::error::ParseError>>(),
+            Err(source) => Err($crate::error::ParseError::Io {
+                line_number: 0,
+                record_id: String::new(),
+                line: String::new(),
+                source,
+            }),
         }
-        vec
     }};
 }
 
@@ -443,11 +458,10 @@ impl<B> Iterator for Records<B>
 where
     B: io::BufRead,
 {
-    type Item = Result<Record, anyhow::Error>;
+    type Item = Result<Record, ParseError>;
 
     fn next(&mut self) -> Option<Self::Item> {
         if self.error_has_occurred {
-            println!("error was encountered in iteration");
             None
         } else {
             let mut record = Record::new();
@@ -460,9 +474,8 @@ where
                     }
                 }
                 Err(err) => {
-                    //println!("we encountered an error {:?}", &err);
                     self.error_has_occurred = true;
-                    Some(Err(anyhow!("next record read error {:?}", err)))
+                    Some(Err(err))
                 }
             }
         }
@@ -470,7 +483,7 @@ where
 }
 
 pub trait GbkRead {
-    fn read(&mut self, record: &mut Record) -> Result<Record, anyhow::Error>;
+    fn read(&mut self, record: &mut Record) -> Result<Record, ParseError>;
 }
 
 ///per line reader for the file
@@ -478,12 +491,15 @@ pub trait GbkRead {
 pub struct Reader<B> {
     reader: B,
     line_buffer: String,
+    line_number: usize,
+    validate: bool,
+    prefer_annotated: bool,
 }
 
-impl Reader<io::BufReader<fs::File>> {
-    /// Read Gbk from given file path in given format.
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read Gbk from given file path, decompressing gzip, BGZF, bzip2, xz or zstd files in given format.
     pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
-        fs::File::open(&path)
+        open(&path)
             .map(Reader::new)
             .with_context(|| format!("Failed to read Gbk from {:#?}", path))
     }
@@ -498,6 +514,9 @@ where
         Reader {
             reader: io::BufReader::new(reader),
             line_buffer: String::new(),
+            line_number: 0,
+            validate: false,
+            prefer_annotated: false,
         }
     }
 }
@@ -510,7 +529,36 @@ where
         Reader {
             reader: bufreader,
             line_buffer: String::new(),
+            line_number: 0,
+            validate: false,
+            prefer_annotated: false,
+        }
+    }
+    ///compares the protein translated from each CDS with its /translation, differences are kept in ```Record::translation_issues``` by locus tag
+    pub fn validate_translations(mut self, validate: bool) -> Self {
+        self.validate = validate;
+        self
+    }
+    ///fills ```SequenceFaa``` with the annotated /translation of a CDS when it has one, rather than the computed protein
+    pub fn prefer_annotated_translation(mut self, prefer_annotated: bool) -> Self {
+        self.prefer_annotated = prefer_annotated;
+        self
+    }
+    //reads the next line into the line buffer, counting the lines for the error messages
+    fn next_line(&mut self, record_id: &str) -> Result<usize, ParseError> {
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: record_id.to_string(),
+                line: String::new(),
+                source,
+            })?;
+        if read > 0 {
+            self.line_number += 1;
         }
+        Ok(read)
     }
     //return an iterator over the records of the genbank file
     pub fn records(self) -> Records<B> {
@@ -529,7 +577,7 @@ where
     #[allow(unused_mut)]
     #[allow(unused_variables)]
     #[allow(unused_assignments)]
-    fn read(&mut self, record: &mut Record) -> Result<Record, anyhow::Error> {
+    fn read(&mut self, record: &mut Record) -> Result<Record, ParseError> {
         record.rec_clear();
         //println!("reading new record");
         //initialise variables with capacity hints for better performance
@@ -537,7 +585,6 @@ where
         let mut source_map = SourceAttributeBuilder::new();
         let mut cds = FeatureAttributeBuilder::new();
         let mut seq_features = SequenceAttributeBuilder::new();
-        let mut cds_counter: i32 = 0;
         let mut source_counter: i32 = 0;
         let mut prev_end: u32 = 0;
         let mut organism = String::with_capacity(64);
@@ -545,12 +592,12 @@ where
         let mut strain = String::with_capacity(64);
         let mut source_name = String::with_capacity(32);
         let mut type_material = String::with_capacity(64);
-        let mut theend: u32 = 0;
-        let mut thestart: u32 = 0;
         let mut db_xref = String::with_capacity(32);
+        let mut first_source = String::new();
+        let mut feature_counter: BTreeMap<String, i32> = BTreeMap::new();
         //check if there are any more lines, if not return the record as is
         if self.line_buffer.is_empty() {
-            self.reader.read_line(&mut self.line_buffer)?;
+            self.next_line(&record.id)?;
             if self.line_buffer.is_empty() {
                 return Ok(record.to_owned());
             }
@@ -566,184 +613,223 @@ where
                 header_iter.next();
                 record.id = header_iter
                     .next()
-                    .ok_or_else(|| anyhow::anyhow!("missing record id"))? // Get &str or error
+                    .ok_or_else(|| missing_locus(self.line_number, &record.id, &self.line_buffer))?
                     .to_string();
                 let lens = header_iter
                     .next()
-                    .ok_or_else(|| anyhow::anyhow!("missing record length"))? // Get &str or error
+                    .ok_or_else(|| missing_locus(self.line_number, &record.id, &self.line_buffer))?
                     .to_string();
-                record.length = lens.trim().parse::<u32>()?;
-                self.line_buffer.clear();
+                record.length = lens
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| missing_locus(self.line_number, &record.id, &self.line_buffer))?;
+                record.header = RecordHeader::from_locus_line(&self.line_buffer);
+                record.topology = Topology::from_locus_line(&self.line_buffer);
+                //collect the header sections up to the FEATURES table
+                let mut header_lines: Vec<String> = Vec::new();
+                loop {
+                    self.line_buffer.clear();
+                    self.next_line(&record.id)?;
+                    if self.line_buffer.is_empty()
+                        || self.line_buffer.starts_with("FEATURES")
+                        || self.line_buffer.starts_with("ORIGIN")
+                    {
+                        break;
+                    }
+                    header_lines.push(self.line_buffer.trim_end().to_string());
+                }
+                record.header.parse_genbank_lines(&header_lines);
+            }
+            //a feature table or sequence without a LOCUS line above it
+            if record.id.is_empty()
+                && (feature_key(&self.line_buffer).is_some()
+                    || self.line_buffer.starts_with("ORIGIN"))
+            {
+                return Err(missing_locus(
+                    self.line_number,
+                    &record.id,
+                    &self.line_buffer,
+                ));
             }
             //collect the source fields and populate the source_map and source_attributes
-            if self.line_buffer.starts_with("     source") {
-                // Use pre-compiled regex from lazy_static for 10-50x performance improvement
-                let location = LOCATION_REGEX
-                    .captures(&self.line_buffer)
-                    .ok_or_else(|| anyhow::anyhow!("missing location"))?;
-                let start = &location[1];
-                let end = &location[2];
-                thestart = start.trim().parse::<u32>()?;
+            if feature_key(&self.line_buffer) == Some("source") {
+                //the location follows the feature key and can continue over several lines
+                let mut location_text = self.line_buffer.trim_start()["source".len()..]
+                    .trim()
+                    .to_string();
+                let feature_line = self.line_number;
                 source_counter += 1;
                 source_name = format!("source_{}_{}", record.id, source_counter).to_string();
-                thestart += prev_end;
-                theend = end.trim().parse::<u32>()? + prev_end;
-                //println!("so the start and end are {:?} {:?}", &thestart, &theend);
+                let mut qualifier_lines = QualifierLines::new();
                 loop {
                     self.line_buffer.clear();
-                    self.reader.read_line(&mut self.line_buffer)?;
-                    if self.line_buffer.starts_with("     CDS") {
+                    self.next_line(&record.id)?;
+                    //the source ends at the next feature key or at the end of the FEATURES table
+                    if feature_key(&self.line_buffer).is_some()
+                        || !self.line_buffer.starts_with(' ')
+                    {
+                        let location = parse_location(&location_text).map_err(|e| {
+                            ParseError::BadLocation {
+                                line_number: feature_line,
+                                record_id: record.id.clone(),
+                                line: location_text.clone(),
+                                message: e.to_string(),
+                            }
+                        })?;
+                        let (start, stop) = record.bounds(&location);
+                        let qualifiers = qualifier_lines.finish();
+                        organism = qualifiers.get("organism").unwrap_or_default().to_string();
+                        mol_type = qualifiers.get("mol_type").unwrap_or_default().to_string();
+                        strain = qualifiers.get("strain").unwrap_or_default().to_string();
+                        type_material = qualifiers
+                            .get("type_material")
+                            .unwrap_or_default()
+                            .to_string();
+                        db_xref = qualifiers.get("db_xref").unwrap_or_default().to_string();
                         //println!("this source name {:?} start {:?} end {:?} organism {:?} mol_type {:?} strain {:?} type_material {:?} db_xref {:?}", &source_name,&thestart, &theend, &organism, &mol_type, &strain, &type_material, &db_xref);
+                        if first_source.is_empty() {
+                            first_source = source_name.clone();
+                        }
                         record
                             .source_map
                             .set_counter(source_name.to_string())
-                            .set_start(RangeValue::Exact(thestart))
-                            .set_stop(RangeValue::Exact(theend))
+                            .set_start(start)
+                            .set_stop(stop)
                             .set_organism(organism.clone())
                             .set_mol_type(mol_type.clone())
                             .set_strain(strain.clone())
                             // culture_collection.clone()
                             .set_type_material(type_material.clone())
-                            .set_db_xref(db_xref.clone());
+                            .set_db_xref(db_xref.clone())
+                            .set_qualifiers(qualifiers);
                         continue 'outer;
                     }
-                    if self.line_buffer.contains("/organism") {
-                        let org: Vec<&str> = self.line_buffer.split('\"').collect();
-                        organism = org[1].to_string();
-                    }
-                    if self.line_buffer.contains("/mol_type") {
-                        let mol: Vec<&str> = self.line_buffer.split('\"').collect();
-                        mol_type = mol[1].to_string();
-                    }
-                    if self.line_buffer.contains("/strain") {
-                        let stra: Vec<&str> = self.line_buffer.split('\"').collect();
-                        strain = stra[1].to_string();
-                    }
-                    //    if self.line_buffer.contains("/culture_collection") {
-                    //        let cc: Vec<&str> = self.line_buffer.split('\"').collect();
-                    //		culture_collection = cc[1].to_string();
-                    //		}
-                    if self.line_buffer.contains("/type_material") {
-                        let mat: Vec<&str> = self.line_buffer.split('\"').collect();
-                        type_material = mat[1].to_string();
-                    }
-                    if self.line_buffer.contains("/db_xref") {
-                        let db: Vec<&str> = self.line_buffer.split('\"').collect();
-                        db_xref = db[1].to_string();
+                    if location_continues(&location_text) {
+                        location_text.push_str(self.line_buffer.trim());
+                        continue;
                     }
+                    //every source qualifier is kept (organism, mol_type, strain, sub_strain, serovar, country...)
+                    qualifier_lines.push_line(&self.line_buffer);
                 }
             }
-            //populate the FeatureAttributes and the coding sequence annotation
-            if self.line_buffer.starts_with("     CDS") {
-                let mut startiter: Vec<_> = Vec::new();
-                let mut enditer: Vec<_> = Vec::new();
-                let mut thestart: u32 = 0;
-                let mut thend: u32 = 0;
-                let mut joined: bool = false;
-                //gather the feature coordinates
-                let joined = self.line_buffer.contains("join");
-                // Use pre-compiled regex from lazy_static for 10-50x performance improvement
-                //let matches: Vec<&regex::Captures> = LOCATION_REGEX.captures_iter(&self.line_buffer).collect();
-                for cap in LOCATION_REGEX.captures_iter(&self.line_buffer) {
-                    cds_counter += 1;
-                    thestart = cap[1]
-                        .parse()
-                        .expect("failed to match and parse numerical start");
-                    theend = cap[2]
-                        .parse()
-                        .expect("failed to match and parse numerical end");
-                    startiter.push(thestart);
-                    enditer.push(theend);
-                }
-                let mut gene = String::new();
-                let mut product = String::new();
-                let strand: i8 = if self.line_buffer.contains("complement") {
-                    -1
-                } else {
-                    1
-                };
-                let mut locus_tag = String::new();
-                let mut codon_start: u8 = 1;
+            //populate the FeatureAttributes for the coding sequences (CDS) and every other feature type in the FEATURES table (gene, rRNA, tRNA, mobile_element...)
+            if let Some(key) = feature_key(&self.line_buffer) {
+                let feature_type = key.to_string();
+                let feature_count = feature_counter.entry(feature_type.clone()).or_insert(0);
+                *feature_count += 1;
+                let feature_count = *feature_count;
+                let feature_line = self.line_number;
+                //the location follows the feature key and can continue over several lines
+                let mut location_text = self.line_buffer.trim_start()[feature_type.len()..]
+                    .trim()
+                    .to_string();
+                let mut qualifier_lines = QualifierLines::new();
                 //loop to populate the feature attributes, when complete it calls to the outer loop directly to prevent reading a new line into self.line_buffer
                 loop {
                     self.line_buffer.clear();
-                    self.reader.read_line(&mut self.line_buffer)?;
-                    if self.line_buffer.contains("/locus_tag=") {
-                        let loctag: Vec<&str> = self.line_buffer.split('\"').collect();
-                        locus_tag = loctag[1].to_string();
-                        //println!("designated locus tag {:?}", &locus_tag);
-                    }
-                    if self.line_buffer.contains("/codon_start") {
-                        let codstart: Vec<&str> = self.line_buffer.split('=').collect();
-                        let valstart = codstart[1].trim().parse::<u8>()?;
-                        codon_start = valstart;
-                        //println!("designated codon start {:?} {:?}", &codon_start, &locus_tag);
-                    }
-                    if self.line_buffer.contains("/gene=") {
-                        let gen: Vec<&str> = self.line_buffer.split('\"').collect();
-                        gene = gen[1].to_string();
-                        //println!("gene designated {:?} {:?}", &gene, &locus_tag);
-                    }
-                    if self.line_buffer.contains("/product") {
-                        let prod: Vec<&str> = self.line_buffer.split('\"').collect();
-                        product = substitute_odd_punctuation(prod[1].to_string())?;
-                        //println!("designated product {:?} {:?}", &product, &locus_tag);
-                    }
-                    if self.line_buffer.starts_with("     CDS")
-                        || self.line_buffer.starts_with("ORIGIN")
-                        || self.line_buffer.starts_with("     gene")
-                        || self.line_buffer.starts_with("     misc_feature")
+                    self.next_line(&record.id)?;
+                    //the feature ends at the next feature key or at the end of the FEATURES table (ORIGIN, CONTIG, BASE COUNT)
+                    if feature_key(&self.line_buffer).is_some()
+                        || !self.line_buffer.starts_with(' ')
                     {
+                        let qualifiers = qualifier_lines.finish();
+                        let mut locus_tag = qualifiers.locus_tag().unwrap_or_default().to_string();
+                        let gene = qualifiers.gene().unwrap_or_default().to_string();
+                        let product = substitute_odd_punctuation(
+                            qualifiers.product().unwrap_or_default().to_string(),
+                        )
+                        .map_err(|e| ParseError::BadQualifier {
+                            line_number: feature_line,
+                            record_id: record.id.clone(),
+                            line: format!("/product={:?}", qualifiers.product()),
+                            message: e.to_string(),
+                        })?;
+                        let codon_start = match qualifiers.get("codon_start") {
+                            Some(value) => value.trim().parse::<u8>().map_err(|e| {
+                                ParseError::BadQualifier {
+                                    line_number: feature_line,
+                                    record_id: record.id.clone(),
+                                    line: format!("/codon_start={}", value),
+                                    message: e.to_string(),
+                                }
+                            })?,
+                            None => 1,
+                        };
+                        let location = parse_location(&location_text).map_err(|e| {
+                            ParseError::BadLocation {
+                                line_number: feature_line,
+                                record_id: record.id.clone(),
+                                line: location_text.clone(),
+                                message: e.to_string(),
+                            }
+                        })?;
+                        let (start, stop) = record.bounds(&location);
                         if locus_tag.is_empty() {
-                            locus_tag = format!("CDS_{}", cds_counter).to_string();
+                            locus_tag = format!("{}_{}", feature_type, feature_count).to_string();
                         }
-                        if joined {
-                            //println!("currently the start is {:?} and the stop is {:?}", &startiter, &enditer);
-                            for (i, m) in startiter.iter().enumerate() {
-                                let loc_tag = format!("{}_{}", locus_tag.clone(), i);
-                                //check we may need to add or subtract one to m
-                                record
-                                    .cds
-                                    .set_counter(loc_tag)
-                                    .set_start(RangeValue::Exact(*m))
-                                    .set_stop(RangeValue::Exact(enditer[i]))
-                                    .set_gene(gene.to_string())
-                                    .set_product(product.to_string())
-                                    .set_codon_start(codon_start)
-                                    .set_strand(strand);
-                            }
-                            continue 'outer;
-                        } else {
-                            record
-                                .cds
-                                .set_counter(locus_tag.clone())
-                                .set_start(RangeValue::Exact(thestart))
-                                .set_stop(RangeValue::Exact(theend))
-                                .set_gene(gene.to_string())
-                                .set_product(product.to_string())
-                                .set_codon_start(codon_start)
-                                .set_strand(strand);
-                            continue 'outer;
+                        let features = record.features_mut(&feature_type);
+                        //features such as repeated misc_feature lines can share a locus tag, keep each one under its own key
+                        if features.attributes.contains_key(&locus_tag) {
+                            locus_tag = format!("{}_{}", locus_tag, feature_count).to_string();
                         }
+                        //joined features are kept whole, the parts are available from the location
+                        features
+                            .set_counter(locus_tag.clone())
+                            .set_start(start)
+                            .set_stop(stop)
+                            .set_gene(gene.to_string())
+                            .set_product(product.to_string())
+                            .set_strand(location.strand())
+                            .set_location(location)
+                            .set_qualifiers(qualifiers);
+                        if feature_type == "CDS" {
+                            features.set_codon_start(codon_start);
+                        }
+                        continue 'outer;
                     }
+                    if location_continues(&location_text) {
+                        location_text.push_str(self.line_buffer.trim());
+                        continue;
+                    }
+                    //keep every qualifier in file order, values can continue over several lines
+                    qualifier_lines.push_line(&self.line_buffer);
                 }
             }
             //check if we have reached the DNA sequence section and populate the record sequences field if so.  Returns the record on finding end of record mark
             if self.line_buffer.starts_with("ORIGIN") {
                 let mut sequences = String::new();
+                let mut last_line = self.line_buffer.trim_end().to_string();
                 let result_seq = loop {
                     self.line_buffer.clear();
-                    self.reader.read_line(&mut self.line_buffer)?;
+                    self.next_line(&record.id)?;
                     if self.line_buffer.starts_with("//") {
                         break sequences;
+                    } else if self.line_buffer.is_empty() {
+                        //the file ends before the end of record mark
+                        return Err(ParseError::TruncatedSequence {
+                            line_number: self.line_number,
+                            record_id: record.id.clone(),
+                            line: last_line,
+                        });
                     } else {
-                        let s: Vec<&str> = self.line_buffer.split_whitespace().collect();
-                        let s = &s[1..];
-                        let sequence = s.iter().join("");
+                        last_line = self.line_buffer.trim_end().to_string();
+                        //skip the position numbering, blank lines hold no sequence
+                        let sequence = self.line_buffer.split_whitespace().skip(1).join("");
                         sequences.push_str(&sequence);
                     }
                 };
                 record.sequence = result_seq.to_string();
+                if (record.sequence.len() as u64) < record.length as u64 {
+                    return Err(ParseError::TruncatedSequence {
+                        line_number: self.line_number,
+                        record_id: record.id.clone(),
+                        line: last_line,
+                    });
+                }
+                //a record can hold several source features, the first one spans the record
+                if !first_source.is_empty() {
+                    record.source_map.set_source_name(first_source.clone());
+                }
                 let mut iterablecount: u32 = 0;
                 //Fields are completed and populated for the FeatureAttributes, collect and populate the SequenceAttributes fields
                 for (key, val) in record.cds.iter_sorted() {
@@ -783,83 +869,91 @@ where
                             _ => (),
                         }
                     }
-                    let sta = a.map(|o| o as usize).ok_or(anyhow!("No value for start"))?;
-                    let sto = b.map(|t| t as usize).ok_or(anyhow!("No value for stop"))? - 1;
-                    let stra = c.map(|u| u).ok_or(anyhow!("No value for strand"))?;
-                    let cod = d
-                        .map(|v| v as usize - 1)
-                        .ok_or(anyhow!("No value for strand"))?;
-
-                    let star = sta.try_into()?;
-                    let stow = sto.try_into()?;
-                    let codd = cod.try_into()?;
-                    let mut sliced_sequence: &str = "";
-                    //collects the DNA sequence and translations on the correct strand
-                    if stra == -1 {
-                        if cod > 1 {
-                            //println!("reverse strand coding start more than one {:?}", &iterablecount);
-                            if sto < record.sequence.len() {
-                                sliced_sequence = &record.sequence[sta + cod..sto + 1];
-                            } else {
-                                sliced_sequence = &record.sequence[sta + cod..sto];
-                            }
-                        } else {
-                            //println!("record sta {:?} sto {:?} cod {:?} stra {:?} record.seq length {:?}", &sta, &sto, &cod, &stra, &record.sequence.len());
-                            //println!("sliced sta {:?} sliced sto {:?} record.id {:?}", sta, sto, &record.id);
-                            //println!("iterable count is {:?} reverse strand codon start one", &iterablecount);
-                            if sto < record.sequence.len() {
-                                sliced_sequence = &record.sequence[sta..sto + 1];
-                            } else {
-                                sliced_sequence = &record.sequence[sta..sto];
-                            }
-                            //println!("iterable count after is {:?}", &iterablecount);
-                        }
-                        let cds_char = sliced_sequence;
-                        let prot_seq = translate(&revcomp(cds_char.as_bytes()));
-                        let parts: Vec<&str> = prot_seq.split('*').collect();
+                    let bad_location = |message: &str| ParseError::BadLocation {
+                        line_number: self.line_number,
+                        record_id: record.id.clone(),
+                        line: key.to_string(),
+                        message: message.to_string(),
+                    };
+                    let star = a.ok_or(bad_location("no value for start"))?;
+                    let stow = b
+                        .ok_or(bad_location("no value for stop"))?
+                        .saturating_sub(1);
+                    let stra = c.ok_or(bad_location("no value for strand"))?;
+                    let codd = d
+                        .map(|v| v.saturating_sub(1))
+                        .ok_or(bad_location("no value for codon start"))?;
+                    let cod = codd as usize;
+                    let location = match record.cds.get_location(key) {
+                        Some(value) => value.clone(),
+                        None => Location::span(
+                            &RangeValue::Exact(star),
+                            &RangeValue::Exact(stow + 1),
+                            stra,
+                        ),
+                    };
+                    //collects the DNA sequence of the parts in biological order on the coding strand, joined features are spliced before translation
+                    let cds_char = location.extract(&record.sequence);
+                    //the table comes from /transl_table, an alternative start codon is read as Met unless the 5' end is partial
+                    let table = record
+                        .cds
+                        .get_qualifiers(key)
+                        .and_then(|q| q.transl_table())
+                        .unwrap_or(DEFAULT_TABLE);
+                    let (code, unknown_table) = genetic_code_or_default(table);
+                    if let Some(issue) = unknown_table {
                         record
-                            .seq_features
-                            .set_counter(key.to_string())
-                            .set_start(RangeValue::Exact(star))
-                            .set_stop(RangeValue::Exact(stow))
-                            .set_sequence_ffn(cds_char.to_string())
-                            .set_sequence_faa(parts[0].to_string())
-                            .set_codon_start(codd)
-                            .set_strand(stra);
-                    } else {
-                        if cod > 1 {
-                            //println!("forward strand codon value more than one cnt {:?}", &iterablecount);
-                            sliced_sequence = &record.sequence[sta + cod - 1..sto];
-                        } else {
-                            //println!("forward strand codon value one cnt {:?}", &iterablecount);
-                            sliced_sequence = &record.sequence[sta - 1..sto];
+                            .translation_issues
+                            .entry(key.to_string())
+                            .or_default()
+                            .push(issue);
+                    }
+                    let prot_seq = code.translate_cds(
+                        cds_char.get(cod..).unwrap_or_default(),
+                        cod == 0 && !location.partial_start(),
+                    );
+                    let parts: Vec<&str> = prot_seq.split('*').collect();
+                    let annotated = record.cds.get_qualifiers(key).and_then(|q| q.translation());
+                    if let (true, Some(annotated)) = (self.validate, annotated) {
+                        let issues = compare_translation(&prot_seq, annotated);
+                        if !issues.is_empty() {
+                            record
+                                .translation_issues
+                                .entry(key.to_string())
+                                .or_default()
+                                .extend(issues);
                         }
-                        let cds_char = sliced_sequence;
-                        let prot_seq = translate(cds_char.as_bytes());
-                        let parts: Vec<&str> = prot_seq.split('*').collect();
-                        record
-                            .seq_features
-                            .set_counter(key.to_string())
-                            .set_start(RangeValue::Exact(star))
-                            .set_stop(RangeValue::Exact(stow))
-                            .set_sequence_ffn(cds_char.to_string())
-                            .set_sequence_faa(parts[0].to_string())
-                            .set_codon_start(codd)
-                            .set_strand(stra);
                     }
+                    let faa = match (self.prefer_annotated, annotated) {
+                        (true, Some(annotated)) => annotated,
+                        _ => parts[0],
+                    };
+                    record
+                        .seq_features
+                        .set_counter(key.to_string())
+                        .set_start(RangeValue::Exact(star))
+                        .set_stop(RangeValue::Exact(stow))
+                        .set_sequence_ffn(cds_char.to_string())
+                        .set_sequence_faa(faa.to_string())
+                        .set_codon_start(codd)
+                        .set_strand(stra);
                 }
                 //return the record when completed
                 return Ok(record.to_owned());
             }
             //clear the line buffer and read the next to continue back to the outer loop
             self.line_buffer.clear();
-            self.reader.read_line(&mut self.line_buffer)?;
+            self.next_line(&record.id)?;
         }
         Ok(record.to_owned())
     }
 }
 
+pub use crate::header::{RecordHeader, Reference, Topology};
+pub use crate::location::Location;
+pub use crate::qualifiers::Qualifiers;
 pub use crate::record::RangeValue;
+pub use crate::translation::TranslationIssue;
 
 //stores the details of the source features in genbank (contigs)
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
@@ -872,6 +966,7 @@ pub enum SourceAttributes {
     CultureCollection { value: String },
     TypeMaterial { value: String },
     DbXref { value: String },
+    Qualifiers { value: Qualifiers },
 }
 
 //macro for creating the getters
@@ -886,7 +981,8 @@ create_getters!(
     Strain { value: String },
     // CultureCollection { value: String},
     TypeMaterial { value: String },
-    DbXref { value: String }
+    DbXref { value: String },
+    Qualifiers { value: Qualifiers }
 );
 
 ///builder for the source information on a per record basis
@@ -919,6 +1015,29 @@ impl SourceAttributeBuilder {
     pub fn get_source_attributes(&self, key: &str) -> Option<&HashSet<SourceAttributes>> {
         self.source_attributes.get(key)
     }
+
+    // Method to collect the qualifiers of a source, records made with the builder fall back to the organism, mol_type, strain, type_material and db_xref fields
+    pub fn source_qualifiers(&self, key: &str) -> Qualifiers {
+        if let Some(qualifiers) = self.get_qualifiers(key) {
+            if !qualifiers.is_empty() {
+                return qualifiers.clone();
+            }
+        }
+        let mut qualifiers = Qualifiers::new();
+        let fields = [
+            ("organism", self.get_organism(key)),
+            ("mol_type", self.get_mol_type(key)),
+            ("strain", self.get_strain(key)),
+            ("type_material", self.get_type_material(key)),
+            ("db_xref", self.get_db_xref(key)),
+        ];
+        for (name, value) in fields {
+            if let Some(value) = value.filter(|v| !v.is_empty()) {
+                qualifiers.push(name, Some(value.to_string()));
+            }
+        }
+        qualifiers
+    }
 }
 
 create_builder!(
@@ -933,7 +1052,8 @@ create_builder!(
     Strain { value: String },
     // CultureCollection { value: String},
     TypeMaterial { value: String },
-    DbXref { value: String }
+    DbXref { value: String },
+    Qualifiers { value: Qualifiers }
 );
 
 ///attributes for each feature, cds or gene
@@ -945,7 +1065,8 @@ pub enum FeatureAttributes {
     Product { value: String },
     CodonStart { value: u8 },
     Strand { value: i8 },
-    //   ec_number { value: String }
+    Location { value: Location },
+    Qualifiers { value: Qualifiers },
 }
 
 create_getters!(
@@ -957,7 +1078,9 @@ create_getters!(
     Gene { value: String },
     Product { value: String },
     CodonStart { value: u8 },
-    Strand { value: i8 }
+    Strand { value: i8 },
+    Location { value: Location },
+    Qualifiers { value: Qualifiers }
 );
 
 ///builder for the feature information on a per coding sequence (CDS) basis
@@ -977,9 +1100,44 @@ create_builder!(
     Gene { value: String },
     Product { value: String },
     CodonStart { value: u8 },
-    Strand { value: i8 }
+    Strand { value: i8 },
+    Location { value: Location },
+    Qualifiers { value: Qualifiers }
 );
 
+impl FeatureAttributeBuilder {
+    ///collects the qualifiers of a feature, records made with the builder fall back to the locus_tag, codon_start, gene and product fields
+    pub fn feature_qualifiers(&self, feature_type: &str, locus_tag: &str) -> Qualifiers {
+        if let Some(qualifiers) = self.get_qualifiers(locus_tag) {
+            if !qualifiers.is_empty() {
+                return qualifiers.clone();
+            }
+        }
+        let mut qualifiers = Qualifiers::new();
+        if !is_generated_key(feature_type, locus_tag) {
+            qualifiers.push("locus_tag", Some(locus_tag.to_string()));
+        }
+        if let Some(codon_start) = self.get_codon_start(locus_tag) {
+            qualifiers.push("codon_start", Some(codon_start.to_string()));
+        }
+        if let Some(gene) = self.get_gene(locus_tag) {
+            if !gene.is_empty() && gene != "unknown" {
+                qualifiers.push("gene", Some(gene.to_string()));
+            }
+        }
+        match self.get_product(locus_tag) {
+            Some(product) if !product.is_empty() => {
+                qualifiers.push("product", Some(product.to_string()))
+            }
+            None if feature_type == "CDS" => {
+                qualifiers.push("product", Some("unknown product".to_string()))
+            }
+            _ => (),
+        }
+        qualifiers
+    }
+}
+
 ///stores the sequences of the coding sequences (genes) and proteins. Also stores start, stop, codon_start and strand information
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 pub enum SequenceAttributes {
@@ -1023,6 +1181,24 @@ create_builder!(
     Strand { value: i8 }
 );
 
+///returns the feature key (source, gene, CDS, rRNA, tRNA, mobile_element...) when the line opens a new feature in the FEATURES table
+///feature keys start at column 6, qualifiers and continuation lines are indented to column 22
+pub fn feature_key(line: &str) -> Option<&str> {
+    match line.strip_prefix("     ") {
+        Some(rest) if !rest.starts_with(' ') => rest.split_whitespace().next(),
+        _ => None,
+    }
+}
+
+//the error for a LOCUS line that cannot be read, or for a record without one
+fn missing_locus(line_number: usize, record_id: &str, line: &str) -> ParseError {
+    ParseError::MissingLocus {
+        line_number,
+        record_id: record_id.to_string(),
+        line: line.trim_end().to_string(),
+    }
+}
+
 ///product lines can contain difficult to parse punctuation such as biochemical symbols like unclosed single quotes, superscripts, single and double brackets etc.
 ///here we substitute these for an underscore
 pub fn substitute_odd_punctuation(input: String) -> Result<String, anyhow::Error> {
@@ -1042,10 +1218,12 @@ pub struct GFFInner {
     pub locus_tag: String,
     pub gene: String,
     // Inference: String,
-    // Parent: String,
     //   db_xref: String,
     pub product: String,
+    ///the ID of the parent feature, e.g. the gene of a CDS
+    pub parent: String,
     // is_circular: bool,
+    pub qualifiers: Qualifiers,
 }
 
 impl GFFInner {
@@ -1055,7 +1233,6 @@ impl GFFInner {
         locus_tag: String,
         gene: String,
         //   Inference: String,
-        //    Parent: String,
         //    db_xref: String,
         product: String,
     ) -> Self {
@@ -1065,8 +1242,20 @@ impl GFFInner {
             locus_tag,
             gene,
             product,
+            parent: String::new(),
+            qualifiers: Qualifiers::new(),
         }
     }
+    ///links the feature to its parent, written as the Parent attribute
+    pub fn set_parent(&mut self, parent: String) -> &mut Self {
+        self.parent = parent;
+        self
+    }
+    ///adds the remaining qualifiers of the feature (db_xref, note, protein_id...) to field9
+    pub fn set_qualifiers(&mut self, qualifiers: Qualifiers) -> &mut Self {
+        self.qualifiers = qualifiers;
+        self
+    }
 }
 
 ///The main GFF3 construct
@@ -1077,13 +1266,15 @@ pub struct GFFOuter<'a> {
     pub type_val: String,
     pub start: u32,
     pub end: u32,
-    pub score: f64,
+    pub score: Option<f64>,
     pub strand: String,
-    pub phase: u8,
+    ///the phase of a CDS line, other features have none
+    pub phase: Option<u8>,
     pub attributes: &'a GFFInner,
 }
 
 impl<'a> GFFOuter<'a> {
+    ///a line with a score and phase, a score of 0 is written as '.' as is the phase of any feature other than a CDS
     pub fn new(
         seqid: String,
         source: String,
@@ -1095,6 +1286,8 @@ impl<'a> GFFOuter<'a> {
         phase: u8,
         attributes: &'a GFFInner,
     ) -> Self {
+        let score = if score == 0.0 { None } else { Some(score) };
+        let phase = if type_val == "CDS" { Some(phase) } else { None };
         GFFOuter {
             seqid,
             source,
@@ -1107,64 +1300,76 @@ impl<'a> GFFOuter<'a> {
             attributes,
         }
     }
+    ///sets the score, None is written as '.'
+    pub fn set_score(&mut self, score: Option<f64>) -> &mut Self {
+        self.score = score;
+        self
+    }
+    ///sets the phase, None is written as '.'
+    pub fn set_phase(&mut self, phase: Option<u8>) -> &mut Self {
+        self.phase = phase;
+        self
+    }
+    ///the column 9 attributes, the reserved ID, Name and Parent tags lead and every value is escaped
     pub fn field9_attributes_build(&self) -> String {
         let mut full_field9 = Vec::new();
-        if !self.attributes.id.is_empty() {
-            full_field9.push(format!("id={}", self.attributes.id));
-        }
-        if !self.attributes.name.is_empty() {
-            full_field9.push(format!("name={}", self.attributes.name));
-        }
-        if !self.attributes.gene.is_empty() {
-            full_field9.push(format!("gene={}", self.attributes.gene));
-        }
-        //    if !self.attributes.Inference.is_empty() {
-        //       full_field9.push(format!("inference={}",self.attributes.Inference));
-        //	  }
-        if !self.attributes.locus_tag.is_empty() {
-            full_field9.push(format!("locus_tag={}", self.attributes.locus_tag));
-        }
-        if !self.attributes.product.is_empty() {
-            full_field9.push(format!("product={}", self.attributes.product));
+        for (tag, value) in [
+            ("ID", &self.attributes.id),
+            ("Name", &self.attributes.name),
+            ("Parent", &self.attributes.parent),
+            ("gene", &self.attributes.gene),
+            ("locus_tag", &self.attributes.locus_tag),
+            ("product", &self.attributes.product),
+        ] {
+            if !value.is_empty() {
+                full_field9.push(format!("{}={}", tag, gff3_escape(value)));
+            }
         }
-        //    if !self.attributes.Parent.is_empty() {
-        //       full_field9.push(format!("Parent={}",self.attributes.Parent));
-        //	  }
-        //       if !self.attributes.db_xref.is_empty() {
-        //          full_field9.push(format!("db_xref={}",self.attributes.db_xref));
-        //	  }
+        //the remaining qualifiers (db_xref, note, protein_id, pseudo...), the phase column already holds the codon start
+        full_field9.extend(gff3_attributes(
+            &self.attributes.qualifiers,
+            &["locus_tag", "gene", "product", "codon_start", "translation"],
+        ));
         full_field9.join(";")
     }
+    ///the nine tab separated columns of the line, a missing score, strand or phase is written as '.'
+    pub fn gff_line(&self) -> String {
+        let field = |value: &str| match value.is_empty() {
+            true => ".".to_string(),
+            false => gff3_escape(value),
+        };
+        format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+            field(&self.seqid),
+            field(&self.source),
+            field(&self.type_val),
+            self.start,
+            self.end,
+            self.score.map_or(".".to_string(), |s| s.to_string()),
+            field(&self.strand),
+            self.phase.map_or(".".to_string(), |p| p.to_string()),
+            match self.field9_attributes_build() {
+                attributes if attributes.is_empty() => ".".to_string(),
+                attributes => attributes,
+            }
+        )
+    }
 }
 
+///indentation of the qualifier column in the gbk FEATURES table
+pub const QUALIFIER_INDENT: &str = "                     ";
+
 ///formats the translation string which can be multiple lines, for gbk
 pub fn format_translation(translation: &str) -> String {
-    //create method to add the protein sequence into the translation qualifier with correct line lengths
-    let mut formatted = String::new();
     let cleaned_translation = translation.replace("\n", "");
-    formatted.push_str("                     /translation=\"");
-    let line_length: usize = 60;
-    let final_num = line_length - 15;
-    formatted.push_str(&format!("{}\n", &cleaned_translation[0..final_num]));
-    for i in (47..translation.len()).step_by(60) {
-        let end = i + 60 - 1;
-        let valid_end = if end >= translation.len() {
-            &cleaned_translation.len() - 1
-        } else {
-            end
-        };
-        formatted.push_str(&format!(
-            "                     {}",
-            &cleaned_translation[i..valid_end]
-        ));
-        //println!("cleaned translation leng is {:?}", &cleaned_translation[i..valid_end].len());
-        if cleaned_translation[i..valid_end].len() < 59 {
-            formatted.push('\"');
-        } else {
-            formatted.push('\n');
-        }
-    }
-    formatted
+    format_qualifier(QUALIFIER_INDENT, "translation", Some(&cleaned_translation))
+}
+
+///checks whether a feature key was made up by the reader (e.g. tRNA_3) because the feature had no locus tag
+pub fn is_generated_key(feature_type: &str, key: &str) -> bool {
+    key.strip_prefix(feature_type)
+        .and_then(|rest| rest.strip_prefix('_'))
+        .is_some_and(|n| !n.is_empty() && n.split('_').all(|p| p.parse::<u32>().is_ok()))
 }
 
 ///writes the DNA sequence in gbk format with numbering
@@ -1185,7 +1390,7 @@ pub fn write_gbk_format_sequence(sequence: &str, file: &mut File) -> io::Result<
         formatted.push('\n');
         index += 60;
     }
-    writeln!(file, "{:>6}", &formatted)?;
+    write!(file, "{:>6}", &formatted)?;
     writeln!(file, "//")?;
     Ok(())
 }
@@ -1214,123 +1419,113 @@ pub fn gbk_write(
             Some(value) => value.to_string(),
             None => "Unknown".to_string(),
         };
-        let mol_type = match &record_vec[i].source_map.get_mol_type(key) {
-            Some(value) => value.to_string(),
-            None => "Unknown".to_string(),
-        };
-        let type_material = match &record_vec[i].source_map.get_type_material(key) {
-            Some(value) => value.to_string(),
-            None => "Unknown".to_string(),
-        };
-        let db_xref = match &record_vec[i].source_map.get_db_xref(key) {
-            Some(value) => value.to_string(),
-            None => "Unknown".to_string(),
+        let source_start = match &record_vec[i].source_map.get_start(key) {
+            Some(value) => (*value).clone(),
+            None => RangeValue::Exact(1),
         };
         let source_stop = match &record_vec[i].source_map.get_stop(key) {
-            Some(value) => value.get_value(),
-            None => {
-                println!("stop value not found");
-                None
-            }
-            .expect("stop value not received"),
+            Some(value) => (*value).clone(),
+            None => RangeValue::Exact(record_vec[i].sequence.len() as u32),
         };
-        writeln!(
-            file,
-            "LOCUS       {}             {} bp    DNA     linear CON {}",
-            &key,
-            &record_vec[i].sequence.len(),
-            &formatted_date
-        )?;
-        writeln!(file, "DEFINITION  {} {}.", &organism, &strain)?;
-        writeln!(file, "ACCESSION   {}", &key)?;
-        writeln!(file, "KEYWORDS    .")?;
-        writeln!(file, "SOURCE      {} {}", &organism, &strain)?;
-        writeln!(file, "  ORGANISM  {} {}", &organism, &strain)?;
-        //write lines for the source
-        writeln!(file, "FEATURES             Location/Qualifiers")?;
-        writeln!(file, "     source          1..{}", &source_stop)?;
-        writeln!(file, "                     /organism=\"{}\"", &strain)?;
-        writeln!(file, "                     /mol_type=\"{}\"", &mol_type)?;
-        writeln!(file, "                     /strain=\"{}\"", &strain)?;
-        if type_material != *"Unknown".to_string() {
+        let header = &record_vec[i].header;
+        if header.is_empty() {
             writeln!(
                 file,
-                "                     /type_material=\"{}\"",
-                &type_material
+                "LOCUS       {}             {} bp    DNA     {} CON {}",
+                &key,
+                &record_vec[i].sequence.len(),
+                &record_vec[i].topology,
+                &formatted_date
             )?;
+            writeln!(file, "DEFINITION  {} {}.", &organism, &strain)?;
+            writeln!(file, "ACCESSION   {}", &key)?;
+            writeln!(file, "KEYWORDS    .")?;
+            writeln!(file, "SOURCE      {} {}", &organism, &strain)?;
+            writeln!(file, "  ORGANISM  {} {}", &organism, &strain)?;
+        } else {
+            //a parsed record keeps its own header, only a missing date is filled in
+            let name = match record_vec[i].id.is_empty() {
+                true => key,
+                false => &record_vec[i].id,
+            };
+            let mut header = header.clone();
+            if header.date.is_empty() {
+                header.date = formatted_date.clone();
+            }
+            writeln!(
+                file,
+                "{}",
+                header.locus_line(name, record_vec[i].sequence.len(), record_vec[i].topology)
+            )?;
+            writeln!(file, "{}", header.genbank_lines())?;
         }
-        writeln!(file, "                     /db_xref=\"{}\"", &db_xref)?;
-        //write lines for each CDS
-        for locus_tag in record_vec[i].cds.attributes.keys() {
-            let start = match &record_vec[i].cds.get_start(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("start value not found");
-                    None
-                }
-                .expect("start value not received"),
+        //write lines for the source
+        writeln!(file, "FEATURES             Location/Qualifiers")?;
+        let source_location = Location::span(&source_start, &source_stop, 1);
+        writeln!(
+            file,
+            "{}",
+            format_location("     source          ", QUALIFIER_INDENT, &source_location)
+        )?;
+        for (name, value) in record_vec[i].source_map.source_qualifiers(key).iter() {
+            writeln!(file, "{}", format_qualifier(QUALIFIER_INDENT, name, value))?;
+        }
+        //write lines for each feature, gene lines are made up from the CDS when the record holds no gene features
+        let write_genes = !record_vec[i].features.contains_key("gene");
+        for (feature_type, locus_tag) in record_vec[i].ordered_features() {
+            let features = match record_vec[i].features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
             };
-            let stop = match &record_vec[i].cds.get_stop(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("stop value not found");
-                    None
+            let (start, stop) = match (features.get_start(locus_tag), features.get_stop(locus_tag))
+            {
+                (Some(start), Some(stop)) => (start.get_value(), stop.get_value()),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} {} has no start or stop", feature_type, locus_tag),
+                    ))
                 }
-                .expect("stop value not received"),
-            };
-            let product = match &record_vec[i].cds.get_product(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown product".to_string(),
-            };
-            let strand = match &record_vec[i].cds.get_strand(locus_tag) {
-                Some(value) => **value,
-                None => 0,
             };
-            let codon_start = match &record_vec[i].cds.get_codon_start(locus_tag) {
-                Some(value) => **value,
+            let strand = match features.get_strand(locus_tag) {
+                Some(value) => *value,
                 None => 0,
             };
-            let gene = match &record_vec[i].cds.get_gene(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown".to_string(),
-            };
-            let translation = match &record_vec[i].seq_features.get_sequence_faa(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown".to_string(),
+            let qualifiers = features.feature_qualifiers(feature_type, locus_tag);
+            let location = match features.get_location(locus_tag) {
+                Some(value) => value.clone(),
+                None => Location::span(&RangeValue::Exact(start), &RangeValue::Exact(stop), strand),
             };
-            if strand == 1 {
-                writeln!(file, "     gene            {}..{}", &start, &stop)?;
-            } else {
+            if feature_type == "CDS" && write_genes {
+                //the gene follows the CDS location, so a CDS over the origin or a join stays one
                 writeln!(
                     file,
-                    "     gene            complement({}..{})",
-                    &start, &stop
-                )?;
-            }
-            writeln!(file, "                     /locus_tag=\"{}\"", &locus_tag)?;
-            if strand == 1 {
-                writeln!(file, "     CDS             {}..{}", &start, &stop)?;
-            } else {
-                writeln!(
-                    file,
-                    "     CDS             complement({}..{})",
-                    &start, &stop
+                    "{}",
+                    format_location("     gene            ", QUALIFIER_INDENT, &location)
                 )?;
+                //a key made up by the reader or builder is not a locus tag
+                if let Some(tag) = qualifiers.locus_tag() {
+                    writeln!(
+                        file,
+                        "{}",
+                        format_qualifier(QUALIFIER_INDENT, "locus_tag", Some(tag))
+                    )?;
+                }
             }
-            writeln!(file, "                     /locus_tag=\"{}\"", &locus_tag)?;
+            let first_prefix = format!("     {:<16}", feature_type);
             writeln!(
                 file,
-                "                     /codon_start=\"{}\"",
-                &codon_start
+                "{}",
+                format_location(&first_prefix, QUALIFIER_INDENT, &location)
             )?;
-            if gene != "unknown" {
-                writeln!(file, "                     /gene=\"{}\"", &gene)?;
+            for (name, value) in qualifiers.iter() {
+                writeln!(file, "{}", format_qualifier(QUALIFIER_INDENT, name, value))?;
             }
-            if translation != "unknown" {
-                let formatted_translation = format_translation(&translation);
-                writeln!(file, "{}", &formatted_translation)?;
+            if feature_type == "CDS" && qualifiers.translation().is_none() {
+                if let Some(translation) = record_vec[i].seq_features.get_sequence_faa(locus_tag) {
+                    writeln!(file, "{}", format_translation(translation))?;
+                }
             }
-            writeln!(file, "                     /product=\"{}\"", &product)?;
         }
         write_gbk_format_sequence(&record_vec[i].sequence, &mut file)?;
     }
@@ -1338,12 +1533,11 @@ pub fn gbk_write(
 }
 
 ///saves the parsed data in gff3 format
-//writes a gff3 file from a genbank
-#[allow(unused_assignments)]
-#[allow(unused_variables)]
+//writes a gff3 file from a genbank, each record is a sequence region named by its id with coordinates counted from its own first base
+//the source feature of each record is found by its own source name, the keys of seq_region stand in for records without one
 pub fn gff_write(
     seq_region: BTreeMap<String, (u32, u32)>,
-    mut record_vec: Vec<Record>,
+    record_vec: Vec<Record>,
     filename: &str,
     dna: bool,
 ) -> io::Result<()> {
@@ -1355,118 +1549,388 @@ pub fn gff_write(
     if file.metadata()?.len() == 0 {
         writeln!(file, "##gff-version 3")?;
     }
-    let mut full_seq = String::new();
-    let mut prev_end: u32 = 0;
-    //println!("this is the full seq_region {:?}", &seq_region);
-    for (k, v) in seq_region.iter() {
-        writeln!(file, "##sequence-region\t{}\t{}\t{}", &k, v.0, v.1)?;
+    //the seqid, source name, length and record of each sequence region
+    //the source name is taken from each record, the seq_region key in the same place is only used when it has none
+    let regions: Vec<(String, String, u32, &Record)> = record_vec
+        .iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let source_name = record
+                .source_map
+                .get_source_name()
+                .or_else(|| seq_region.keys().nth(i))
+                .cloned()
+                .unwrap_or_default();
+            let seqid = match record.id.is_empty() {
+                true => source_name.clone(),
+                false => record.id.clone(),
+            };
+            let length = match record.sequence.is_empty() {
+                true => record.length,
+                false => record.sequence.len() as u32,
+            };
+            (seqid, source_name, length, record)
+        })
+        .collect();
+    for (seqid, _, length, _) in regions.iter() {
+        writeln!(
+            file,
+            "##sequence-region {} 1 {}",
+            gff3_escape(seqid),
+            length
+        )?;
     }
-    for ((source_name, (seq_start, seq_end)), record) in seq_region.iter().zip(record_vec.drain(..))
-    {
-        if dna {
-            full_seq.push_str(&record.sequence);
+    let mut used_ids: HashSet<String> = HashSet::new();
+    for (seqid, source_name, length, record) in regions.iter() {
+        let (length, circular) = (*length, record.is_circular());
+        //the source feature is written as the region line of the sequence
+        let mut source_qualifiers = record.source_map.source_qualifiers(source_name);
+        if circular {
+            source_qualifiers.push("Is_circular", Some("true".to_string()));
         }
-        for locus_tag in record.cds.attributes.keys() {
-            let start = match record.cds.get_start(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("start value not found");
-                    None
-                }
-                .expect("start value not received"),
+        let source_start = record
+            .source_map
+            .get_start(source_name)
+            .map_or(1, |v| v.get_value());
+        let source_stop = record
+            .source_map
+            .get_stop(source_name)
+            .map_or(length, |v| v.get_value());
+        let region_id = format!("{}:{}..{}", seqid, source_start, source_stop);
+        let mut region = GFFInner::new(
+            unique_id(&region_id, &mut used_ids),
+            String::new(),
+            String::new(),
+            String::new(),
+            String::new(),
+        );
+        region.set_qualifiers(source_qualifiers);
+        let region_line = GFFOuter::new(
+            seqid.clone(),
+            ".".to_string(),
+            "region".to_string(),
+            source_start,
+            source_stop,
+            0.0,
+            "+".to_string(),
+            0,
+            &region,
+        );
+        writeln!(file, "{}", region_line.gff_line())?;
+        //the IDs of the gene features by locus tag, the CDS and RNAs of a gene name it as their Parent
+        let mut gene_ids: HashMap<String, String> = HashMap::new();
+        if let Some(genes) = record.features_of("gene") {
+            for key in genes.attributes.keys() {
+                let locus_tag = genes
+                    .get_qualifiers(key)
+                    .and_then(|q| q.locus_tag())
+                    .unwrap_or(key);
+                let gene_id = unique_id(&format!("gene-{}", locus_tag), &mut used_ids);
+                gene_ids.insert(key.to_string(), gene_id);
+            }
+        }
+        //records without gene features get a gene line made up for each CDS
+        let write_genes = !record.features.contains_key("gene");
+        for (feature_type, key) in record.ordered_features() {
+            let features = match record.features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
             };
-            let stop = match record.cds.get_stop(locus_tag) {
-                Some(value) => value.get_value(),
-                None => {
-                    println!("stop value not found");
-                    None
+            let (start, stop) = match (features.get_start(key), features.get_stop(key)) {
+                (Some(start), Some(stop)) => (start.get_value(), stop.get_value()),
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("{} {} has no start or stop", feature_type, key),
+                    ))
                 }
-                .expect("stop value not received"),
             };
-            let gene = match record.cds.get_gene(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown".to_string(),
+            let strand = match features.get_strand(key) {
+                Some(1) => "+",
+                Some(-1) => "-",
+                _ => ".",
             };
-            let product = match record.cds.get_product(locus_tag) {
-                Some(value) => value.to_string(),
-                None => "unknown product".to_string(),
+            let qualifiers = features.feature_qualifiers(feature_type, key);
+            let locus_tag = qualifiers.locus_tag().unwrap_or_default().to_string();
+            let gene = qualifiers.gene().unwrap_or_default().to_string();
+            let product = qualifiers.product().unwrap_or_default().to_string();
+            let name = match gene.is_empty() {
+                true => locus_tag.clone(),
+                false => gene.clone(),
             };
-            let strand = match record.cds.get_strand(locus_tag) {
-                Some(valu) => match valu {
-                    1 => "+".to_string(),
-                    -1 => "-".to_string(),
-                    _ => {
-                        println!(
-                            "unexpected strand value {} for locus_tag {}",
-                            valu, locus_tag
+            let tag = match locus_tag.is_empty() {
+                true => key,
+                false => &locus_tag,
+            };
+            let mut parent = String::new();
+            let id = match feature_type {
+                "gene" => match gene_ids.get(key) {
+                    Some(gene_id) => gene_id.clone(),
+                    None => unique_id(&format!("gene-{}", tag), &mut used_ids),
+                },
+                _ => {
+                    if feature_type == "CDS" && write_genes {
+                        parent = unique_id(&format!("gene-{}", tag), &mut used_ids);
+                        let gene_line = GFFInner::new(
+                            parent.clone(),
+                            name.clone(),
+                            locus_tag.clone(),
+                            gene.clone(),
+                            String::new(),
                         );
-                        "unknownstrand".to_string()
+                        let gene_outer = GFFOuter::new(
+                            seqid.clone(),
+                            ".".to_string(),
+                            "gene".to_string(),
+                            start,
+                            stop,
+                            0.0,
+                            strand.to_string(),
+                            0,
+                            &gene_line,
+                        );
+                        writeln!(file, "{}", gene_outer.gff_line())?;
+                    } else if let Some(gene_id) = gene_ids.get(tag) {
+                        parent = gene_id.clone();
                     }
-                },
-                None => "unknownvalue".to_string(),
+                    let prefix = feature_type.to_lowercase();
+                    unique_id(&format!("{}-{}", prefix, tag), &mut used_ids)
+                }
             };
-            let phase = match record.cds.get_codon_start(locus_tag) {
-                Some(valuer) => match valuer {
-                    1 => 0,
-                    2 => 1,
-                    3 => 2,
-                    _ => {
-                        println!(
-                            "unexpected phase value {} in the bagging area for locus_tag {}",
-                            valuer, locus_tag
-                        );
-                        1
+            let codon_start = match feature_type {
+                "CDS" => Some(features.get_codon_start(key).copied().unwrap_or(1)),
+                _ => None,
+            };
+            let location = match features.get_location(key) {
+                Some(value) => value.clone(),
+                None => Location::span(
+                    &RangeValue::Exact(start),
+                    &RangeValue::Exact(stop),
+                    features.get_strand(key).copied().unwrap_or(1),
+                ),
+            };
+            //a joined feature is written as one line per part, the lines share the ID
+            let mut parts = location_parts(&location, length, circular, codon_start);
+            if parts.is_empty() {
+                parts.push((start, stop, codon_start.map(|c| c - 1)));
+            }
+            let mut gff_inner = GFFInner::new(id, name, locus_tag.clone(), gene, product);
+            gff_inner
+                .set_parent(parent)
+                .set_qualifiers(qualifiers.clone());
+            for (part_start, part_end, phase) in parts {
+                let mut gff_outer = GFFOuter::new(
+                    seqid.clone(),
+                    ".".to_string(),
+                    feature_type.to_string(),
+                    part_start,
+                    part_end,
+                    0.0,
+                    strand.to_string(),
+                    0,
+                    &gff_inner,
+                );
+                gff_outer.set_phase(phase);
+                writeln!(file, "{}", gff_outer.gff_line())?;
+            }
+        }
+    }
+    //one FASTA entry per sequence region
+    if dna {
+        writeln!(file, "##FASTA")?;
+        for (seqid, _, _, record) in regions.iter() {
+            writeln!(file, "{}", fasta_entry(seqid, &record.sequence))?;
+        }
+    }
+    Ok(())
+}
+
+///saves the coding sequences and RNA genes in GTF 2.2 format
+//the gene_id of each transcript is its locus tag, the transcript_id is made from the feature type and the gene_id
+pub fn gtf_write(record_vec: Vec<Record>, filename: &str) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .append(true) // Enable appending to the file
+        .create(true) // Create the file if it doesn't exist
+        .open(filename)?;
+    let mut used_ids: HashSet<String> = HashSet::new();
+    for record in record_vec.iter() {
+        let length = match record.sequence.is_empty() {
+            true => record.length,
+            false => record.sequence.len() as u32,
+        };
+        for (feature_type, key) in record.ordered_features() {
+            if feature_type != "CDS" && !RNA_FEATURES.contains(&feature_type) {
+                continue;
+            }
+            let features = match record.features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
+            };
+            let location = match features.get_location(key) {
+                Some(value) => value.clone(),
+                None => match (features.get_start(key), features.get_stop(key)) {
+                    (Some(start), Some(stop)) => {
+                        Location::span(start, stop, features.get_strand(key).copied().unwrap_or(1))
                     }
+                    _ => continue,
                 },
-                None => 1,
             };
-            let gff_inner = GFFInner::new(
-                locus_tag.to_string(),
-                source_name.clone(),
-                locus_tag.to_string(),
-                gene,
-                //  &record.cds.get_Inference(&locus_tag),
-                //  &record.cds.get_Parent(&locus_tag),
-                //   db_xref,
-                product,
+            let qualifiers = features.feature_qualifiers(feature_type, key);
+            let gene_id = qualifiers.locus_tag().unwrap_or(key).to_string();
+            let transcript_id = unique_id(
+                &format!("{}-{}", feature_type.to_lowercase(), gene_id),
+                &mut used_ids,
             );
-            let gff_outer = GFFOuter::new(
-                source_name.clone(),
-                ".".to_string(),
-                "CDS".to_string(),
-                start + prev_end,
-                stop + prev_end,
-                0.0,
-                strand,
-                phase,
-                &gff_inner,
-            );
-            let field9_attributes = gff_outer.field9_attributes_build();
-            //println!("{}\t{}\t{}\t{:?}\t{:?}\t{}\t{}\t{}\t{}", gff_outer.seqid, gff_outer.source, gff_outer.type_val, gff_outer.start, gff_outer.end, gff_outer.score, gff_outer.strand, gff_outer.phase, field9_attributes);
-            writeln!(
-                file,
-                "{}\t{}\t{}\t{:?}\t{:?}\t{}\t{}\t{}\t{}",
-                gff_outer.seqid,
-                gff_outer.source,
-                gff_outer.type_val,
-                gff_outer.start,
-                gff_outer.end,
-                gff_outer.score,
-                gff_outer.strand,
-                gff_outer.phase,
-                field9_attributes
-            )?;
+            let mut attributes: Vec<(String, String)> = Vec::new();
+            if let Some(gene) = qualifiers.gene() {
+                attributes.push(("gene_name".to_string(), gene.to_string()));
+            }
+            let (biotype, codon_start) = match feature_type {
+                "CDS" => (
+                    "protein_coding",
+                    Some(features.get_codon_start(key).copied().unwrap_or(1)),
+                ),
+                _ => (feature_type, None),
+            };
+            attributes.push(("gene_biotype".to_string(), biotype.to_string()));
+            if let Some(product) = qualifiers.product() {
+                attributes.push(("product".to_string(), product.to_string()));
+            }
+            let transcript = Transcript {
+                seqid: record.id.clone(),
+                gene_id,
+                transcript_id,
+                location,
+                codon_start,
+                attributes,
+            };
+            for line in transcript.gtf_lines(length, record.is_circular()) {
+                writeln!(file, "{}", line)?;
+            }
         }
-        prev_end = *seq_end;
     }
-    if dna {
-        writeln!(file, "##FASTA")?;
-        //writeln!(file, ">{}\n",&filename.to_string())?;
-        writeln!(file, "{}", full_seq)?;
+    Ok(())
+}
+
+///saves the coding sequences in BED format, BED6 or BED12 with one block per part of a joined CDS
+//coordinates are 0-based and half-open, each CDS is named by its locus tag with a score of 0
+pub fn bed_write(record_vec: Vec<Record>, filename: &str, columns: usize) -> io::Result<()> {
+    let mut file = OpenOptions::new()
+        .append(true) // Enable appending to the file
+        .create(true) // Create the file if it doesn't exist
+        .open(filename)?;
+    for record in record_vec.iter() {
+        let length = match record.sequence.is_empty() {
+            true => record.length,
+            false => record.sequence.len() as u32,
+        };
+        for (feature_type, key) in record.ordered_features() {
+            if feature_type != "CDS" {
+                continue;
+            }
+            let location = match record.cds.get_location(key) {
+                Some(value) => value.clone(),
+                None => match (record.cds.get_start(key), record.cds.get_stop(key)) {
+                    (Some(start), Some(stop)) => Location::span(
+                        start,
+                        stop,
+                        record.cds.get_strand(key).copied().unwrap_or(1),
+                    ),
+                    _ => continue,
+                },
+            };
+            let qualifiers = record.cds.feature_qualifiers(feature_type, key);
+            let mut interval =
+                BedRecord::from_location(&record.id, &location, length, record.is_circular());
+            interval.name = Some(qualifiers.locus_tag().unwrap_or(key).to_string());
+            interval.score = Some(0.0);
+            writeln!(file, "{}", interval.to_line(columns))?;
+        }
     }
     Ok(())
 }
 
+///saves the parsed data in embl format
+//converts each genbank record to an embl record, the header is given its EMBL line codes and every feature is written with its qualifiers
+pub fn embl_write(record_vec: Vec<Record>, filename: &str) -> io::Result<()> {
+    let file = OpenOptions::new()
+        .append(true) // Enable appending to the file
+        .create(true) // Create the file if it doesn't exist
+        .open(filename)?;
+    let mut writer = EmblWriter::new(io::BufWriter::new(file));
+    for record in record_vec.iter() {
+        writer.write_entry(&EmblEntry::from(record))?;
+    }
+    writer.flush()
+}
+
+impl From<&Record> for EmblEntry {
+    fn from(record: &Record) -> Self {
+        let mut features: Vec<(String, Location, Qualifiers)> = Vec::new();
+        //the source features come first, ordered by their start
+        let mut sources: Vec<(u32, &String)> = record
+            .source_map
+            .source_attributes
+            .keys()
+            .map(|key| {
+                let start = record
+                    .source_map
+                    .get_start(key)
+                    .map_or(1, |v| v.get_value());
+                (start, key)
+            })
+            .collect();
+        sources.sort();
+        for (_, key) in sources {
+            let start = record
+                .source_map
+                .get_start(key)
+                .cloned()
+                .unwrap_or(RangeValue::Exact(1));
+            let stop = match record.source_map.get_stop(key) {
+                Some(value) => value.clone(),
+                None => RangeValue::Exact(record.sequence.len() as u32),
+            };
+            features.push((
+                "source".to_string(),
+                Location::span(&start, &stop, 1),
+                record.source_map.source_qualifiers(key),
+            ));
+        }
+        for (feature_type, locus_tag) in record.ordered_features() {
+            let feature = match record.features_of(feature_type) {
+                Some(value) => value,
+                None => continue,
+            };
+            let location = match feature.get_location(locus_tag) {
+                Some(value) => value.clone(),
+                None => match (feature.get_start(locus_tag), feature.get_stop(locus_tag)) {
+                    (Some(start), Some(stop)) => Location::span(
+                        start,
+                        stop,
+                        feature.get_strand(locus_tag).copied().unwrap_or(1),
+                    ),
+                    _ => continue,
+                },
+            };
+            let mut qualifiers = feature.feature_qualifiers(feature_type, locus_tag);
+            if feature_type == "CDS" && qualifiers.translation().is_none() {
+                if let Some(translation) = record.seq_features.get_sequence_faa(locus_tag) {
+                    qualifiers.push("translation", Some(translation.replace('\n', "")));
+                }
+            }
+            features.push((feature_type.to_string(), location, qualifiers));
+        }
+        EmblEntry {
+            name: record.id.clone(),
+            header: record.header.clone(),
+            topology: record.topology,
+            features,
+            sequence: record.sequence.clone(),
+        }
+    }
+}
+
 ///saves the parsed data in gff3 format
 //writes a gff3 file from a genbank
 #[allow(unused_assignments)]
@@ -1571,21 +2035,7 @@ pub fn orig_gff_write(
                 phase,
                 &gff_inner,
             );
-            let field9_attributes = gff_outer.field9_attributes_build();
-            //println!("{}\t{}\t{}\t{:?}\t{:?}\t{}\t{}\t{}\t{}", gff_outer.seqid, gff_outer.source, gff_outer.type_val, gff_outer.start, gff_outer.end, gff_outer.score, gff_outer.strand, gff_outer.phase, field9_attributes);
-            writeln!(
-                file,
-                "{}\t{}\t{}\t{:?}\t{:?}\t{}\t{}\t{}\t{}",
-                gff_outer.seqid,
-                gff_outer.source,
-                gff_outer.type_val,
-                gff_outer.start,
-                gff_outer.end,
-                gff_outer.score,
-                gff_outer.strand,
-                gff_outer.phase,
-                field9_attributes
-            )?;
+            writeln!(file, "{}", gff_outer.gff_line())?;
         }
         prev_end = val.1;
     }
@@ -1610,6 +2060,11 @@ pub struct Record {
     pub cds: FeatureAttributeBuilder,
     pub source_map: SourceAttributeBuilder,
     pub seq_features: SequenceAttributeBuilder,
+    pub features: BTreeMap<String, FeatureAttributeBuilder>,
+    pub header: RecordHeader,
+    pub topology: Topology,
+    ///differences between the computed and annotated proteins by locus tag, filled by a reader validating translations, and CDS with an unknown /transl_table
+    pub translation_issues: BTreeMap<String, Vec<TranslationIssue>>,
 }
 
 impl Record {
@@ -1625,6 +2080,10 @@ impl Record {
             source_map: SourceAttributeBuilder::new(),
             cds: FeatureAttributeBuilder::new(),
             seq_features: SequenceAttributeBuilder::new(),
+            features: BTreeMap::new(),
+            header: RecordHeader::new(),
+            topology: Topology::Linear,
+            translation_issues: BTreeMap::new(),
         }
     }
     pub fn is_empty(&mut self) -> bool {
@@ -1654,6 +2113,116 @@ impl Record {
     pub fn strand(&mut self) -> i32 {
         self.strand
     }
+    pub fn topology(&self) -> Topology {
+        self.topology
+    }
+    pub fn is_circular(&self) -> bool {
+        self.topology == Topology::Circular
+    }
+    ///the start and stop stored for a location, a feature running over the origin of a circular record ends past the record length
+    fn bounds(&self, location: &Location) -> (RangeValue, RangeValue) {
+        match self.is_circular() {
+            true => location.circular_bounds(self.length),
+            false => (location.start_value(), location.end_value()),
+        }
+    }
+    ///moves base ```origin``` of a circular record to position 1, e.g. to start the record at dnaA
+    ///the sequence is rotated and the coordinates of every source and feature are remapped, features that now run over the origin become joins
+    pub fn rotate(&mut self, origin: u32) -> Result<(), anyhow::Error> {
+        let length = self.sequence.len() as u32;
+        if !self.is_circular() {
+            return Err(anyhow!("record {} is not circular", self.id));
+        }
+        if origin == 0 || origin > length {
+            return Err(anyhow!(
+                "origin {} is outside record {} of {} bp",
+                origin,
+                self.id,
+                length
+            ));
+        }
+        let cut = origin as usize - 1;
+        self.sequence = format!("{}{}", &self.sequence[cut..], &self.sequence[..cut]);
+        self.length = length;
+        let sources: Vec<String> = self.source_map.source_attributes.keys().cloned().collect();
+        for source in sources {
+            let (start, stop) = match (
+                self.source_map.get_start(&source),
+                self.source_map.get_stop(&source),
+            ) {
+                (Some(start), Some(stop)) => (start.clone(), stop.clone()),
+                _ => continue,
+            };
+            let location = Location::span(&start, &stop, 1).rotate(origin, length);
+            let (start, stop) = self.bounds(&location);
+            self.source_map.retain_attributes(&source, |a| {
+                !matches!(
+                    a,
+                    SourceAttributes::Start { .. } | SourceAttributes::Stop { .. }
+                )
+            });
+            self.source_map
+                .set_counter(source)
+                .set_start(start)
+                .set_stop(stop);
+        }
+        let feature_types: Vec<String> =
+            self.feature_types().iter().map(|t| t.to_string()).collect();
+        for feature_type in feature_types {
+            let locus_tags: Vec<String> = match self.features_of(&feature_type) {
+                Some(features) => features.attributes.keys().cloned().collect(),
+                None => continue,
+            };
+            for locus_tag in locus_tags {
+                let features = self.features_mut(&feature_type);
+                let location = match features.get_location(&locus_tag) {
+                    Some(location) => location.clone(),
+                    None => match (
+                        features.get_start(&locus_tag),
+                        features.get_stop(&locus_tag),
+                        features.get_strand(&locus_tag),
+                    ) {
+                        (Some(start), Some(stop), Some(strand)) => {
+                            Location::span(start, stop, *strand)
+                        }
+                        _ => continue,
+                    },
+                };
+                let location = location.rotate(origin, length);
+                let (start, stop) = self.bounds(&location);
+                let features = self.features_mut(&feature_type);
+                features.retain_attributes(&locus_tag, |a| {
+                    !matches!(
+                        a,
+                        FeatureAttributes::Start { .. }
+                            | FeatureAttributes::Stop { .. }
+                            | FeatureAttributes::Location { .. }
+                    )
+                });
+                features
+                    .set_counter(locus_tag.clone())
+                    .set_start(start.clone())
+                    .set_stop(stop.clone())
+                    .set_location(location);
+                //the coding sequences are unchanged, only their coordinates move
+                if feature_type == "CDS"
+                    && self.seq_features.seq_attributes.contains_key(&locus_tag)
+                {
+                    self.seq_features.retain_attributes(&locus_tag, |a| {
+                        !matches!(
+                            a,
+                            SequenceAttributes::Start { .. } | SequenceAttributes::Stop { .. }
+                        )
+                    });
+                    self.seq_features
+                        .set_counter(locus_tag)
+                        .set_start(RangeValue::Exact(start.get_value()))
+                        .set_stop(RangeValue::Exact(stop.get_value() - 1));
+                }
+            }
+        }
+        Ok(())
+    }
     pub fn cds(&mut self) -> FeatureAttributeBuilder {
         self.cds.clone()
     }
@@ -1663,6 +2232,49 @@ impl Record {
     pub fn seq_features(&mut self) -> SequenceAttributeBuilder {
         self.seq_features.clone()
     }
+    ///the feature types present in the record, CDS first followed by gene, rRNA, tRNA, mobile_element etc. in alphabetical order
+    pub fn feature_types(&self) -> Vec<&str> {
+        let mut types = Vec::new();
+        if !self.cds.attributes.is_empty() {
+            types.push("CDS");
+        }
+        types.extend(self.features.keys().map(|k| k.as_str()));
+        types
+    }
+    ///the features of a given type, CDS are held in the cds field and all other types in features
+    pub fn features_of(&self, feature_type: &str) -> Option<&FeatureAttributeBuilder> {
+        if feature_type == "CDS" {
+            Some(&self.cds)
+        } else {
+            self.features.get(feature_type)
+        }
+    }
+    ///mutable access to the features of a given type, creating an empty builder for a new feature type
+    pub fn features_mut(&mut self, feature_type: &str) -> &mut FeatureAttributeBuilder {
+        if feature_type == "CDS" {
+            &mut self.cds
+        } else {
+            self.features.entry(feature_type.to_string()).or_default()
+        }
+    }
+    ///every (feature type, locus tag) pair ordered by start coordinate, a gene is placed ahead of the CDS or RNA it encloses
+    pub fn ordered_features(&self) -> Vec<(&str, &str)> {
+        let mut ordered: Vec<(u32, u8, &str, &str)> = Vec::new();
+        for feature_type in self.feature_types() {
+            if let Some(features) = self.features_of(feature_type) {
+                for locus_tag in features.attributes.keys() {
+                    let start = features
+                        .get_start(locus_tag)
+                        .map(|v| v.get_value())
+                        .unwrap_or(0);
+                    let rank = if feature_type == "gene" { 0 } else { 1 };
+                    ordered.push((start, rank, feature_type, locus_tag));
+                }
+            }
+        }
+        ordered.sort();
+        ordered.into_iter().map(|(_, _, t, k)| (t, k)).collect()
+    }
     fn rec_clear(&mut self) {
         self.id.clear();
         self.length = 0;
@@ -1673,6 +2285,10 @@ impl Record {
         self.source_map = SourceAttributeBuilder::new();
         self.cds = FeatureAttributeBuilder::new();
         self.seq_features = SequenceAttributeBuilder::new();
+        self.features = BTreeMap::new();
+        self.header = RecordHeader::new();
+        self.topology = Topology::Linear;
+        self.translation_issues = BTreeMap::new();
     }
 }
 
@@ -1796,4 +2412,697 @@ mod tests {
             }
         }
     }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_all_feature_types() {
+        let file_gbk = "K12_ribo.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed");
+        assert_eq!(record.feature_types(), vec!["CDS", "gene"]);
+        let genes = record.features_of("gene").expect("missing gene features");
+        assert_eq!(genes.get_start("b3305").map(|v| v.get_value()), Some(373));
+        assert_eq!(genes.get_stop("b3305").map(|v| v.get_value()), Some(906));
+        assert_eq!(genes.get_strand("b3305"), Some(&-1));
+        assert_eq!(
+            record.ordered_features(),
+            vec![
+                ("gene", "b3304"),
+                ("CDS", "b3304"),
+                ("gene", "b3305"),
+                ("CDS", "b3305")
+            ]
+        );
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_qualifiers() {
+        let file_gbk = "K12_ribo.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed");
+        let qualifiers = record
+            .cds
+            .get_qualifiers("b3304")
+            .expect("missing qualifiers");
+        assert_eq!(qualifiers.protein_id(), Some("NP_417763.1"));
+        assert_eq!(qualifiers.transl_table(), Some(11));
+        assert_eq!(qualifiers.get("gene_synonym"), Some("ECK3291"));
+        assert_eq!(
+            qualifiers.db_xrefs(),
+            vec![
+                "UniProtKB/Swiss-Prot:P0C018",
+                "ASAP:ABE-0010825",
+                "ECOCYC:EG10879",
+                "GeneID:947804"
+            ]
+        );
+        assert!(!qualifiers.is_pseudo());
+        let source = record
+            .source_map
+            .get_qualifiers("source_NC_000913_1")
+            .expect("missing source qualifiers");
+        assert_eq!(source.get("sub_strain"), Some("MG1655"));
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_qualifiers_round_trip() {
+        let file_gbk = "K12_ribo.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed").clone();
+        let output = std::env::temp_dir().join("microbiorust_qualifiers_round_trip.gbk");
+        let _ = std::fs::remove_file(&output);
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        seq_region.insert("source_NC_000913_1".to_string(), (1, 913));
+        gbk_write(seq_region, vec![record.clone()], output.to_str().unwrap())
+            .expect("failed to write gbk");
+        let written = genbank!(output.to_str().unwrap());
+        let rewritten = written.first().expect("no record written");
+        assert_eq!(
+            rewritten.cds.get_qualifiers("b3305"),
+            record.cds.get_qualifiers("b3305")
+        );
+        assert_eq!(
+            rewritten.seq_features.get_sequence_faa("b3305"),
+            record.seq_features.get_sequence_faa("b3305")
+        );
+        let _ = std::fs::remove_file(&output);
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_multiline_qualifiers() {
+        let file_gbk = "rhizexample.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed");
+        let qualifiers = record
+            .cds
+            .get_qualifiers("pRL80008")
+            .expect("missing qualifiers");
+        assert_eq!(
+            qualifiers.notes(),
+            vec!["N-terminus is truncated relative to the A. tumefaciens and R. etli putative integrase matches"]
+        );
+        let translation = qualifiers.translation().expect("missing translation");
+        assert!(translation.starts_with('M') && !translation.contains(' '));
+        assert_eq!(
+            Some(&translation.to_string()),
+            record.seq_features.get_sequence_faa("pRL80008")
+        );
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_locations() {
+        let file_gbk = "K12_ribo.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed");
+        assert_eq!(
+            record.source_map.get_start("source_NC_000913_1"),
+            Some(&RangeValue::LessThan(1))
+        );
+        assert_eq!(
+            record.source_map.get_stop("source_NC_000913_1"),
+            Some(&RangeValue::GreaterThan(913))
+        );
+        let location = record.cds.get_location("b3304").expect("missing location");
+        assert_eq!(location.to_string(), "complement(10..363)");
+        assert_eq!(location.strand(), -1);
+        //the reverse strand gene is read on its coding strand
+        let ffn = record
+            .seq_features
+            .get_sequence_ffn("b3304")
+            .expect("missing ffn");
+        assert!(ffn.starts_with("atggataagaaatctgctcgtatccgtcgtgcg"));
+        assert_eq!(ffn.len(), 354);
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_joined_feature() {
+        let file_gbk = "rhizexample.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed");
+        assert!(record.cds.get_start("pRL80039_0").is_none());
+        assert_eq!(
+            record.cds.get_start("pRL80039"),
+            Some(&RangeValue::Exact(41369))
+        );
+        assert_eq!(
+            record.cds.get_stop("pRL80039"),
+            Some(&RangeValue::Exact(42216))
+        );
+        let location = record
+            .cds
+            .get_location("pRL80039")
+            .expect("missing location");
+        assert_eq!(
+            location.segments(),
+            vec![(41369, 42067, 1), (42067, 42216, 1)]
+        );
+        let spliced = format!(
+            "{}{}",
+            &record.sequence[41368..42067],
+            &record.sequence[42066..42216]
+        );
+        assert_eq!(
+            record.seq_features.get_sequence_ffn("pRL80039"),
+            Some(&spliced)
+        );
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_header() {
+        let file_gbk = "rhizexample.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed");
+        let header = &record.header;
+        assert_eq!(header.molecule_type, "DNA");
+        assert!(record.is_circular());
+        assert_eq!(header.division, "BCT");
+        assert_eq!(header.date, "14-JUL-2016");
+        assert_eq!(header.accessions, vec!["AM236082"]);
+        assert_eq!(header.version, "AM236082.1");
+        assert_eq!(header.bioproject(), Some("PRJNA344"));
+        assert_eq!(header.biosample(), Some("SAMEA1705944"));
+        assert_eq!(header.organism, "Rhizobium johnstonii 3841");
+        assert_eq!(header.lineage.first().map(|t| t.as_str()), Some("Bacteria"));
+        assert_eq!(header.references.len(), 2);
+        assert_eq!(header.references[0].pubmed, "16640791");
+        assert_eq!(header.references[1].bases, "(bases 1 to 147463)");
+        assert_eq!(header.references[1].title, "Direct Submission");
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_header_round_trip() {
+        let file_gbk = "rhizexample.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed").clone();
+        let output = std::env::temp_dir().join("microbiorust_header_round_trip.gbk");
+        let _ = std::fs::remove_file(&output);
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        seq_region.insert("source_AM236082_1".to_string(), (1, 147463));
+        gbk_write(seq_region, vec![record.clone()], output.to_str().unwrap())
+            .expect("failed to write gbk");
+        //the written header matches the original up to the FEATURES table
+        let header_lines = |path: &str| -> Vec<String> {
+            std::fs::read_to_string(path)
+                .expect("failed to read gbk")
+                .lines()
+                .take_while(|line| !line.starts_with("FEATURES"))
+                .map(|line| line.to_string())
+                .collect()
+        };
+        assert_eq!(
+            header_lines(output.to_str().unwrap()),
+            header_lines(file_gbk)
+        );
+        let written = genbank!(output.to_str().unwrap());
+        assert_eq!(
+            written.first().expect("no record written").header,
+            record.header
+        );
+        let _ = std::fs::remove_file(&output);
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_rotate_circular_record() {
+        let file_gbk = "rhizexample.gbk";
+        let records = genbank!(&file_gbk);
+        let original = records.first().expect("no record parsed").clone();
+        let mut record = original.clone();
+        record.rotate(3000).expect("failed to rotate");
+        assert_eq!(record.sequence.len(), original.sequence.len());
+        assert_eq!(&record.sequence[..10], &original.sequence[2999..3009]);
+        //pRL80003 now runs over the origin
+        let location = record
+            .cds
+            .get_location("pRL80003")
+            .expect("missing location");
+        assert_eq!(location.to_string(), "join(146919..147463,1..673)");
+        assert_eq!(
+            record.cds.get_start("pRL80003"),
+            Some(&RangeValue::Exact(146919))
+        );
+        assert_eq!(
+            record.cds.get_stop("pRL80003"),
+            Some(&RangeValue::Exact(148136))
+        );
+        assert_eq!(
+            Some(&location.extract(&record.sequence)),
+            original.seq_features.get_sequence_ffn("pRL80003")
+        );
+        assert_eq!(
+            record
+                .features_of("gene")
+                .and_then(|g| g.get_location("pRL80001"))
+                .map(|l| l.to_string()),
+            Some("144465..145661".to_string())
+        );
+        assert_eq!(
+            record.source_map.get_stop("source_AM236082_1"),
+            Some(&RangeValue::Exact(147463))
+        );
+        //rotating back to the old origin gives back the original coordinates
+        record.rotate(147463 - 3000 + 2).expect("failed to rotate");
+        assert_eq!(record.sequence, original.sequence);
+        assert_eq!(
+            record.cds.get_location("pRL80003").map(|l| l.to_string()),
+            Some("2455..3672".to_string())
+        );
+        let file_gbk = "K12_ribo.gbk";
+        let mut linear = genbank!(&file_gbk).remove(0);
+        assert!(linear.rotate(10).is_err());
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_translation_tables() {
+        let file_gbk = "rhizexample.gbk";
+        let records = genbank!(&file_gbk);
+        let record = records.first().expect("no record parsed");
+        //every complete CDS translates to its annotated /translation with table 11
+        for (locus_tag, qualifiers) in record
+            .cds
+            .attributes
+            .keys()
+            .filter_map(|k| record.cds.get_qualifiers(k).map(|q| (k, q)))
+        {
+            if qualifiers.is_pseudo() {
+                continue;
+            }
+            if let Some(translation) = qualifiers.translation() {
+                assert_eq!(
+                    record.seq_features.get_sequence_faa(locus_tag),
+                    Some(&translation.to_string()),
+                    "{}",
+                    locus_tag
+                );
+            }
+        }
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_validate_translations() {
+        //move the start of pRL80001 by one base to shift its reading frame
+        let content = std::fs::read_to_string("rhizexample.gbk")
+            .expect("failed to read gbk")
+            .replacen(
+                "     CDS             1..1197",
+                "     CDS             2..1197",
+                1,
+            );
+        let mut records = Reader::new(content.as_bytes())
+            .validate_translations(true)
+            .records();
+        let record = records.next().expect("no record").expect("failed to parse");
+        assert_eq!(record.translation_issues.len(), 1);
+        let issues = record
+            .translation_issues
+            .get("pRL80001")
+            .expect("no issues for pRL80001");
+        assert!(issues
+            .iter()
+            .any(|i| matches!(i, TranslationIssue::InternalStop { .. })));
+        assert!(issues
+            .iter()
+            .any(|i| matches!(i, TranslationIssue::FirstCodonDifference { .. })));
+        let annotated = record
+            .cds
+            .get_qualifiers("pRL80001")
+            .and_then(|q| q.translation())
+            .expect("missing translation")
+            .to_string();
+        assert_ne!(
+            record.seq_features.get_sequence_faa("pRL80001"),
+            Some(&annotated)
+        );
+        let mut records = Reader::new(content.as_bytes())
+            .prefer_annotated_translation(true)
+            .records();
+        let record = records.next().expect("no record").expect("failed to parse");
+        assert!(record.translation_issues.is_empty());
+        assert_eq!(
+            record.seq_features.get_sequence_faa("pRL80001"),
+            Some(&annotated)
+        );
+        //an unknown /transl_table falls back to table 11 and is reported alongside any differences
+        let content = content.replacen("/transl_table=11", "/transl_table=99", 1);
+        let mut records = Reader::new(content.as_bytes())
+            .validate_translations(true)
+            .records();
+        let record = records.next().expect("no record").expect("failed to parse");
+        let issues = record
+            .translation_issues
+            .get("pRL80001")
+            .expect("no issues for pRL80001");
+        assert_eq!(issues[0], TranslationIssue::UnknownTable { table: 99 });
+        assert!(issues
+            .iter()
+            .any(|i| matches!(i, TranslationIssue::InternalStop { .. })));
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_parse_errors() {
+        let content = std::fs::read_to_string("rhizexample.gbk").expect("failed to read gbk");
+        let first_error = |text: &str| {
+            Reader::new(text.as_bytes())
+                .records()
+                .find_map(|r| r.err())
+                .expect("no error returned")
+        };
+        let bad_location = content.replacen(
+            "     CDS             1..1197",
+            "     CDS             1..x197",
+            1,
+        );
+        match first_error(&bad_location) {
+            ParseError::BadLocation {
+                line_number,
+                record_id,
+                line,
+                ..
+            } => {
+                assert_eq!(line_number, 45);
+                assert_eq!(record_id, "AM236082");
+                assert_eq!(line, "1..x197");
+            }
+            other => panic!("expected a bad location, got {:?}", other),
+        }
+        //the file ends in the middle of the sequence
+        let truncated: String = content
+            .lines()
+            .take(3000)
+            .map(|l| format!("{}\n", l))
+            .collect();
+        let error = first_error(&truncated);
+        assert!(matches!(error, ParseError::TruncatedSequence { .. }));
+        assert_eq!(error.line_number(), 3000);
+        //the sequence is shorter than the length on the LOCUS line
+        let error = first_error(&format!("{}//\n", truncated));
+        assert!(matches!(error, ParseError::TruncatedSequence { .. }));
+        let no_locus = content.lines().skip(1).join("\n");
+        assert!(matches!(
+            first_error(&no_locus),
+            ParseError::MissingLocus { .. }
+        ));
+        assert_eq!(
+            try_genbank!("K12_ribo.gbk").expect("failed to parse").len(),
+            1
+        );
+        assert!(matches!(
+            try_genbank!("missing.gbk"),
+            Err(ParseError::Io { .. })
+        ));
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_gff_write() {
+        //two records, each is its own sequence region with its own coordinates
+        let mut records = genbank!("rhizexample.gbk");
+        records.extend(genbank!("K12_ribo.gbk"));
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        for record in records.iter() {
+            let source_name = record.source_map.source_name.clone().expect("no source");
+            seq_region.insert(source_name, (1, record.sequence.len() as u32));
+        }
+        let output = std::env::temp_dir().join("microbiorust_test_gff_write.gff");
+        let _ = std::fs::remove_file(&output);
+        gff_write(seq_region, records.clone(), output.to_str().unwrap(), true)
+            .expect("failed to write gff");
+        let content = std::fs::read_to_string(&output).expect("failed to read gff");
+        std::fs::remove_file(&output).ok();
+        assert!(content.contains("##sequence-region AM236082 1 147463\n"));
+        assert!(content.contains("##sequence-region NC_000913 1 913\n"));
+        assert!(content.contains(">AM236082\n"));
+        assert!(content.contains(">NC_000913\n"));
+        let (features, _) = content.split_once("##FASTA").expect("no FASTA section");
+        for line in features.lines().filter(|l| !l.starts_with('#')) {
+            let columns: Vec<&str> = line.split('\t').collect();
+            assert_eq!(columns.len(), 9, "{}", line);
+            assert_eq!(columns[5], ".");
+            assert!(columns[8].starts_with("ID="), "{}", line);
+            assert!(!columns[8].contains(";name="));
+        }
+        assert!(content.contains(
+            "NC_000913\t.\tCDS\t10\t363\t.\t-\t0\tID=cds-b3304;Name=rplR;Parent=gene-b3304;"
+        ));
+        //the written file reads back into the same features
+        let gff_records: Vec<Record> = crate::gff::Reader::new(content.as_bytes())
+            .records()
+            .map(|r| r.expect("failed to read gff"))
+            .collect();
+        assert_eq!(gff_records.len(), 2);
+        assert!(gff_records[0].is_circular());
+        for (gff_record, record) in gff_records.iter().zip(records.iter()) {
+            assert_eq!(gff_record.id, record.id);
+            assert_eq!(gff_record.sequence, record.sequence);
+            assert_eq!(gff_record.cds.attributes.len(), record.cds.attributes.len());
+            for locus_tag in record.cds.attributes.keys() {
+                assert_eq!(
+                    gff_record.cds.get_location(locus_tag),
+                    record.cds.get_location(locus_tag)
+                );
+                assert_eq!(
+                    gff_record.seq_features.get_sequence_faa(locus_tag),
+                    record.seq_features.get_sequence_faa(locus_tag)
+                );
+            }
+        }
+    }
+    #[test]
+    fn test_made_up_gene_lines() {
+        //a record without gene features gets a gene line per CDS, a CDS without a locus tag gets none
+        let mut record = genbank!("K12_ribo.gbk").remove(0);
+        record.features.remove("gene");
+        record
+            .features_mut("CDS")
+            .set_counter("CDS_3".to_string())
+            .set_start(RangeValue::Exact(20))
+            .set_stop(RangeValue::Exact(28))
+            .set_strand(1)
+            .set_location("join(20..25,26..28)".parse().unwrap());
+        let output = std::env::temp_dir().join("microbiorust_test_made_up_gene_lines.gbk");
+        let _ = std::fs::remove_file(&output);
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        seq_region.insert("source_NC_000913_1".to_string(), (1, 913));
+        gbk_write(seq_region, vec![record], output.to_str().unwrap()).expect("failed to write gbk");
+        let content = std::fs::read_to_string(&output).expect("failed to read gbk");
+        std::fs::remove_file(&output).ok();
+        assert!(content.contains(
+            "     gene            join(20..25,26..28)\n     CDS             join(20..25,26..28)\n"
+        ));
+        assert!(!content.contains("/locus_tag=\"CDS_3\""));
+        assert!(content.contains(
+            "     gene            complement(10..363)\n                     /locus_tag=\"b3304\"\n"
+        ));
+    }
+    #[test]
+    fn test_write_feature_without_stop() {
+        let mut record = genbank!("K12_ribo.gbk").remove(0);
+        record
+            .features_mut("misc_feature")
+            .set_counter("no_stop".to_string())
+            .set_start(RangeValue::Exact(20));
+        let output = std::env::temp_dir().join("microbiorust_test_feature_without_stop.gbk");
+        let _ = std::fs::remove_file(&output);
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        seq_region.insert("source_NC_000913_1".to_string(), (1, 913));
+        let result = gbk_write(
+            seq_region.clone(),
+            vec![record.clone()],
+            output.to_str().unwrap(),
+        );
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let _ = std::fs::remove_file(&output);
+        let result = gff_write(seq_region, vec![record], output.to_str().unwrap(), false);
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let _ = std::fs::remove_file(&output);
+    }
+    #[test]
+    fn test_gff_write_unsorted_ids() {
+        //the records are not in the order of their source names, each region keeps its own source qualifiers
+        let mut records = genbank!("K12_ribo.gbk");
+        records.extend(genbank!("rhizexample.gbk"));
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        for record in records.iter() {
+            let source_name = record.source_map.source_name.clone().expect("no source");
+            seq_region.insert(source_name, (1, record.sequence.len() as u32));
+        }
+        let output = std::env::temp_dir().join("microbiorust_test_gff_write_unsorted.gff");
+        let _ = std::fs::remove_file(&output);
+        gff_write(seq_region, records, output.to_str().unwrap(), false)
+            .expect("failed to write gff");
+        let content = std::fs::read_to_string(&output).expect("failed to read gff");
+        std::fs::remove_file(&output).ok();
+        let region = |seqid: &str| {
+            content
+                .lines()
+                .find(|l| l.starts_with(&format!("{}\t.\tregion\t", seqid)))
+                .expect("no region line")
+                .to_string()
+        };
+        assert!(region("NC_000913").contains("organism=Escherichia coli str. K-12 substr. MG1655"));
+        assert!(region("NC_000913").contains("strain=K-12"));
+        assert!(region("AM236082").contains("organism=Rhizobium johnstonii 3841"));
+        assert!(region("AM236082").contains("strain=3841"));
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_gtf_write() {
+        let mut records = genbank!("rhizexample.gbk");
+        records.extend(genbank!("K12_ribo.gbk"));
+        let output = std::env::temp_dir().join("microbiorust_test_gtf_write.gtf");
+        let _ = std::fs::remove_file(&output);
+        gtf_write(records.clone(), output.to_str().unwrap()).expect("failed to write gtf");
+        let content = std::fs::read_to_string(&output).expect("failed to read gtf");
+        std::fs::remove_file(&output).ok();
+        assert!(content.contains(
+            "NC_000913\t.\tCDS\t13\t363\t.\t-\t0\tgene_id \"b3304\"; transcript_id \"cds-b3304\"; gene_name \"rplR\";"
+        ));
+        assert!(content.contains("NC_000913\t.\tstop_codon\t10\t12\t.\t-\t0\t"));
+        //the stop codons are added back to the CDS when the file is read
+        let gtf_records: Vec<Record> = crate::gtf::Reader::new(content.as_bytes())
+            .records()
+            .map(|r| r.expect("failed to read gtf"))
+            .collect();
+        assert_eq!(gtf_records.len(), 2);
+        for (gtf_record, record) in gtf_records.iter().zip(records.iter()) {
+            assert_eq!(gtf_record.id, record.id);
+            assert_eq!(gtf_record.cds.attributes.len(), record.cds.attributes.len());
+            for locus_tag in record.cds.attributes.keys() {
+                assert_eq!(
+                    gtf_record.cds.get_location(locus_tag),
+                    record.cds.get_location(locus_tag)
+                );
+                assert_eq!(
+                    gtf_record.cds.get_codon_start(locus_tag),
+                    record.cds.get_codon_start(locus_tag)
+                );
+            }
+        }
+    }
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_embl_write() {
+        let records = genbank!("K12_ribo.gbk");
+        let output = std::env::temp_dir().join("microbiorust_test_gbk_embl_write.embl");
+        let _ = std::fs::remove_file(&output);
+        embl_write(records.clone(), output.to_str().unwrap()).expect("failed to write embl");
+        let content = std::fs::read_to_string(&output).expect("failed to read embl");
+        assert!(content.lines().all(|line| line.len() <= 80));
+        assert!(content.contains("FT   CDS             complement(10..363)\n"));
+        assert!(content.contains("FT                   /gene=\"rplR\"\n"));
+        let embl_records = crate::embl!(output.to_str().unwrap());
+        std::fs::remove_file(&output).ok();
+        assert_eq!(embl_records.len(), records.len());
+        for (embl_record, record) in embl_records.iter().zip(records.iter()) {
+            assert_eq!(embl_record.id, record.id);
+            assert_eq!(embl_record.sequence, record.sequence.to_lowercase());
+            assert_eq!(embl_record.header.organism, record.header.organism);
+            assert_eq!(embl_record.header.lineage, record.header.lineage);
+            for (locus_tag, _) in record.cds.attributes.iter() {
+                assert_eq!(
+                    embl_record.cds.get_location(locus_tag),
+                    record.cds.get_location(locus_tag)
+                );
+                assert_eq!(
+                    embl_record.seq_features.get_sequence_faa(locus_tag),
+                    record.seq_features.get_sequence_faa(locus_tag)
+                );
+            }
+        }
+    }
+    #[cfg(feature = "gzip")]
+    #[test]
+    #[allow(unused_mut)]
+    #[allow(unused_variables)]
+    #[allow(dead_code)]
+    #[allow(unused_assignments)]
+    #[allow(unused_imports)]
+    fn test_compressed_input() {
+        use std::io::Write;
+        let plain = genbank!("K12_ribo.gbk");
+        let path = std::env::temp_dir().join("microbiorust_test_K12_ribo.gbk.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder
+            .write_all(&std::fs::read("K12_ribo.gbk").unwrap())
+            .unwrap();
+        encoder.finish().unwrap();
+        let records = genbank!(&path);
+        let from_file: Vec<Record> = Reader::from_file(&path)
+            .unwrap()
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(records.len(), plain.len());
+        assert_eq!(from_file.len(), plain.len());
+        for (record, plain) in records
+            .iter()
+            .chain(from_file.iter())
+            .zip(plain.iter().cycle())
+        {
+            assert_eq!(record.id, plain.id);
+            assert_eq!(record.sequence, plain.sequence);
+            assert_eq!(record.cds.attributes.len(), plain.cds.attributes.len());
+        }
+    }
 }
@@ -0,0 +1,928 @@
+//! # A GFF3 reader
+//!
+//! GFF3 files such as those of Prokka, Bakta and NCBI PGAP are read line by line into ```Feature```s with percent-decoded attributes.
+//! The ```##sequence-region``` directives and the sequences of a trailing ```##FASTA``` block are kept by the reader.
+//! ```records()``` assembles the features into the same ```gbk::Record``` the GenBank reader yields: lines sharing an ```ID``` (e.g. a CDS over several exons)
+//! become one joined feature, ```locus_tag``` and ```gene``` are inherited through the ```Parent``` hierarchy (gene → mRNA → CDS),
+//! and the CDS sequences are extracted and translated, so a GFF3 file can be written out with ```gbk_write```.
+//!
+//!```rust
+//! use microBioRust::gff::Reader;
+//!
+//! let text = "##gff-version 3\n\
+//!     ##sequence-region ctg1 1 30\n\
+//!     ctg1\tProkka\tgene\t1\t15\t.\t+\t.\tID=TEST_00001_gene;locus_tag=TEST_00001\n\
+//!     ctg1\tProkka\tCDS\t1\t15\t.\t+\t0\tID=TEST_00001;Parent=TEST_00001_gene;product=ABC transporter%2C permease\n\
+//!     ##FASTA\n\
+//!     >ctg1\n\
+//!     ATGAAACCCGGGTAACTAACTTATTTGCCC\n";
+//! let mut records = Reader::new(text.as_bytes()).records();
+//! let record = records.next().unwrap().unwrap();
+//! assert_eq!((record.id.as_str(), record.length), ("ctg1", 30));
+//! assert_eq!(record.cds.get_product("TEST_00001").unwrap(), "ABC transporter_ permease");
+//! assert_eq!(record.seq_features.get_sequence_faa("TEST_00001").unwrap(), "MKPG");
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk::{substitute_odd_punctuation, Record};
+use crate::header::Topology;
+use crate::location::{Location, Position};
+use crate::qualifiers::{gff3_unescape, Qualifiers};
+use crate::record::RangeValue;
+use crate::translation::{genetic_code_or_default, DEFAULT_TABLE};
+use anyhow::{anyhow, Context};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    io,
+    path::Path,
+    str::FromStr,
+};
+
+///attributes with a meaning in GFF3 that are not carried over to the qualifiers of a record
+const RESERVED_ATTRIBUTES: [&str; 11] = [
+    "ID",
+    "Name",
+    "Alias",
+    "Parent",
+    "Target",
+    "Gap",
+    "Derives_from",
+    "Is_circular",
+    "gbkey",
+    "start_range",
+    "end_range",
+];
+
+///qualifiers written as ```key=true``` in GFF3 that are flags without a value in GenBank and EMBL files
+const FLAG_QUALIFIERS: [&str; 6] = [
+    "pseudo",
+    "environmental_sample",
+    "focus",
+    "germline",
+    "ribosomal_slippage",
+    "trans_splicing",
+];
+
+//a parent chain deeper than this is taken to be a loop
+const MAX_PARENT_DEPTH: usize = 16;
+
+///a feature line of a GFF3 file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feature {
+    pub seqid: String,
+    pub source: String,
+    pub feature_type: String,
+    pub start: u32,
+    pub end: u32,
+    pub score: Option<f64>,
+    ///1 for '+', -1 for '-' and 0 for an unstranded ('.') or unknown ('?') feature
+    pub strand: i8,
+    pub phase: Option<u8>,
+    ///the column 9 tags in file order with their percent-decoded values, a tag can have several comma separated values
+    pub attributes: Vec<(String, Vec<String>)>,
+}
+
+impl Feature {
+    ///the first value of an attribute
+    pub fn attribute(&self, tag: &str) -> Option<&str> {
+        self.attribute_values(tag).first().map(|v| v.as_str())
+    }
+    ///every value of an attribute, empty when the tag is missing
+    pub fn attribute_values(&self, tag: &str) -> &[String] {
+        self.attributes
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, values)| values.as_slice())
+            .unwrap_or_default()
+    }
+    pub fn id(&self) -> Option<&str> {
+        self.attribute("ID")
+    }
+    pub fn name(&self) -> Option<&str> {
+        self.attribute("Name")
+    }
+    pub fn parents(&self) -> &[String] {
+        self.attribute_values("Parent")
+    }
+    ///reads the first eight columns of a GFF3 or GTF line, returning the feature without attributes and the text of column 9
+    pub fn from_columns(line: &str) -> Result<(Self, &str), anyhow::Error> {
+        let columns: Vec<&str> = line.trim_end_matches(['\r', '\n']).split('\t').collect();
+        if columns.len() != 9 {
+            return Err(anyhow!("expected 9 columns, found {}", columns.len()));
+        }
+        let coordinate = |text: &str, name: &str| {
+            text.parse::<u32>()
+                .ok()
+                .filter(|v| *v > 0)
+                .ok_or(anyhow!("bad {} {:?}", name, text))
+        };
+        let start = coordinate(columns[3], "start")?;
+        let end = coordinate(columns[4], "end")?;
+        if start > end {
+            return Err(anyhow!("start {} is after end {}", start, end));
+        }
+        let score = match columns[5] {
+            "." => None,
+            text => Some(
+                text.parse::<f64>()
+                    .map_err(|_| anyhow!("bad score {:?}", text))?,
+            ),
+        };
+        let strand = match columns[6] {
+            "+" => 1,
+            "-" => -1,
+            "." | "?" => 0,
+            text => return Err(anyhow!("bad strand {:?}", text)),
+        };
+        let phase = match columns[7] {
+            "." => None,
+            "0" => Some(0),
+            "1" => Some(1),
+            "2" => Some(2),
+            text => return Err(anyhow!("bad phase {:?}", text)),
+        };
+        let feature = Feature {
+            seqid: gff3_unescape(columns[0]),
+            source: gff3_unescape(columns[1]),
+            feature_type: gff3_unescape(columns[2]),
+            start,
+            end,
+            score,
+            strand,
+            phase,
+            attributes: Vec::new(),
+        };
+        Ok((feature, columns[8]))
+    }
+}
+
+impl FromStr for Feature {
+    type Err = anyhow::Error;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (mut feature, attributes) = Feature::from_columns(line)?;
+        feature.attributes = parse_attributes(attributes)?;
+        Ok(feature)
+    }
+}
+
+///reads the ```tag=value1,value2;tag=value``` pairs of column 9, decoding the %XX escapes of tags and values
+pub fn parse_attributes(text: &str) -> Result<Vec<(String, Vec<String>)>, anyhow::Error> {
+    let mut attributes: Vec<(String, Vec<String>)> = Vec::new();
+    for pair in text.split(';').map(|p| p.trim()).filter(|p| !p.is_empty()) {
+        if pair == "." {
+            continue;
+        }
+        let (tag, value) = pair
+            .split_once('=')
+            .ok_or(anyhow!("attribute {:?} has no value", pair))?;
+        let tag = gff3_unescape(tag.trim());
+        let values: Vec<String> = value.split(',').map(gff3_unescape).collect();
+        match attributes.iter_mut().find(|(t, _)| *t == tag) {
+            Some((_, existing)) => existing.extend(values),
+            None => attributes.push((tag, values)),
+        }
+    }
+    Ok(attributes)
+}
+
+///an iterator over the features of a GFF3 file, ending at the ```##FASTA``` block
+#[derive(Debug)]
+pub struct Features<'a, B> {
+    reader: &'a mut Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Features<'_, B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Feature, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_feature() {
+            Ok(Some((_, feature))) => Some(Ok(feature)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///an iterator over the records of a GFF3 file, one per sequence region
+///the records are assembled once the whole file has been read, as the sequences follow the features
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    records: Option<VecDeque<Record>>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Records<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        if self.records.is_none() {
+            match self.reader.read_records() {
+                Ok(records) => self.records = Some(records.into()),
+                Err(err) => {
+                    self.error_has_occurred = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.records.as_mut().and_then(|r| r.pop_front()).map(Ok)
+    }
+}
+
+///per line reader for the file
+#[derive(Debug, Default)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+    sequence_regions: Vec<(String, u32, u32)>,
+    sequences: Vec<(String, String)>,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read GFF3 from given file path, decompressing gzip, BGZF, bzip2, xz or zstd files.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read GFF3 from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new GFF3 reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+            sequence_regions: Vec::new(),
+            sequences: Vec::new(),
+        }
+    }
+    ///the ```##sequence-region``` directives read so far as (seqid, start, end), in file order
+    pub fn sequence_regions(&self) -> &[(String, u32, u32)] {
+        &self.sequence_regions
+    }
+    ///the (seqid, sequence) pairs of the ```##FASTA``` block, filled once the features have been read
+    pub fn sequences(&self) -> &[(String, String)] {
+        &self.sequences
+    }
+    //reads the next line into the line buffer, counting the lines for the error messages
+    fn next_line(&mut self) -> Result<usize, ParseError> {
+        self.line_buffer.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: String::new(),
+                line: String::new(),
+                source,
+            })?;
+        if read > 0 {
+            self.line_number += 1;
+        }
+        Ok(read)
+    }
+    fn malformed(&self, message: String) -> ParseError {
+        let line = self.line_buffer.trim_end().to_string();
+        ParseError::MalformedLine {
+            line_number: self.line_number,
+            record_id: line.split('\t').next().unwrap_or_default().to_string(),
+            line,
+            message,
+        }
+    }
+    ///reads up to the next feature line with its line number, keeping the directives on the way
+    ///returns None at the end of the file or once the ```##FASTA``` block has been read
+    pub fn read_feature(&mut self) -> Result<Option<(usize, Feature)>, ParseError> {
+        loop {
+            if self.next_line()? == 0 {
+                return Ok(None);
+            }
+            let line = self.line_buffer.trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(region) = line.strip_prefix("##sequence-region") {
+                let fields: Vec<&str> = region.split_whitespace().collect();
+                let region = match fields.as_slice() {
+                    [seqid, start, end] => start
+                        .parse::<u32>()
+                        .ok()
+                        .zip(end.parse::<u32>().ok())
+                        .map(|(start, end)| (gff3_unescape(seqid), start, end)),
+                    _ => None,
+                };
+                match region {
+                    Some(region) => self.sequence_regions.push(region),
+                    None => return Err(self.malformed("bad sequence region".to_string())),
+                }
+                continue;
+            }
+            //the sequences are at the end of the file, a '>' line without the directive is taken as the start of the block
+            if line == "##FASTA" || line.starts_with('>') {
+                self.read_fasta()?;
+                return Ok(None);
+            }
+            if line.starts_with('#') {
+                continue;
+            }
+            let feature = line
+                .parse::<Feature>()
+                .map_err(|e| self.malformed(e.to_string()))?;
+            return Ok(Some((self.line_number, feature)));
+        }
+    }
+    //reads the sequences of the ##FASTA block to the end of the file
+    fn read_fasta(&mut self) -> Result<(), ParseError> {
+        if self.line_buffer.starts_with("##FASTA") {
+            self.next_line()?;
+        }
+        while !self.line_buffer.is_empty() {
+            let line = self.line_buffer.trim();
+            match line.strip_prefix('>') {
+                Some(header) => {
+                    let seqid = header.split_whitespace().next().unwrap_or_default();
+                    self.sequences.push((seqid.to_string(), String::new()));
+                }
+                None if line.is_empty() => (),
+                None => match self.sequences.last_mut() {
+                    Some((_, sequence)) => sequence.push_str(line),
+                    None => return Err(self.malformed("sequence without a header".to_string())),
+                },
+            }
+            self.next_line()?;
+        }
+        Ok(())
+    }
+    ///return an iterator over the features of the file
+    pub fn features(&mut self) -> Features<'_, B> {
+        Features {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+    ///return an iterator over the records of the file
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            records: None,
+            error_has_occurred: false,
+        }
+    }
+    //reads the whole file and builds one record per sequence region, in file order
+    fn read_records(&mut self) -> Result<Vec<Record>, ParseError> {
+        let mut features: Vec<(usize, Feature)> = Vec::new();
+        while let Some(feature) = self.read_feature()? {
+            features.push(feature);
+        }
+        let mut seqids: Vec<String> = Vec::new();
+        let regions = self.sequence_regions.iter().map(|(s, _, _)| s);
+        let sequences = self.sequences.iter().map(|(s, _)| s);
+        for seqid in regions
+            .chain(features.iter().map(|(_, f)| &f.seqid))
+            .chain(sequences)
+        {
+            if !seqids.contains(seqid) {
+                seqids.push(seqid.clone());
+            }
+        }
+        let merged = merge_features(features);
+        let mut records = Vec::with_capacity(seqids.len());
+        for seqid in seqids {
+            let sequence = self
+                .sequences
+                .iter()
+                .find(|(s, _)| *s == seqid)
+                .map(|(_, sequence)| sequence.clone())
+                .unwrap_or_default();
+            let region_end = self
+                .sequence_regions
+                .iter()
+                .find(|(s, _, _)| *s == seqid)
+                .map(|(_, _, end)| *end);
+            records.push(assemble_record(&seqid, sequence, region_end, &merged)?);
+        }
+        Ok(records)
+    }
+}
+
+///the (start, end, phase) of the GFF3 lines of a location, one line per part sorted by start
+///a part running over the origin of a circular record ends past the record length, as GFF3 has no way to wrap
+///the phase of each part follows from the codon start of a CDS, parts of other features have no phase
+pub fn location_parts(
+    location: &Location,
+    length: u32,
+    circular: bool,
+    codon_start: Option<u8>,
+) -> Vec<(u32, u32, Option<u8>)> {
+    let mut parts: Vec<(u32, u32, Option<u8>)> = Vec::new();
+    //bases of the parts read before this one, in biological order
+    let mut offset: u32 = 0;
+    for part in location.parts() {
+        if part.is_remote() || matches!(part, Location::Between { .. }) {
+            continue;
+        }
+        let (start, end) = match circular {
+            true => {
+                let (start, end) = part.circular_bounds(length);
+                (start.get_value(), end.get_value())
+            }
+            false => (part.start(), part.end()),
+        };
+        let phase = codon_start.map(|c| (c as i64 - 1 - offset as i64).rem_euclid(3) as u8);
+        offset += end - start + 1;
+        parts.push((start, end, phase));
+    }
+    parts.sort();
+    parts
+}
+
+///makes an ID unique within a file by adding a numeric suffix
+pub(crate) fn unique_id(base: &str, used: &mut HashSet<String>) -> String {
+    let mut id = base.to_string();
+    let mut suffix = 1;
+    while used.contains(&id) {
+        suffix += 1;
+        id = format!("{}_{}", base, suffix);
+    }
+    used.insert(id.clone());
+    id
+}
+
+///formats a sequence for the ##FASTA block, with a header line and 60 bases per line
+pub(crate) fn fasta_entry(seqid: &str, sequence: &str) -> String {
+    let mut entry = format!(">{}", seqid);
+    for line in sequence.as_bytes().chunks(60) {
+        entry.push('\n');
+        entry.push_str(&String::from_utf8_lossy(line));
+    }
+    entry
+}
+
+//the lines of a feature sharing one ID, such as the exons of a spliced CDS
+#[derive(Debug)]
+struct MergedFeature {
+    line_number: usize,
+    feature: Feature,
+    parts: Vec<(u32, u32, Option<u8>)>,
+}
+
+//groups the lines sharing an ID on the same sequence, other lines are kept as they are
+fn merge_features(features: Vec<(usize, Feature)>) -> Vec<MergedFeature> {
+    let mut merged: Vec<MergedFeature> = Vec::new();
+    let mut by_id: HashMap<(String, String), usize> = HashMap::new();
+    for (line_number, feature) in features {
+        let part = (feature.start, feature.end, feature.phase);
+        if let Some(id) = feature.id() {
+            let key = (feature.seqid.clone(), id.to_string());
+            if let Some(&index) = by_id.get(&key) {
+                merged[index].parts.push(part);
+                continue;
+            }
+            by_id.insert(key, merged.len());
+        }
+        merged.push(MergedFeature {
+            line_number,
+            feature,
+            parts: vec![part],
+        });
+    }
+    merged
+}
+
+//the first value of a tag on a feature or on its closest ancestor that has it
+fn inherited<'a>(
+    merged: &'a [MergedFeature],
+    ids: &HashMap<&str, usize>,
+    feature: &'a Feature,
+    tag: &str,
+    depth: usize,
+) -> Option<&'a str> {
+    if let Some(value) = feature.attribute(tag) {
+        return Some(value);
+    }
+    //a gene is named by its Name when it has no gene tag
+    if tag == "gene" && feature.feature_type == "gene" {
+        if let Some(name) = feature.name() {
+            return Some(name);
+        }
+    }
+    if depth >= MAX_PARENT_DEPTH {
+        return None;
+    }
+    feature
+        .parents()
+        .iter()
+        .filter_map(|parent| ids.get(parent.as_str()))
+        .find_map(|&index| inherited(merged, ids, &merged[index].feature, tag, depth + 1))
+}
+
+//the qualifiers of a feature, the inherited gene and locus_tag lead as in GenBank files
+fn feature_qualifiers(
+    feature: &Feature,
+    gene: Option<&str>,
+    locus_tag: Option<&str>,
+) -> Qualifiers {
+    let mut qualifiers = Qualifiers::new();
+    for (tag, value) in [("gene", gene), ("locus_tag", locus_tag)] {
+        if let Some(value) = value.filter(|_| feature.attribute(tag).is_none()) {
+            qualifiers.push(tag, Some(value.to_string()));
+        }
+    }
+    for (tag, values) in feature.attributes.iter() {
+        let key = match tag.as_str() {
+            t if RESERVED_ATTRIBUTES.contains(&t) => continue,
+            "Dbxref" => "db_xref",
+            "Note" => "note",
+            "Ontology_term" => continue,
+            t => t,
+        };
+        for value in values {
+            match FLAG_QUALIFIERS.contains(&key) && value == "true" {
+                true => qualifiers.push(key, None),
+                false => qualifiers.push(key, Some(value.clone())),
+            }
+        }
+    }
+    qualifiers
+}
+
+//the location of the parts of a feature, a part ending past the length of a circular record runs over the origin
+fn feature_location(merged: &MergedFeature, strand: i8, length: u32, circular: bool) -> Location {
+    let mut parts = merged.parts.clone();
+    parts.sort_by_key(|(start, end, _)| (*start, *end));
+    let fuzzy_start = merged
+        .feature
+        .attribute_values("start_range")
+        .first()
+        .map(|v| v == ".");
+    let fuzzy_end = merged
+        .feature
+        .attribute_values("end_range")
+        .get(1)
+        .map(|v| v == ".");
+    let last = parts.len() - 1;
+    let mut locations: Vec<Location> = parts
+        .iter()
+        .enumerate()
+        .map(|(i, (start, end, _))| {
+            let wrap = |v: u32| match circular && length > 0 && v > length {
+                true => v - length,
+                false => v,
+            };
+            let start = match (i, fuzzy_start) {
+                (0, Some(true)) => Position::Before(wrap(*start)),
+                _ => Position::Exact(wrap(*start)),
+            };
+            let end = match (i == last, fuzzy_end) {
+                (true, Some(true)) => Position::After(wrap(*end)),
+                _ => Position::Exact(wrap(*end)),
+            };
+            Location::Range { start, end, strand }
+        })
+        .collect();
+    if strand == -1 {
+        locations.reverse();
+    }
+    match locations.len() {
+        1 => locations.remove(0),
+        _ => Location::Join(locations),
+    }
+}
+
+//builds the record of one sequence region from the merged features
+fn assemble_record(
+    seqid: &str,
+    sequence: String,
+    region_end: Option<u32>,
+    merged: &[MergedFeature],
+) -> Result<Record, ParseError> {
+    let mut record = Record::new();
+    let on_sequence: Vec<&MergedFeature> =
+        merged.iter().filter(|m| m.feature.seqid == seqid).collect();
+    let ids: HashMap<&str, usize> = merged
+        .iter()
+        .enumerate()
+        .filter_map(|(i, m)| m.feature.id().map(|id| (id, i)))
+        .collect();
+    let is_source =
+        |m: &MergedFeature| matches!(m.feature.feature_type.as_str(), "region" | "source");
+    record.id = seqid.to_string();
+    record.length = match (region_end, sequence.len()) {
+        (Some(end), _) => end,
+        (None, 0) => on_sequence.iter().map(|m| m.feature.end).max().unwrap_or(0),
+        (None, length) => length as u32,
+    };
+    record.sequence = sequence;
+    //the accession names the record when it is written to GenBank
+    record.header.accessions = vec![seqid.to_string()];
+    record.header.molecule_type = "DNA".to_string();
+    if on_sequence
+        .iter()
+        .any(|m| is_source(m) && m.feature.attribute("Is_circular") == Some("true"))
+    {
+        record.topology = Topology::Circular;
+    }
+    //the region lines become source features, a record without one gets a source over the whole sequence
+    let mut source_counter = 0;
+    for source in on_sequence.iter().filter(|m| is_source(m)) {
+        source_counter += 1;
+        let source_name = format!("source_{}_{}", seqid, source_counter);
+        let qualifiers = feature_qualifiers(&source.feature, None, None);
+        let field = |tag: &str| qualifiers.get(tag).unwrap_or_default().to_string();
+        record
+            .source_map
+            .set_counter(source_name)
+            .set_start(RangeValue::Exact(source.feature.start))
+            .set_stop(RangeValue::Exact(source.feature.end))
+            .set_organism(field("organism"))
+            .set_mol_type(field("mol_type"))
+            .set_strain(field("strain"))
+            .set_type_material(field("type_material"))
+            .set_db_xref(field("db_xref"))
+            .set_qualifiers(qualifiers.clone());
+    }
+    if source_counter == 0 {
+        record
+            .source_map
+            .set_counter(format!("source_{}_1", seqid))
+            .set_start(RangeValue::Exact(1))
+            .set_stop(RangeValue::Exact(record.length));
+    }
+    record
+        .source_map
+        .set_source_name(format!("source_{}_1", seqid));
+    let mut feature_counter: HashMap<String, i32> = HashMap::new();
+    for merged_feature in on_sequence.iter().filter(|m| !is_source(m)) {
+        let feature = &merged_feature.feature;
+        let feature_type = feature.feature_type.clone();
+        let feature_count = feature_counter.entry(feature_type.clone()).or_insert(0);
+        *feature_count += 1;
+        let feature_count = *feature_count;
+        let gene = inherited(merged, &ids, feature, "gene", 0);
+        let own_locus_tag = feature.attribute("locus_tag");
+        let locus_tag = inherited(merged, &ids, feature, "locus_tag", 0);
+        let qualifiers = feature_qualifiers(feature, gene, locus_tag);
+        let product = substitute_odd_punctuation(
+            feature.attribute("product").unwrap_or_default().to_string(),
+        )
+        .map_err(|e| ParseError::BadQualifier {
+            line_number: merged_feature.line_number,
+            record_id: seqid.to_string(),
+            line: format!("product={:?}", feature.attribute("product")),
+            message: e.to_string(),
+        })?;
+        let strand = match feature.strand {
+            -1 => -1,
+            _ => 1,
+        };
+        let location =
+            feature_location(merged_feature, strand, record.length, record.is_circular());
+        //the phase of the part read first gives the codon start
+        let first_part = match strand {
+            -1 => merged_feature.parts.iter().max_by_key(|(_, end, _)| *end),
+            _ => merged_feature
+                .parts
+                .iter()
+                .min_by_key(|(start, _, _)| *start),
+        };
+        let codon_start = first_part.and_then(|(_, _, phase)| *phase).unwrap_or(0) + 1;
+        let mut key = match (own_locus_tag.or(locus_tag), feature.id()) {
+            (Some(locus_tag), _) => locus_tag.to_string(),
+            (None, Some(id)) => id.to_string(),
+            (None, None) => format!("{}_{}", feature_type, feature_count),
+        };
+        let start = match location.start_value() {
+            RangeValue::LessThan(_) => {
+                RangeValue::LessThan(merged_feature.parts.iter().map(|p| p.0).min().unwrap_or(0))
+            }
+            _ => RangeValue::Exact(merged_feature.parts.iter().map(|p| p.0).min().unwrap_or(0)),
+        };
+        let stop = match location.end_value() {
+            RangeValue::GreaterThan(_) => {
+                RangeValue::GreaterThan(merged_feature.parts.iter().map(|p| p.1).max().unwrap_or(0))
+            }
+            _ => RangeValue::Exact(merged_feature.parts.iter().map(|p| p.1).max().unwrap_or(0)),
+        };
+        let features = record.features_mut(&feature_type);
+        //exons and other children can share the locus tag of their gene, keep each one under its own key
+        if features.attributes.contains_key(&key) {
+            key = format!("{}_{}", key, feature_count);
+        }
+        features
+            .set_counter(key.clone())
+            .set_start(start.clone())
+            .set_stop(stop.clone())
+            .set_gene(gene.unwrap_or_default().to_string())
+            .set_product(product)
+            .set_strand(strand)
+            .set_location(location.clone())
+            .set_qualifiers(qualifiers.clone());
+        if feature_type != "CDS" {
+            continue;
+        }
+        features.set_codon_start(codon_start);
+        if record.sequence.is_empty() {
+            continue;
+        }
+        //the coding sequence is spliced from the parts and translated with the /transl_table of the CDS
+        let cds_char = location.extract(&record.sequence);
+        let table = qualifiers.transl_table().unwrap_or(DEFAULT_TABLE);
+        let (code, unknown_table) = genetic_code_or_default(table);
+        if let Some(issue) = unknown_table {
+            record
+                .translation_issues
+                .entry(key.clone())
+                .or_default()
+                .push(issue);
+        }
+        let cod = (codon_start - 1) as usize;
+        let prot_seq = code.translate_cds(
+            cds_char.get(cod..).unwrap_or_default(),
+            cod == 0 && !location.partial_start(),
+        );
+        let faa = prot_seq.split('*').next().unwrap_or_default().to_string();
+        record
+            .seq_features
+            .set_counter(key)
+            .set_start(RangeValue::Exact(start.get_value()))
+            .set_stop(RangeValue::Exact(stop.get_value() - 1))
+            .set_sequence_ffn(cds_char)
+            .set_sequence_faa(faa)
+            .set_codon_start(codon_start - 1)
+            .set_strand(strand);
+    }
+    Ok(record)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbk::gbk_write;
+    use std::collections::BTreeMap;
+
+    const EXAMPLE: &str = "##gff-version 3\n\
+        ##sequence-region ctg1 1 60\n\
+        ctg1\tRefSeq\tregion\t1\t60\t.\t+\t.\tID=ctg1:1..60;Dbxref=taxon:511145;Is_circular=true;mol_type=genomic DNA;strain=K-12\n\
+        ctg1\tProkka\tgene\t1\t15\t.\t+\t.\tID=TEST_00001_gene;locus_tag=TEST_00001;gene=abcA\n\
+        ctg1\tProkka\tCDS\t1\t15\t.\t+\t0\tID=TEST_00001;Parent=TEST_00001_gene;product=ABC transporter%2C permease;inference=ab initio prediction:Prodigal:002006\n\
+        ctg1\tProkka\tgene\t21\t52\t.\t-\t.\tID=gene2;Name=xyzB;locus_tag=TEST_00002\n\
+        ctg1\tProkka\tmRNA\t21\t52\t.\t-\t.\tID=rna2;Parent=gene2\n\
+        ctg1\tProkka\tCDS\t21\t29\t.\t-\t0\tID=cds2;Parent=rna2;product=spliced protein;pseudo=true\n\
+        ctg1\tProkka\tCDS\t41\t52\t.\t-\t0\tID=cds2;Parent=rna2;product=spliced protein;pseudo=true\n\
+        ctg1\tProkka\trepeat_region\t55\t65\t.\t.\t.\tNote=over the origin\n\
+        ##FASTA\n\
+        >ctg1 test contig\n\
+        ATGAAACCCGGGTAACTAACTTATTTGCCCGAACTCGTGT\n\
+        ACGATCAGCCATGAATTAGA\n";
+
+    #[test]
+    fn test_parse_feature() {
+        let feature: Feature =
+            "ctg1\tProkka\tCDS\t21\t29\t1.5e-3\t-\t2\tID=cds2;Parent=rna2,rna3;Note=L18%3B part of 50S"
+                .parse()
+                .unwrap();
+        assert_eq!((feature.start, feature.end, feature.strand), (21, 29, -1));
+        assert_eq!((feature.score, feature.phase), (Some(1.5e-3), Some(2)));
+        assert_eq!(feature.parents(), ["rna2", "rna3"]);
+        assert_eq!(feature.attribute("Note"), Some("L18; part of 50S"));
+        assert!("ctg1\tProkka\tCDS\t29\t21\t.\t-\t0\tID=cds2"
+            .parse::<Feature>()
+            .is_err());
+        assert!("ctg1\tProkka\tCDS\t21\t29\t.\t-\t0"
+            .parse::<Feature>()
+            .is_err());
+        assert!("ctg1\tProkka\tCDS\t21\t29\t.\t-\t0\tID"
+            .parse::<Feature>()
+            .is_err());
+    }
+    #[test]
+    fn test_read_features() {
+        let mut reader = Reader::new(EXAMPLE.as_bytes());
+        let features: Vec<Feature> = reader.features().map(|f| f.unwrap()).collect();
+        assert_eq!(features.len(), 8);
+        assert_eq!(reader.sequence_regions(), [("ctg1".to_string(), 1, 60)]);
+        assert_eq!(reader.sequences().len(), 1);
+        assert_eq!(reader.sequences()[0].1.len(), 60);
+        let text = EXAMPLE.replacen("\t-\t0\tID=cds2", "\t-\tx\tID=cds2", 1);
+        match Reader::new(text.as_bytes())
+            .features()
+            .find_map(|f| f.err())
+        {
+            Some(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                ..
+            }) => assert_eq!((line_number, record_id.as_str()), (8, "ctg1")),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_assemble_records() {
+        let records: Vec<Record> = Reader::new(EXAMPLE.as_bytes())
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!((record.length, record.is_circular()), (60, true));
+        assert_eq!(
+            record.source_map.get_strain("source_ctg1_1").unwrap(),
+            "K-12"
+        );
+        //the gene and locus tag are inherited through the mRNA
+        assert_eq!(record.cds.get_gene("TEST_00002").unwrap(), "xyzB");
+        let location = record.cds.get_location("TEST_00002").unwrap();
+        assert_eq!(location.to_string(), "complement(join(21..29,41..52))");
+        let qualifiers = record.cds.get_qualifiers("TEST_00002").unwrap();
+        assert_eq!(qualifiers.locus_tag(), Some("TEST_00002"));
+        assert!(qualifiers.is_pseudo());
+        assert_eq!(
+            record.seq_features.get_sequence_faa("TEST_00002").unwrap(),
+            "MADRGK"
+        );
+        assert_eq!(
+            record
+                .cds
+                .get_qualifiers("TEST_00001")
+                .unwrap()
+                .get("product"),
+            Some("ABC transporter, permease")
+        );
+        assert_eq!(
+            record
+                .features_of("mRNA")
+                .unwrap()
+                .get_gene("TEST_00002")
+                .unwrap(),
+            "xyzB"
+        );
+        //a feature past the end of a circular record runs over the origin
+        let repeat = record.features_of("repeat_region").unwrap();
+        assert_eq!(
+            repeat.get_location("repeat_region_1").unwrap().to_string(),
+            "55..5"
+        );
+        assert_eq!(
+            repeat.get_stop("repeat_region_1"),
+            Some(&RangeValue::Exact(65))
+        );
+    }
+    #[test]
+    fn test_gff_to_genbank() {
+        let records: Vec<Record> = Reader::new(EXAMPLE.as_bytes())
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let mut seq_region: BTreeMap<String, (u32, u32)> = BTreeMap::new();
+        seq_region.insert("source_ctg1_1".to_string(), (1, 60));
+        let output = std::env::temp_dir().join("microbiorust_gff_to_genbank.gbk");
+        let _ = std::fs::remove_file(&output);
+        gbk_write(seq_region, records, output.to_str().unwrap()).expect("failed to write gbk");
+        let written = crate::genbank!(output.to_str().unwrap());
+        std::fs::remove_file(&output).ok();
+        let record = written.first().expect("no record written");
+        assert_eq!(record.id, "ctg1");
+        assert!(record.is_circular());
+        assert_eq!(
+            record.seq_features.get_sequence_faa("TEST_00002").unwrap(),
+            "MADRGK"
+        );
+        assert_eq!(
+            record.cds.get_location("TEST_00002").unwrap().to_string(),
+            "complement(join(21..29,41..52))"
+        );
+    }
+}
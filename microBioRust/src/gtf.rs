@@ -0,0 +1,729 @@
+//! # GTF 2.2 reading and writing
+//!
+//! GTF lines have the nine columns of GFF3 with ```key "value";``` attributes, every line names its ```gene_id``` and ```transcript_id```.
+//! The reader gives the lines as ```gff::Feature```s and ```records()``` assembles them into ```gbk::Record```s: a gene feature per ```gene_id```,
+//! a CDS per transcript with its ```stop_codon``` added back (GTF leaves the stop codon out of the CDS lines) and an RNA feature for transcripts made of exons only.
+//! GTF has no sequences, the records hold the features only.
+//!
+//! ```gbk::gtf_write``` and ```embl::gtf_write``` write each CDS as a transcript with exon, CDS, start_codon and stop_codon lines,
+//! the ```gene_id``` is the locus tag and the ```transcript_id``` is made from it, RNA genes are written as exons.
+//!
+//!```rust
+//! use microBioRust::gtf::Reader;
+//!
+//! let text = "NC_000913\t.\texon\t10\t363\t.\t-\t.\tgene_id \"b3304\"; transcript_id \"cds-b3304\"; gene_name \"rplR\";\n\
+//!     NC_000913\t.\tCDS\t13\t363\t.\t-\t0\tgene_id \"b3304\"; transcript_id \"cds-b3304\"; gene_name \"rplR\";\n\
+//!     NC_000913\t.\tstop_codon\t10\t12\t.\t-\t0\tgene_id \"b3304\"; transcript_id \"cds-b3304\"; gene_name \"rplR\";\n";
+//! let mut records = Reader::new(text.as_bytes()).records();
+//! let record = records.next().unwrap().unwrap();
+//! assert_eq!(record.cds.get_location("b3304").unwrap().to_string(), "complement(10..363)");
+//! assert_eq!(record.cds.get_gene("b3304").unwrap(), "rplR");
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk::{substitute_odd_punctuation, Record};
+use crate::gff::{location_parts, Feature};
+use crate::location::{Location, Position};
+use crate::qualifiers::Qualifiers;
+use crate::record::RangeValue;
+use anyhow::{anyhow, Context};
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    path::Path,
+};
+
+///the RNA feature types written as transcripts made of exons
+pub const RNA_FEATURES: [&str; 5] = ["rRNA", "tRNA", "tmRNA", "ncRNA", "misc_RNA"];
+
+///attributes describing the GTF structure rather than the feature, they are not carried over to the qualifiers
+const STRUCTURE_ATTRIBUTES: [&str; 9] = [
+    "gene_id",
+    "transcript_id",
+    "gene_name",
+    "exon_number",
+    "exon_id",
+    "gene_biotype",
+    "gene_type",
+    "transcript_biotype",
+    "transcript_type",
+];
+
+///reads the ```key "value";``` pairs of a GTF line, values can be quoted or bare and a key can be repeated
+pub fn parse_attributes(text: &str) -> Result<Vec<(String, Vec<String>)>, anyhow::Error> {
+    let mut attributes: Vec<(String, Vec<String>)> = Vec::new();
+    let mut rest = text.trim();
+    while !rest.is_empty() {
+        let (key, after_key) = rest
+            .split_once(char::is_whitespace)
+            .ok_or(anyhow!("attribute {:?} has no value", rest))?;
+        let after_key = after_key.trim_start();
+        //a quoted value can hold ';', a bare value ends at the next ';'
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted
+                    .find('"')
+                    .ok_or(anyhow!("unterminated value for {:?}", key))?;
+                (&quoted[..end], &quoted[end + 1..])
+            }
+            None => {
+                let end = after_key.find(';').unwrap_or(after_key.len());
+                (after_key[..end].trim(), &after_key[end..])
+            }
+        };
+        match attributes.iter_mut().find(|(k, _)| k == key) {
+            Some((_, values)) => values.push(value.to_string()),
+            None => attributes.push((key.to_string(), vec![value.to_string()])),
+        }
+        rest = after_value.trim_start();
+        rest = rest.strip_prefix(';').unwrap_or(rest).trim_start();
+    }
+    Ok(attributes)
+}
+
+///reads a GTF line, every line must have a gene_id
+pub fn parse_line(line: &str) -> Result<Feature, anyhow::Error> {
+    let (mut feature, attributes) = Feature::from_columns(line)?;
+    feature.attributes = parse_attributes(attributes)?;
+    if feature.attribute("gene_id").is_none() {
+        return Err(anyhow!("no gene_id"));
+    }
+    Ok(feature)
+}
+
+///an iterator over the lines of a GTF file
+#[derive(Debug)]
+pub struct Features<'a, B> {
+    reader: &'a mut Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Features<'_, B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Feature, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_feature() {
+            Ok(Some((_, feature))) => Some(Ok(feature)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///an iterator over the records of a GTF file, one per seqname, assembled once the whole file has been read
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    records: Option<VecDeque<Record>>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Records<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        if self.records.is_none() {
+            let mut features: Vec<(usize, Feature)> = Vec::new();
+            loop {
+                match self.reader.read_feature() {
+                    Ok(Some(feature)) => features.push(feature),
+                    Ok(None) => break,
+                    Err(err) => {
+                        self.error_has_occurred = true;
+                        return Some(Err(err));
+                    }
+                }
+            }
+            match assemble_records(features) {
+                Ok(records) => self.records = Some(records.into()),
+                Err(err) => {
+                    self.error_has_occurred = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        self.records.as_mut().and_then(|r| r.pop_front()).map(Ok)
+    }
+}
+
+///per line reader for the file
+#[derive(Debug, Default)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read GTF from given file path, decompressing gzip, BGZF, bzip2, xz or zstd files.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read GTF from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new GTF reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+        }
+    }
+    ///reads the next feature line with its line number, skipping comments, returns None at the end of the file
+    pub fn read_feature(&mut self) -> Result<Option<(usize, Feature)>, ParseError> {
+        loop {
+            self.line_buffer.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.line_buffer)
+                .map_err(|source| ParseError::Io {
+                    line_number: self.line_number + 1,
+                    record_id: String::new(),
+                    line: String::new(),
+                    source,
+                })?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let line = self.line_buffer.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let feature = parse_line(line).map_err(|e| ParseError::MalformedLine {
+                line_number: self.line_number,
+                record_id: line.split('\t').next().unwrap_or_default().to_string(),
+                line: line.to_string(),
+                message: e.to_string(),
+            })?;
+            return Ok(Some((self.line_number, feature)));
+        }
+    }
+    ///return an iterator over the lines of the file
+    pub fn features(&mut self) -> Features<'_, B> {
+        Features {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+    ///return an iterator over the records of the file
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            records: None,
+            error_has_occurred: false,
+        }
+    }
+}
+
+//adds the stop codon parts to the CDS parts they touch, parts of the CDS itself are kept apart as they can overlap at a frameshift
+fn merge_parts(mut parts: Vec<(u32, u32)>, stop_codon: Vec<(u32, u32)>) -> Vec<(u32, u32)> {
+    parts.sort();
+    for (start, end) in stop_codon {
+        match parts
+            .iter_mut()
+            .find(|(s, e)| start == *e + 1 || end + 1 == *s)
+        {
+            Some(part) => *part = (part.0.min(start), part.1.max(end)),
+            None => parts.push((start, end)),
+        }
+    }
+    parts.sort();
+    parts
+}
+
+//the location of the parts of a transcript in biological order
+fn parts_location(parts: &[(u32, u32)], strand: i8) -> Location {
+    let mut locations: Vec<Location> = parts
+        .iter()
+        .map(|(start, end)| Location::Range {
+            start: Position::Exact(*start),
+            end: Position::Exact(*end),
+            strand,
+        })
+        .collect();
+    if strand == -1 {
+        locations.reverse();
+    }
+    match locations.len() {
+        1 => locations.remove(0),
+        _ => Location::Join(locations),
+    }
+}
+
+//the qualifiers of a transcript, led by its locus tag and gene name
+fn transcript_qualifiers(feature: &Feature) -> Qualifiers {
+    let mut qualifiers = Qualifiers::new();
+    if let Some(gene) = feature.attribute("gene_name") {
+        qualifiers.push("gene", Some(gene.to_string()));
+    }
+    qualifiers.push(
+        "locus_tag",
+        feature.attribute("gene_id").map(|g| g.to_string()),
+    );
+    for (key, values) in feature.attributes.iter() {
+        if STRUCTURE_ATTRIBUTES.contains(&key.as_str()) {
+            continue;
+        }
+        for value in values {
+            qualifiers.push(key, Some(value.clone()));
+        }
+    }
+    qualifiers
+}
+
+//the lines of a transcript with their line numbers, keyed by seqname and transcript_id
+type TranscriptLines<'a> = ((&'a str, &'a str), Vec<(usize, &'a Feature)>);
+
+//builds one record per seqname, with the genes and transcripts in file order
+fn assemble_records(features: Vec<(usize, Feature)>) -> Result<Vec<Record>, ParseError> {
+    let mut seqids: Vec<&str> = Vec::new();
+    //the lines of each gene and of each transcript, keyed with their seqname
+    let mut genes: Vec<((&str, &str), Vec<&Feature>)> = Vec::new();
+    let mut transcripts: Vec<TranscriptLines> = Vec::new();
+    let mut gene_index: HashMap<(&str, &str), usize> = HashMap::new();
+    let mut transcript_index: HashMap<(&str, &str), usize> = HashMap::new();
+    for (line_number, feature) in features.iter() {
+        let seqid = feature.seqid.as_str();
+        if !seqids.contains(&seqid) {
+            seqids.push(seqid);
+        }
+        let gene_id = feature.attribute("gene_id").unwrap_or_default();
+        let index = *gene_index.entry((seqid, gene_id)).or_insert_with(|| {
+            genes.push(((seqid, gene_id), Vec::new()));
+            genes.len() - 1
+        });
+        genes[index].1.push(feature);
+        if let Some(transcript_id) = feature.attribute("transcript_id") {
+            let index = *transcript_index
+                .entry((seqid, transcript_id))
+                .or_insert_with(|| {
+                    transcripts.push(((seqid, transcript_id), Vec::new()));
+                    transcripts.len() - 1
+                });
+            transcripts[index].1.push((*line_number, feature));
+        }
+    }
+    let mut records = Vec::with_capacity(seqids.len());
+    for seqid in seqids {
+        let mut record = Record::new();
+        record.id = seqid.to_string();
+        record.length = features
+            .iter()
+            .filter(|(_, f)| f.seqid == seqid)
+            .map(|(_, f)| f.end)
+            .max()
+            .unwrap_or(0);
+        record.header.accessions = vec![seqid.to_string()];
+        let source_name = format!("source_{}_1", seqid);
+        record
+            .source_map
+            .set_counter(source_name.clone())
+            .set_start(RangeValue::Exact(1))
+            .set_stop(RangeValue::Exact(record.length));
+        record.source_map.set_source_name(source_name);
+        //a gene spans every line of its gene_id
+        for ((_, gene_id), lines) in genes.iter().filter(|((s, _), _)| *s == seqid) {
+            let start = lines.iter().map(|f| f.start).min().unwrap_or(0);
+            let end = lines.iter().map(|f| f.end).max().unwrap_or(0);
+            let strand = match lines[0].strand {
+                -1 => -1,
+                _ => 1,
+            };
+            let mut qualifiers = Qualifiers::new();
+            let gene = lines[0].attribute("gene_name").unwrap_or_default();
+            if !gene.is_empty() {
+                qualifiers.push("gene", Some(gene.to_string()));
+            }
+            qualifiers.push("locus_tag", Some(gene_id.to_string()));
+            record
+                .features_mut("gene")
+                .set_counter(gene_id.to_string())
+                .set_start(RangeValue::Exact(start))
+                .set_stop(RangeValue::Exact(end))
+                .set_gene(gene.to_string())
+                .set_strand(strand)
+                .set_location(Location::span(
+                    &RangeValue::Exact(start),
+                    &RangeValue::Exact(end),
+                    strand,
+                ))
+                .set_qualifiers(qualifiers);
+        }
+        for ((_, transcript_id), lines) in transcripts.iter().filter(|((s, _), _)| *s == seqid) {
+            let (line_number, first) = lines[0];
+            let strand = match first.strand {
+                -1 => -1,
+                _ => 1,
+            };
+            let of_type = |types: &[&str]| -> Vec<&Feature> {
+                lines
+                    .iter()
+                    .map(|(_, f)| *f)
+                    .filter(|f| types.contains(&f.feature_type.as_str()))
+                    .collect()
+            };
+            let cds = of_type(&["CDS"]);
+            //a coding transcript becomes a CDS with its stop codon, other transcripts become an RNA feature from their exons
+            let (feature_type, parts) = match cds.is_empty() {
+                false => ("CDS".to_string(), cds.clone()),
+                true => {
+                    let biotype = first
+                        .attribute("transcript_biotype")
+                        .or(first.attribute("gene_biotype"))
+                        .unwrap_or_default();
+                    let feature_type = match RNA_FEATURES.iter().find(|t| **t == biotype) {
+                        Some(rna) => rna.to_string(),
+                        None => "misc_RNA".to_string(),
+                    };
+                    (feature_type, of_type(&["exon"]))
+                }
+            };
+            if parts.is_empty() {
+                continue;
+            }
+            let stop_codon = match feature_type.as_str() {
+                "CDS" => of_type(&["stop_codon"]),
+                _ => Vec::new(),
+            };
+            let parts = merge_parts(
+                parts.iter().map(|f| (f.start, f.end)).collect(),
+                stop_codon.iter().map(|f| (f.start, f.end)).collect(),
+            );
+            let location = parts_location(&parts, strand);
+            let qualifiers = transcript_qualifiers(first);
+            let gene_id = first.attribute("gene_id").unwrap_or_default();
+            let gene = first.attribute("gene_name").unwrap_or_default();
+            let product =
+                substitute_odd_punctuation(qualifiers.product().unwrap_or_default().to_string())
+                    .map_err(|e| ParseError::BadQualifier {
+                        line_number,
+                        record_id: seqid.to_string(),
+                        line: format!("product {:?}", qualifiers.product()),
+                        message: e.to_string(),
+                    })?;
+            //the frame of the CDS line read first gives the codon start
+            let first_cds = match strand {
+                -1 => cds.iter().max_by_key(|f| f.end),
+                _ => cds.iter().min_by_key(|f| f.start),
+            };
+            let codon_start = first_cds.and_then(|f| f.phase).unwrap_or(0) + 1;
+            let features = record.features_mut(&feature_type);
+            //a gene with several transcripts keeps the others under their transcript_id
+            let key = match features.attributes.contains_key(gene_id) {
+                true => transcript_id.to_string(),
+                false => gene_id.to_string(),
+            };
+            features
+                .set_counter(key)
+                .set_start(location.start_value())
+                .set_stop(location.end_value())
+                .set_gene(gene.to_string())
+                .set_product(product)
+                .set_strand(strand)
+                .set_location(location)
+                .set_qualifiers(qualifiers);
+            if feature_type == "CDS" {
+                features.set_codon_start(codon_start);
+            }
+        }
+        records.push(record);
+    }
+    Ok(records)
+}
+
+///a transcript to write as GTF, the exons of a CDS or of an RNA
+#[derive(Debug, Clone)]
+pub struct Transcript {
+    pub seqid: String,
+    pub gene_id: String,
+    pub transcript_id: String,
+    pub location: Location,
+    ///the codon start of a CDS, None for an RNA
+    pub codon_start: Option<u8>,
+    ///attributes written after gene_id and transcript_id, e.g. gene_name
+    pub attributes: Vec<(String, String)>,
+}
+
+impl Transcript {
+    //the attribute column shared by every line of the transcript
+    fn attribute_text(&self) -> String {
+        let quote = |value: &str| value.replace('"', "'");
+        let mut text = format!(
+            "gene_id \"{}\"; transcript_id \"{}\";",
+            quote(&self.gene_id),
+            quote(&self.transcript_id)
+        );
+        for (key, value) in self.attributes.iter() {
+            text.push_str(&format!(" {} \"{}\";", key, quote(value)));
+        }
+        text
+    }
+    ///the exon lines, plus the CDS, start_codon and stop_codon lines of a coding transcript
+    ///the CDS lines leave out the stop codon as GTF 2.2 asks, a partial end has no start or stop codon line
+    pub fn gtf_lines(&self, length: u32, circular: bool) -> Vec<String> {
+        let strand = self.location.strand();
+        let strand_text = if strand == -1 { "-" } else { "+" };
+        let attributes = self.attribute_text();
+        let line = |feature_type: &str, start: u32, end: u32, frame: Option<u8>| {
+            format!(
+                "{}\t.\t{}\t{}\t{}\t.\t{}\t{}\t{}",
+                self.seqid,
+                feature_type,
+                start,
+                end,
+                strand_text,
+                frame.map_or(".".to_string(), |f| f.to_string()),
+                attributes
+            )
+        };
+        let parts: Vec<(u32, u32)> = location_parts(&self.location, length, circular, None)
+            .into_iter()
+            .map(|(start, end, _)| (start, end))
+            .collect();
+        let mut lines: Vec<String> = parts
+            .iter()
+            .map(|(start, end)| line("exon", *start, *end, None))
+            .collect();
+        let codon_start = match self.codon_start {
+            Some(codon_start) => codon_start,
+            None => return lines,
+        };
+        //the parts in biological order, trimmed from either end by a number of bases
+        let mut coding: Vec<(u32, u32)> = parts.clone();
+        if strand == -1 {
+            coding.reverse();
+        }
+        let partial_end = match strand {
+            -1 => matches!(self.location.start_value(), RangeValue::LessThan(_)),
+            _ => matches!(self.location.end_value(), RangeValue::GreaterThan(_)),
+        };
+        let (cds, stop_codon) = match partial_end {
+            true => (coding.clone(), Vec::new()),
+            false => split_bases(&coding, total_bases(&coding).saturating_sub(3), strand),
+        };
+        for (start, end, frame) in frames(&cds, strand, codon_start) {
+            lines.push(line("CDS", start, end, Some(frame)));
+        }
+        if codon_start == 1 && !self.location.partial_start() {
+            let (start_codon, _) = split_bases(&coding, 3, strand);
+            for (start, end, frame) in frames(&start_codon, strand, 1) {
+                lines.push(line("start_codon", start, end, Some(frame)));
+            }
+        }
+        for (start, end, frame) in frames(&stop_codon, strand, 1) {
+            lines.push(line("stop_codon", start, end, Some(frame)));
+        }
+        lines
+    }
+}
+
+//spans of a transcript as (start, end)
+type Parts = Vec<(u32, u32)>;
+
+fn total_bases(parts: &[(u32, u32)]) -> u32 {
+    parts.iter().map(|(start, end)| end - start + 1).sum()
+}
+
+//splits parts in biological order after the given number of bases
+fn split_bases(parts: &[(u32, u32)], bases: u32, strand: i8) -> (Parts, Parts) {
+    let (mut head, mut tail) = (Vec::new(), Vec::new());
+    let mut remaining = bases;
+    for (start, end) in parts.iter().copied() {
+        let size = end - start + 1;
+        if remaining >= size {
+            head.push((start, end));
+            remaining -= size;
+        } else if remaining == 0 {
+            tail.push((start, end));
+        } else {
+            match strand {
+                -1 => {
+                    head.push((end - remaining + 1, end));
+                    tail.push((start, end - remaining));
+                }
+                _ => {
+                    head.push((start, start + remaining - 1));
+                    tail.push((start + remaining, end));
+                }
+            }
+            remaining = 0;
+        }
+    }
+    (head, tail)
+}
+
+//the frame of each part in biological order, sorted by start for writing
+fn frames(parts: &[(u32, u32)], strand: i8, codon_start: u8) -> Vec<(u32, u32, u8)> {
+    let mut offset: i64 = 0;
+    let mut framed: Vec<(u32, u32, u8)> = parts
+        .iter()
+        .map(|(start, end)| {
+            let frame = (codon_start as i64 - 1 - offset).rem_euclid(3) as u8;
+            offset += (end - start + 1) as i64;
+            (*start, *end, frame)
+        })
+        .collect();
+    if strand == -1 {
+        framed.reverse();
+    }
+    framed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_attributes() {
+        let attributes = parse_attributes(
+            "gene_id \"b3304\"; transcript_id \"t1\"; exon_number 2; tag \"a;b\"; tag \"c\";",
+        )
+        .unwrap();
+        assert_eq!(
+            attributes[0],
+            ("gene_id".to_string(), vec!["b3304".to_string()])
+        );
+        assert_eq!(
+            attributes[2],
+            ("exon_number".to_string(), vec!["2".to_string()])
+        );
+        assert_eq!(
+            attributes[3],
+            ("tag".to_string(), vec!["a;b".to_string(), "c".to_string()])
+        );
+        assert!(parse_attributes("gene_id \"b3304").is_err());
+        assert!(parse_line("ctg1\t.\tCDS\t1\t9\t.\t+\t0\ttranscript_id \"t1\";").is_err());
+    }
+    #[test]
+    fn test_read_records() {
+        //a spliced CDS on the reverse strand, its stop codon is split over the intron
+        let text = "ctg1\tsrc\texon\t100\t200\t.\t-\t.\tgene_id \"g1\"; transcript_id \"t1\"; gene_name \"abcA\";\n\
+            ctg1\tsrc\texon\t300\t400\t.\t-\t.\tgene_id \"g1\"; transcript_id \"t1\"; gene_name \"abcA\";\n\
+            ctg1\tsrc\tCDS\t302\t349\t.\t-\t0\tgene_id \"g1\"; transcript_id \"t1\"; gene_name \"abcA\";\n\
+            ctg1\tsrc\tstart_codon\t347\t349\t.\t-\t0\tgene_id \"g1\"; transcript_id \"t1\"; gene_name \"abcA\";\n\
+            ctg1\tsrc\tstop_codon\t300\t301\t.\t-\t0\tgene_id \"g1\"; transcript_id \"t1\"; gene_name \"abcA\";\n\
+            ctg1\tsrc\tstop_codon\t200\t200\t.\t-\t1\tgene_id \"g1\"; transcript_id \"t1\"; gene_name \"abcA\";\n\
+            ctg1\tsrc\texon\t500\t575\t.\t+\t.\tgene_id \"g2\"; transcript_id \"t2\"; gene_biotype \"tRNA\";\n";
+        let records: Vec<Record> = Reader::new(text.as_bytes())
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(
+            record.cds.get_location("g1").unwrap().to_string(),
+            "complement(join(200..200,300..349))"
+        );
+        assert_eq!(record.cds.get_codon_start("g1"), Some(&1));
+        assert_eq!(record.cds.get_gene("g1").unwrap(), "abcA");
+        let genes = record.features_of("gene").unwrap();
+        assert_eq!(genes.get_start("g1"), Some(&RangeValue::Exact(100)));
+        assert_eq!(genes.get_stop("g1"), Some(&RangeValue::Exact(400)));
+        //written back with the same CDS and stop codon lines
+        let transcript = Transcript {
+            seqid: "ctg1".to_string(),
+            gene_id: "g1".to_string(),
+            transcript_id: "t1".to_string(),
+            location: record.cds.get_location("g1").unwrap().clone(),
+            codon_start: Some(1),
+            attributes: Vec::new(),
+        };
+        let lines = transcript.gtf_lines(1000, false);
+        let coding: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|l| l.split('\t').collect::<Vec<&str>>())
+            .filter(|c| c[2] != "exon")
+            .map(|c| vec![c[2], c[3], c[4], c[7]])
+            .collect();
+        assert_eq!(
+            coding,
+            vec![
+                vec!["CDS", "302", "349", "0"],
+                vec!["start_codon", "347", "349", "0"],
+                vec!["stop_codon", "200", "200", "1"],
+                vec!["stop_codon", "300", "301", "0"],
+            ]
+        );
+        let trna = record.features_of("tRNA").unwrap();
+        assert_eq!(trna.get_location("g2").unwrap().to_string(), "500..575");
+        let text = "ctg1\tsrc\texon\t100\t200\t.\t-\t.\ttranscript_id \"t1\";\n";
+        match Reader::new(text.as_bytes()).records().next() {
+            Some(Err(ParseError::MalformedLine { line_number, .. })) => assert_eq!(line_number, 1),
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_transcript_lines() {
+        let transcript = Transcript {
+            seqid: "ctg1".to_string(),
+            gene_id: "g1".to_string(),
+            transcript_id: "cds-g1".to_string(),
+            location: "complement(join(99..200,300..350))".parse().unwrap(),
+            codon_start: Some(1),
+            attributes: vec![("gene_name".to_string(), "abcA".to_string())],
+        };
+        let lines = transcript.gtf_lines(1000, false);
+        let columns: Vec<Vec<&str>> = lines
+            .iter()
+            .map(|l| l.split('\t').take(8).collect())
+            .collect();
+        let expected = [
+            ["exon", "99", "200", "."],
+            ["exon", "300", "350", "."],
+            ["CDS", "102", "200", "0"],
+            ["CDS", "300", "350", "0"],
+            ["start_codon", "348", "350", "0"],
+            ["stop_codon", "99", "101", "0"],
+        ];
+        assert_eq!(columns.len(), expected.len());
+        for (columns, expected) in columns.iter().zip(expected.iter()) {
+            assert_eq!([columns[2], columns[3], columns[4], columns[7]], *expected);
+        }
+        assert!(lines[0].ends_with("gene_id \"g1\"; transcript_id \"cds-g1\"; gene_name \"abcA\";"));
+        //a stop codon split by the intron
+        let transcript = Transcript {
+            location: "join(1..10,21..22)".parse().unwrap(),
+            ..transcript
+        };
+        let lines = transcript.gtf_lines(1000, false);
+        let stops: Vec<&String> = lines
+            .iter()
+            .filter(|l| l.contains("\tstop_codon\t"))
+            .collect();
+        assert_eq!(stops.len(), 2);
+        assert!(stops[0].starts_with("ctg1\t.\tstop_codon\t10\t10\t.\t+\t0"));
+        assert!(stops[1].starts_with("ctg1\t.\tstop_codon\t21\t22\t.\t+\t2"));
+    }
+}
@@ -0,0 +1,810 @@
+//! # Record headers
+//!
+//! The annotation above the feature table of a GenBank record (the details of the ```LOCUS``` line, ```DEFINITION```, ```ACCESSION```, ```VERSION```, ```DBLINK```,
+//! ```KEYWORDS```, ```SOURCE``` with the ```ORGANISM``` lineage, every ```REFERENCE``` block and ```COMMENT```) is kept in a ```RecordHeader```,
+//! so the writers can give it back instead of making up a header from the source feature.
+//! The ```ID```, ```AC```, ```PR```, ```DT```, ```DE```, ```KW```, ```OS```/```OC```, reference and ```CC``` lines of an EMBL record are read into the same fields,
+//! ```embl_id_line``` and ```embl_lines``` write them back in EMBL layout.
+//!
+//!```rust
+//! use microBioRust::header::{RecordHeader, Topology};
+//!
+//! let lines = vec![
+//!     "DEFINITION  Rhizobium johnstonii 3841 plasmid pRL8.".to_string(),
+//!     "ACCESSION   AM236082".to_string(),
+//!     "DBLINK      BioProject: PRJNA344".to_string(),
+//!     "            BioSample: SAMEA1705944".to_string(),
+//!     "KEYWORDS    complete genome.".to_string(),
+//! ];
+//! let locus = "LOCUS       AM236082              147463 bp    DNA     circular BCT 14-JUL-2016";
+//! let mut header = RecordHeader::from_locus_line(locus);
+//! header.parse_genbank_lines(&lines);
+//! assert_eq!(Topology::from_locus_line(locus), Topology::Circular);
+//! assert_eq!(header.division, "BCT");
+//! assert_eq!(header.bioproject(), Some("PRJNA344"));
+//! assert_eq!(header.keywords, vec!["complete genome"]);
+//!```
+
+use std::fmt;
+
+///the topology of the molecule, given on the LOCUS line of GenBank and the ID line of EMBL records
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topology {
+    #[default]
+    Linear,
+    Circular,
+}
+
+impl Topology {
+    ///reads the topology from a GenBank LOCUS line or an EMBL ID line, a line without one is linear
+    pub fn from_locus_line(line: &str) -> Self {
+        match line
+            .split(|c: char| c.is_whitespace() || c == ';')
+            .any(|field| field == "circular")
+        {
+            true => Topology::Circular,
+            false => Topology::Linear,
+        }
+    }
+}
+
+impl fmt::Display for Topology {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Topology::Linear => write!(f, "linear"),
+            Topology::Circular => write!(f, "circular"),
+        }
+    }
+}
+
+///a REFERENCE block of the header
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Reference {
+    pub number: String,
+    ///the text following the reference number, e.g. ```(bases 1 to 147463)```
+    pub bases: String,
+    pub authors: String,
+    pub consortium: String,
+    pub title: String,
+    pub journal: String,
+    pub pubmed: String,
+    pub remark: String,
+    ///any other GenBank subsection (MEDLINE...) as the subsection it follows (empty when it follows the REFERENCE line), keyword and text
+    pub other: Vec<(String, String, String)>,
+}
+
+///the header of a record, text fields hold the section with its continuation lines joined by a space
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct RecordHeader {
+    ///DNA, RNA, mRNA, ss-DNA...
+    pub molecule_type: String,
+    ///the three letter GenBank division, e.g. BCT, CON, or the EMBL taxonomic division, e.g. PRO
+    pub division: String,
+    ///the EMBL data class, e.g. STD, CON, WGS
+    pub data_class: String,
+    pub date: String,
+    ///the text of the EMBL DT lines, e.g. ```04-MAY-2006 (Rel. 87, Created)```
+    pub embl_dates: Vec<String>,
+    pub definition: String,
+    pub accessions: Vec<String>,
+    pub version: String,
+    ///DBLINK cross references in file order, e.g. ("BioProject", "PRJNA344")
+    pub dblink: Vec<(String, String)>,
+    ///the keywords without the closing '.', empty for a ```KEYWORDS    .``` line
+    pub keywords: Vec<String>,
+    pub source: String,
+    pub organism: String,
+    ///the taxonomic lineage following the ORGANISM line, from the domain downwards
+    pub lineage: Vec<String>,
+    pub references: Vec<Reference>,
+    ///the COMMENT section, line breaks are kept
+    pub comment: String,
+    ///any other header section (PROJECT, SEGMENT, PRIMARY...) as keyword and text, line breaks are kept
+    pub other: Vec<(String, String)>,
+}
+
+//width of the keyword column of the header
+const KEYWORD_WIDTH: usize = 12;
+
+//header lines are at most 79 characters wide
+const LINE_WIDTH: usize = 79;
+
+//width of the line code column of EMBL lines, e.g. "DE   "
+const EMBL_CODE_WIDTH: usize = 5;
+
+//EMBL lines are at most 80 characters wide
+const EMBL_LINE_WIDTH: usize = 80;
+
+//the EMBL taxonomic divisions, GenBank divisions are mapped onto these when writing an ID line
+const EMBL_DIVISIONS: [&str; 15] = [
+    "PHG", "ENV", "FUN", "HUM", "INV", "MAM", "VRT", "MUS", "PLN", "PRO", "ROD", "SYN", "TGN",
+    "UNC", "VRL",
+];
+
+impl RecordHeader {
+    pub fn new() -> Self {
+        RecordHeader::default()
+    }
+    ///true when nothing has been read into the header
+    pub fn is_empty(&self) -> bool {
+        *self == RecordHeader::default()
+    }
+    ///reads the molecule type, division and date from a LOCUS line, see ```Topology::from_locus_line``` for the topology
+    pub fn from_locus_line(line: &str) -> Self {
+        let mut header = RecordHeader::new();
+        //LOCUS, name, length and unit come first, the remaining fields are optional
+        let mut fields = line.split_whitespace().skip(4);
+        if let Some(molecule_type) = fields.next() {
+            header.molecule_type = molecule_type.to_string();
+        }
+        for field in fields {
+            match field {
+                "linear" | "circular" => (),
+                f if f.len() == 11 && f.matches('-').count() == 2 => header.date = f.to_string(),
+                f => header.division = f.to_string(),
+            }
+        }
+        header
+    }
+    ///reads the header sections between the LOCUS line and the FEATURES table
+    pub fn parse_genbank_lines(&mut self, lines: &[String]) {
+        //group each section with its continuation lines, subsections such as ORGANISM or AUTHORS start with spaces
+        let mut sections: Vec<(String, bool, Vec<String>)> = Vec::new();
+        for line in lines {
+            let line = line.trim_end();
+            let keyword = line.get(..KEYWORD_WIDTH).unwrap_or(line).trim();
+            let text = line.get(KEYWORD_WIDTH..).unwrap_or("").to_string();
+            match sections.last_mut() {
+                Some((_, _, texts)) if keyword.is_empty() => texts.push(text),
+                _ if keyword.is_empty() => (),
+                _ => sections.push((keyword.to_string(), line.starts_with(' '), vec![text])),
+            }
+        }
+        //the last known subsection of the current reference, unknown ones are written back after it
+        let mut subsection = String::new();
+        for (keyword, indented, texts) in sections {
+            let joined = join_lines(&texts);
+            match keyword.as_str() {
+                "DEFINITION" => self.definition = joined,
+                "ACCESSION" => {
+                    self.accessions = joined.split_whitespace().map(String::from).collect()
+                }
+                "VERSION" => self.version = joined,
+                "DBLINK" => {
+                    for text in texts.iter() {
+                        match text.split_once(':') {
+                            Some((key, value)) => self
+                                .dblink
+                                .push((key.trim().to_string(), value.trim().to_string())),
+                            //a long list of identifiers continues on the next line
+                            None => {
+                                if let Some((_, value)) = self.dblink.last_mut() {
+                                    value.push_str(text.trim());
+                                }
+                            }
+                        }
+                    }
+                }
+                "KEYWORDS" => {
+                    self.keywords = joined
+                        .strip_suffix('.')
+                        .unwrap_or(&joined)
+                        .split(';')
+                        .map(|k| k.trim().to_string())
+                        .filter(|k| !k.is_empty())
+                        .collect()
+                }
+                "SOURCE" => self.source = joined,
+                "ORGANISM" => {
+                    //the organism name can wrap, the lineage starts at the first line listing taxa
+                    let mut lineage = String::new();
+                    for (i, text) in texts.iter().map(|t| t.trim()).enumerate() {
+                        if i == 0 {
+                            self.organism = text.to_string();
+                        } else if lineage.is_empty() && !text.contains(';') && !text.ends_with('.')
+                        {
+                            self.organism.push(' ');
+                            self.organism.push_str(text);
+                        } else {
+                            if !lineage.is_empty() {
+                                lineage.push(' ');
+                            }
+                            lineage.push_str(text);
+                        }
+                    }
+                    self.lineage = lineage
+                        .strip_suffix('.')
+                        .unwrap_or(&lineage)
+                        .split(';')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect();
+                }
+                "REFERENCE" => {
+                    let (number, bases) = joined.split_once(' ').unwrap_or((&joined, ""));
+                    self.references.push(Reference {
+                        number: number.to_string(),
+                        bases: bases.trim().to_string(),
+                        ..Default::default()
+                    });
+                    subsection.clear();
+                }
+                "AUTHORS" | "CONSRTM" | "TITLE" | "JOURNAL" | "PUBMED" | "REMARK" => {
+                    subsection = keyword.clone();
+                    if let Some(reference) = self.references.last_mut() {
+                        match keyword.as_str() {
+                            "AUTHORS" => reference.authors = joined,
+                            "CONSRTM" => reference.consortium = joined,
+                            "TITLE" => reference.title = joined,
+                            "JOURNAL" => reference.journal = joined,
+                            "PUBMED" => reference.pubmed = joined,
+                            _ => reference.remark = joined,
+                        }
+                    }
+                }
+                "COMMENT" => self.comment = texts.join("\n").trim_end().to_string(),
+                _ => match self.references.last_mut() {
+                    Some(reference) if indented => {
+                        reference.other.push((subsection.clone(), keyword, joined))
+                    }
+                    _ => self
+                        .other
+                        .push((keyword, texts.join("\n").trim_end().to_string())),
+                },
+            }
+        }
+    }
+    ///reads the version, molecule type, data class and division from an EMBL ID line, see ```Topology::from_locus_line``` for the topology
+    pub fn from_id_line(line: &str) -> Self {
+        let mut header = RecordHeader::new();
+        //ID   accession; SV version; topology; molecule type; data class; division; length BP.
+        let fields: Vec<&str> = line
+            .get(EMBL_CODE_WIDTH..)
+            .unwrap_or("")
+            .split(';')
+            .map(|f| f.trim())
+            .collect();
+        if let (Some(accession), Some(version)) = (
+            fields.first(),
+            fields.get(1).and_then(|f| f.strip_prefix("SV ")),
+        ) {
+            header.version = format!("{}.{}", accession, version.trim());
+        }
+        header.molecule_type = fields.get(3).unwrap_or(&"").to_string();
+        header.data_class = fields.get(4).unwrap_or(&"").to_string();
+        header.division = fields.get(5).unwrap_or(&"").to_string();
+        header
+    }
+    ///reads the lines of an EMBL record between the ID line and the feature table, lines are kept with their two letter line code
+    pub fn parse_embl_lines(&mut self, lines: &[String]) {
+        //group the lines sharing a line code, each RN line starts a new reference
+        let mut sections: Vec<(String, Vec<String>)> = Vec::new();
+        for line in lines {
+            let line = line.trim_end();
+            let code = line.get(..2).unwrap_or(line);
+            let text = line.get(EMBL_CODE_WIDTH..).unwrap_or("").to_string();
+            match sections.last_mut() {
+                Some((last, texts)) if last == code && code != "RN" => texts.push(text),
+                _ => sections.push((code.to_string(), vec![text])),
+            }
+        }
+        for (code, texts) in sections {
+            let joined = join_lines(&texts);
+            match code.as_str() {
+                "AC" => {
+                    self.accessions = joined
+                        .split(';')
+                        .map(|a| a.trim().to_string())
+                        .filter(|a| !a.is_empty())
+                        .collect()
+                }
+                "PR" => {
+                    for project in joined.split(';') {
+                        if let Some(value) = project.trim().strip_prefix("Project:") {
+                            self.dblink
+                                .push(("BioProject".to_string(), value.trim().to_string()));
+                        }
+                    }
+                }
+                //the date the entry was last updated is on the last DT line
+                "DT" => {
+                    if let Some(date) = texts.last().and_then(|t| t.split_whitespace().next()) {
+                        self.date = date.to_string();
+                    }
+                    self.embl_dates = texts.iter().map(|t| t.trim().to_string()).collect();
+                }
+                "DE" => self.definition = joined,
+                "KW" => {
+                    self.keywords = joined
+                        .strip_suffix('.')
+                        .unwrap_or(&joined)
+                        .split(';')
+                        .map(|k| k.trim().to_string())
+                        .filter(|k| !k.is_empty())
+                        .collect()
+                }
+                "OS" => {
+                    self.organism = joined.clone();
+                    self.source = joined;
+                }
+                "OC" => {
+                    self.lineage = joined
+                        .strip_suffix('.')
+                        .unwrap_or(&joined)
+                        .split(';')
+                        .map(|t| t.trim().to_string())
+                        .filter(|t| !t.is_empty())
+                        .collect()
+                }
+                "RN" => self.references.push(Reference {
+                    number: joined.trim_matches(|c| c == '[' || c == ']').to_string(),
+                    ..Default::default()
+                }),
+                "RP" | "RX" | "RG" | "RA" | "RT" | "RL" | "RC" => {
+                    if let Some(reference) = self.references.last_mut() {
+                        match code.as_str() {
+                            "RP" => {
+                                let ranges: Vec<String> = joined
+                                    .split(',')
+                                    .filter_map(|r| r.trim().split_once('-'))
+                                    .map(|(start, end)| format!("{} to {}", start, end))
+                                    .collect();
+                                reference.bases = format!("(bases {})", ranges.join("; "));
+                            }
+                            "RX" => {
+                                for xref in texts.iter() {
+                                    if let Some(pubmed) = xref.trim().strip_prefix("PUBMED;") {
+                                        reference.pubmed =
+                                            pubmed.trim().trim_end_matches('.').to_string();
+                                    }
+                                }
+                            }
+                            "RG" => reference.consortium = joined,
+                            "RA" => reference.authors = joined.trim_end_matches(';').to_string(),
+                            "RT" => {
+                                reference.title =
+                                    joined.trim_end_matches(';').trim_matches('"').to_string()
+                            }
+                            "RL" => reference.journal = joined,
+                            _ => reference.remark = joined,
+                        }
+                    }
+                }
+                "DR" => {
+                    for xref in texts.iter() {
+                        if let Some(value) = xref.trim().strip_prefix("BioSample;") {
+                            self.dblink.push((
+                                "BioSample".to_string(),
+                                value.trim().trim_end_matches('.').to_string(),
+                            ));
+                        }
+                    }
+                }
+                "CC" => self.comment = texts.join("\n").trim_end().to_string(),
+                _ => (),
+            }
+        }
+    }
+    pub fn bioproject(&self) -> Option<&str> {
+        self.dblink_value("BioProject")
+    }
+    pub fn biosample(&self) -> Option<&str> {
+        self.dblink_value("BioSample")
+    }
+    fn dblink_value(&self, key: &str) -> Option<&str> {
+        self.dblink
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+    ///formats the LOCUS line of a record with the given name, length and topology
+    pub fn locus_line(&self, name: &str, length: usize, topology: Topology) -> String {
+        let field = |value: &str, default: &'static str| match value.is_empty() {
+            true => default.to_string(),
+            false => value.to_string(),
+        };
+        format!(
+            "LOCUS       {:<16} {:>11} bp    {:<7} {:<8} {} {}",
+            name,
+            length,
+            field(&self.molecule_type, "DNA"),
+            topology.to_string(),
+            field(&self.division, "CON"),
+            self.date
+        )
+        .trim_end()
+        .to_string()
+    }
+    ///formats the sections between the LOCUS line and the FEATURES table, without a trailing newline
+    pub fn genbank_lines(&self) -> String {
+        let mut lines: Vec<String> = Vec::new();
+        lines.push(format_section("DEFINITION", &self.definition));
+        lines.push(format_section("ACCESSION", &self.accessions.join(" ")));
+        if !self.version.is_empty() {
+            lines.push(format_section("VERSION", &self.version));
+        }
+        for (i, (key, value)) in self.dblink.iter().enumerate() {
+            let keyword = if i == 0 { "DBLINK" } else { "" };
+            lines.push(format_section(keyword, &format!("{}: {}", key, value)));
+        }
+        let keywords = match self.keywords.is_empty() {
+            true => ".".to_string(),
+            false => format!("{}.", self.keywords.join("; ")),
+        };
+        lines.push(format_section("KEYWORDS", &keywords));
+        lines.push(format_section("SOURCE", &self.source));
+        lines.push(format_section("  ORGANISM", &self.organism));
+        if !self.lineage.is_empty() {
+            lines.push(format_section("", &format!("{}.", self.lineage.join("; "))));
+        }
+        for reference in self.references.iter() {
+            let number = match reference.bases.is_empty() {
+                true => reference.number.clone(),
+                false => format!("{:<2} {}", reference.number, reference.bases),
+            };
+            lines.push(format_section("REFERENCE", &number));
+            let write_other = |after: &str, lines: &mut Vec<String>| {
+                for (_, keyword, value) in reference.other.iter().filter(|(a, _, _)| a == after) {
+                    lines.push(format_section(&format!("  {}", keyword), value));
+                }
+            };
+            write_other("", &mut lines);
+            for (keyword, value) in [
+                ("  AUTHORS", &reference.authors),
+                ("  CONSRTM", &reference.consortium),
+                ("  TITLE", &reference.title),
+                ("  JOURNAL", &reference.journal),
+                ("   PUBMED", &reference.pubmed),
+                ("  REMARK", &reference.remark),
+            ] {
+                if !value.is_empty() {
+                    lines.push(format_section(keyword, value));
+                }
+                write_other(keyword.trim(), &mut lines);
+            }
+        }
+        if !self.comment.is_empty() {
+            lines.push(format_block("COMMENT", &self.comment));
+        }
+        for (keyword, value) in self.other.iter() {
+            lines.push(format_block(keyword, value));
+        }
+        lines.join("\n")
+    }
+    ///formats the EMBL ID line of a record, GenBank molecule types and divisions are given their EMBL names
+    pub fn embl_id_line(&self, name: &str, length: usize, topology: Topology) -> String {
+        let version = self
+            .version
+            .rsplit_once('.')
+            .map(|(_, v)| v)
+            .filter(|v| !v.is_empty() && v.chars().all(|c| c.is_ascii_digit()))
+            .unwrap_or("1");
+        let molecule_type = match self.molecule_type.as_str() {
+            "" | "DNA" => "genomic DNA",
+            "RNA" => "genomic RNA",
+            other => other,
+        };
+        let data_class = match (self.data_class.as_str(), self.division.as_str()) {
+            ("", "CON") => "CON",
+            ("", _) => "STD",
+            (data_class, _) => data_class,
+        };
+        let division = match self.division.as_str() {
+            d if EMBL_DIVISIONS.contains(&d) => d,
+            "BCT" => "PRO",
+            "PRI" => "HUM",
+            "PAT" | "SYN" => "SYN",
+            _ => "UNC",
+        };
+        format!(
+            "ID   {}; SV {}; {}; {}; {}; {}; {} BP.",
+            name, version, topology, molecule_type, data_class, division, length
+        )
+    }
+    ///formats the lines between the ID line and the feature table of an EMBL record, each block is followed by an ```XX``` line
+    pub fn embl_lines(&self) -> String {
+        let mut blocks: Vec<String> = Vec::new();
+        let accessions: Vec<String> = self.accessions.iter().map(|a| format!("{};", a)).collect();
+        blocks.push(format_embl_section("AC", &accessions.join(" ")));
+        if let Some(project) = self.bioproject() {
+            blocks.push(format_embl_section("PR", &format!("Project:{};", project)));
+        }
+        let dates: Vec<String> = match self.embl_dates.is_empty() {
+            true if self.date.is_empty() => Vec::new(),
+            true => vec![format!("DT   {}", self.date)],
+            false => self
+                .embl_dates
+                .iter()
+                .map(|date| format!("DT   {}", date))
+                .collect(),
+        };
+        if !dates.is_empty() {
+            blocks.push(dates.join("\n"));
+        }
+        let definition = match self.definition.is_empty() {
+            true => ".",
+            false => &self.definition,
+        };
+        blocks.push(format_embl_section("DE", definition));
+        let keywords = match self.keywords.is_empty() {
+            true => ".".to_string(),
+            false => format!("{}.", self.keywords.join("; ")),
+        };
+        blocks.push(format_embl_section("KW", &keywords));
+        let mut organism = format_embl_section("OS", &self.organism);
+        if !self.lineage.is_empty() {
+            organism.push('\n');
+            organism.push_str(&format_embl_section(
+                "OC",
+                &format!("{}.", self.lineage.join("; ")),
+            ));
+        }
+        blocks.push(organism);
+        for (i, reference) in self.references.iter().enumerate() {
+            let number = match reference.number.parse::<usize>() {
+                Ok(number) => number,
+                Err(_) => i + 1,
+            };
+            let mut lines = vec![format!("RN   [{}]", number)];
+            //"(bases 1 to 147463; 150000 to 150100)" is written as "1-147463, 150000-150100"
+            let ranges: Vec<String> = reference
+                .bases
+                .trim_start_matches("(bases")
+                .trim_end_matches(')')
+                .split(';')
+                .filter_map(|r| r.split_once(" to "))
+                .map(|(start, end)| format!("{}-{}", start.trim(), end.trim()))
+                .collect();
+            if !ranges.is_empty() {
+                lines.push(format_embl_section("RP", &ranges.join(", ")));
+            }
+            if !reference.pubmed.is_empty() {
+                lines.push(format!("RX   PUBMED; {}.", reference.pubmed));
+            }
+            if !reference.consortium.is_empty() {
+                lines.push(format_embl_section("RG", &reference.consortium));
+            }
+            if !reference.authors.is_empty() {
+                lines.push(format_embl_section(
+                    "RA",
+                    &format!("{};", reference.authors),
+                ));
+            }
+            let title = match reference.title.is_empty() {
+                true => ";".to_string(),
+                false => format!("\"{}\";", reference.title),
+            };
+            lines.push(format_embl_section("RT", &title));
+            if !reference.remark.is_empty() {
+                lines.push(format_embl_section("RC", &reference.remark));
+            }
+            lines.push(format_embl_section("RL", &reference.journal));
+            blocks.push(lines.join("\n"));
+        }
+        if let Some(biosample) = self.biosample() {
+            blocks.push(format!("DR   BioSample; {}.", biosample));
+        }
+        if !self.comment.is_empty() {
+            let comment: Vec<String> = self
+                .comment
+                .lines()
+                .map(|line| format!("CC   {}", line).trim_end().to_string())
+                .collect();
+            blocks.push(comment.join("\n"));
+        }
+        blocks
+            .iter()
+            .map(|block| format!("{}\nXX", block))
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
+
+//joins the continuation lines of a section with single spaces
+fn join_lines(texts: &[String]) -> String {
+    texts
+        .iter()
+        .map(|t| t.trim())
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<&str>>()
+        .join(" ")
+}
+
+///formats a header section, wrapping the text at a space onto continuation lines indented to the text column
+pub fn format_section(keyword: &str, text: &str) -> String {
+    wrap_text(text, LINE_WIDTH - KEYWORD_WIDTH)
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => format!("{:<12}{}", keyword, line),
+            _ => format!("{:<12}{}", "", line),
+        })
+        .map(|line| line.trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+///formats an EMBL section, every line starts with the two letter line code and long text is wrapped at a space
+pub fn format_embl_section(code: &str, text: &str) -> String {
+    wrap_text(text, EMBL_LINE_WIDTH - EMBL_CODE_WIDTH)
+        .iter()
+        .map(|line| format!("{:<5}{}", code, line).trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+//splits text into lines of at most width characters
+fn wrap_text(text: &str, width: usize) -> Vec<&str> {
+    let mut lines: Vec<&str> = Vec::new();
+    let mut rest = text.trim();
+    while rest.len() > width {
+        let mut cut = width;
+        while !rest.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        //wrap at the last space that fits, a single long word is cut at the line width
+        if !rest[cut..].starts_with(' ') {
+            if let Some(space) = rest[..cut].rfind(' ') {
+                if space > 0 {
+                    cut = space;
+                }
+            }
+        }
+        lines.push(&rest[..cut]);
+        rest = rest[cut..].trim_start_matches(' ');
+    }
+    lines.push(rest);
+    lines
+}
+
+//formats a section whose line breaks are meaningful, such as COMMENT tables
+fn format_block(keyword: &str, text: &str) -> String {
+    text.lines()
+        .enumerate()
+        .map(|(i, line)| match i {
+            0 => format!("{:<12}{}", keyword, line),
+            _ => format!("{:<12}{}", "", line),
+        })
+        .map(|line| line.trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_locus_line() {
+        let header = RecordHeader::from_locus_line(
+            "LOCUS       NC_000913                913 bp    DNA     linear   CON 01-Sep-2025",
+        );
+        assert_eq!(header.molecule_type, "DNA");
+        assert_eq!(header.division, "CON");
+        assert_eq!(header.date, "01-Sep-2025");
+        let line =
+            "LOCUS       AM236082              147463 bp    DNA     circular BCT 14-JUL-2016";
+        let topology = Topology::from_locus_line(line);
+        assert_eq!(topology, Topology::Circular);
+        assert_eq!(
+            RecordHeader::from_locus_line(line).locus_line("AM236082", 147463, topology),
+            line
+        );
+        assert_eq!(
+            Topology::from_locus_line(
+                "ID   AM236082; SV 1; linear; genomic DNA; STD; PRO; 6666 BP."
+            ),
+            Topology::Linear
+        );
+    }
+    #[test]
+    fn test_sections() {
+        let lines: Vec<String> = [
+            "DEFINITION  Rhizobium leguminosarum bv. viciae plasmid pRL8 complete genome,",
+            "            strain 3841.",
+            "ACCESSION   AM236082",
+            "VERSION     AM236082.1",
+            "KEYWORDS    complete genome.",
+            "SOURCE      Rhizobium johnstonii 3841",
+            "  ORGANISM  Rhizobium johnstonii 3841",
+            "            Bacteria; Pseudomonadati; Pseudomonadota; Alphaproteobacteria;",
+            "            Hyphomicrobiales; Rhizobiaceae; Rhizobium/Agrobacterium group;",
+            "            Rhizobium; Rhizobium johnstonii.",
+            "REFERENCE   1",
+            "  AUTHORS   Young,J.P., Crossman,L.C. and Parkhill,J.",
+            "  TITLE     The genome of Rhizobium leguminosarum",
+            "  JOURNAL   Genome Biol. 7 (4), R34 (2006)",
+            "  MEDLINE   96123456",
+            "   PUBMED   16640791",
+            "REFERENCE   2  (bases 1 to 147463)",
+            "  AUTHORS   Crossman,L.C.",
+            "COMMENT     first line",
+            "            second line",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let mut header = RecordHeader::new();
+        header.parse_genbank_lines(&lines);
+        assert_eq!(
+            header.definition,
+            "Rhizobium leguminosarum bv. viciae plasmid pRL8 complete genome, strain 3841."
+        );
+        assert_eq!(header.accessions, vec!["AM236082"]);
+        assert_eq!(header.organism, "Rhizobium johnstonii 3841");
+        assert_eq!(header.lineage.len(), 9);
+        assert_eq!(header.lineage[8], "Rhizobium johnstonii");
+        assert_eq!(header.references.len(), 2);
+        assert_eq!(header.references[0].pubmed, "16640791");
+        assert_eq!(
+            header.references[0].other,
+            vec![(
+                "JOURNAL".to_string(),
+                "MEDLINE".to_string(),
+                "96123456".to_string()
+            )]
+        );
+        assert_eq!(header.references[1].bases, "(bases 1 to 147463)");
+        assert_eq!(header.comment, "first line\nsecond line");
+        let written: Vec<String> = header
+            .genbank_lines()
+            .lines()
+            .map(|l| l.to_string())
+            .collect();
+        assert_eq!(written, lines);
+    }
+    #[test]
+    fn test_embl_sections() {
+        let id = "ID   AM236082; SV 1; linear; genomic DNA; STD; PRO; 6666 BP.";
+        let lines: Vec<String> = [
+            "AC   AM236082;",
+            "XX",
+            "PR   Project:PRJNA344;",
+            "XX",
+            "DT   04-MAY-2006 (Rel. 87, Created)",
+            "DT   06-FEB-2015 (Rel. 123, Last updated, Version 9)",
+            "XX",
+            "DE   Rhizobium leguminosarum bv. viciae plasmid pRL8 complete genome, strain",
+            "DE   3841",
+            "XX",
+            "KW   complete genome.",
+            "XX",
+            "OS   Rhizobium leguminosarum bv. viciae 3841",
+            "OC   Bacteria; Proteobacteria; Alphaproteobacteria; Rhizobiales; Rhizobiaceae;",
+            "OC   Rhizobium/Agrobacterium group; Rhizobium.",
+            "XX",
+            "RN   [1]",
+            "RP   1-147463",
+            "RX   PUBMED; 16640791.",
+            "RA   Crossman L.C.;",
+            "RT   ;",
+            "RL   Submitted (21-FEB-2006) to the INSDC.",
+            "XX",
+            "DR   BioSample; SAMEA1705944.",
+            "XX",
+        ]
+        .iter()
+        .map(|l| l.to_string())
+        .collect();
+        let mut header = RecordHeader::from_id_line(id);
+        header.parse_embl_lines(&lines);
+        assert_eq!(header.version, "AM236082.1");
+        assert_eq!(header.division, "PRO");
+        assert_eq!(header.date, "06-FEB-2015");
+        assert_eq!(header.bioproject(), Some("PRJNA344"));
+        assert_eq!(header.biosample(), Some("SAMEA1705944"));
+        assert_eq!(header.lineage.len(), 7);
+        assert_eq!(header.references[0].bases, "(bases 1 to 147463)");
+        assert_eq!(header.references[0].pubmed, "16640791");
+        assert_eq!(header.embl_id_line("AM236082", 6666, Topology::Linear), id);
+        let written: Vec<String> = header.embl_lines().lines().map(|l| l.to_string()).collect();
+        assert_eq!(written, lines);
+        //GenBank molecule types and divisions are given their EMBL names
+        let genbank = RecordHeader::from_locus_line(
+            "LOCUS       AM236082              147463 bp    DNA     circular BCT 14-JUL-2016",
+        );
+        assert_eq!(
+            genbank.embl_id_line("AM236082", 147463, Topology::Circular),
+            "ID   AM236082; SV 1; circular; genomic DNA; STD; PRO; 147463 BP."
+        );
+    }
+}
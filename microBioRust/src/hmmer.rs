@@ -0,0 +1,694 @@
+//! # HMMER3 tabular output
+//!
+//! Reads the ```--tblout``` (one line per target) and ```--domtblout``` (one line per domain) tables of hmmscan, hmmsearch, phmmer and jackhmmer
+//! into ```TargetHit```s and ```DomainHit```s with their e-values, scores and biases, and the model, alignment and envelope coordinates of each domain.
+//! The same ```Reader``` gives either with ```targets()``` or ```domains()```, ```#``` comment lines are skipped.
+//!
+//! ```best_domains``` keeps the domains of each CDS of a ```gbk::Record``` below an i-Evalue that do not overlap a better one,
+//! whether the protein is the query (hmmscan against Pfam or TIGRFAM) or the target (hmmsearch of a model against the proteins of ```gbk_to_faa```).
+//! ```annotate_domains``` adds them to the CDS as /note qualifiers and gives their genome coordinates, mapped from the protein with ```protein_location```.
+//!
+//!```rust
+//! use microBioRust::hmmer::Reader;
+//!
+//! let text = concat!(
+//!     "#                                                                            --- full sequence --- -------------- this domain -------------   hmm coord   ali coord   env coord\n",
+//!     "# target name        accession   tlen query name           accession   qlen   E-value  score  bias   #  of  c-Evalue  i-Evalue  score  bias  from    to  from    to  from    to  acc description of target\n",
+//!     "Ribosomal_L6         PF00347.27    77 b3304                -            117   1.1e-40  137.2   4.1   1   2   1.2e-20   1.1e-20   72.3   0.1     1    76     5    80     3    82 0.95 Ribosomal protein L6 domain\n",
+//! );
+//! let domains: Vec<_> = Reader::new(text.as_bytes()).domains().map(|d| d.unwrap()).collect();
+//! assert_eq!(domains[0].target_accession.as_deref(), Some("PF00347.27"));
+//! assert_eq!((domains[0].env_from, domains[0].env_to), (3, 82));
+//! assert_eq!(domains[0].i_evalue, 1.1e-20);
+//! assert_eq!(domains[0].description, "Ribosomal protein L6 domain");
+//!```
+
+use crate::blast::cds_locus_tag;
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk::{FeatureAttributes, Record};
+use crate::location::{Location, Position};
+use anyhow::{anyhow, Context};
+use std::{collections::BTreeMap, io, path::Path};
+
+//the columns before the description of a --tblout line
+const TBLOUT_COLUMNS: usize = 18;
+//the columns before the description of a --domtblout line
+const DOMTBLOUT_COLUMNS: usize = 22;
+
+///a line of a ```--tblout``` table, the scores of a whole target sequence
+#[derive(Debug, Clone, PartialEq)]
+pub struct TargetHit {
+    pub target_name: String,
+    ///None for ```-```
+    pub target_accession: Option<String>,
+    pub query_name: String,
+    pub query_accession: Option<String>,
+    pub evalue: f64,
+    pub score: f64,
+    pub bias: f64,
+    ///the e-value, score and bias of the best domain
+    pub best_domain_evalue: f64,
+    pub best_domain_score: f64,
+    pub best_domain_bias: f64,
+    ///the expected number of domains and the counts of regions, clusters, overlaps, envelopes, domains, reported and included domains
+    pub expected_domains: f64,
+    pub regions: u32,
+    pub clusters: u32,
+    pub overlaps: u32,
+    pub envelopes: u32,
+    pub domains: u32,
+    pub reported: u32,
+    pub included: u32,
+    pub description: String,
+}
+
+///a line of a ```--domtblout``` table, one domain of a target, coordinates count from 1
+///```ali_*``` and ```env_*``` are positions on the sequence and ```hmm_*``` on the model, whichever of target and query each one is
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainHit {
+    pub target_name: String,
+    pub target_accession: Option<String>,
+    pub target_length: u32,
+    pub query_name: String,
+    pub query_accession: Option<String>,
+    pub query_length: u32,
+    ///the scores of the whole sequence
+    pub evalue: f64,
+    pub score: f64,
+    pub bias: f64,
+    ///the number of this domain and the number of domains of the target
+    pub domain_number: u32,
+    pub domain_count: u32,
+    ///the conditional and independent e-values of the domain
+    pub c_evalue: f64,
+    pub i_evalue: f64,
+    pub domain_score: f64,
+    pub domain_bias: f64,
+    pub hmm_from: u32,
+    pub hmm_to: u32,
+    pub ali_from: u32,
+    pub ali_to: u32,
+    pub env_from: u32,
+    pub env_to: u32,
+    ///the mean posterior probability of the aligned residues
+    pub accuracy: f64,
+    pub description: String,
+}
+
+//splits the whitespace separated columns before the description, the description keeps its spaces
+fn split_columns(line: &str, fixed: usize) -> Result<(Vec<&str>, &str), anyhow::Error> {
+    let mut columns = Vec::with_capacity(fixed);
+    let mut rest = line.trim_start();
+    while columns.len() < fixed && !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        columns.push(&rest[..end]);
+        rest = rest[end..].trim_start();
+    }
+    if columns.len() < fixed {
+        return Err(anyhow!(
+            "expected at least {} columns, found {}",
+            fixed,
+            columns.len()
+        ));
+    }
+    Ok((columns, rest.trim_end()))
+}
+
+//reads a number of a column, naming the column in the error
+fn column<T: std::str::FromStr>(
+    columns: &[&str],
+    index: usize,
+    name: &str,
+) -> Result<T, anyhow::Error>
+where
+    T::Err: std::fmt::Display,
+{
+    columns[index]
+        .parse::<T>()
+        .map_err(|e| anyhow!("bad {} {:?}: {}", name, columns[index], e))
+}
+
+//an accession column, - when the model or sequence has none
+fn accession(text: &str) -> Option<String> {
+    match text {
+        "-" => None,
+        accession => Some(accession.to_string()),
+    }
+}
+
+impl TargetHit {
+    ///reads a whitespace separated ```--tblout``` line
+    pub fn from_line(line: &str) -> Result<Self, anyhow::Error> {
+        let (c, description) = split_columns(line, TBLOUT_COLUMNS)?;
+        Ok(TargetHit {
+            target_name: c[0].to_string(),
+            target_accession: accession(c[1]),
+            query_name: c[2].to_string(),
+            query_accession: accession(c[3]),
+            evalue: column(&c, 4, "E-value")?,
+            score: column(&c, 5, "score")?,
+            bias: column(&c, 6, "bias")?,
+            best_domain_evalue: column(&c, 7, "best domain E-value")?,
+            best_domain_score: column(&c, 8, "best domain score")?,
+            best_domain_bias: column(&c, 9, "best domain bias")?,
+            expected_domains: column(&c, 10, "exp")?,
+            regions: column(&c, 11, "reg")?,
+            clusters: column(&c, 12, "clu")?,
+            overlaps: column(&c, 13, "ov")?,
+            envelopes: column(&c, 14, "env")?,
+            domains: column(&c, 15, "dom")?,
+            reported: column(&c, 16, "rep")?,
+            included: column(&c, 17, "inc")?,
+            description: description.to_string(),
+        })
+    }
+}
+
+impl DomainHit {
+    ///reads a whitespace separated ```--domtblout``` line
+    pub fn from_line(line: &str) -> Result<Self, anyhow::Error> {
+        let (c, description) = split_columns(line, DOMTBLOUT_COLUMNS)?;
+        Ok(DomainHit {
+            target_name: c[0].to_string(),
+            target_accession: accession(c[1]),
+            target_length: column(&c, 2, "tlen")?,
+            query_name: c[3].to_string(),
+            query_accession: accession(c[4]),
+            query_length: column(&c, 5, "qlen")?,
+            evalue: column(&c, 6, "E-value")?,
+            score: column(&c, 7, "score")?,
+            bias: column(&c, 8, "bias")?,
+            domain_number: column(&c, 9, "domain number")?,
+            domain_count: column(&c, 10, "domain count")?,
+            c_evalue: column(&c, 11, "c-Evalue")?,
+            i_evalue: column(&c, 12, "i-Evalue")?,
+            domain_score: column(&c, 13, "domain score")?,
+            domain_bias: column(&c, 14, "domain bias")?,
+            hmm_from: column(&c, 15, "hmm from")?,
+            hmm_to: column(&c, 16, "hmm to")?,
+            ali_from: column(&c, 17, "ali from")?,
+            ali_to: column(&c, 18, "ali to")?,
+            env_from: column(&c, 19, "env from")?,
+            env_to: column(&c, 20, "env to")?,
+            accuracy: column(&c, 21, "acc")?,
+            description: description.to_string(),
+        })
+    }
+    //true when the envelopes of the two domains share a residue
+    fn overlaps(&self, other: &DomainHit) -> bool {
+        self.env_from <= other.env_to && other.env_from <= self.env_to
+    }
+}
+
+///the genome location of amino acids ```from``` to ```to``` (counting from 1) of the protein of a CDS
+///the codon start shifts the first codon as /codon_start does, parts of a join give a join and spans over the origin of a circular record end before they start
+pub fn protein_location(
+    location: &Location,
+    codon_start: u8,
+    from: u32,
+    to: u32,
+    length: u32,
+) -> Option<Location> {
+    if from == 0 || to < from {
+        return None;
+    }
+    //0-based offsets of the first and last base in the spliced coding sequence
+    let first = codon_start.max(1) as u32 - 1 + 3 * (from - 1);
+    let last = codon_start.max(1) as u32 - 1 + 3 * to - 1;
+    let mut parts = Vec::new();
+    let mut offset = 0;
+    for (start, end, strand) in location.segments() {
+        let span = match start <= end {
+            true => end - start + 1,
+            false => length - start + 1 + end,
+        };
+        let (lo, hi) = (first.max(offset), last.min(offset + span - 1));
+        if lo <= hi {
+            //the genome position of an offset within the segment
+            let position = |k: u32| match strand {
+                -1 => match end > k - offset {
+                    true => end - (k - offset),
+                    false => length + end - (k - offset),
+                },
+                _ => match start + (k - offset) > length && length > 0 {
+                    true => start + (k - offset) - length,
+                    false => start + (k - offset),
+                },
+            };
+            let (part_start, part_end) = match strand {
+                -1 => (position(hi), position(lo)),
+                _ => (position(lo), position(hi)),
+            };
+            parts.push(Location::Range {
+                start: Position::Exact(part_start),
+                end: Position::Exact(part_end),
+                strand,
+            });
+        }
+        offset += span;
+    }
+    match parts.len() {
+        0 => None,
+        1 => parts.pop(),
+        _ => Some(Location::Join(parts)),
+    }
+}
+
+///the domains of each CDS with an i-Evalue of at most ```max_evalue```, the best one first kept where envelopes overlap, in protein order
+///the CDS is named by the query (hmmscan) or the target (hmmsearch) as in ```blast::join_hits```, domains naming no CDS are left out
+pub fn best_domains<'a>(
+    record: &Record,
+    domains: &'a [DomainHit],
+    max_evalue: f64,
+) -> BTreeMap<String, Vec<&'a DomainHit>> {
+    let mut by_cds: BTreeMap<String, Vec<&'a DomainHit>> = BTreeMap::new();
+    for domain in domains.iter().filter(|d| d.i_evalue <= max_evalue) {
+        if let Some(locus_tag) = cds_locus_tag(record, &domain.query_name)
+            .or_else(|| cds_locus_tag(record, &domain.target_name))
+        {
+            by_cds.entry(locus_tag).or_default().push(domain);
+        }
+    }
+    for candidates in by_cds.values_mut() {
+        candidates.sort_by(|a, b| {
+            a.i_evalue
+                .total_cmp(&b.i_evalue)
+                .then(b.domain_score.total_cmp(&a.domain_score))
+        });
+        let mut kept: Vec<&DomainHit> = Vec::new();
+        for domain in candidates.iter() {
+            if !kept.iter().any(|k| k.overlaps(domain)) {
+                kept.push(domain);
+            }
+        }
+        kept.sort_by_key(|d| (d.env_from, d.env_to));
+        *candidates = kept;
+    }
+    by_cds
+}
+
+///a domain placed on a CDS of a record
+#[derive(Debug, Clone, PartialEq)]
+pub struct DomainAnnotation {
+    pub locus_tag: String,
+    ///the name, accession and description of the profile HMM
+    pub model: String,
+    pub model_accession: Option<String>,
+    pub model_description: String,
+    pub i_evalue: f64,
+    pub score: f64,
+    ///the envelope on the protein, counting from 1
+    pub protein_from: u32,
+    pub protein_to: u32,
+    ///the envelope on the genome, None when the CDS has no location
+    pub location: Option<Location>,
+}
+
+///places the best domains (```best_domains```) on the CDS of the record, each one added as a /note qualifier such as
+///```Ribosomal_L6 (PF00347.27) domain at amino acids 3..82, i-Evalue 1.1e-20```, and returns them in locus tag order
+pub fn annotate_domains(
+    record: &mut Record,
+    domains: &[DomainHit],
+    max_evalue: f64,
+) -> Vec<DomainAnnotation> {
+    let length = record.sequence.len().max(record.length as usize) as u32;
+    let mut annotations = Vec::new();
+    for (locus_tag, best) in best_domains(record, domains, max_evalue) {
+        let location = record.cds.get_location(&locus_tag).cloned();
+        let codon_start = record.cds.get_codon_start(&locus_tag).copied().unwrap_or(1);
+        let mut qualifiers = record
+            .cds
+            .get_qualifiers(&locus_tag)
+            .cloned()
+            .unwrap_or_default();
+        for domain in best {
+            //the model is whichever side of the hit is not the CDS
+            let model_is_target = cds_locus_tag(record, &domain.query_name).is_some();
+            let (model, model_accession, model_description) = match model_is_target {
+                true => (
+                    &domain.target_name,
+                    &domain.target_accession,
+                    domain.description.clone(),
+                ),
+                false => (&domain.query_name, &domain.query_accession, String::new()),
+            };
+            let note = match model_accession {
+                Some(accession) => format!(
+                    "{} ({}) domain at amino acids {}..{}, i-Evalue {:e}",
+                    model, accession, domain.env_from, domain.env_to, domain.i_evalue
+                ),
+                None => format!(
+                    "{} domain at amino acids {}..{}, i-Evalue {:e}",
+                    model, domain.env_from, domain.env_to, domain.i_evalue
+                ),
+            };
+            qualifiers.push("note", Some(note));
+            annotations.push(DomainAnnotation {
+                locus_tag: locus_tag.clone(),
+                model: model.clone(),
+                model_accession: model_accession.clone(),
+                model_description,
+                i_evalue: domain.i_evalue,
+                score: domain.domain_score,
+                protein_from: domain.env_from,
+                protein_to: domain.env_to,
+                location: location.as_ref().and_then(|l| {
+                    protein_location(l, codon_start, domain.env_from, domain.env_to, length)
+                }),
+            });
+        }
+        record.cds.retain_attributes(&locus_tag, |a| {
+            !matches!(a, FeatureAttributes::Qualifiers { .. })
+        });
+        record.cds.set_counter(locus_tag).set_qualifiers(qualifiers);
+    }
+    annotations
+}
+
+///an iterator over the per target lines of a --tblout file
+#[derive(Debug)]
+pub struct Targets<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Targets<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<TargetHit, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_target() {
+            Ok(Some(hit)) => Some(Ok(hit)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///an iterator over the per domain lines of a --domtblout file
+#[derive(Debug)]
+pub struct Domains<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Domains<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<DomainHit, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_domain() {
+            Ok(Some(hit)) => Some(Ok(hit)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read a HMMER table from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read HMMER table from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new HMMER table reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+        }
+    }
+    //reads the next line that is not a comment into the buffer, false at the end of the file
+    fn next_line(&mut self) -> Result<bool, ParseError> {
+        loop {
+            self.line_buffer.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.line_buffer)
+                .map_err(|source| ParseError::Io {
+                    line_number: self.line_number + 1,
+                    record_id: String::new(),
+                    line: String::new(),
+                    source,
+                })?;
+            if read == 0 {
+                return Ok(false);
+            }
+            self.line_number += 1;
+            let line = self.line_buffer.trim();
+            if !line.is_empty() && !line.starts_with('#') {
+                return Ok(true);
+            }
+        }
+    }
+    //the error for the line in the buffer
+    fn malformed(&self, message: String) -> ParseError {
+        let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+        ParseError::MalformedLine {
+            line_number: self.line_number,
+            record_id: line
+                .split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            line: line.to_string(),
+            message,
+        }
+    }
+    ///reads the next line of a --tblout file, returns None at the end of the file
+    pub fn read_target(&mut self) -> Result<Option<TargetHit>, ParseError> {
+        if !self.next_line()? {
+            return Ok(None);
+        }
+        TargetHit::from_line(&self.line_buffer)
+            .map(Some)
+            .map_err(|e| self.malformed(e.to_string()))
+    }
+    ///reads the next line of a --domtblout file, returns None at the end of the file
+    pub fn read_domain(&mut self) -> Result<Option<DomainHit>, ParseError> {
+        if !self.next_line()? {
+            return Ok(None);
+        }
+        DomainHit::from_line(&self.line_buffer)
+            .map(Some)
+            .map_err(|e| self.malformed(e.to_string()))
+    }
+    ///return an iterator over the lines of a --tblout file
+    pub fn targets(self) -> Targets<B> {
+        Targets {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+    ///return an iterator over the lines of a --domtblout file
+    pub fn domains(self) -> Domains<B> {
+        Domains {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbk::Reader as GbkReader;
+
+    #[test]
+    fn test_tblout() {
+        let text = concat!(
+            "#                                                               --- full sequence ---- --- best 1 domain ---- --- domain number estimation ----\n",
+            "# target name        accession  query name           accession    E-value  score  bias   E-value  score  bias   exp reg clu  ov env dom rep inc description of target\n",
+            "#------------------- ---------- -------------------- ---------- --------- ------ ----- --------- ------ -----   --- --- --- --- --- --- --- --- ---------------------\n",
+            "Ribosomal_L6         PF00347.27 b3304                -            1.1e-40  137.2   4.1   2.3e-20   72.3   0.4   2.3   2   0   0   2   2   2   2 Ribosomal protein L6 domain\n",
+            "TIGR03654            TIGR03654  b3304                -           3.5e-70  233.1   0.2     4e-70  232.9   0.2   1.0   1   0   0   1   1   1   1 -\n",
+            "#\n# Program:         hmmscan\n",
+            "broken              -          b3305                -           1e-5 12.0\n",
+        );
+        let mut reader = Reader::new(text.as_bytes());
+        let first = reader.read_target().unwrap().unwrap();
+        assert_eq!(first.target_name, "Ribosomal_L6");
+        assert_eq!(first.query_accession, None);
+        assert_eq!(
+            (first.evalue, first.score, first.bias),
+            (1.1e-40, 137.2, 4.1)
+        );
+        assert_eq!(first.best_domain_evalue, 2.3e-20);
+        assert_eq!(
+            (first.expected_domains, first.domains, first.included),
+            (2.3, 2, 2)
+        );
+        assert_eq!(first.description, "Ribosomal protein L6 domain");
+        let second = reader.read_target().unwrap().unwrap();
+        assert_eq!(second.target_accession.as_deref(), Some("TIGR03654"));
+        assert_eq!(second.description, "-");
+        match reader.read_target() {
+            Err(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                message,
+                ..
+            }) => {
+                assert_eq!((line_number, record_id.as_str()), (8, "broken"));
+                assert!(
+                    message.contains("expected at least 18 columns"),
+                    "{}",
+                    message
+                );
+            }
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_domains_on_cds() {
+        //hmmscan lines with the protein as query, and an hmmsearch line with the protein as target
+        let text = concat!(
+            "# target name        accession   tlen query name           accession   qlen   E-value  score  bias   #  of  c-Evalue  i-Evalue  score  bias  from    to  from    to  from    to  acc description of target\n",
+            "Ribosomal_L6         PF00347.27    77 b3304                -            117   1.1e-40  137.2   4.1   1   2   1.2e-20   1.1e-20   72.3   0.1     1    76     5    80     3    82 0.95 Ribosomal protein L6 domain\n",
+            "Ribosomal_L6         PF00347.27    77 b3304                -            117   1.1e-40  137.2   4.1   2   2   2.2e-19   2.0e-19   68.9   0.2     2    75    88   115    86   117 0.93 Ribosomal protein L6 domain\n",
+            "Other_fam            PF99999.1     90 b3304                -            117   1.0e-10   40.0   0.1   1   1   1.0e-10   1.0e-10   40.0   0.1     1    60    50    90    48    95 0.90 An overlapping family\n",
+            "Weak_fam             PF88888.1     40 b3304                -            117   1.0e-02   10.0   0.1   1   1   1.0e-02   1.0e-02   10.0   0.1     1    20     1    10     1    12 0.80 A weak family\n",
+            "NC_000913|b3305      -            177 rplF_model           -            170   1.0e-50  170.0   0.1   1   1   1.0e-50   1.0e-50  170.0   0.1     1   170     2   171     1   172 0.97 50S ribosomal subunit protein L6\n",
+            "unrelated            -            100 rplF_model           -            170   1.0e-50  170.0   0.1   1   1   1.0e-50   1.0e-50  170.0   0.1     1   170     2   99      1   100 0.97 -\n",
+        );
+        let domains: Vec<DomainHit> = Reader::new(text.as_bytes())
+            .domains()
+            .map(|d| d.unwrap())
+            .collect();
+        assert_eq!(domains.len(), 6);
+        assert_eq!((domains[1].domain_number, domains[1].domain_count), (2, 2));
+        assert_eq!(
+            (domains[1].hmm_from, domains[1].ali_from, domains[1].env_to),
+            (2, 88, 117)
+        );
+        let mut record = GbkReader::from_file("K12_ribo.gbk")
+            .unwrap()
+            .records()
+            .next()
+            .unwrap()
+            .unwrap();
+        let best = best_domains(&record, &domains, 1e-5);
+        assert_eq!(best.len(), 2);
+        let envelopes: Vec<(u32, u32)> = best["b3304"]
+            .iter()
+            .map(|d| (d.env_from, d.env_to))
+            .collect();
+        assert_eq!(envelopes, vec![(3, 82), (86, 117)]);
+        assert_eq!(best["b3305"][0].query_name, "rplF_model");
+        let annotations = annotate_domains(&mut record, &domains, 1e-5);
+        assert_eq!(annotations.len(), 3);
+        //b3304 is complement(10..363), amino acid 3 starts at 363 - 6
+        assert_eq!(
+            annotations[0].model_description,
+            "Ribosomal protein L6 domain"
+        );
+        assert_eq!(
+            annotations[0]
+                .location
+                .as_ref()
+                .map(|l| l.to_string())
+                .as_deref(),
+            Some("complement(118..357)")
+        );
+        assert_eq!(annotations[2].model, "rplF_model");
+        assert_eq!(annotations[2].model_description, "");
+        assert_eq!(
+            annotations[2]
+                .location
+                .as_ref()
+                .map(|l| l.to_string())
+                .as_deref(),
+            Some("complement(391..906)")
+        );
+        let notes = record.cds.get_qualifiers("b3304").unwrap().notes();
+        assert!(notes
+            .contains(&"Ribosomal_L6 (PF00347.27) domain at amino acids 3..82, i-Evalue 1.1e-20"));
+        assert_eq!(
+            record.cds.get_qualifiers("b3304").unwrap().locus_tag(),
+            Some("b3304")
+        );
+    }
+    #[test]
+    fn test_protein_location() {
+        let join = Location::Join(vec![
+            Location::Range {
+                start: Position::Exact(100),
+                end: Position::Exact(105),
+                strand: 1,
+            },
+            Location::Range {
+                start: Position::Exact(200),
+                end: Position::Exact(211),
+                strand: 1,
+            },
+        ]);
+        //codon 2 spans the two parts, codon 3 and 4 lie in the second
+        assert_eq!(
+            protein_location(&join, 1, 2, 3, 1000).unwrap().to_string(),
+            "join(103..105,200..202)"
+        );
+        assert_eq!(
+            protein_location(&join, 1, 3, 4, 1000).unwrap().to_string(),
+            "200..205"
+        );
+        assert_eq!(
+            protein_location(&join, 2, 1, 1, 1000).unwrap().to_string(),
+            "101..103"
+        );
+        assert_eq!(protein_location(&join, 1, 10, 12, 1000), None);
+        //a CDS over the origin of a circular record
+        let wrapping = Location::Range {
+            start: Position::Exact(995),
+            end: Position::Exact(6),
+            strand: 1,
+        };
+        assert_eq!(
+            protein_location(&wrapping, 1, 2, 3, 1000)
+                .unwrap()
+                .to_string(),
+            "998..3"
+        );
+    }
+}
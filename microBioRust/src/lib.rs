@@ -9,6 +9,24 @@
 //!  Additionally, you can create new features and records and save them either in genbank or gff3 format
 //!
 #![allow(non_snake_case)]
+pub mod alignment;
+pub mod bed;
+pub mod blast;
+pub mod compression;
 pub mod embl;
+pub mod error;
+pub mod fasta;
+pub mod fastq;
 pub mod gbk;
+pub mod gff;
+pub mod gtf;
+pub mod header;
+pub mod hmmer;
+pub mod location;
+pub mod paf;
+pub mod qualifiers;
 pub mod record;
+pub mod sam;
+pub mod translation;
+pub mod tree;
+pub mod vcf;
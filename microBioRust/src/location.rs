@@ -0,0 +1,816 @@
+//! # Feature locations shared by the GenBank and EMBL parsers
+//!
+//! The INSDC location grammar is parsed into a ```Location``` tree, each part of the tree carries its own strand and fuzzy bounds.
+//! Supported are single bases (```467```), spans (```340..565```), fuzzy ends (```<345..>500```), ```one-of(102,104)``` and ```(102.110)``` positions,
+//! sites between two bases (```123^124```), ```complement(...)```, ```join(...)```, ```order(...)``` and remote references (```J00194.1:100..202```).
+//! Formatting a ```Location``` gives back the INSDC text, used by the writers.
+//!
+//!```rust
+//! use microBioRust::location::Location;
+//!
+//! let location: Location = "complement(join(<10..363,373..>906))".parse().unwrap();
+//! assert_eq!(location.strand(), -1);
+//! assert_eq!((location.start(), location.end()), (10, 906));
+//! //the parts are in biological order, the second span is read first on the reverse strand
+//! assert_eq!(location.parts()[0].to_string(), "complement(373..>906)");
+//! assert_eq!(location.to_string(), "complement(join(<10..363,373..>906))");
+//!```
+
+use crate::qualifiers::line_width;
+use crate::record::RangeValue;
+use anyhow::anyhow;
+use bio::alphabets::dna::revcomp;
+use std::fmt;
+use std::str::FromStr;
+
+///a position in a location, exact or fuzzy
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Position {
+    Exact(u32),
+    ///```<345```, the feature starts before this base
+    Before(u32),
+    ///```>500```, the feature ends after this base
+    After(u32),
+    ///```one-of(102,104)```
+    OneOf(Vec<u32>),
+    ///```(102.110)```, a single base somewhere between the two
+    Within(u32, u32),
+}
+
+impl Position {
+    pub fn value(&self) -> u32 {
+        match self {
+            Position::Exact(v) | Position::Before(v) | Position::After(v) => *v,
+            Position::OneOf(values) => values.first().copied().unwrap_or(0),
+            Position::Within(first, _) => *first,
+        }
+    }
+    ///the position after moving base ```origin``` of a circular sequence of the given length to position 1
+    pub fn rotate(&self, origin: u32, length: u32) -> Self {
+        let shift = |v: u32| (v + length - origin) % length + 1;
+        match self {
+            Position::Exact(v) => Position::Exact(shift(*v)),
+            Position::Before(v) => Position::Before(shift(*v)),
+            Position::After(v) => Position::After(shift(*v)),
+            Position::OneOf(values) => Position::OneOf(values.iter().map(|v| shift(*v)).collect()),
+            Position::Within(first, last) => Position::Within(shift(*first), shift(*last)),
+        }
+    }
+}
+
+impl From<&Position> for RangeValue {
+    fn from(position: &Position) -> Self {
+        match position {
+            Position::Before(v) => RangeValue::LessThan(*v),
+            Position::After(v) => RangeValue::GreaterThan(*v),
+            other => RangeValue::Exact(other.value()),
+        }
+    }
+}
+
+impl From<&RangeValue> for Position {
+    fn from(value: &RangeValue) -> Self {
+        match value {
+            RangeValue::Exact(v) => Position::Exact(*v),
+            RangeValue::LessThan(v) => Position::Before(*v),
+            RangeValue::GreaterThan(v) => Position::After(*v),
+        }
+    }
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Position::Exact(v) => write!(f, "{}", v),
+            Position::Before(v) => write!(f, "<{}", v),
+            Position::After(v) => write!(f, ">{}", v),
+            Position::OneOf(values) => {
+                let values: Vec<String> = values.iter().map(|v| v.to_string()).collect();
+                write!(f, "one-of({})", values.join(","))
+            }
+            Position::Within(first, last) => write!(f, "({}.{})", first, last),
+        }
+    }
+}
+
+///a feature location, strand is 1 for the forward and -1 for the reverse strand
+///the parts of a join or order are kept in biological order, so complement(join(a,b)) is stored as join(complement(b),complement(a))
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Location {
+    Range {
+        start: Position,
+        end: Position,
+        strand: i8,
+    },
+    Single {
+        position: Position,
+        strand: i8,
+    },
+    ///a site between two bases, ```123^124```
+    Between {
+        before: u32,
+        after: u32,
+        strand: i8,
+    },
+    Join(Vec<Location>),
+    Order(Vec<Location>),
+    ///a location on another record, ```J00194.1:100..202```
+    Remote {
+        accession: String,
+        location: Box<Location>,
+    },
+}
+
+impl Location {
+    ///a simple span from start to end, with the fuzzy ends of the ```RangeValue```s
+    pub fn span(start: &RangeValue, end: &RangeValue, strand: i8) -> Self {
+        Location::Range {
+            start: start.into(),
+            end: end.into(),
+            strand,
+        }
+    }
+    ///the same location on the opposite strand
+    pub fn complement(&self) -> Self {
+        match self {
+            Location::Range { start, end, strand } => Location::Range {
+                start: start.clone(),
+                end: end.clone(),
+                strand: -strand,
+            },
+            Location::Single { position, strand } => Location::Single {
+                position: position.clone(),
+                strand: -strand,
+            },
+            Location::Between {
+                before,
+                after,
+                strand,
+            } => Location::Between {
+                before: *before,
+                after: *after,
+                strand: -strand,
+            },
+            Location::Join(parts) => {
+                Location::Join(parts.iter().rev().map(|p| p.complement()).collect())
+            }
+            Location::Order(parts) => {
+                Location::Order(parts.iter().rev().map(|p| p.complement()).collect())
+            }
+            Location::Remote {
+                accession,
+                location,
+            } => Location::Remote {
+                accession: accession.clone(),
+                location: Box::new(location.complement()),
+            },
+        }
+    }
+    ///the simple locations (spans, single bases, sites and remote references) in biological order
+    pub fn parts(&self) -> Vec<&Location> {
+        match self {
+            Location::Join(parts) | Location::Order(parts) => {
+                parts.iter().flat_map(|p| p.parts()).collect()
+            }
+            other => vec![other],
+        }
+    }
+    ///true for a join or order of more than one part
+    pub fn is_compound(&self) -> bool {
+        self.parts().len() > 1
+    }
+    pub fn is_remote(&self) -> bool {
+        matches!(self, Location::Remote { .. })
+    }
+    ///-1 when every part is on the reverse strand, otherwise 1
+    pub fn strand(&self) -> i8 {
+        let parts = self.parts();
+        if !parts.is_empty() && parts.iter().all(|p| p.part_strand() == -1) {
+            -1
+        } else {
+            1
+        }
+    }
+    fn part_strand(&self) -> i8 {
+        match self {
+            Location::Range { strand, .. }
+            | Location::Single { strand, .. }
+            | Location::Between { strand, .. } => *strand,
+            Location::Remote { location, .. } => location.strand(),
+            Location::Join(_) | Location::Order(_) => self.strand(),
+        }
+    }
+    //the lower and upper bound of a simple location
+    fn bounds(&self) -> Option<(&Position, &Position)> {
+        match self {
+            Location::Range { start, end, .. } => Some((start, end)),
+            Location::Single { position, .. } => Some((position, position)),
+            _ => None,
+        }
+    }
+    //the parts on this record, or every part when the whole location is remote
+    fn local_parts(&self) -> Vec<&Location> {
+        let parts = self.parts();
+        let local: Vec<&Location> = parts.iter().copied().filter(|p| !p.is_remote()).collect();
+        if local.is_empty() {
+            parts
+                .into_iter()
+                .map(|p| match p {
+                    Location::Remote { location, .. } => location.as_ref(),
+                    other => other,
+                })
+                .collect()
+        } else {
+            local
+        }
+    }
+    ///the lowest base covered by the location on this record
+    pub fn start(&self) -> u32 {
+        self.start_value().get_value()
+    }
+    ///the highest base covered by the location on this record
+    pub fn end(&self) -> u32 {
+        self.end_value().get_value()
+    }
+    ///the lowest bound of the location, keeping a fuzzy ```<``` start
+    pub fn start_value(&self) -> RangeValue {
+        self.local_parts()
+            .iter()
+            .flat_map(|p| match p {
+                Location::Between { before, .. } => vec![RangeValue::Exact(*before)],
+                other => other
+                    .bounds()
+                    .map(|(start, _)| vec![RangeValue::from(start)])
+                    .unwrap_or_else(|| vec![other.start_value()]),
+            })
+            .min_by_key(|v| v.get_value())
+            .unwrap_or(RangeValue::Exact(0))
+    }
+    ///the highest bound of the location, keeping a fuzzy ```>``` end
+    pub fn end_value(&self) -> RangeValue {
+        self.local_parts()
+            .iter()
+            .flat_map(|p| match p {
+                Location::Between { after, .. } => vec![RangeValue::Exact(*after)],
+                other => other
+                    .bounds()
+                    .map(|(_, end)| vec![RangeValue::from(end)])
+                    .unwrap_or_else(|| vec![other.end_value()]),
+            })
+            .max_by_key(|v| v.get_value())
+            .unwrap_or(RangeValue::Exact(0))
+    }
+    ///the (start, end, strand) coordinates of each part on this record, in biological order
+    pub fn segments(&self) -> Vec<(u32, u32, i8)> {
+        self.parts()
+            .into_iter()
+            .filter(|p| !p.is_remote())
+            .map(|p| (p.start(), p.end(), p.part_strand()))
+            .collect()
+    }
+    ///the sequence of the location, the parts are concatenated in biological order and reverse complemented on the reverse strand
+    ///sites between two bases and parts on other records add no sequence, ends past the sequence are cut to its length
+    ///a span whose start is after its end, such as ```4641000..250```, runs over the origin of a circular sequence
+    pub fn extract(&self, sequence: &str) -> String {
+        let mut extracted = String::new();
+        for part in self.parts() {
+            let (start, end) = match part.bounds() {
+                Some((start, end)) => (start.value() as usize, end.value() as usize),
+                None => continue,
+            };
+            let slice = match start > end {
+                true => format!(
+                    "{}{}",
+                    sequence.get(start - 1..).unwrap_or_default(),
+                    sequence.get(..end.min(sequence.len())).unwrap_or_default()
+                ),
+                false => match sequence.get(start.max(1) - 1..end.min(sequence.len())) {
+                    Some(slice) => slice.to_string(),
+                    None => continue,
+                },
+            };
+            if part.part_strand() == -1 {
+                extracted.push_str(&String::from_utf8_lossy(&revcomp(slice.as_bytes())));
+            } else {
+                extracted.push_str(&slice);
+            }
+        }
+        extracted
+    }
+    ///true when the 5' end of the location is partial, a ```<``` start on the forward strand or a ```>``` end on the reverse strand
+    pub fn partial_start(&self) -> bool {
+        let first = self.parts().into_iter().find(|p| p.bounds().is_some());
+        match first.and_then(|p| p.bounds().map(|bounds| (p.part_strand(), bounds))) {
+            Some((-1, (_, end))) => matches!(end, Position::After(_)),
+            Some((_, (start, _))) => matches!(start, Position::Before(_)),
+            None => false,
+        }
+    }
+    ///true when the location runs over the origin of a circular sequence, either as a span such as ```4641000..250```
+    ///or as parts that go back to the start of the sequence, such as ```join(4641000..4641652,1..250)```
+    pub fn wraps_origin(&self) -> bool {
+        let parts: Vec<&Location> = self
+            .parts()
+            .into_iter()
+            .filter(|p| p.bounds().is_some())
+            .collect();
+        let backwards = |p: &&Location| p.bounds().is_some_and(|(s, e)| s.value() > e.value());
+        parts.iter().any(backwards)
+            || parts.windows(2).any(|pair| match pair[0].part_strand() {
+                -1 => pair[1].start() > pair[0].end(),
+                _ => pair[1].end() < pair[0].start(),
+            })
+    }
+    ///the lowest and highest bound of the location on a circular sequence of the given length
+    ///for a location running over the origin the bases after the origin are counted on from the length, so ```join(4641000..4641652,1..250)```
+    ///on a 4641652 bp sequence is bounded by 4641000 and 4641902 as in GFF3, other locations have the bounds of ```start_value``` and ```end_value```
+    pub fn circular_bounds(&self, length: u32) -> (RangeValue, RangeValue) {
+        if !self.wraps_origin() {
+            return (self.start_value(), self.end_value());
+        }
+        //walk the parts along the forward strand, adding the length once the coordinates go back to the start
+        let mut parts: Vec<(&Position, &Position)> = self
+            .parts()
+            .into_iter()
+            .filter_map(|p| p.bounds())
+            .collect();
+        if self.strand() == -1 {
+            parts.reverse();
+        }
+        let shifted = |position: &Position, offset: u32| match RangeValue::from(position) {
+            RangeValue::Exact(v) => RangeValue::Exact(v + offset),
+            RangeValue::LessThan(v) => RangeValue::LessThan(v + offset),
+            RangeValue::GreaterThan(v) => RangeValue::GreaterThan(v + offset),
+        };
+        let mut offset = 0;
+        let mut previous = 0;
+        let mut bounds: Vec<RangeValue> = Vec::new();
+        for (start, end) in parts {
+            if start.value() + offset < previous {
+                offset += length;
+            }
+            bounds.push(shifted(start, offset));
+            if end.value() < start.value() {
+                offset += length;
+            }
+            bounds.push(shifted(end, offset));
+            previous = end.value() + offset;
+        }
+        let start = bounds.iter().min_by_key(|v| v.get_value()).cloned();
+        let end = bounds.iter().max_by_key(|v| v.get_value()).cloned();
+        (
+            start.unwrap_or(RangeValue::Exact(0)),
+            end.unwrap_or(RangeValue::Exact(0)),
+        )
+    }
+    ///the location after moving base ```origin``` of a circular sequence of the given length to position 1
+    ///a span that now runs over the origin is split into a join, and the parts of a join that met at the old origin are merged back into one span
+    pub fn rotate(&self, origin: u32, length: u32) -> Location {
+        if origin == 1 {
+            return self.clone();
+        }
+        match self {
+            //a span over the whole sequence stays in place
+            Location::Range { start, end, .. } if start.value() == 1 && end.value() == length => {
+                self.clone()
+            }
+            Location::Range { start, end, strand } => {
+                let (start, end) = (start.rotate(origin, length), end.rotate(origin, length));
+                if start.value() <= end.value() {
+                    return Location::Range {
+                        start,
+                        end,
+                        strand: *strand,
+                    };
+                }
+                let to_end = Location::Range {
+                    start,
+                    end: Position::Exact(length),
+                    strand: *strand,
+                };
+                let from_start = Location::Range {
+                    start: Position::Exact(1),
+                    end,
+                    strand: *strand,
+                };
+                match strand {
+                    -1 => Location::Join(vec![from_start, to_end]),
+                    _ => Location::Join(vec![to_end, from_start]),
+                }
+            }
+            Location::Single { position, strand } => Location::Single {
+                position: position.rotate(origin, length),
+                strand: *strand,
+            },
+            Location::Between {
+                before,
+                after,
+                strand,
+            } => Location::Between {
+                before: Position::Exact(*before).rotate(origin, length).value(),
+                after: Position::Exact(*after).rotate(origin, length).value(),
+                strand: *strand,
+            },
+            Location::Join(parts) | Location::Order(parts) => {
+                let mut rotated: Vec<Location> = Vec::new();
+                for (i, part) in parts.iter().enumerate() {
+                    let mut pieces: Vec<Location> = part
+                        .rotate(origin, length)
+                        .parts()
+                        .into_iter()
+                        .cloned()
+                        .collect();
+                    if i > 0 && meet_at_origin(&parts[i - 1], part, length) {
+                        if let (Some(last), Some(first)) = (rotated.pop(), pieces.first()) {
+                            pieces[0] = last.merge(first);
+                        }
+                    }
+                    rotated.extend(pieces);
+                }
+                match (rotated.len(), self) {
+                    (1, Location::Join(_)) => rotated.remove(0),
+                    (_, Location::Join(_)) => Location::Join(rotated),
+                    _ => Location::Order(rotated),
+                }
+            }
+            Location::Remote { .. } => self.clone(),
+        }
+    }
+    //one span from two contiguous spans in biological order
+    fn merge(&self, next: &Location) -> Location {
+        match (self, next) {
+            (Location::Range { start, strand, .. }, Location::Range { end: next_end, .. })
+                if *strand != -1 =>
+            {
+                Location::Range {
+                    start: start.clone(),
+                    end: next_end.clone(),
+                    strand: *strand,
+                }
+            }
+            (
+                Location::Range { end, strand, .. },
+                Location::Range {
+                    start: next_start, ..
+                },
+            ) => Location::Range {
+                start: next_start.clone(),
+                end: end.clone(),
+                strand: *strand,
+            },
+            _ => self.clone(),
+        }
+    }
+}
+
+//true when two consecutive parts of a join are split by the origin of a circular sequence, e.g. 4641000..4641652 and 1..250
+fn meet_at_origin(previous: &Location, next: &Location, length: u32) -> bool {
+    match (previous, next) {
+        (
+            Location::Range {
+                start, end, strand, ..
+            },
+            Location::Range {
+                start: next_start,
+                end: next_end,
+                strand: next_strand,
+            },
+        ) if strand == next_strand => match strand {
+            -1 => start.value() == 1 && next_end.value() == length,
+            _ => end.value() == length && next_start.value() == 1,
+        },
+        _ => false,
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        //a location entirely on the reverse strand is written as complement() of its forward strand form
+        if self.strand() == -1 && !self.is_remote() {
+            return write!(f, "complement({})", self.complement());
+        }
+        match self {
+            Location::Range { start, end, .. } => write!(f, "{}..{}", start, end),
+            Location::Single { position, .. } => write!(f, "{}", position),
+            Location::Between { before, after, .. } => write!(f, "{}^{}", before, after),
+            Location::Join(parts) | Location::Order(parts) => {
+                let name = if matches!(self, Location::Join(_)) {
+                    "join"
+                } else {
+                    "order"
+                };
+                let parts: Vec<String> = parts.iter().map(|p| p.to_string()).collect();
+                write!(f, "{}({})", name, parts.join(","))
+            }
+            Location::Remote {
+                accession,
+                location,
+            } => write!(f, "{}:{}", accession, location),
+        }
+    }
+}
+
+impl FromStr for Location {
+    type Err = anyhow::Error;
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        parse_location(text)
+    }
+}
+
+///parses an INSDC location, whitespace (e.g. from a location wrapped over several lines) is ignored
+pub fn parse_location(text: &str) -> Result<Location, anyhow::Error> {
+    let cleaned: String = text.chars().filter(|c| !c.is_whitespace()).collect();
+    let mut parser = LocationParser {
+        text: &cleaned,
+        pos: 0,
+    };
+    let location = parser.location()?;
+    if parser.pos != cleaned.len() {
+        return Err(anyhow!(
+            "unexpected '{}' in location {}",
+            &cleaned[parser.pos..],
+            text
+        ));
+    }
+    Ok(location)
+}
+
+//recursive descent parser over a location with the whitespace removed
+struct LocationParser<'a> {
+    text: &'a str,
+    pos: usize,
+}
+
+impl LocationParser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+    fn eat(&mut self, token: &str) -> bool {
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+    fn expect(&mut self, token: &str) -> Result<(), anyhow::Error> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "expected '{}' at '{}' in location {}",
+                token,
+                self.rest(),
+                self.text
+            ))
+        }
+    }
+    fn location(&mut self) -> Result<Location, anyhow::Error> {
+        if self.eat("complement(") {
+            let inner = self.location()?;
+            self.expect(")")?;
+            return Ok(inner.complement());
+        }
+        if self.eat("join(") {
+            return Ok(Location::Join(self.list()?));
+        }
+        if self.eat("order(") {
+            return Ok(Location::Order(self.list()?));
+        }
+        if let Some(accession) = self.accession() {
+            let location = self.location()?;
+            return Ok(Location::Remote {
+                accession,
+                location: Box::new(location),
+            });
+        }
+        self.simple()
+    }
+    fn list(&mut self) -> Result<Vec<Location>, anyhow::Error> {
+        let mut parts = vec![self.location()?];
+        while self.eat(",") {
+            parts.push(self.location()?);
+        }
+        self.expect(")")?;
+        Ok(parts)
+    }
+    //an accession such as J00194.1 followed by a colon
+    fn accession(&mut self) -> Option<String> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_' || c == '.'))
+            .unwrap_or(rest.len());
+        let accession = &rest[..length];
+        if rest[length..].starts_with(':') && accession.chars().any(|c| c.is_ascii_alphabetic()) {
+            let accession = accession.to_string();
+            self.pos += length + 1;
+            Some(accession)
+        } else {
+            None
+        }
+    }
+    fn simple(&mut self) -> Result<Location, anyhow::Error> {
+        let first = self.position()?;
+        if self.eat("..") {
+            let end = self.position()?;
+            return Ok(Location::Range {
+                start: first,
+                end,
+                strand: 1,
+            });
+        }
+        if self.eat("^") {
+            let after = self.number()?;
+            return Ok(Location::Between {
+                before: first.value(),
+                after,
+                strand: 1,
+            });
+        }
+        Ok(Location::Single {
+            position: first,
+            strand: 1,
+        })
+    }
+    fn position(&mut self) -> Result<Position, anyhow::Error> {
+        if self.eat("<") {
+            return Ok(Position::Before(self.number()?));
+        }
+        if self.eat(">") {
+            return Ok(Position::After(self.number()?));
+        }
+        if self.eat("one-of(") {
+            let mut values = vec![self.number()?];
+            while self.eat(",") {
+                values.push(self.number()?);
+            }
+            self.expect(")")?;
+            return Ok(Position::OneOf(values));
+        }
+        if self.eat("(") {
+            let first = self.number()?;
+            self.expect(".")?;
+            let last = self.number()?;
+            self.expect(")")?;
+            return Ok(Position::Within(first, last));
+        }
+        Ok(Position::Exact(self.number()?))
+    }
+    fn number(&mut self) -> Result<u32, anyhow::Error> {
+        let rest = self.rest();
+        let length = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        if length == 0 {
+            return Err(anyhow!(
+                "expected a number at '{}' in location {}",
+                rest,
+                self.text
+            ));
+        }
+        let value = rest[..length].parse::<u32>()?;
+        self.pos += length;
+        Ok(value)
+    }
+}
+
+///checks whether the location text of a feature continues on the next line, i.e. it has unclosed brackets or ends with a comma
+pub fn location_continues(text: &str) -> bool {
+    let text = text.trim_end();
+    text.ends_with(',') || text.matches('(').count() > text.matches(')').count()
+}
+
+///formats the location of a feature for a feature table, long locations are wrapped after a comma
+///first_prefix starts the first line (e.g. the feature key), prefix starts each continuation line
+pub fn format_location(first_prefix: &str, prefix: &str, location: &Location) -> String {
+    let text = location.to_string();
+    let width = line_width(prefix);
+    let mut lines: Vec<String> = Vec::new();
+    let mut line = first_prefix.to_string();
+    for piece in text.split_inclusive(',') {
+        if line.len() + piece.len() > width && line.len() > prefix.len() {
+            lines.push(line);
+            line = prefix.to_string();
+        }
+        line.push_str(piece);
+    }
+    lines.push(line);
+    lines.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_simple_locations() {
+        let location = parse_location("340..565").unwrap();
+        assert_eq!(
+            location,
+            Location::Range {
+                start: Position::Exact(340),
+                end: Position::Exact(565),
+                strand: 1
+            }
+        );
+        let fuzzy = parse_location("<1..>913").unwrap();
+        assert_eq!(fuzzy.start_value(), RangeValue::LessThan(1));
+        assert_eq!(fuzzy.end_value(), RangeValue::GreaterThan(913));
+        assert_eq!(fuzzy.to_string(), "<1..>913");
+        let single = parse_location("complement(467)").unwrap();
+        assert_eq!(
+            (single.start(), single.end(), single.strand()),
+            (467, 467, -1)
+        );
+        let site = parse_location("123^124").unwrap();
+        assert_eq!((site.start(), site.end()), (123, 124));
+        assert_eq!(
+            parse_location("one-of(102,104)..200").unwrap().to_string(),
+            "one-of(102,104)..200"
+        );
+        assert_eq!(parse_location("(102.110)").unwrap().start(), 102);
+    }
+    #[test]
+    fn test_parse_compound_locations() {
+        let location = parse_location("complement(join(2691..4571,\n 4918..5163))").unwrap();
+        let parts = location.parts();
+        assert_eq!(parts.len(), 2);
+        assert_eq!((parts[0].start(), parts[0].strand()), (4918, -1));
+        assert_eq!((location.start(), location.end()), (2691, 5163));
+        assert_eq!(
+            location.to_string(),
+            "complement(join(2691..4571,4918..5163))"
+        );
+        let mixed = parse_location("join(complement(4918..5163),2691..4571)").unwrap();
+        assert_eq!(mixed.strand(), 1);
+        assert_eq!(mixed.to_string(), "join(complement(4918..5163),2691..4571)");
+        let order = parse_location("order(1..10,20..30)").unwrap();
+        assert!(order.is_compound());
+        assert_eq!(order.to_string(), "order(1..10,20..30)");
+        let remote = parse_location("join(J00194.1:100..202,1..245)").unwrap();
+        assert!(remote.parts()[0].is_remote());
+        assert_eq!((remote.start(), remote.end()), (1, 245));
+        assert_eq!(remote.to_string(), "join(J00194.1:100..202,1..245)");
+        assert!(parse_location("join(1..10,").is_err());
+        assert!(parse_location("1..10x").is_err());
+    }
+    #[test]
+    fn test_extract() {
+        let sequence = "AAACCCGGGTTT";
+        let joined = parse_location("join(1..3,7..9)").unwrap();
+        assert_eq!(joined.extract(sequence), "AAAGGG");
+        assert_eq!(joined.segments(), vec![(1, 3, 1), (7, 9, 1)]);
+        let reverse = parse_location("complement(join(1..3,7..9))").unwrap();
+        assert_eq!(reverse.extract(sequence), "CCCTTT");
+        assert_eq!(reverse.segments(), vec![(7, 9, -1), (1, 3, -1)]);
+        let origin = parse_location("join(10..12,1..3)").unwrap();
+        assert_eq!(origin.extract(sequence), "TTTAAA");
+        assert!(parse_location("<1..9").unwrap().partial_start());
+        assert!(!parse_location("complement(<1..9)").unwrap().partial_start());
+        assert!(parse_location("complement(1..>9)").unwrap().partial_start());
+    }
+    #[test]
+    fn test_circular_locations() {
+        let sequence = "AAACCCGGGTTT";
+        let origin = parse_location("join(10..12,1..3)").unwrap();
+        assert!(origin.wraps_origin());
+        assert_eq!(
+            origin.circular_bounds(12),
+            (RangeValue::Exact(10), RangeValue::Exact(15))
+        );
+        let span = parse_location("complement(10..3)").unwrap();
+        assert!(span.wraps_origin());
+        assert_eq!(span.extract(sequence), "TTTAAA");
+        let reverse = parse_location("complement(join(10..12,1..3))").unwrap();
+        assert!(reverse.wraps_origin());
+        assert_eq!(reverse.extract(sequence), "TTTAAA");
+        assert!(!parse_location("complement(join(1..3,7..9))")
+            .unwrap()
+            .wraps_origin());
+        //moving base 10 to position 1 closes the join over the old origin
+        assert_eq!(origin.rotate(10, 12).to_string(), "1..6");
+        assert_eq!(reverse.rotate(10, 12).to_string(), "complement(1..6)");
+        //a span over the new origin is split into a join
+        let rotated = parse_location("2..5").unwrap().rotate(4, 12);
+        assert_eq!(rotated.to_string(), "join(11..12,1..2)");
+        assert_eq!(rotated.rotate(10, 12).to_string(), "2..5");
+        let rotated = parse_location("complement(<2..5)").unwrap().rotate(4, 12);
+        assert_eq!(rotated.to_string(), "complement(join(<11..12,1..2))");
+        assert_eq!(
+            parse_location("1..12").unwrap().rotate(4, 12).to_string(),
+            "1..12"
+        );
+    }
+    #[test]
+    fn test_format_location() {
+        assert!(location_continues("join(1..10,"));
+        assert!(!location_continues("complement(1..10)"));
+        let parts: Vec<String> = (0..20)
+            .map(|i| format!("{}..{}", i * 1000 + 1, i * 1000 + 500))
+            .collect();
+        let location = parse_location(&format!("join({})", parts.join(","))).unwrap();
+        let formatted = format_location("     CDS             ", &" ".repeat(21), &location);
+        assert!(formatted.lines().count() > 1);
+        assert!(formatted.lines().all(|l| l.len() <= 79));
+        assert_eq!(parse_location(&formatted[21..]).unwrap(), location);
+    }
+}
@@ -0,0 +1,813 @@
+//! # PAF alignments and feature liftover
+//!
+//! Reads the Pairwise mApping Format of minimap2 into ```Record```s with 0-based, half-open query and target coordinates as in the file,
+//! the optional fields typed as in SAM, the ```cg:Z``` CIGAR parsed with ```sam::parse_cigar``` and the ```cs:Z``` difference string into ```CsOp```s.
+//! ```Record::aligned_blocks``` gives the gapless blocks of an alignment, from its CIGAR or its cs string.
+//!
+//! ```liftover``` carries the features of a ```gbk::Record``` over to a new assembly, the record being the query of the alignments,
+//! e.g. ```minimap2 -cx asm5 --cs new_assembly.fa old_assembly.fa```. Each feature is placed through the alignment covering most of its bases
+//! and reported as ```Clean``` (every base aligned and no indel changing its length), ```Partial``` or ```Failed```.
+//! ```apply_liftover``` adds the lifted features, with their gene, product and qualifiers, to the record of the new assembly.
+//!
+//!```rust
+//! use microBioRust::paf::Reader;
+//!
+//! let text = "old_ctg\t913\t5\t400\t+\tnew_ctg\t6000\t1005\t1402\t390\t397\t60\tNM:i:7\tcg:Z:100M2D295M\tcs:Z::100-ac:295\n";
+//! let alignments: Vec<_> = Reader::new(text.as_bytes()).records().map(|r| r.unwrap()).collect();
+//! assert_eq!((alignments[0].query_start, alignments[0].target_end), (5, 1402));
+//! assert_eq!(alignments[0].cigar.as_ref().map(|c| c.len()), Some(3));
+//! assert_eq!(alignments[0].aligned_blocks(), vec![(5, 1005, 100), (105, 1107, 295)]);
+//! assert_eq!(alignments[0].lift(9), Some(1009));
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk::Record as GbkRecord;
+use crate::location::{Location, Position};
+use crate::record::RangeValue;
+use crate::sam::{parse_cigar, CigarOp, TagValue};
+use crate::translation::{genetic_code, DEFAULT_TABLE};
+use anyhow::{anyhow, Context};
+use std::{fmt, io, path::Path};
+
+///the (query start, target start, length) of a gapless block of an alignment
+pub type Block = (u32, u32, u32);
+
+///an operation of a ```cs``` difference string
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CsOp {
+    ///```:10```, identical bases
+    Match(u32),
+    ///```=ACGT```, identical bases written out in the long form
+    Identical(String),
+    ///```*ag```, the target base and the query base
+    Substitution(char, char),
+    ///```+ac```, bases in the query only
+    Insertion(String),
+    ///```-ac```, bases in the target only
+    Deletion(String),
+    ///```~gt20ag```, an intron of the target with its splice signals
+    Intron(String, u32, String),
+}
+
+impl CsOp {
+    ///the number of query and target bases the operation moves along
+    pub fn lengths(&self) -> (u32, u32) {
+        match self {
+            CsOp::Match(length) => (*length, *length),
+            CsOp::Identical(bases) => (bases.len() as u32, bases.len() as u32),
+            CsOp::Substitution(..) => (1, 1),
+            CsOp::Insertion(bases) => (bases.len() as u32, 0),
+            CsOp::Deletion(bases) => (0, bases.len() as u32),
+            CsOp::Intron(_, length, _) => (0, *length),
+        }
+    }
+}
+
+impl fmt::Display for CsOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CsOp::Match(length) => write!(f, ":{}", length),
+            CsOp::Identical(bases) => write!(f, "={}", bases),
+            CsOp::Substitution(target, query) => write!(f, "*{}{}", target, query),
+            CsOp::Insertion(bases) => write!(f, "+{}", bases),
+            CsOp::Deletion(bases) => write!(f, "-{}", bases),
+            CsOp::Intron(donor, length, acceptor) => write!(f, "~{}{}{}", donor, length, acceptor),
+        }
+    }
+}
+
+///reads a ```cs``` difference string, short (```:10```) or long (```=ACGT```) form
+pub fn parse_cs(text: &str) -> Result<Vec<CsOp>, anyhow::Error> {
+    let mut operations = Vec::new();
+    let chars: Vec<char> = text.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let kind = chars[i];
+        let end = chars[i + 1..]
+            .iter()
+            .position(|c| matches!(c, ':' | '=' | '*' | '+' | '-' | '~'))
+            .map_or(chars.len(), |p| i + 1 + p);
+        let value: String = chars[i + 1..end].iter().collect();
+        let operation = match kind {
+            ':' => CsOp::Match(
+                value
+                    .parse()
+                    .map_err(|_| anyhow!("bad cs match length {:?} in {:?}", value, text))?,
+            ),
+            '=' => CsOp::Identical(value),
+            '*' => match value.chars().collect::<Vec<char>>()[..] {
+                [target, query] => CsOp::Substitution(target, query),
+                _ => return Err(anyhow!("bad cs substitution {:?} in {:?}", value, text)),
+            },
+            '+' => CsOp::Insertion(value),
+            '-' => CsOp::Deletion(value),
+            '~' => {
+                //two bases, the intron length and two bases
+                let bases: Vec<char> = value.chars().collect();
+                let is_base = |c: &char| c.is_ascii_alphabetic();
+                match bases.len() > 4
+                    && bases[..2].iter().all(is_base)
+                    && bases[bases.len() - 2..].iter().all(is_base)
+                    && bases[2..bases.len() - 2].iter().all(|c| c.is_ascii_digit())
+                {
+                    true => CsOp::Intron(
+                        bases[..2].iter().collect(),
+                        value[2..value.len() - 2]
+                            .parse()
+                            .map_err(|_| anyhow!("bad cs intron {:?} in {:?}", value, text))?,
+                        bases[bases.len() - 2..].iter().collect(),
+                    ),
+                    false => return Err(anyhow!("bad cs intron {:?} in {:?}", value, text)),
+                }
+            }
+            _ => return Err(anyhow!("bad cs operation {:?} in {:?}", kind, text)),
+        };
+        if matches!(kind, '=' | '+' | '-') && operation.lengths() == (0, 0) {
+            return Err(anyhow!(
+                "cs operation {:?} has no bases in {:?}",
+                kind,
+                text
+            ));
+        }
+        operations.push(operation);
+        i = end;
+    }
+    Ok(operations)
+}
+
+///a line of a PAF file, coordinates count from 0 and the ends are not included
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    pub query_name: String,
+    pub query_length: u32,
+    pub query_start: u32,
+    pub query_end: u32,
+    ///1 when the query and target are on the same strand, -1 when the query is reverse complemented
+    pub strand: i8,
+    pub target_name: String,
+    pub target_length: u32,
+    pub target_start: u32,
+    pub target_end: u32,
+    ///the number of matching bases and of bases in the alignment, gaps included
+    pub matches: u32,
+    pub block_length: u32,
+    pub mapq: u8,
+    ///the optional fields in file order
+    pub tags: Vec<(String, TagValue)>,
+    ///the ```cg``` CIGAR, I for bases in the query only and D for bases in the target only
+    pub cigar: Option<Vec<CigarOp>>,
+    pub cs: Option<Vec<CsOp>>,
+}
+
+impl Record {
+    ///reads a tab separated PAF line
+    pub fn from_line(line: &str) -> Result<Self, anyhow::Error> {
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 12 {
+            return Err(anyhow!(
+                "expected at least 12 tab separated columns, found {}",
+                columns.len()
+            ));
+        }
+        let number = |index: usize, name: &str| -> Result<u32, anyhow::Error> {
+            columns[index]
+                .parse::<u32>()
+                .map_err(|e| anyhow!("bad {} {:?}: {}", name, columns[index], e))
+        };
+        let mut tags = Vec::new();
+        for tag in columns[12..].iter().filter(|t| !t.is_empty()) {
+            let mut parts = tag.splitn(3, ':');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(name), Some(kind), Some(value)) if name.len() == 2 => {
+                    tags.push((name.to_string(), TagValue::parse(kind, value)?))
+                }
+                _ => return Err(anyhow!("optional field {:?} is not TAG:TYPE:VALUE", tag)),
+            }
+        }
+        let text_tag = |name: &str| {
+            tags.iter()
+                .find(|(n, _)| n == name)
+                .and_then(|(_, v)| v.as_str())
+        };
+        let cigar = text_tag("cg").map(parse_cigar).transpose()?;
+        let cs = text_tag("cs").map(parse_cs).transpose()?;
+        let record = Record {
+            query_name: columns[0].to_string(),
+            query_length: number(1, "query length")?,
+            query_start: number(2, "query start")?,
+            query_end: number(3, "query end")?,
+            strand: match columns[4] {
+                "+" => 1,
+                "-" => -1,
+                strand => return Err(anyhow!("bad strand {:?}", strand)),
+            },
+            target_name: columns[5].to_string(),
+            target_length: number(6, "target length")?,
+            target_start: number(7, "target start")?,
+            target_end: number(8, "target end")?,
+            matches: number(9, "matches")?,
+            block_length: number(10, "block length")?,
+            mapq: number(11, "mapping quality")?.min(255) as u8,
+            tags,
+            cigar,
+            cs,
+        };
+        if record.query_end < record.query_start || record.target_end < record.target_start {
+            return Err(anyhow!("an alignment end is before its start"));
+        }
+        //the CIGAR and the cs string must walk the whole alignment
+        let spans = (
+            record.query_end - record.query_start,
+            record.target_end - record.target_start,
+        );
+        if let Some(cigar) = &record.cigar {
+            let query: u32 = cigar
+                .iter()
+                .filter(|o| o.consumes_query())
+                .map(|o| o.length)
+                .sum();
+            let target: u32 = cigar
+                .iter()
+                .filter(|o| o.consumes_reference())
+                .map(|o| o.length)
+                .sum();
+            if (query, target) != spans {
+                return Err(anyhow!(
+                    "the CIGAR covers {} query and {} target bases, the alignment {} and {}",
+                    query,
+                    target,
+                    spans.0,
+                    spans.1
+                ));
+            }
+        }
+        if let Some(cs) = &record.cs {
+            let (query, target) = cs
+                .iter()
+                .map(|o| o.lengths())
+                .fold((0, 0), |(q, t), (oq, ot)| (q + oq, t + ot));
+            if (query, target) != spans {
+                return Err(anyhow!(
+                    "the cs string covers {} query and {} target bases, the alignment {} and {}",
+                    query,
+                    target,
+                    spans.0,
+                    spans.1
+                ));
+            }
+        }
+        Ok(record)
+    }
+    ///the value of an optional field, e.g. ```NM``` or ```tp```
+    pub fn tag(&self, name: &str) -> Option<&TagValue> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+    ///the share of matching bases in the alignment, from 0 to 1
+    pub fn identity(&self) -> f64 {
+        match self.block_length {
+            0 => 0.0,
+            length => self.matches as f64 / length as f64,
+        }
+    }
+    ///the (query start, target start, length) of each gapless block, with query starts on the forward strand of the query
+    ///taken from the CIGAR, else the cs string, else the whole alignment when query and target spans have the same length
+    pub fn aligned_blocks(&self) -> Vec<Block> {
+        //(query bases, target bases, aligned) steps along the target
+        let steps: Vec<(u32, u32, bool)> = match (&self.cigar, &self.cs) {
+            (Some(cigar), _) => cigar
+                .iter()
+                .map(|o| {
+                    let query = if o.consumes_query() { o.length } else { 0 };
+                    let target = if o.consumes_reference() { o.length } else { 0 };
+                    (query, target, matches!(o.kind, 'M' | '=' | 'X'))
+                })
+                .collect(),
+            (None, Some(cs)) => cs
+                .iter()
+                .map(|o| {
+                    let (query, target) = o.lengths();
+                    (query, target, query == target)
+                })
+                .collect(),
+            (None, None) => {
+                let length = self.query_end - self.query_start;
+                match length == self.target_end - self.target_start {
+                    true => vec![(length, length, true)],
+                    false => Vec::new(),
+                }
+            }
+        };
+        let mut blocks: Vec<Block> = Vec::new();
+        let mut target = self.target_start;
+        //the query is walked backwards from its end on the reverse strand
+        let mut query = match self.strand {
+            -1 => self.query_end,
+            _ => self.query_start,
+        };
+        for (query_bases, target_bases, aligned) in steps {
+            if aligned && query_bases > 0 {
+                let block_start = match self.strand {
+                    -1 => query - query_bases,
+                    _ => query,
+                };
+                //adjacent steps such as =, X and = make one block
+                match blocks.last_mut() {
+                    Some(last)
+                        if last.1 + last.2 == target
+                            && self.strand == 1
+                            && last.0 + last.2 == block_start =>
+                    {
+                        last.2 += query_bases
+                    }
+                    Some(last)
+                        if last.1 + last.2 == target
+                            && self.strand == -1
+                            && block_start + query_bases == last.0 =>
+                    {
+                        last.0 = block_start;
+                        last.2 += query_bases
+                    }
+                    _ => blocks.push((block_start, target, query_bases)),
+                }
+            }
+            match self.strand {
+                -1 => query -= query_bases,
+                _ => query += query_bases,
+            }
+            target += target_bases;
+        }
+        blocks
+    }
+    ///the target position aligned to a query position (both from 0), None for bases in gaps or outside the alignment
+    pub fn lift(&self, position: u32) -> Option<u32> {
+        self.aligned_blocks()
+            .into_iter()
+            .find(|(query, _, length)| *query <= position && position < query + length)
+            .map(|(query, target, length)| match self.strand {
+                -1 => target + (query + length - 1 - position),
+                _ => target + (position - query),
+            })
+    }
+}
+
+///how much of a feature was carried over to the new assembly
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiftStatus {
+    ///every base aligned and each part keeps its length
+    Clean,
+    ///some bases in gaps or outside the alignment, or an indel changing the length of a part
+    Partial,
+    ///no aligned base
+    Failed,
+}
+
+///the liftover of one feature of the source record
+#[derive(Debug, Clone, PartialEq)]
+pub struct Liftover {
+    pub feature_type: String,
+    pub locus_tag: String,
+    pub status: LiftStatus,
+    ///the target sequence of the alignment used, None for a failed lift
+    pub target: Option<String>,
+    ///the location on the target, each part from its first to its last aligned base
+    pub location: Option<Location>,
+    ///the number of bases of the feature and of those aligned
+    pub length: u32,
+    pub aligned: u32,
+}
+
+//the 0-based, inclusive query ranges of the parts of a location in biological order, a part over the origin is split in two
+fn part_ranges(location: &Location, length: u32) -> Vec<((u32, u32), i8)> {
+    let mut ranges = Vec::new();
+    for (start, end, strand) in location.segments() {
+        match start <= end {
+            true => ranges.push(((start - 1, end - 1), strand)),
+            false => {
+                let (before, after) = ((start - 1, length - 1), (0, end - 1));
+                match strand {
+                    -1 => ranges.extend([(after, strand), (before, strand)]),
+                    _ => ranges.extend([(before, strand), (after, strand)]),
+                }
+            }
+        }
+    }
+    ranges
+}
+
+//lifts the parts of a feature through one alignment, giving the lifted parts and the number of aligned bases
+fn lift_parts(
+    alignment: &Record,
+    blocks: &[Block],
+    parts: &[((u32, u32), i8)],
+) -> (Vec<Location>, u32, bool) {
+    let mut lifted = Vec::new();
+    let mut aligned = 0;
+    let mut same_lengths = true;
+    let to_target = |query: u32, block: &Block| match alignment.strand {
+        -1 => block.1 + (block.0 + block.2 - 1 - query),
+        _ => block.1 + (query - block.0),
+    };
+    for ((start, end), strand) in parts.iter().copied() {
+        let mut bounds: Option<(u32, u32)> = None;
+        let mut part_aligned = 0;
+        for block in blocks.iter() {
+            let (lo, hi) = (start.max(block.0), end.min(block.0 + block.2 - 1));
+            if block.2 == 0 || lo > hi {
+                continue;
+            }
+            part_aligned += hi - lo + 1;
+            let (a, b) = (to_target(lo, block), to_target(hi, block));
+            let (a, b) = (a.min(b), a.max(b));
+            bounds = Some(match bounds {
+                Some((s, e)) => (s.min(a), e.max(b)),
+                None => (a, b),
+            });
+        }
+        aligned += part_aligned;
+        if let Some((s, e)) = bounds {
+            same_lengths &= e - s == end - start;
+            lifted.push(Location::Range {
+                start: Position::Exact(s + 1),
+                end: Position::Exact(e + 1),
+                strand: strand * alignment.strand,
+            });
+        }
+    }
+    (lifted, aligned, same_lengths)
+}
+
+///lifts every feature of a record over to the targets of the alignments whose query is the record (its name, accession or version)
+///each feature goes through the alignment aligning most of its bases, the features are given in the order of ```ordered_features```
+pub fn liftover(record: &GbkRecord, alignments: &[Record]) -> Vec<Liftover> {
+    let names: Vec<&str> = [record.id.as_str(), record.header.version.as_str()]
+        .into_iter()
+        .chain(record.header.accessions.iter().map(|a| a.as_str()))
+        .filter(|name| !name.is_empty())
+        .collect();
+    let length = record.sequence.len().max(record.length as usize) as u32;
+    let usable: Vec<(&Record, Vec<Block>)> = alignments
+        .iter()
+        .filter(|a| names.contains(&a.query_name.as_str()))
+        .map(|a| (a, a.aligned_blocks()))
+        .collect();
+    let mut lifts = Vec::new();
+    for (feature_type, key) in record.ordered_features() {
+        let Some(features) = record.features_of(feature_type) else {
+            continue;
+        };
+        let location = match features.get_location(key) {
+            Some(value) => value.clone(),
+            None => match (features.get_start(key), features.get_stop(key)) {
+                (Some(start), Some(stop)) => {
+                    Location::span(start, stop, features.get_strand(key).copied().unwrap_or(1))
+                }
+                _ => continue,
+            },
+        };
+        let parts = part_ranges(&location, length);
+        let feature_length: u32 = parts.iter().map(|((s, e), _)| e - s + 1).sum();
+        let mut best: Option<(&Record, Vec<Location>, u32, bool)> = None;
+        for (alignment, blocks) in usable.iter() {
+            let (lifted, aligned, same_lengths) = lift_parts(alignment, blocks, &parts);
+            if aligned > 0 && best.as_ref().is_none_or(|b| aligned > b.2) {
+                best = Some((alignment, lifted, aligned, same_lengths));
+            }
+        }
+        let lift = match best {
+            Some((alignment, mut lifted, aligned, same_lengths)) => Liftover {
+                feature_type: feature_type.to_string(),
+                locus_tag: key.to_string(),
+                status: match aligned == feature_length
+                    && same_lengths
+                    && lifted.len() == parts.len()
+                {
+                    true => LiftStatus::Clean,
+                    false => LiftStatus::Partial,
+                },
+                target: Some(alignment.target_name.clone()),
+                location: match lifted.len() {
+                    1 => lifted.pop(),
+                    _ => Some(Location::Join(lifted)),
+                },
+                length: feature_length,
+                aligned,
+            },
+            None => Liftover {
+                feature_type: feature_type.to_string(),
+                locus_tag: key.to_string(),
+                status: LiftStatus::Failed,
+                target: None,
+                location: None,
+                length: feature_length,
+                aligned: 0,
+            },
+        };
+        lifts.push(lift);
+    }
+    lifts
+}
+
+///adds the lifted features on the target record to it, with the gene, product, codon start and qualifiers of the source feature
+///partial lifts get a /note saying so, CDS are extracted from the target sequence and translated, returns the number of features added
+pub fn apply_liftover(target: &mut GbkRecord, source: &GbkRecord, lifts: &[Liftover]) -> usize {
+    let mut added = 0;
+    let target_id = target.id.clone();
+    for lift in lifts
+        .iter()
+        .filter(|l| l.target.as_deref() == Some(target_id.as_str()))
+    {
+        let (Some(location), Some(features)) =
+            (&lift.location, source.features_of(&lift.feature_type))
+        else {
+            continue;
+        };
+        let key = &lift.locus_tag;
+        let mut qualifiers = features.feature_qualifiers(&lift.feature_type, key);
+        if lift.status == LiftStatus::Partial {
+            qualifiers.push(
+                "note",
+                Some(format!(
+                    "partial liftover from {}, {} of {} bases aligned",
+                    source.id, lift.aligned, lift.length
+                )),
+            );
+        }
+        let codon_start = features.get_codon_start(key).copied().unwrap_or(1);
+        let start = location.start_value();
+        let stop = location.end_value();
+        let strand = location.strand();
+        let target_features = target.features_mut(&lift.feature_type);
+        target_features
+            .set_counter(key.clone())
+            .set_start(start.clone())
+            .set_stop(stop.clone())
+            .set_gene(features.get_gene(key).cloned().unwrap_or_default())
+            .set_product(features.get_product(key).cloned().unwrap_or_default())
+            .set_strand(strand)
+            .set_location(location.clone())
+            .set_qualifiers(qualifiers.clone());
+        added += 1;
+        if lift.feature_type != "CDS" {
+            continue;
+        }
+        target_features.set_codon_start(codon_start);
+        if target.sequence.is_empty() {
+            continue;
+        }
+        let cds_char = location.extract(&target.sequence);
+        let Some(code) = genetic_code(qualifiers.transl_table().unwrap_or(DEFAULT_TABLE)) else {
+            continue;
+        };
+        let cod = (codon_start.max(1) - 1) as usize;
+        let prot_seq = code.translate_cds(cds_char.get(cod..).unwrap_or_default(), cod == 0);
+        let faa = prot_seq.split('*').next().unwrap_or_default().to_string();
+        target
+            .seq_features
+            .set_counter(key.clone())
+            .set_start(RangeValue::Exact(start.get_value()))
+            .set_stop(RangeValue::Exact(stop.get_value() - 1))
+            .set_sequence_ffn(cds_char)
+            .set_sequence_faa(faa)
+            .set_codon_start(codon_start.max(1) - 1)
+            .set_strand(strand);
+    }
+    added
+}
+
+///an iterator over the alignments of a file
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Records<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read PAF from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read PAF from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new PAF reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+        }
+    }
+    ///reads the next alignment, returns None at the end of the file
+    pub fn read_record(&mut self) -> Result<Option<Record>, ParseError> {
+        loop {
+            self.line_buffer.clear();
+            let read = self
+                .reader
+                .read_line(&mut self.line_buffer)
+                .map_err(|source| ParseError::Io {
+                    line_number: self.line_number + 1,
+                    record_id: String::new(),
+                    line: String::new(),
+                    source,
+                })?;
+            if read == 0 {
+                return Ok(None);
+            }
+            self.line_number += 1;
+            let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = Record::from_line(line).map_err(|e| ParseError::MalformedLine {
+                line_number: self.line_number,
+                record_id: line.split('\t').next().unwrap_or_default().to_string(),
+                line: line.to_string(),
+                message: e.to_string(),
+            })?;
+            return Ok(Some(record));
+        }
+    }
+    ///return an iterator over the alignments of the file
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbk::Reader as GbkReader;
+
+    #[test]
+    fn test_read_paf() {
+        let text = concat!(
+            "q1\t1000\t10\t34\t-\tt1\t5000\t100\t143\t20\t45\t60\ttp:A:P\tcs:Z::10*ag+tt-c:5~gt20ag:3=ACG\n",
+            "q2\t1000\t0\t30\t+\tt1\t5000\t0\t30\t30\t30\t0\n",
+            "q3\t1000\t0\t30\t+\tt1\t5000\t0\t30\t30\t30\t0\tcg:Z:20M\n",
+        );
+        let mut reader = Reader::new(text.as_bytes());
+        let first = reader.read_record().unwrap().unwrap();
+        assert_eq!(first.strand, -1);
+        assert_eq!(first.tag("tp"), Some(&TagValue::Char('P')));
+        let cs = first.cs.clone().unwrap();
+        assert_eq!(
+            cs,
+            vec![
+                CsOp::Match(10),
+                CsOp::Substitution('a', 'g'),
+                CsOp::Insertion("tt".to_string()),
+                CsOp::Deletion("c".to_string()),
+                CsOp::Match(5),
+                CsOp::Intron("gt".to_string(), 20, "ag".to_string()),
+                CsOp::Match(3),
+                CsOp::Identical("ACG".to_string()),
+            ]
+        );
+        assert_eq!(
+            cs.iter().map(|o| o.to_string()).collect::<String>(),
+            ":10*ag+tt-c:5~gt20ag:3=ACG"
+        );
+        //the query runs backwards from 34 along the target from 100
+        assert_eq!(
+            first.aligned_blocks(),
+            vec![(23, 100, 11), (16, 112, 5), (10, 137, 6)]
+        );
+        assert_eq!(
+            (first.lift(33), first.lift(23), first.lift(21)),
+            (Some(100), Some(110), None)
+        );
+        let second = reader.read_record().unwrap().unwrap();
+        assert_eq!((second.cigar.clone(), second.cs.clone()), (None, None));
+        assert_eq!(second.aligned_blocks(), vec![(0, 0, 30)]);
+        assert_eq!(second.identity(), 1.0);
+        match reader.read_record() {
+            Err(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                message,
+                ..
+            }) => {
+                assert_eq!((line_number, record_id.as_str()), (3, "q3"));
+                assert!(message.contains("the CIGAR covers 20 query"), "{}", message);
+            }
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+        for bad in ["*a", "~gt2", "~g2t0ag", "~gt20a", ":x", "+"] {
+            assert!(parse_cs(bad).is_err(), "{}", bad);
+        }
+    }
+    #[test]
+    fn test_liftover() {
+        let mut source = GbkReader::from_file("K12_ribo.gbk")
+            .unwrap()
+            .records()
+            .next()
+            .unwrap()
+            .unwrap();
+        //a feature before the aligned region cannot be lifted
+        source
+            .features_mut("misc_feature")
+            .set_counter("misc_feature_1".to_string())
+            .set_start(RangeValue::Exact(1))
+            .set_stop(RangeValue::Exact(4))
+            .set_strand(1);
+        let text = concat!(
+            "NC_000913\t913\t5\t400\t+\tnew_ctg\t6000\t1005\t1400\t395\t395\t60\tcg:Z:395M\n",
+            "NC_000913\t913\t400\t913\t-\tnew_ctg\t6000\t5000\t5516\t510\t516\t60\tcg:Z:200M3D313M\n",
+            "plasmid\t5000\t0\t913\t+\tnew_ctg\t6000\t0\t913\t913\t913\t60\n",
+        );
+        let alignments: Vec<Record> = Reader::new(text.as_bytes())
+            .records()
+            .map(|r| r.unwrap())
+            .collect();
+        let lifts = liftover(&source, &alignments);
+        let find = |feature_type: &str, locus_tag: &str| {
+            lifts
+                .iter()
+                .find(|l| l.feature_type == feature_type && l.locus_tag == locus_tag)
+                .unwrap()
+        };
+        //b3304 is complement(10..363) within the first alignment
+        let b3304 = find("CDS", "b3304");
+        assert_eq!(b3304.status, LiftStatus::Clean);
+        assert_eq!(b3304.target.as_deref(), Some("new_ctg"));
+        assert_eq!(
+            b3304.location.as_ref().unwrap().to_string(),
+            "complement(1010..1363)"
+        );
+        //b3305 is complement(373..906), its last 28 bases (373..400) are in the other alignment and it spans a deletion
+        let b3305 = find("CDS", "b3305");
+        assert_eq!(b3305.status, LiftStatus::Partial);
+        assert_eq!((b3305.aligned, b3305.length), (506, 534));
+        assert_eq!(b3305.location.as_ref().unwrap().to_string(), "5008..5516");
+        assert_eq!(
+            find("misc_feature", "misc_feature_1").status,
+            LiftStatus::Failed
+        );
+        let mut target = GbkRecord::new();
+        target.id = "new_ctg".to_string();
+        target.length = 6000;
+        let added = apply_liftover(&mut target, &source, &lifts);
+        assert_eq!(
+            added,
+            lifts
+                .iter()
+                .filter(|l| l.status != LiftStatus::Failed)
+                .count()
+        );
+        assert_eq!(
+            target.cds.get_product("b3304"),
+            source.cds.get_product("b3304")
+        );
+        assert_eq!(target.cds.get_strand("b3305"), Some(&1));
+        let notes = target.cds.get_qualifiers("b3305").unwrap().notes();
+        assert!(notes.contains(&"partial liftover from NC_000913, 506 of 534 bases aligned"));
+    }
+}
@@ -0,0 +1,457 @@
+//! # Feature qualifiers shared by the GenBank and EMBL parsers
+//!
+//! Every qualifier of a feature (```/locus_tag```, ```/db_xref```, ```/note```, ```/pseudo```...) is kept in a ```Qualifiers``` ordered multi-map,
+//! so repeated keys such as ```/db_xref``` and valueless flags such as ```/pseudo``` survive a round trip through the writers.
+//!
+//!```rust
+//! use microBioRust::qualifiers::Qualifiers;
+//!
+//! let mut qualifiers = Qualifiers::new();
+//! qualifiers.push("locus_tag", Some("b3304".to_string()));
+//! qualifiers.push("db_xref", Some("GeneID:947804".to_string()));
+//! qualifiers.push("db_xref", Some("ECOCYC:EG10879".to_string()));
+//! qualifiers.push("pseudo", None);
+//! assert_eq!(qualifiers.locus_tag(), Some("b3304"));
+//! assert_eq!(qualifiers.db_xrefs(), vec!["GeneID:947804", "ECOCYC:EG10879"]);
+//! assert!(qualifiers.is_pseudo());
+//!```
+
+///qualifiers whose values are written without quotes in GenBank and EMBL files
+pub const UNQUOTED_QUALIFIERS: [&str; 11] = [
+    "anticodon",
+    "citation",
+    "codon_start",
+    //the Artemis colour of a feature, found in Sanger and ENA files
+    "colour",
+    "direction",
+    "estimated_length",
+    "number",
+    "rpt_type",
+    "rpt_unit_range",
+    "transl_except",
+    "transl_table",
+];
+
+///ordered multi-map of the qualifiers of one feature, keys are stored without the leading '/'
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Qualifiers {
+    entries: Vec<(String, Option<String>)>,
+}
+
+impl Qualifiers {
+    pub fn new() -> Self {
+        Qualifiers {
+            entries: Vec::new(),
+        }
+    }
+    ///appends a qualifier, keeping any earlier values with the same key
+    pub fn push(&mut self, key: &str, value: Option<String>) {
+        self.entries.push((key.to_string(), value));
+    }
+    ///replaces every value of a key with a single value, appending the key if it is new
+    pub fn set(&mut self, key: &str, value: Option<String>) {
+        let mut seen = false;
+        self.entries.retain_mut(|(k, v)| {
+            if k != key {
+                return true;
+            }
+            if seen {
+                return false;
+            }
+            seen = true;
+            *v = value.clone();
+            true
+        });
+        if !seen {
+            self.push(key, value);
+        }
+    }
+    ///removes every value of a key
+    pub fn remove(&mut self, key: &str) {
+        self.entries.retain(|(k, _)| k != key);
+    }
+    ///the first value of a key, None for a missing key or a valueless flag
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .and_then(|(_, v)| v.as_deref())
+    }
+    ///every value of a key in file order
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        self.entries
+            .iter()
+            .filter(|(k, _)| k == key)
+            .filter_map(|(_, v)| v.as_deref())
+            .collect()
+    }
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.iter().any(|(k, _)| k == key)
+    }
+    ///iterates over (key, value) pairs in file order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&str>)> {
+        self.entries.iter().map(|(k, v)| (k.as_str(), v.as_deref()))
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    pub fn locus_tag(&self) -> Option<&str> {
+        self.get("locus_tag")
+    }
+    pub fn old_locus_tag(&self) -> Option<&str> {
+        self.get("old_locus_tag")
+    }
+    pub fn gene(&self) -> Option<&str> {
+        self.get("gene")
+    }
+    pub fn product(&self) -> Option<&str> {
+        self.get("product")
+    }
+    pub fn protein_id(&self) -> Option<&str> {
+        self.get("protein_id")
+    }
+    pub fn db_xrefs(&self) -> Vec<&str> {
+        self.get_all("db_xref")
+    }
+    pub fn notes(&self) -> Vec<&str> {
+        self.get_all("note")
+    }
+    pub fn inferences(&self) -> Vec<&str> {
+        self.get_all("inference")
+    }
+    pub fn ec_numbers(&self) -> Vec<&str> {
+        self.get_all("EC_number")
+    }
+    pub fn translation(&self) -> Option<&str> {
+        self.get("translation")
+    }
+    pub fn codon_start(&self) -> Option<u8> {
+        self.get("codon_start").and_then(|v| v.trim().parse().ok())
+    }
+    pub fn transl_table(&self) -> Option<u8> {
+        self.get("transl_table").and_then(|v| v.trim().parse().ok())
+    }
+    ///true for features flagged /pseudo or carrying a /pseudogene qualifier
+    pub fn is_pseudo(&self) -> bool {
+        self.contains("pseudo") || self.contains("pseudogene")
+    }
+}
+
+///collects the qualifier lines of one feature, joining the continuation lines of quoted values that wrap across several lines
+///continuation lines are joined with a space, apart from /translation which is joined without one, and escaped ```""``` quotes are unescaped
+#[derive(Debug, Default, Clone)]
+pub struct QualifierLines {
+    qualifiers: Qualifiers,
+    open: Option<(String, String)>,
+}
+
+impl QualifierLines {
+    pub fn new() -> Self {
+        QualifierLines {
+            qualifiers: Qualifiers::new(),
+            open: None,
+        }
+    }
+    ///adds a line of the feature table, the text should have the indentation (and any EMBL ```FT``` line code) removed
+    pub fn push_line(&mut self, text: &str) {
+        let text = text.trim();
+        if let Some((key, raw)) = &mut self.open {
+            if !raw.is_empty() && key != "translation" {
+                raw.push(' ');
+            }
+            raw.push_str(text);
+            if closes_quote(raw) {
+                let (key, raw) = self.open.take().unwrap_or_default();
+                self.qualifiers.push(&key, Some(unquote(&raw)));
+            }
+            return;
+        }
+        match text.strip_prefix('/') {
+            Some(rest) => match rest.split_once('=') {
+                Some((key, value)) => match value.strip_prefix('"') {
+                    Some(quoted) if closes_quote(quoted) => {
+                        self.qualifiers.push(key, Some(unquote(quoted)))
+                    }
+                    Some(quoted) => self.open = Some((key.to_string(), quoted.to_string())),
+                    None => self.qualifiers.push(key, Some(value.to_string())),
+                },
+                None => self.qualifiers.push(rest, None),
+            },
+            //an unquoted value such as /transl_except can also wrap onto the next line
+            None => {
+                if let Some((_, Some(value))) = self.qualifiers.entries.last_mut() {
+                    value.push_str(text);
+                }
+            }
+        }
+    }
+    ///returns the qualifiers collected so far and resets the collector for the next feature, an unterminated quoted value is kept as it is
+    pub fn finish(&mut self) -> Qualifiers {
+        if let Some((key, raw)) = self.open.take() {
+            self.qualifiers.push(&key, Some(raw.replace("\"\"", "\"")));
+        }
+        std::mem::take(&mut self.qualifiers)
+    }
+}
+
+//a quoted value ends with an odd number of quotes, as quotes inside the value are doubled
+fn closes_quote(raw: &str) -> bool {
+    raw.chars().rev().take_while(|c| *c == '"').count() % 2 == 1
+}
+
+//removes the closing quote and unescapes doubled quotes
+fn unquote(raw: &str) -> String {
+    raw.strip_suffix('"').unwrap_or(raw).replace("\"\"", "\"")
+}
+
+///splits a single line qualifier such as ```/product="50S ribosomal subunit protein L18"``` into its key and value
+///quotes are removed from the value, a valueless flag such as ```/pseudo``` has a value of None
+pub fn parse_qualifier(text: &str) -> Option<(String, Option<String>)> {
+    let mut lines = QualifierLines::new();
+    lines.push_line(text);
+    lines.finish().entries.into_iter().next()
+}
+
+///formats a qualifier for a GenBank or EMBL feature table, wrapping long values onto continuation lines
+///prefix is the indentation of the qualifier column, e.g. 21 spaces for GenBank or "FT" plus 19 spaces for EMBL
+pub fn format_qualifier(prefix: &str, key: &str, value: Option<&str>) -> String {
+    let text = match value {
+        None => format!("/{}", key),
+        Some(v) if UNQUOTED_QUALIFIERS.contains(&key) => format!("/{}={}", key, v),
+        Some(v) => format!("/{}=\"{}\"", key, v.replace('"', "\"\"")),
+    };
+    //where the value inside the opening quote starts, its quotes come in escaped pairs
+    let quoted_from = match value {
+        Some(_) if !UNQUOTED_QUALIFIERS.contains(&key) => key.len() + 3,
+        _ => text.len(),
+    };
+    let width = line_width(prefix) - prefix.len();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut start = 0;
+    while text.len() - start > width {
+        let mut cut = start + width;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        //free text is wrapped at a space, sequences such as translations are cut at the line width
+        if let Some(space) = text[start..cut].rfind(' ') {
+            if space > 0 {
+                cut = start + space;
+            }
+        }
+        //a line ending inside a "" pair would read as the closing quote
+        if cut > quoted_from
+            && text[quoted_from..cut]
+                .chars()
+                .rev()
+                .take_while(|c| *c == '"')
+                .count()
+                % 2
+                == 1
+        {
+            cut -= 1;
+        }
+        lines.push(&text[start..cut]);
+        start = cut + text[cut..].len() - text[cut..].trim_start_matches(' ').len();
+    }
+    lines.push(&text[start..]);
+    lines
+        .iter()
+        .map(|line| format!("{}{}", prefix, line))
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+///the width of a feature table line, GenBank lines are at most 79 characters wide and EMBL ```FT``` lines 80
+pub fn line_width(prefix: &str) -> usize {
+    match prefix.starts_with("FT") {
+        true => 80,
+        false => 79,
+    }
+}
+
+///percent-encodes the characters with a reserved meaning in GFF3 column 9 (tab, newline, ';', '=', '&', ',' and '%')
+pub fn gff3_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\t' | '\n' | '\r' | ';' | '=' | '&' | ',' | '%' => {
+                escaped.push_str(&format!("%{:02X}", c as u32))
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+///decodes the %XX escapes of GFF3 column 9, a '%' not followed by two hex digits is kept as it is
+pub fn gff3_unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+///formats the qualifiers not already covered by the GFF3 writers as column 9 attributes, repeated keys are joined with commas and flags such as /pseudo are written as pseudo=true
+///the /db_xref and /note qualifiers are written as the reserved Dbxref and Note tags
+pub fn gff3_attributes(qualifiers: &Qualifiers, skip: &[&str]) -> Vec<String> {
+    let mut attributes: Vec<(&str, Vec<String>)> = Vec::new();
+    for (key, value) in qualifiers.iter() {
+        if skip.contains(&key) {
+            continue;
+        }
+        //the GFF3 tags reserved for cross references and notes
+        let key = match key {
+            "db_xref" => "Dbxref",
+            "note" => "Note",
+            other => other,
+        };
+        let value = gff3_escape(value.unwrap_or("true"));
+        match attributes.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, values)) => values.push(value),
+            None => attributes.push((key, vec![value])),
+        }
+    }
+    attributes
+        .into_iter()
+        .map(|(key, values)| format!("{}={}", key, values.join(",")))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_parse_qualifier() {
+        assert_eq!(
+            parse_qualifier("                     /locus_tag=\"b3304\""),
+            Some(("locus_tag".to_string(), Some("b3304".to_string())))
+        );
+        assert_eq!(
+            parse_qualifier("/codon_start=1"),
+            Some(("codon_start".to_string(), Some("1".to_string())))
+        );
+        assert_eq!(
+            parse_qualifier("/pseudo"),
+            Some(("pseudo".to_string(), None))
+        );
+        assert_eq!(parse_qualifier("SEVLVAASTVEKAIAEQLKYTG"), None);
+    }
+    #[test]
+    fn test_continuation_lines() {
+        let mut lines = QualifierLines::new();
+        for line in [
+            "/note=\"N-terminus is truncated relative to the A.",
+            "tumefaciens and R. etli putative integrase matches\"",
+            "/experiment=\"the \"\"quoted\"\"",
+            "word\"",
+            "/translation=\"MDKKSARIRRATRARRKLQELGATRLVVHRTPRHIYAQVIAPNG",
+            "SEVLVAASTVEKAIAEQLKYTGNKDAAAAVGKAVAERALEKGIKDVSFDRSGFQYHGR",
+            "VQALADAAREAGLQF\"",
+            "/pseudo",
+        ] {
+            lines.push_line(line);
+        }
+        let qualifiers = lines.finish();
+        assert_eq!(
+            qualifiers.notes(),
+            vec!["N-terminus is truncated relative to the A. tumefaciens and R. etli putative integrase matches"]
+        );
+        assert_eq!(qualifiers.get("experiment"), Some("the \"quoted\" word"));
+        assert_eq!(qualifiers.translation(), Some("MDKKSARIRRATRARRKLQELGATRLVVHRTPRHIYAQVIAPNGSEVLVAASTVEKAIAEQLKYTGNKDAAAAVGKAVAERALEKGIKDVSFDRSGFQYHGRVQALADAAREAGLQF"));
+        assert!(qualifiers.is_pseudo());
+        assert!(lines.finish().is_empty());
+    }
+    #[test]
+    fn test_multimap_order() {
+        let mut qualifiers = Qualifiers::new();
+        qualifiers.push("db_xref", Some("GeneID:947804".to_string()));
+        qualifiers.push("transl_table", Some("11".to_string()));
+        qualifiers.push("db_xref", Some("ECOCYC:EG10879".to_string()));
+        assert_eq!(qualifiers.transl_table(), Some(11));
+        qualifiers.set("db_xref", Some("ASAP:ABE-0010825".to_string()));
+        let keys: Vec<&str> = qualifiers.iter().map(|(k, _)| k).collect();
+        assert_eq!(keys, vec!["db_xref", "transl_table"]);
+        assert_eq!(qualifiers.db_xrefs(), vec!["ASAP:ABE-0010825"]);
+    }
+    #[test]
+    fn test_format_qualifier() {
+        let prefix = " ".repeat(21);
+        let formatted = format_qualifier(
+            &prefix,
+            "product",
+            Some("bifunctional aspartokinase/homoserine dehydrogenase I with a long name"),
+        );
+        let lines: Vec<&str> = formatted.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|l| l.len() <= 79));
+        //the line is never cut between the two quotes of an escaped quote
+        let value = format!("{}\"quoted\" word", "x".repeat(50));
+        let formatted = format_qualifier(&prefix, "note", Some(&value));
+        assert_eq!(
+            formatted,
+            format!(
+                "{}/note=\"{}\n{}\"\"quoted\"\" word\"",
+                prefix,
+                "x".repeat(50),
+                prefix
+            )
+        );
+        let mut lines = QualifierLines::new();
+        for line in formatted.lines() {
+            lines.push_line(line.trim());
+        }
+        //read back with the quotes, the continuation line is joined with a space
+        assert_eq!(
+            lines.finish().get("note"),
+            Some(format!("{} \"quoted\" word", "x".repeat(50)).as_str())
+        );
+        assert_eq!(
+            format_qualifier(&prefix, "transl_table", Some("11")),
+            format!("{}/transl_table=11", prefix)
+        );
+        assert_eq!(
+            format_qualifier(&prefix, "pseudo", None),
+            format!("{}/pseudo", prefix)
+        );
+    }
+    #[test]
+    fn test_gff3_attributes() {
+        let mut qualifiers = Qualifiers::new();
+        qualifiers.push("locus_tag", Some("b3304".to_string()));
+        qualifiers.push("db_xref", Some("GeneID:947804".to_string()));
+        qualifiers.push("note", Some("L18; part of 50S".to_string()));
+        qualifiers.push("db_xref", Some("ECOCYC:EG10879".to_string()));
+        qualifiers.push("pseudo", None);
+        assert_eq!(
+            gff3_attributes(&qualifiers, &["locus_tag"]),
+            vec![
+                "Dbxref=GeneID:947804,ECOCYC:EG10879",
+                "Note=L18%3B part of 50S",
+                "pseudo=true"
+            ]
+        );
+        assert_eq!(gff3_unescape("L18%3B part of 50S"), "L18; part of 50S");
+        assert_eq!(gff3_unescape("100%"), "100%");
+        assert_eq!(gff3_unescape(&gff3_escape("a=b,c%d")), "a=b,c%d");
+    }
+}
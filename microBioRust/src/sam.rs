@@ -0,0 +1,805 @@
+//! # SAM reading and feature coverage
+//!
+//! A streaming reader for the SAM text format. The ```@HD```, ```@SQ```, ```@RG```, ```@PG``` and ```@CO``` lines are kept in a ```Header```
+//! and each alignment line is a ```Record``` with its ```Flags```, parsed CIGAR operations and typed optional tags (```NM:i:0```, ```RG:Z:lane1```, ```B``` arrays...).
+//! Compressed files (gzip, BGZF...) are read transparently, BAM and CRAM are not read.
+//!
+//! ```feature_counts``` counts the reads overlapping each CDS of a ```gbk::Record``` and their mean depth over the CDS, a quick counts table
+//! in the spirit of featureCounts. Unmapped, secondary, supplementary and QC failed alignments are left out, as are duplicates and low mapping qualities
+//! when ```CountOptions``` says so.
+//!
+//!```rust
+//! use microBioRust::gbk::Reader as GbkReader;
+//! use microBioRust::sam::{feature_counts, CountOptions, Reader};
+//!
+//! let text = concat!(
+//!     "@HD\tVN:1.6\tSO:coordinate\n",
+//!     "@SQ\tSN:NC_000913\tLN:913\n",
+//!     "read1\t0\tNC_000913\t20\t60\t50M\t*\t0\t0\t*\t*\tNM:i:0\n",
+//!     "read2\t16\tNC_000913\t350\t60\t10M5N10M\t*\t0\t0\t*\t*\n",
+//! );
+//! let mut reader = Reader::new(text.as_bytes());
+//! assert_eq!(reader.read_header().unwrap().references(), vec![("NC_000913", Some(913))]);
+//!
+//! let record = GbkReader::from_file("K12_ribo.gbk").unwrap().records().next().unwrap().unwrap();
+//! let counts = feature_counts(&record, reader.records(), &CountOptions::default()).unwrap();
+//! assert_eq!(counts[0].locus_tag, "b3304");
+//! assert_eq!(counts[0].reads, 2);
+//! assert_eq!(counts[0].mean_depth, 60.0 / 354.0);
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk;
+use anyhow::{anyhow, Context};
+use std::{fmt, io, path::Path};
+
+///a header line, ```@SQ SN:chr LN:100``` gives the record type ```SQ``` and the fields (SN, chr), (LN, 100)
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct HeaderLine {
+    pub record_type: String,
+    pub fields: Vec<(String, String)>,
+    ///the text of a ```@CO``` comment line
+    pub comment: String,
+}
+
+impl HeaderLine {
+    ///reads a header line without its leading ```@```
+    pub fn parse(text: &str) -> Result<Self, anyhow::Error> {
+        let (record_type, rest) = text.split_once('\t').unwrap_or((text, ""));
+        if record_type.len() != 2 {
+            return Err(anyhow!("bad header record type {:?}", record_type));
+        }
+        let mut line = HeaderLine {
+            record_type: record_type.to_string(),
+            ..Default::default()
+        };
+        if record_type == "CO" {
+            line.comment = rest.to_string();
+            return Ok(line);
+        }
+        for field in rest.split('\t').filter(|f| !f.is_empty()) {
+            let (tag, value) = field
+                .split_once(':')
+                .ok_or(anyhow!("header field {:?} is not TAG:VALUE", field))?;
+            line.fields.push((tag.to_string(), value.to_string()));
+        }
+        Ok(line)
+    }
+    ///the value of a field, e.g. ```SN``` or ```ID```
+    pub fn get(&self, tag: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(t, _)| t == tag)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+///the header lines of a file in file order
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Header {
+    pub lines: Vec<HeaderLine>,
+}
+
+impl Header {
+    ///the lines of a record type, e.g. ```SQ``` or ```RG```
+    pub fn lines_of<'a>(&'a self, record_type: &'a str) -> impl Iterator<Item = &'a HeaderLine> {
+        self.lines
+            .iter()
+            .filter(move |line| line.record_type == record_type)
+    }
+    ///the reference sequences (```@SQ```) with their length
+    pub fn references(&self) -> Vec<(&str, Option<u32>)> {
+        self.lines_of("SQ")
+            .filter_map(|line| {
+                line.get("SN")
+                    .map(|name| (name, line.get("LN").and_then(|l| l.parse().ok())))
+            })
+            .collect()
+    }
+    ///the read groups (```@RG```)
+    pub fn read_groups(&self) -> Vec<&HeaderLine> {
+        self.lines_of("RG").collect()
+    }
+    ///the read group with the given ID
+    pub fn read_group(&self, id: &str) -> Option<&HeaderLine> {
+        self.lines_of("RG").find(|line| line.get("ID") == Some(id))
+    }
+}
+
+///the FLAG column, a bit field
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Flags(pub u16);
+
+impl Flags {
+    pub const PAIRED: u16 = 0x1;
+    pub const PROPER_PAIR: u16 = 0x2;
+    pub const UNMAPPED: u16 = 0x4;
+    pub const MATE_UNMAPPED: u16 = 0x8;
+    pub const REVERSE: u16 = 0x10;
+    pub const MATE_REVERSE: u16 = 0x20;
+    pub const FIRST_IN_PAIR: u16 = 0x40;
+    pub const SECOND_IN_PAIR: u16 = 0x80;
+    pub const SECONDARY: u16 = 0x100;
+    pub const QC_FAIL: u16 = 0x200;
+    pub const DUPLICATE: u16 = 0x400;
+    pub const SUPPLEMENTARY: u16 = 0x800;
+
+    ///true when every bit of the mask is set
+    pub fn contains(&self, mask: u16) -> bool {
+        self.0 & mask == mask
+    }
+    pub fn is_paired(&self) -> bool {
+        self.contains(Self::PAIRED)
+    }
+    pub fn is_proper_pair(&self) -> bool {
+        self.contains(Self::PROPER_PAIR)
+    }
+    pub fn is_unmapped(&self) -> bool {
+        self.contains(Self::UNMAPPED)
+    }
+    pub fn is_mate_unmapped(&self) -> bool {
+        self.contains(Self::MATE_UNMAPPED)
+    }
+    pub fn is_reverse(&self) -> bool {
+        self.contains(Self::REVERSE)
+    }
+    pub fn is_mate_reverse(&self) -> bool {
+        self.contains(Self::MATE_REVERSE)
+    }
+    pub fn is_first_in_pair(&self) -> bool {
+        self.contains(Self::FIRST_IN_PAIR)
+    }
+    pub fn is_second_in_pair(&self) -> bool {
+        self.contains(Self::SECOND_IN_PAIR)
+    }
+    pub fn is_secondary(&self) -> bool {
+        self.contains(Self::SECONDARY)
+    }
+    pub fn is_qc_fail(&self) -> bool {
+        self.contains(Self::QC_FAIL)
+    }
+    pub fn is_duplicate(&self) -> bool {
+        self.contains(Self::DUPLICATE)
+    }
+    pub fn is_supplementary(&self) -> bool {
+        self.contains(Self::SUPPLEMENTARY)
+    }
+}
+
+///a CIGAR operation, ```M```, ```I```, ```D```, ```N```, ```S```, ```H```, ```P```, ```=``` or ```X``` with its length
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CigarOp {
+    pub kind: char,
+    pub length: u32,
+}
+
+impl CigarOp {
+    ///true for operations moving along the reference: M, D, N, = and X
+    pub fn consumes_reference(&self) -> bool {
+        matches!(self.kind, 'M' | 'D' | 'N' | '=' | 'X')
+    }
+    ///true for operations moving along the read: M, I, S, = and X
+    pub fn consumes_query(&self) -> bool {
+        matches!(self.kind, 'M' | 'I' | 'S' | '=' | 'X')
+    }
+}
+
+impl fmt::Display for CigarOp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.length, self.kind)
+    }
+}
+
+///reads a CIGAR string, ```*``` gives no operations
+pub fn parse_cigar(text: &str) -> Result<Vec<CigarOp>, anyhow::Error> {
+    let mut operations = Vec::new();
+    if text == "*" {
+        return Ok(operations);
+    }
+    let mut length = String::new();
+    for c in text.chars() {
+        match c {
+            '0'..='9' => length.push(c),
+            'M' | 'I' | 'D' | 'N' | 'S' | 'H' | 'P' | '=' | 'X' => {
+                operations.push(CigarOp {
+                    kind: c,
+                    length: length
+                        .parse()
+                        .map_err(|_| anyhow!("operation {:?} has no length in {:?}", c, text))?,
+                });
+                length.clear();
+            }
+            _ => return Err(anyhow!("bad CIGAR operation {:?} in {:?}", c, text)),
+        }
+    }
+    if !length.is_empty() {
+        return Err(anyhow!("CIGAR {:?} ends without an operation", text));
+    }
+    Ok(operations)
+}
+
+///the value of an optional field, typed by its ```A```, ```i```, ```f```, ```Z```, ```H``` or ```B``` code
+#[derive(Debug, Clone, PartialEq)]
+pub enum TagValue {
+    Char(char),
+    Int(i64),
+    Float(f32),
+    String(String),
+    ///a hex encoded byte array, kept as written
+    Hex(String),
+    ///a ```B``` array of one of the integer subtypes c, C, s, S, i or I
+    IntArray(Vec<i64>),
+    ///a ```B:f``` array
+    FloatArray(Vec<f32>),
+}
+
+impl TagValue {
+    ///reads the type code and value of an optional field
+    pub fn parse(kind: &str, value: &str) -> Result<Self, anyhow::Error> {
+        let bad = |e: &dyn fmt::Display| anyhow!("bad {} value {:?}: {}", kind, value, e);
+        Ok(match kind {
+            "A" => TagValue::Char(value.chars().next().ok_or(anyhow!("empty A value"))?),
+            "i" => TagValue::Int(value.parse().map_err(|e| bad(&e))?),
+            "f" => TagValue::Float(value.parse().map_err(|e| bad(&e))?),
+            "Z" => TagValue::String(value.to_string()),
+            "H" => TagValue::Hex(value.to_string()),
+            "B" => {
+                let mut items = value.split(',');
+                match items.next() {
+                    Some("f") => TagValue::FloatArray(
+                        items
+                            .map(|v| v.parse().map_err(|e| bad(&e)))
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    Some("c" | "C" | "s" | "S" | "i" | "I") => TagValue::IntArray(
+                        items
+                            .map(|v| v.parse().map_err(|e| bad(&e)))
+                            .collect::<Result<_, _>>()?,
+                    ),
+                    _ => return Err(anyhow!("bad B array subtype in {:?}", value)),
+                }
+            }
+            _ => return Err(anyhow!("unknown tag type {:?}", kind)),
+        })
+    }
+    ///the value as an integer, for ```i``` tags
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            TagValue::Int(value) => Some(*value),
+            _ => None,
+        }
+    }
+    ///the value as text, for ```Z``` and ```H``` tags
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            TagValue::String(value) | TagValue::Hex(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+///an alignment line, positions count from 1 as in the file and 0 means unset
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Record {
+    pub qname: String,
+    pub flags: Flags,
+    ///```*``` when unset
+    pub rname: String,
+    pub pos: u32,
+    pub mapq: u8,
+    pub cigar: Vec<CigarOp>,
+    pub rnext: String,
+    pub pnext: u32,
+    pub tlen: i64,
+    ///```*``` when not stored
+    pub seq: String,
+    pub qual: String,
+    ///the optional fields in file order
+    pub tags: Vec<(String, TagValue)>,
+}
+
+impl Record {
+    ///reads a tab separated alignment line
+    pub fn from_line(line: &str) -> Result<Self, anyhow::Error> {
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 11 {
+            return Err(anyhow!(
+                "expected at least 11 tab separated columns, found {}",
+                columns.len()
+            ));
+        }
+        fn number<T>(columns: &[&str], index: usize, name: &str) -> Result<T, anyhow::Error>
+        where
+            T: std::str::FromStr,
+            T::Err: std::fmt::Display,
+        {
+            columns[index]
+                .parse::<T>()
+                .map_err(|e| anyhow!("bad {} {:?}: {}", name, columns[index], e))
+        }
+        let mut tags = Vec::new();
+        for tag in columns[11..].iter().filter(|t| !t.is_empty()) {
+            let mut parts = tag.splitn(3, ':');
+            match (parts.next(), parts.next(), parts.next()) {
+                (Some(name), Some(kind), Some(value)) if name.len() == 2 => {
+                    tags.push((name.to_string(), TagValue::parse(kind, value)?))
+                }
+                _ => return Err(anyhow!("optional field {:?} is not TAG:TYPE:VALUE", tag)),
+            }
+        }
+        Ok(Record {
+            qname: columns[0].to_string(),
+            flags: Flags(number(&columns, 1, "FLAG")?),
+            rname: columns[2].to_string(),
+            pos: number(&columns, 3, "POS")?,
+            mapq: number(&columns, 4, "MAPQ")?,
+            cigar: parse_cigar(columns[5])?,
+            rnext: columns[6].to_string(),
+            pnext: number(&columns, 7, "PNEXT")?,
+            tlen: number(&columns, 8, "TLEN")?,
+            seq: columns[9].to_string(),
+            qual: columns[10].to_string(),
+            tags,
+        })
+    }
+    ///the value of an optional field, e.g. ```NM``` or ```RG```
+    pub fn tag(&self, name: &str) -> Option<&TagValue> {
+        self.tags.iter().find(|(n, _)| n == name).map(|(_, v)| v)
+    }
+    ///true when the read is placed on a reference with a CIGAR
+    pub fn is_mapped(&self) -> bool {
+        !self.flags.is_unmapped() && self.pos > 0 && !self.cigar.is_empty()
+    }
+    ///the number of reference bases covered by the alignment, deletions and skipped regions included
+    pub fn reference_length(&self) -> u32 {
+        self.cigar
+            .iter()
+            .filter(|op| op.consumes_reference())
+            .map(|op| op.length)
+            .sum()
+    }
+    ///the last reference base of the alignment
+    pub fn reference_end(&self) -> u32 {
+        (self.pos + self.reference_length()).saturating_sub(1)
+    }
+    ///the (start, end) reference spans with read bases aligned to them (M, = and X), split at deletions and introns
+    pub fn aligned_blocks(&self) -> Vec<(u32, u32)> {
+        let mut blocks: Vec<(u32, u32)> = Vec::new();
+        let mut position = self.pos;
+        for op in self.cigar.iter() {
+            match op.kind {
+                'M' | '=' | 'X' if op.length > 0 => {
+                    let end = position + op.length - 1;
+                    match blocks.last_mut() {
+                        Some(last) if last.1 + 1 == position => last.1 = end,
+                        _ => blocks.push((position, end)),
+                    }
+                    position += op.length;
+                }
+                'D' | 'N' => position += op.length,
+                _ => (),
+            }
+        }
+        blocks
+    }
+}
+
+///which alignments ```feature_counts``` uses, unmapped, secondary, supplementary and QC failed alignments are always left out
+#[derive(Debug, Clone, PartialEq)]
+pub struct CountOptions {
+    ///alignments with a lower mapping quality are left out
+    pub min_mapq: u8,
+    ///count alignments flagged as duplicates
+    pub count_duplicates: bool,
+}
+
+impl Default for CountOptions {
+    fn default() -> Self {
+        CountOptions {
+            min_mapq: 0,
+            count_duplicates: true,
+        }
+    }
+}
+
+///the reads and depth over one CDS
+#[derive(Debug, Clone, PartialEq)]
+pub struct FeatureCoverage {
+    pub locus_tag: String,
+    pub gene: Option<String>,
+    ///the number of bases of the CDS, joined parts added up
+    pub length: u32,
+    ///the alignments with an aligned base in the CDS, a read over two CDS counts for both
+    pub reads: u64,
+    ///the aligned bases over the CDS divided by its length
+    pub mean_depth: f64,
+}
+
+//the (start, end) spans of a location on the record, a span over the origin is split in two
+fn feature_spans(location: &crate::location::Location, length: u32) -> Vec<(u32, u32)> {
+    let mut spans = Vec::new();
+    for (start, end, _) in location.segments() {
+        match start <= end {
+            true => spans.push((start, end)),
+            false => {
+                spans.push((start, length));
+                spans.push((1, end));
+            }
+        }
+    }
+    spans
+}
+
+///counts the reads and the mean depth over each CDS of a record from a stream of alignments
+///only the alignments on the record (matching its name, accession or version) are used, the CDS are given in locus tag order
+pub fn feature_counts<I>(
+    record: &gbk::Record,
+    alignments: I,
+    options: &CountOptions,
+) -> Result<Vec<FeatureCoverage>, ParseError>
+where
+    I: IntoIterator<Item = Result<Record, ParseError>>,
+{
+    let length = record.sequence.len().max(record.length as usize) as u32;
+    let names: Vec<&str> = [record.id.as_str(), record.header.version.as_str()]
+        .into_iter()
+        .chain(record.header.accessions.iter().map(|a| a.as_str()))
+        .filter(|name| !name.is_empty())
+        .collect();
+    let mut coverages = Vec::new();
+    //(start, end, index of the CDS) sorted by start, to find the CDS a read overlaps
+    let mut spans: Vec<(u32, u32, usize)> = Vec::new();
+    for (locus_tag, _) in record.cds.attributes.iter() {
+        let Some(location) = record.cds.get_location(locus_tag) else {
+            continue;
+        };
+        let cds_spans = feature_spans(location, length);
+        for (start, end) in cds_spans.iter() {
+            spans.push((*start, *end, coverages.len()));
+        }
+        coverages.push(FeatureCoverage {
+            locus_tag: locus_tag.clone(),
+            gene: record.cds.get_gene(locus_tag).cloned(),
+            length: cds_spans.iter().map(|(s, e)| e - s + 1).sum(),
+            reads: 0,
+            mean_depth: 0.0,
+        });
+    }
+    spans.sort();
+    let longest = spans.iter().map(|(s, e, _)| e - s + 1).max().unwrap_or(0);
+    let mut aligned_bases = vec![0u64; coverages.len()];
+    for alignment in alignments {
+        let alignment = alignment?;
+        let flags = alignment.flags;
+        if !alignment.is_mapped()
+            || flags.is_secondary()
+            || flags.is_supplementary()
+            || flags.is_qc_fail()
+            || (flags.is_duplicate() && !options.count_duplicates)
+            || alignment.mapq < options.min_mapq
+            || !names.contains(&alignment.rname.as_str())
+        {
+            continue;
+        }
+        let mut overlapped: Vec<usize> = Vec::new();
+        for (block_start, block_end) in alignment.aligned_blocks() {
+            //spans starting after the block cannot overlap, nor can those ending before it
+            let last = spans.partition_point(|(start, _, _)| *start <= block_end);
+            for (start, end, index) in spans[..last].iter().rev() {
+                if start + longest <= block_start {
+                    break;
+                }
+                if *end < block_start {
+                    continue;
+                }
+                aligned_bases[*index] += (block_end.min(*end) - block_start.max(*start) + 1) as u64;
+                if !overlapped.contains(index) {
+                    overlapped.push(*index);
+                }
+            }
+        }
+        for index in overlapped {
+            coverages[index].reads += 1;
+        }
+    }
+    for (coverage, bases) in coverages.iter_mut().zip(aligned_bases) {
+        if coverage.length > 0 {
+            coverage.mean_depth = bases as f64 / coverage.length as f64;
+        }
+    }
+    Ok(coverages)
+}
+
+///writes the coverages as a tab separated table with a header line: locus_tag, gene, length, reads and mean_depth
+pub fn counts_table(coverages: &[FeatureCoverage]) -> String {
+    let mut table = String::from("locus_tag\tgene\tlength\treads\tmean_depth\n");
+    for coverage in coverages {
+        table.push_str(&format!(
+            "{}\t{}\t{}\t{}\t{:.2}\n",
+            coverage.locus_tag,
+            coverage.gene.as_deref().unwrap_or("."),
+            coverage.length,
+            coverage.reads,
+            coverage.mean_depth
+        ));
+    }
+    table
+}
+
+///an iterator over the alignments of a file
+#[derive(Debug)]
+pub struct Records<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Records<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Record, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_record() {
+            Ok(Some(record)) => Some(Ok(record)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+    header: Header,
+    header_read: bool,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read SAM from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read SAM from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new SAM reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+            header: Header::default(),
+            header_read: false,
+        }
+    }
+    //reads the next line into the buffer, false at the end of the file
+    fn next_line(&mut self) -> Result<bool, ParseError> {
+        self.line_buffer.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: String::new(),
+                line: String::new(),
+                source,
+            })?;
+        if read > 0 {
+            self.line_number += 1;
+        }
+        Ok(read > 0)
+    }
+    //the error for the line in the buffer
+    fn malformed(&self, message: String) -> ParseError {
+        let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+        ParseError::MalformedLine {
+            line_number: self.line_number,
+            record_id: line.split('\t').next().unwrap_or_default().to_string(),
+            line: line.to_string(),
+            message,
+        }
+    }
+    ///reads the ```@``` header lines, done by the first ```read_record``` when not called before
+    pub fn read_header(&mut self) -> Result<&Header, ParseError> {
+        while !self.header_read {
+            //the header ends at the first line not starting with '@'
+            let starts_header = match self.reader.fill_buf() {
+                Ok(buffer) => buffer.first() == Some(&b'@'),
+                Err(source) => {
+                    return Err(ParseError::Io {
+                        line_number: self.line_number + 1,
+                        record_id: String::new(),
+                        line: String::new(),
+                        source,
+                    })
+                }
+            };
+            if !starts_header || !self.next_line()? {
+                self.header_read = true;
+                break;
+            }
+            let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+            let header_line =
+                HeaderLine::parse(&line[1..]).map_err(|e| self.malformed(e.to_string()))?;
+            self.header.lines.push(header_line);
+        }
+        Ok(&self.header)
+    }
+    ///the header lines, filled in by ```read_header``` or the first ```read_record```
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    ///reads the next alignment, returns None at the end of the file
+    pub fn read_record(&mut self) -> Result<Option<Record>, ParseError> {
+        self.read_header()?;
+        loop {
+            if !self.next_line()? {
+                return Ok(None);
+            }
+            let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = Record::from_line(line).map_err(|e| self.malformed(e.to_string()))?;
+            return Ok(Some(record));
+        }
+    }
+    ///return an iterator over the alignments of the file
+    pub fn records(self) -> Records<B> {
+        Records {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbk::Reader as GbkReader;
+
+    const SAM: &str = concat!(
+        "@HD\tVN:1.6\tSO:coordinate\n",
+        "@SQ\tSN:NC_000913\tLN:913\n",
+        "@SQ\tSN:plasmid\tLN:5000\n",
+        "@RG\tID:lane1\tSM:isolate1\tPL:ILLUMINA\n",
+        "@PG\tID:bwa\tPN:bwa\tVN:0.7.17\n",
+        "@CO\tmapped with bwa mem\n",
+        "read1\t99\tNC_000913\t20\t60\t5S50M\t=\t200\t230\tACGT\t*\tNM:i:1\tRG:Z:lane1\n",
+        "read2\t16\tNC_000913\t350\t60\t10M5N10M\t*\t0\t0\t*\t*\tXA:A:u\tZB:B:s,1,-2,3\n",
+        "read3\t4\t*\t0\t0\t*\t*\t0\t0\tACGT\tIIII\n",
+        "read4\t256\tNC_000913\t100\t0\t50M\t*\t0\t0\t*\t*\n",
+        "read5\t1024\tNC_000913\t400\t3\t12M2D8M\t*\t0\t0\t*\t*\tXF:f:0.5\n",
+        "read6\t0\tplasmid\t400\t60\t20M\t*\t0\t0\t*\t*\n",
+    );
+
+    #[test]
+    fn test_read_sam() {
+        let mut reader = Reader::new(SAM.as_bytes());
+        let header = reader.read_header().unwrap().clone();
+        assert_eq!(
+            header.references(),
+            vec![("NC_000913", Some(913)), ("plasmid", Some(5000))]
+        );
+        assert_eq!(
+            header.read_group("lane1").unwrap().get("SM"),
+            Some("isolate1")
+        );
+        assert_eq!(
+            header.lines_of("CO").next().unwrap().comment,
+            "mapped with bwa mem"
+        );
+        let records: Vec<Record> = reader.records().map(|r| r.unwrap()).collect();
+        assert_eq!(records.len(), 6);
+        let first = &records[0];
+        assert!(
+            first.flags.is_paired()
+                && first.flags.is_proper_pair()
+                && first.flags.is_first_in_pair()
+        );
+        assert!(first.flags.is_mate_reverse());
+        assert_eq!(
+            first.cigar[0],
+            CigarOp {
+                kind: 'S',
+                length: 5
+            }
+        );
+        assert_eq!(first.tag("NM").and_then(|t| t.as_int()), Some(1));
+        assert_eq!(first.tag("RG").and_then(|t| t.as_str()), Some("lane1"));
+        assert_eq!(records[1].aligned_blocks(), vec![(350, 359), (365, 374)]);
+        assert_eq!(records[1].reference_end(), 374);
+        assert_eq!(
+            records[1].tag("ZB"),
+            Some(&TagValue::IntArray(vec![1, -2, 3]))
+        );
+        assert!(!records[2].is_mapped());
+        assert_eq!(records[4].aligned_blocks(), vec![(400, 411), (414, 421)]);
+        assert_eq!(records[4].tag("XF"), Some(&TagValue::Float(0.5)));
+    }
+    #[test]
+    fn test_feature_counts() {
+        let record = GbkReader::from_file("K12_ribo.gbk")
+            .unwrap()
+            .records()
+            .next()
+            .unwrap()
+            .unwrap();
+        let counts = feature_counts(
+            &record,
+            Reader::new(SAM.as_bytes()).records(),
+            &CountOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(counts.len(), 2);
+        assert_eq!(
+            (counts[0].locus_tag.as_str(), counts[0].length),
+            ("b3304", 354)
+        );
+        //read1 and the first block of read2, the secondary read4 is left out
+        assert_eq!(counts[0].reads, 2);
+        assert_eq!(counts[0].mean_depth, 60.0 / 354.0);
+        //the end of read2 and the duplicate read5, read6 is on another sequence
+        assert_eq!(counts[1].reads, 2);
+        assert_eq!(counts[1].mean_depth, 22.0 / 534.0);
+        let strict = CountOptions {
+            min_mapq: 10,
+            count_duplicates: false,
+        };
+        let counts =
+            feature_counts(&record, Reader::new(SAM.as_bytes()).records(), &strict).unwrap();
+        assert_eq!(counts[1].reads, 1);
+        assert!(counts_table(&counts).starts_with(
+            "locus_tag\tgene\tlength\treads\tmean_depth\nb3304\trplR\t354\t2\t0.17\n"
+        ));
+    }
+    #[test]
+    fn test_bad_line() {
+        let text = SAM.replace("10M5N10M", "10M5Q10M");
+        let mut records = Reader::new(text.as_bytes()).records();
+        assert!(records.next().unwrap().is_ok());
+        match records.next() {
+            Some(Err(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                ..
+            })) => {
+                assert_eq!(line_number, 8);
+                assert_eq!(record_id, "read2");
+            }
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+        assert!(records.next().is_none());
+        //values out of range for their column are not wrapped
+        assert!(Record::from_line("r\t70000\tchr\t1\t60\t4M\t*\t0\t0\t*\t*").is_err());
+        assert!(Record::from_line("r\t0\tchr\t-1\t60\t4M\t*\t0\t0\t*\t*").is_err());
+        assert!(Record::from_line("r\t0\tchr\t1\t300\t4M\t*\t0\t0\t*\t*").is_err());
+        let record = Record::from_line("r\t0\tchr\t0\t60\t0M4I\t*\t0\t0\t*\t*").unwrap();
+        assert!(record.aligned_blocks().is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+//! # Translation with the NCBI genetic codes
+//!
+//! Every NCBI translation table (```/transl_table```) is available from ```genetic_code```, bacterial records use table 11 unless a feature says otherwise.
+//! The first codon of a complete coding sequence is translated to Met when it is an alternative initiation codon (GTG, TTG...) of the table,
+//! and codons with IUPAC ambiguity codes are translated when every codon they stand for gives the same amino acid (or B, Z, J), otherwise X.
+//!
+//!```rust
+//! use microBioRust::translation::genetic_code;
+//!
+//! let bacterial = genetic_code(11).unwrap();
+//! assert_eq!(bacterial.translate("GTGAAATGA"), "VK*");
+//! assert_eq!(bacterial.translate_cds("GTGAAATGA", true), "MK*");
+//! //TGA is tryptophan in Mycoplasma and Spiroplasma
+//! assert_eq!(genetic_code(4).unwrap().translate("TGGTGA"), "WW");
+//! //GCN is always alanine, AAR always lysine
+//! assert_eq!(bacterial.translate("GCNAAR"), "AK");
+//!```
+
+///the translation table used when a feature has no /transl_table
+pub const DEFAULT_TABLE: u8 = 11;
+
+///an NCBI genetic code, the amino acids and start codons are listed in the NCBI codon order (TTT, TTC, TTA, TTG, TCT...)
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GeneticCode {
+    pub id: u8,
+    pub name: &'static str,
+    amino_acids: &'static str,
+    starts: &'static str,
+}
+
+///the NCBI translation tables by id, tables 7, 8, 17 to 20 and 32 are not assigned
+pub const GENETIC_CODES: [GeneticCode; 26] = [
+    GeneticCode {
+        id: 1,
+        name: "Standard",
+        amino_acids: "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M------**--*----M---------------M----------------------------",
+    },
+    GeneticCode {
+        id: 2,
+        name: "Vertebrate Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+        starts: "----------**--------------------MMMM----------**---M------------",
+    },
+    GeneticCode {
+        id: 3,
+        name: "Yeast Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWTTTTPPPPHHQQRRRRIIMMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------**----------------------MM---------------M------------",
+    },
+    GeneticCode {
+        id: 4,
+        name: "Mold, Protozoan, and Coelenterate Mitochondrial and Mycoplasma/Spiroplasma",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--MM------**-------M------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 5,
+        name: "Invertebrate Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSSSVVVVAAAADDEEGGGG",
+        starts: "---M------**--------------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 6,
+        name: "Ciliate, Dasycladacean and Hexamita Nuclear",
+        amino_acids: "FFLLSSSSYYQQCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--------------*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 9,
+        name: "Echinoderm and Flatworm Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "----------**-----------------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 10,
+        name: "Euplotid Nuclear",
+        amino_acids: "FFLLSSSSYY**CCCWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------**-----------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 11,
+        name: "Bacterial, Archaeal and Plant Plastid",
+        amino_acids: "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M------**--*----M------------MMMM---------------M------------",
+    },
+    GeneticCode {
+        id: 12,
+        name: "Alternative Yeast Nuclear",
+        amino_acids: "FFLLSSSSYY**CC*WLLLSPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------**--*----M---------------M----------------------------",
+    },
+    GeneticCode {
+        id: 13,
+        name: "Ascidian Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNKKSSGGVVVVAAAADDEEGGGG",
+        starts: "---M------**----------------------MM---------------M------------",
+    },
+    GeneticCode {
+        id: 14,
+        name: "Alternative Flatworm Mitochondrial",
+        amino_acids: "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "-----------*-----------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 15,
+        name: "Blepharisma Macronuclear",
+        amino_acids: "FFLLSSSSYY*QCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------*---*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 16,
+        name: "Chlorophycean Mitochondrial",
+        amino_acids: "FFLLSSSSYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------*---*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 21,
+        name: "Trematode Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIMMTTTTNNNKSSSSVVVVAAAADDEEGGGG",
+        starts: "----------**-----------------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 22,
+        name: "Scenedesmus obliquus Mitochondrial",
+        amino_acids: "FFLLSS*SYY*LCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "------*---*---*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 23,
+        name: "Thraustochytrium Mitochondrial",
+        amino_acids: "FF*LSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--*-------**--*-----------------M--M---------------M------------",
+    },
+    GeneticCode {
+        id: 24,
+        name: "Rhabdopleuridae Mitochondrial",
+        amino_acids: "FFLLSSSSYY**CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        starts: "---M------**-------M---------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 25,
+        name: "Candidate Division SR1 and Gracilibacteria",
+        amino_acids: "FFLLSSSSYY**CCGWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "---M------**-----------------------M---------------M------------",
+    },
+    GeneticCode {
+        id: 26,
+        name: "Pachysolen tannophilus Nuclear",
+        amino_acids: "FFLLSSSSYY**CC*WLLLAPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------**--*----M---------------M----------------------------",
+    },
+    GeneticCode {
+        id: 27,
+        name: "Karyorelict Nuclear",
+        amino_acids: "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--------------*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 28,
+        name: "Condylostoma Nuclear",
+        amino_acids: "FFLLSSSSYYQQCCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------**--*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 29,
+        name: "Mesodinium Nuclear",
+        amino_acids: "FFLLSSSSYYYYCC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--------------*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 30,
+        name: "Peritrich Nuclear",
+        amino_acids: "FFLLSSSSYYEECC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "--------------*--------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 31,
+        name: "Blastocrithidia Nuclear",
+        amino_acids: "FFLLSSSSYYEECCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        starts: "----------**-----------------------M----------------------------",
+    },
+    GeneticCode {
+        id: 33,
+        name: "Cephalodiscidae Mitochondrial UAA-Tyr",
+        amino_acids: "FFLLSSSSYYY*CCWWLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSSKVVVVAAAADDEEGGGG",
+        starts: "---M-------*-------M---------------M---------------M------------",
+    },
+];
+
+///the NCBI genetic code with the given /transl_table id
+pub fn genetic_code(id: u8) -> Option<&'static GeneticCode> {
+    GENETIC_CODES.iter().find(|code| code.id == id)
+}
+
+///the genetic code for a /transl_table, an unknown table gives the ```DEFAULT_TABLE``` code and an ```UnknownTable``` issue
+pub fn genetic_code_or_default(id: u8) -> (&'static GeneticCode, Option<TranslationIssue>) {
+    match genetic_code(id) {
+        Some(code) => (code, None),
+        None => (
+            genetic_code(DEFAULT_TABLE).unwrap_or(&GENETIC_CODES[0]),
+            Some(TranslationIssue::UnknownTable { table: id }),
+        ),
+    }
+}
+
+//the bases a nucleotide or IUPAC ambiguity code stands for, as indices in the NCBI order T, C, A, G
+fn bases(nucleotide: u8) -> &'static [usize] {
+    match nucleotide.to_ascii_uppercase() {
+        b'T' | b'U' => &[0],
+        b'C' => &[1],
+        b'A' => &[2],
+        b'G' => &[3],
+        b'Y' => &[0, 1],
+        b'R' => &[2, 3],
+        b'W' => &[0, 2],
+        b'S' => &[1, 3],
+        b'K' => &[0, 3],
+        b'M' => &[1, 2],
+        b'B' => &[0, 1, 3],
+        b'D' => &[0, 2, 3],
+        b'H' => &[0, 1, 2],
+        b'V' => &[1, 2, 3],
+        b'N' => &[0, 1, 2, 3],
+        _ => &[],
+    }
+}
+
+//the positions in the codon order of every codon an ambiguous codon stands for, empty for a codon with a gap or an unknown character
+fn codon_indices(codon: &[u8]) -> Vec<usize> {
+    let mut indices = Vec::new();
+    if let [first, second, third] = codon {
+        for a in bases(*first) {
+            for b in bases(*second) {
+                for c in bases(*third) {
+                    indices.push(a * 16 + b * 4 + c);
+                }
+            }
+        }
+    }
+    indices
+}
+
+impl GeneticCode {
+    ///the amino acid of a codon, ```*``` for a stop codon
+    ///an ambiguous codon gives the amino acid shared by every codon it stands for, B (N or D), Z (Q or E), J (I or L) or otherwise X
+    pub fn translate_codon(&self, codon: &[u8]) -> char {
+        let amino_acids = self.amino_acids.as_bytes();
+        let mut found: Vec<u8> = codon_indices(codon)
+            .into_iter()
+            .map(|i| amino_acids[i])
+            .collect();
+        found.sort_unstable();
+        found.dedup();
+        match found.as_slice() {
+            [single] => *single as char,
+            b"DN" => 'B',
+            b"EQ" => 'Z',
+            b"IL" => 'J',
+            _ => 'X',
+        }
+    }
+    ///true when the codon can initiate translation in this table, every codon an ambiguous codon stands for must be a start codon
+    pub fn is_start(&self, codon: &[u8]) -> bool {
+        let indices = codon_indices(codon);
+        !indices.is_empty() && indices.iter().all(|i| self.starts.as_bytes()[*i] == b'M')
+    }
+    ///true when every codon an ambiguous codon stands for is a stop codon
+    pub fn is_stop(&self, codon: &[u8]) -> bool {
+        self.translate_codon(codon) == '*'
+    }
+    ///translates every complete codon of the sequence, stop codons are kept as ```*```
+    pub fn translate(&self, sequence: &str) -> String {
+        sequence
+            .as_bytes()
+            .chunks_exact(3)
+            .map(|codon| self.translate_codon(codon))
+            .collect()
+    }
+    ///translates a coding sequence, when the 5' end is complete an alternative initiation codon (GTG, TTG...) at the start is translated to Met
+    pub fn translate_cds(&self, sequence: &str, complete_start: bool) -> String {
+        let mut protein = self.translate(sequence);
+        if complete_start && self.is_start(sequence.as_bytes().get(..3).unwrap_or_default()) {
+            protein.replace_range(..1, "M");
+        }
+        protein
+    }
+}
+
+///a difference between the protein translated from the sequence of a CDS and its annotated /translation
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TranslationIssue {
+    ///a stop codon before the end of the coding sequence, at this amino acid position (from 1)
+    InternalStop { position: usize },
+    ///the proteins have different lengths, the closing stop codon is not counted
+    LengthDifference { computed: usize, annotated: usize },
+    ///the first amino acids differ, e.g. an alternative start codon or a codon_start off by one
+    FirstCodonDifference { computed: char, annotated: char },
+    ///the first position after the start where the proteins differ
+    Mismatch {
+        position: usize,
+        computed: char,
+        annotated: char,
+    },
+    ///the /transl_table is not a known genetic code, the CDS was translated with ```DEFAULT_TABLE``` instead
+    UnknownTable { table: u8 },
+}
+
+///compares a translated CDS (with any closing stop) against its annotated /translation, an empty list means they agree
+pub fn compare_translation(computed: &str, annotated: &str) -> Vec<TranslationIssue> {
+    let mut issues = Vec::new();
+    let computed: Vec<char> = computed
+        .strip_suffix('*')
+        .unwrap_or(computed)
+        .chars()
+        .collect();
+    let annotated: Vec<char> = annotated.chars().collect();
+    for (i, amino_acid) in computed.iter().enumerate() {
+        if *amino_acid == '*' {
+            issues.push(TranslationIssue::InternalStop { position: i + 1 });
+        }
+    }
+    if computed.len() != annotated.len() {
+        issues.push(TranslationIssue::LengthDifference {
+            computed: computed.len(),
+            annotated: annotated.len(),
+        });
+    }
+    match (computed.first(), annotated.first()) {
+        (Some(c), Some(a)) if c != a => issues.push(TranslationIssue::FirstCodonDifference {
+            computed: *c,
+            annotated: *a,
+        }),
+        _ => (),
+    }
+    //internal stops are already reported
+    let mismatch = computed
+        .iter()
+        .zip(annotated.iter())
+        .enumerate()
+        .skip(1)
+        .find(|(_, (c, a))| c != a && **c != '*');
+    if let Some((i, (c, a))) = mismatch {
+        issues.push(TranslationIssue::Mismatch {
+            position: i + 1,
+            computed: *c,
+            annotated: *a,
+        });
+    }
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_genetic_codes() {
+        for code in GENETIC_CODES.iter() {
+            assert_eq!(code.amino_acids.len(), 64, "table {}", code.id);
+            assert_eq!(code.starts.len(), 64, "table {}", code.id);
+        }
+        assert!(genetic_code(7).is_none());
+        assert_eq!(
+            genetic_code_or_default(7),
+            (
+                genetic_code(DEFAULT_TABLE).unwrap(),
+                Some(TranslationIssue::UnknownTable { table: 7 })
+            )
+        );
+        assert_eq!(genetic_code_or_default(4), (genetic_code(4).unwrap(), None));
+        let standard = genetic_code(1).unwrap();
+        assert_eq!(standard.translate("ATGGCCTAATAGTGA"), "MA***");
+        assert!(!standard.is_start(b"GTG"));
+        let bacterial = genetic_code(DEFAULT_TABLE).unwrap();
+        assert!(bacterial.is_start(b"GTG") && bacterial.is_start(b"ttg"));
+        assert_eq!(bacterial.translate_cds("TTGGTGTAA", true), "MV*");
+        assert_eq!(bacterial.translate_cds("TTGGTGTAA", false), "LV*");
+        assert_eq!(genetic_code(2).unwrap().translate("AGAATA"), "*M");
+        assert_eq!(genetic_code(25).unwrap().translate("TGA"), "G");
+        //an incomplete codon at the end is left out
+        assert_eq!(bacterial.translate("AUGGC"), "M");
+    }
+    #[test]
+    fn test_ambiguity_codes() {
+        let bacterial = genetic_code(11).unwrap();
+        assert_eq!(bacterial.translate_codon(b"GGN"), 'G');
+        assert_eq!(bacterial.translate_codon(b"TAR"), '*');
+        assert_eq!(bacterial.translate_codon(b"RAY"), 'B');
+        assert_eq!(bacterial.translate_codon(b"SAR"), 'Z');
+        assert_eq!(bacterial.translate_codon(b"MTT"), 'J');
+        assert_eq!(bacterial.translate_codon(b"NNN"), 'X');
+        assert_eq!(bacterial.translate_codon(b"A-G"), 'X');
+        assert!(bacterial.is_start(b"NTG"));
+        assert!(!bacterial.is_start(b"ANG"));
+    }
+    #[test]
+    fn test_compare_translation() {
+        assert!(compare_translation("MKV*", "MKV").is_empty());
+        assert_eq!(
+            compare_translation("VK*V*", "MKLV"),
+            vec![
+                TranslationIssue::InternalStop { position: 3 },
+                TranslationIssue::FirstCodonDifference {
+                    computed: 'V',
+                    annotated: 'M'
+                },
+            ]
+        );
+        assert_eq!(
+            compare_translation("MKA", "MKVL"),
+            vec![
+                TranslationIssue::LengthDifference {
+                    computed: 3,
+                    annotated: 4
+                },
+                TranslationIssue::Mismatch {
+                    position: 3,
+                    computed: 'A',
+                    annotated: 'V'
+                },
+            ]
+        );
+    }
+}
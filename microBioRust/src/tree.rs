@@ -0,0 +1,934 @@
+//! # Phylogenetic trees
+//!
+//! A ```Tree``` holds its nodes in a vector, each ```Node``` with its label, branch length, support value, parent and children.
+//! Trees are read from Newick (one or more trees, each ending with ```;```) and from the TREES block of NEXUS files (with its ```translate``` table),
+//! the reader recognises NEXUS from the ```#NEXUS``` line. Internal node labels that are numbers, such as bootstrap values, are read as supports.
+//!
+//! Trees can be traversed (```preorder```, ```postorder```, ```leaves```), rerooted at the midpoint or on an outgroup, pruned of tips,
+//! and give the patristic distances between their tips, e.g. to compare with a ```seqmetrics``` distance matrix in the order of ```Alignment::ids```.
+//! ```Tree::to_newick```, ```write_newick``` and ```write_nexus``` write them back.
+//!
+//!```rust
+//! use microBioRust::tree::Tree;
+//!
+//! let mut tree = Tree::from_newick("((kleb:0.1,ecoli:0.2)95:0.05,salm:0.4,vibrio:0.9);").unwrap();
+//! assert_eq!(tree.leaf_labels(), vec!["kleb", "ecoli", "salm", "vibrio"]);
+//! let kleb = tree.find("kleb").unwrap();
+//! assert_eq!(tree.nodes[tree.nodes[kleb].parent.unwrap()].support, Some(95.0));
+//!
+//! let (labels, distances) = tree.patristic_matrix();
+//! assert_eq!(labels[3], "vibrio");
+//! assert!((distances[0][1] - 0.3).abs() < 1e-9);
+//!
+//! tree.root_with_outgroup(&["vibrio"]).unwrap();
+//! assert_eq!(tree.to_newick(), "(vibrio:0.45,((kleb:0.1,ecoli:0.2)95:0.05,salm:0.4):0.45);");
+//! tree.prune(&["salm"]).unwrap();
+//! assert_eq!(tree.to_newick(), "(vibrio:0.45,(kleb:0.1,ecoli:0.2)95:0.5);");
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use anyhow::{anyhow, Context};
+use std::{
+    collections::HashMap,
+    fmt,
+    io::{self, Write},
+    path::Path,
+};
+
+///a node of a tree, the branch length and support belong to the branch leading to the node from its parent
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Node {
+    pub label: Option<String>,
+    pub branch_length: Option<f64>,
+    pub support: Option<f64>,
+    pub parent: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+impl Node {
+    pub fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+///a tree with its nodes held in a vector, nodes refer to each other by their index
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tree {
+    pub nodes: Vec<Node>,
+    pub root: usize,
+    ///the name of a NEXUS tree
+    pub name: Option<String>,
+    ///true for a ```[&R]``` tree, false for ```[&U]```, None when not said
+    pub rooted: Option<bool>,
+}
+
+impl Default for Tree {
+    fn default() -> Self {
+        Tree {
+            nodes: vec![Node::default()],
+            root: 0,
+            name: None,
+            rooted: None,
+        }
+    }
+}
+
+//characters that end an unquoted label
+const NEWICK_PUNCTUATION: &str = "():,;[";
+
+//reads a number of a Newick string
+fn parse_number(text: &str, what: &str) -> Result<f64, anyhow::Error> {
+    text.trim()
+        .parse::<f64>()
+        .map_err(|e| anyhow!("bad {} {:?}: {}", what, text, e))
+}
+
+//writes a label, quoting it when it holds Newick punctuation, quotes or whitespace
+fn quote_label(label: &str) -> String {
+    match label
+        .chars()
+        .any(|c| NEWICK_PUNCTUATION.contains(c) || c == ']' || c == '\'' || c.is_whitespace())
+    {
+        true => format!("'{}'", label.replace('\'', "''")),
+        false => label.to_string(),
+    }
+}
+
+impl Tree {
+    ///reads a tree in Newick format, the closing ```;``` may be left out
+    pub fn from_newick(text: &str) -> Result<Self, anyhow::Error> {
+        let mut tree = Tree::default();
+        let mut current = tree.root;
+        let chars: Vec<char> = text.chars().collect();
+        let mut i = 0;
+        //true once the label and length of the current node can no longer be given
+        let mut closed = false;
+        while i < chars.len() {
+            let c = chars[i];
+            match c {
+                '(' => {
+                    if closed || tree.nodes[current].label.is_some() {
+                        return Err(anyhow!("unexpected '(' at character {}", i + 1));
+                    }
+                    current = tree.add_child(current);
+                    i += 1;
+                }
+                ',' => {
+                    let parent = tree.nodes[current]
+                        .parent
+                        .ok_or(anyhow!("',' outside brackets at character {}", i + 1))?;
+                    current = tree.add_child(parent);
+                    closed = false;
+                    i += 1;
+                }
+                ')' => {
+                    current = tree.nodes[current]
+                        .parent
+                        .ok_or(anyhow!("unbalanced ')' at character {}", i + 1))?;
+                    closed = false;
+                    i += 1;
+                }
+                ':' => {
+                    let start = i + 1;
+                    let mut end = start;
+                    while end < chars.len() && !NEWICK_PUNCTUATION.contains(chars[end]) {
+                        end += 1;
+                    }
+                    let length: String = chars[start..end].iter().collect();
+                    tree.nodes[current].branch_length =
+                        Some(parse_number(&length, "branch length")?);
+                    closed = true;
+                    i = end;
+                }
+                ';' => break,
+                '[' => {
+                    let end = chars[i..]
+                        .iter()
+                        .position(|c| *c == ']')
+                        .map(|p| i + p)
+                        .ok_or(anyhow!("unterminated comment at character {}", i + 1))?;
+                    let comment: String = chars[i + 1..end].iter().collect();
+                    match comment.trim() {
+                        "&R" | "&r" => tree.rooted = Some(true),
+                        "&U" | "&u" => tree.rooted = Some(false),
+                        _ => (),
+                    }
+                    i = end + 1;
+                }
+                c if c.is_whitespace() => i += 1,
+                _ => {
+                    if closed || tree.nodes[current].label.is_some() {
+                        return Err(anyhow!("unexpected label at character {}", i + 1));
+                    }
+                    let mut label = String::new();
+                    if c == '\'' {
+                        //a quoted label, '' stands for a quote
+                        i += 1;
+                        loop {
+                            match (chars.get(i), chars.get(i + 1)) {
+                                (Some('\''), Some('\'')) => {
+                                    label.push('\'');
+                                    i += 2;
+                                }
+                                (Some('\''), _) => {
+                                    i += 1;
+                                    break;
+                                }
+                                (Some(c), _) => {
+                                    label.push(*c);
+                                    i += 1;
+                                }
+                                (None, _) => return Err(anyhow!("unterminated quoted label")),
+                            }
+                        }
+                    } else {
+                        while i < chars.len()
+                            && !NEWICK_PUNCTUATION.contains(chars[i])
+                            && !chars[i].is_whitespace()
+                        {
+                            label.push(chars[i]);
+                            i += 1;
+                        }
+                    }
+                    //numbers after a closing bracket are support values, such as bootstraps
+                    let node = &mut tree.nodes[current];
+                    match (node.is_leaf(), label.parse::<f64>()) {
+                        (false, Ok(support)) => node.support = Some(support),
+                        _ => node.label = Some(label),
+                    }
+                }
+            }
+        }
+        if current != tree.root {
+            return Err(anyhow!("unbalanced brackets, a ')' is missing"));
+        }
+        Ok(tree)
+    }
+    //adds an empty child to a node, returning its index
+    fn add_child(&mut self, parent: usize) -> usize {
+        let child = self.nodes.len();
+        self.nodes.push(Node {
+            parent: Some(parent),
+            ..Default::default()
+        });
+        self.nodes[parent].children.push(child);
+        child
+    }
+    ///the tree in Newick format, ending with ```;```
+    pub fn to_newick(&self) -> String {
+        let mut text = String::new();
+        self.write_node(self.root, &mut text);
+        text.push(';');
+        text
+    }
+    fn write_node(&self, index: usize, text: &mut String) {
+        let node = &self.nodes[index];
+        if !node.is_leaf() {
+            text.push('(');
+            for (i, child) in node.children.iter().enumerate() {
+                if i > 0 {
+                    text.push(',');
+                }
+                self.write_node(*child, text);
+            }
+            text.push(')');
+        }
+        match (&node.label, node.support) {
+            (Some(label), _) => text.push_str(&quote_label(label)),
+            (None, Some(support)) => text.push_str(&support.to_string()),
+            (None, None) => (),
+        }
+        if let Some(length) = node.branch_length {
+            text.push_str(&format!(":{}", length));
+        }
+    }
+    ///the nodes with each parent before its children, children in order
+    pub fn preorder(&self) -> Vec<usize> {
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            order.push(index);
+            stack.extend(self.nodes[index].children.iter().rev());
+        }
+        order
+    }
+    ///the nodes with each node after its children
+    pub fn postorder(&self) -> Vec<usize> {
+        //a reversed preorder visiting the last child first has children before parents
+        let mut order = Vec::with_capacity(self.nodes.len());
+        let mut stack = vec![self.root];
+        while let Some(index) = stack.pop() {
+            order.push(index);
+            stack.extend(self.nodes[index].children.iter());
+        }
+        order.reverse();
+        order
+    }
+    ///the tips from left to right
+    pub fn leaves(&self) -> Vec<usize> {
+        self.preorder()
+            .into_iter()
+            .filter(|i| self.nodes[*i].is_leaf())
+            .collect()
+    }
+    ///the labels of the tips from left to right, unlabelled tips give an empty label
+    pub fn leaf_labels(&self) -> Vec<String> {
+        self.leaves()
+            .into_iter()
+            .map(|i| self.nodes[i].label.clone().unwrap_or_default())
+            .collect()
+    }
+    ///the node with the given label
+    pub fn find(&self, label: &str) -> Option<usize> {
+        self.preorder()
+            .into_iter()
+            .find(|i| self.nodes[*i].label.as_deref() == Some(label))
+    }
+    //the node followed by its ancestors up to the root
+    fn ancestors(&self, index: usize) -> Vec<usize> {
+        let mut path = vec![index];
+        while let Some(parent) = self.nodes[*path.last().unwrap_or(&index)].parent {
+            path.push(parent);
+        }
+        path
+    }
+    ///the most recent common ancestor of the nodes
+    pub fn mrca(&self, nodes: &[usize]) -> Option<usize> {
+        let (first, rest) = nodes.split_first()?;
+        let mut common = self.ancestors(*first);
+        for node in rest {
+            let ancestors = self.ancestors(*node);
+            common.retain(|a| ancestors.contains(a));
+        }
+        common.first().copied()
+    }
+    ///the sum of the branch lengths from the node up to the root, missing lengths count as 0
+    pub fn distance_to_root(&self, index: usize) -> f64 {
+        self.ancestors(index)
+            .into_iter()
+            .filter(|i| *i != self.root)
+            .map(|i| self.nodes[i].branch_length.unwrap_or(0.0))
+            .sum()
+    }
+    //the distances from a node to every node of the tree along the branches
+    fn distances_from(&self, start: usize) -> Vec<f64> {
+        let mut distances = vec![f64::NAN; self.nodes.len()];
+        distances[start] = 0.0;
+        let mut stack = vec![start];
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let mut neighbours: Vec<(usize, f64)> = node
+                .children
+                .iter()
+                .map(|c| (*c, self.nodes[*c].branch_length.unwrap_or(0.0)))
+                .collect();
+            if let Some(parent) = node.parent {
+                neighbours.push((parent, node.branch_length.unwrap_or(0.0)));
+            }
+            for (next, length) in neighbours {
+                if distances[next].is_nan() {
+                    distances[next] = distances[index] + length;
+                    stack.push(next);
+                }
+            }
+        }
+        distances
+    }
+    ///the patristic distances (sums of branch lengths) between every pair of tips, with the tip labels in leaf order
+    pub fn patristic_matrix(&self) -> (Vec<String>, Vec<Vec<f64>>) {
+        let leaves = self.leaves();
+        let matrix = leaves
+            .iter()
+            .map(|leaf| {
+                let distances = self.distances_from(*leaf);
+                leaves.iter().map(|other| distances[*other]).collect()
+            })
+            .collect();
+        (self.leaf_labels(), matrix)
+    }
+    ///the patristic distances between the tips with the given labels in that order, e.g. the ids of an alignment
+    pub fn patristic_matrix_for(&self, labels: &[String]) -> Result<Vec<Vec<f64>>, anyhow::Error> {
+        let tips = labels
+            .iter()
+            .map(|label| {
+                self.find(label)
+                    .ok_or(anyhow!("no tip {:?} in the tree", label))
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        Ok(tips
+            .iter()
+            .map(|tip| {
+                let distances = self.distances_from(*tip);
+                tips.iter().map(|other| distances[*other]).collect()
+            })
+            .collect())
+    }
+    ///places the root on the branch above a node, at the given distance from the node
+    ///the old root is removed when it is left with a single child, supports stay with their branches
+    pub fn reroot_on_branch(&mut self, node: usize, distance: f64) -> Result<(), anyhow::Error> {
+        let parent = self.nodes[node]
+            .parent
+            .ok_or(anyhow!("the root has no branch above it"))?;
+        let length = self.nodes[node].branch_length;
+        let distance = distance.clamp(0.0, length.unwrap_or(0.0));
+        //turn the branches from the parent up to the old root around
+        let path = self.ancestors(parent);
+        let lengths: Vec<Option<f64>> = path.iter().map(|i| self.nodes[*i].branch_length).collect();
+        let supports: Vec<Option<f64>> = path.iter().map(|i| self.nodes[*i].support).collect();
+        for i in 0..path.len() - 1 {
+            let (child, up) = (path[i], path[i + 1]);
+            self.nodes[up].children.retain(|c| *c != child);
+            self.nodes[child].children.push(up);
+            self.nodes[up].parent = Some(child);
+            self.nodes[up].branch_length = lengths[i];
+            self.nodes[up].support = supports[i];
+        }
+        let old_root = self.root;
+        self.nodes[parent].children.retain(|c| *c != node);
+        let root = self.nodes.len();
+        self.nodes.push(Node {
+            children: vec![node, parent],
+            ..Default::default()
+        });
+        self.nodes[node].parent = Some(root);
+        self.nodes[node].branch_length = length.map(|_| distance);
+        self.nodes[parent].parent = Some(root);
+        self.nodes[parent].branch_length = length.map(|l| l - distance);
+        self.nodes[parent].support = self.nodes[node].support;
+        self.root = root;
+        if self.nodes[old_root].children.len() == 1 {
+            self.splice(old_root);
+        }
+        self.compact();
+        Ok(())
+    }
+    //removes a node with a single child, joining its branch to the child's
+    fn splice(&mut self, index: usize) {
+        let child = self.nodes[index].children[0];
+        let node = self.nodes[index].clone();
+        self.nodes[child].parent = node.parent;
+        self.nodes[child].branch_length =
+            match (node.branch_length, self.nodes[child].branch_length) {
+                (None, None) => None,
+                (a, b) => Some(a.unwrap_or(0.0) + b.unwrap_or(0.0)),
+            };
+        if self.nodes[child].support.is_none() {
+            self.nodes[child].support = node.support;
+        }
+        match node.parent {
+            Some(parent) => {
+                for c in self.nodes[parent].children.iter_mut() {
+                    if *c == index {
+                        *c = child;
+                    }
+                }
+            }
+            None => {
+                self.root = child;
+                self.nodes[child].branch_length = None;
+                self.nodes[child].support = None;
+            }
+        }
+        self.nodes[index].children.clear();
+        self.nodes[index].parent = None;
+    }
+    //renumbers the nodes reachable from the root in preorder, dropping the others
+    fn compact(&mut self) {
+        let order = self.preorder();
+        let mut new_index = vec![usize::MAX; self.nodes.len()];
+        for (i, old) in order.iter().enumerate() {
+            new_index[*old] = i;
+        }
+        let nodes = order
+            .iter()
+            .map(|old| {
+                let mut node = self.nodes[*old].clone();
+                node.parent = node.parent.map(|p| new_index[p]);
+                node.children = node.children.iter().map(|c| new_index[*c]).collect();
+                node
+            })
+            .collect();
+        self.nodes = nodes;
+        self.root = 0;
+    }
+    ///roots the tree on the branch above the most recent common ancestor of the outgroup tips, halfway along it
+    pub fn root_with_outgroup(&mut self, labels: &[&str]) -> Result<(), anyhow::Error> {
+        let tips = labels
+            .iter()
+            .map(|label| {
+                self.find(label)
+                    .ok_or(anyhow!("no tip {:?} in the tree", label))
+            })
+            .collect::<Result<Vec<usize>, _>>()?;
+        let ancestor = self.mrca(&tips).ok_or(anyhow!("no outgroup tips given"))?;
+        if ancestor == self.root {
+            return Err(anyhow!(
+                "the outgroup {:?} is not a clade of the tree",
+                labels
+            ));
+        }
+        let half = self.nodes[ancestor].branch_length.unwrap_or(0.0) / 2.0;
+        self.reroot_on_branch(ancestor, half)
+    }
+    ///roots the tree halfway between the two tips furthest apart
+    pub fn midpoint_root(&mut self) -> Result<(), anyhow::Error> {
+        let leaves = self.leaves();
+        let mut furthest = (0.0, self.root, self.root);
+        for leaf in leaves.iter() {
+            let distances = self.distances_from(*leaf);
+            for other in leaves.iter() {
+                if distances[*other] > furthest.0 {
+                    furthest = (distances[*other], *leaf, *other);
+                }
+            }
+        }
+        let (diameter, a, b) = furthest;
+        if a == b {
+            return Err(anyhow!("the tree has no two tips apart"));
+        }
+        //walk up from the tip further from the root until the midpoint is passed
+        let (from, to) = match self.distance_to_root(a) >= self.distance_to_root(b) {
+            true => (a, b),
+            false => (b, a),
+        };
+        let ancestor = self.mrca(&[from, to]).unwrap_or(self.root);
+        let mut walked = 0.0;
+        for node in self.ancestors(from) {
+            if node == ancestor {
+                break;
+            }
+            let length = self.nodes[node].branch_length.unwrap_or(0.0);
+            if walked + length >= diameter / 2.0 {
+                let distance = diameter / 2.0 - walked;
+                //the midpoint is the ancestor of a rooted tree already
+                if (length - distance).abs() < f64::EPSILON
+                    && self.nodes[node].parent == Some(self.root)
+                    && self.nodes[self.root].children.len() == 2
+                {
+                    return Ok(());
+                }
+                return self.reroot_on_branch(node, distance);
+            }
+            walked += length;
+        }
+        Ok(())
+    }
+    ///removes the tips with the given labels, internal nodes left with one child are joined to it
+    pub fn prune(&mut self, labels: &[&str]) -> Result<(), anyhow::Error> {
+        for label in labels {
+            let tip = self
+                .find(label)
+                .filter(|t| self.nodes[*t].is_leaf())
+                .ok_or(anyhow!("no tip {:?} in the tree", label))?;
+            let mut index = tip;
+            //remove the tip and any ancestors left without children
+            loop {
+                let Some(parent) = self.nodes[index].parent else {
+                    return Err(anyhow!("pruning {:?} leaves an empty tree", label));
+                };
+                self.nodes[parent].children.retain(|c| *c != index);
+                self.nodes[index].parent = None;
+                if !self.nodes[parent].children.is_empty() {
+                    if self.nodes[parent].children.len() == 1 {
+                        self.splice(parent);
+                    }
+                    break;
+                }
+                index = parent;
+            }
+        }
+        self.compact();
+        Ok(())
+    }
+}
+
+impl fmt::Display for Tree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_newick())
+    }
+}
+
+///writes trees in Newick format, one per line
+pub fn write_newick<W: Write>(writer: &mut W, trees: &[Tree]) -> io::Result<()> {
+    for tree in trees {
+        writeln!(writer, "{}", tree.to_newick())?;
+    }
+    Ok(())
+}
+
+///writes trees as the TREES block of a NEXUS file, unnamed trees are called tree1, tree2...
+pub fn write_nexus<W: Write>(writer: &mut W, trees: &[Tree]) -> io::Result<()> {
+    writeln!(writer, "#NEXUS\nbegin trees;")?;
+    for (i, tree) in trees.iter().enumerate() {
+        let name = match &tree.name {
+            Some(name) => quote_label(name),
+            None => format!("tree{}", i + 1),
+        };
+        let rooted = match tree.rooted {
+            Some(true) => "[&R] ",
+            Some(false) => "[&U] ",
+            None => "",
+        };
+        writeln!(writer, "\ttree {} = {}{}", name, rooted, tree.to_newick())?;
+    }
+    writeln!(writer, "end;")
+}
+
+//the text of a NEXUS statement without its comments, to recognise the command
+fn without_comments(text: &str) -> String {
+    let mut plain = String::new();
+    let mut depth = 0;
+    for c in text.chars() {
+        match c {
+            '[' => depth += 1,
+            ']' if depth > 0 => depth -= 1,
+            c if depth == 0 => plain.push(c),
+            _ => (),
+        }
+    }
+    plain
+}
+
+//reads the pairs of a translate command, ```1 kleb_rpoB, 2 'E. coli'```
+fn parse_translate(text: &str) -> Result<HashMap<String, String>, anyhow::Error> {
+    let mut table = HashMap::new();
+    for pair in text.split(',').filter(|p| !p.trim().is_empty()) {
+        let (key, label) = pair
+            .trim()
+            .split_once(char::is_whitespace)
+            .ok_or(anyhow!("translate entry {:?} has no label", pair.trim()))?;
+        let label = label.trim();
+        let label = match label.strip_prefix('\'').and_then(|l| l.strip_suffix('\'')) {
+            Some(quoted) => quoted.replace("''", "'"),
+            None => label.to_string(),
+        };
+        table.insert(key.to_string(), label);
+    }
+    Ok(table)
+}
+
+///an iterator over the trees of a file
+#[derive(Debug)]
+pub struct Trees<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Trees<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Tree, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_tree() {
+            Ok(Some(tree)) => Some(Ok(tree)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///reader for Newick and NEXUS files, reading one ```;``` ended statement at a time
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    buffer: Vec<u8>,
+    line_number: usize,
+    nexus: Option<bool>,
+    in_trees_block: bool,
+    translate: HashMap<String, String>,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read trees from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read trees from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new tree reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            buffer: Vec::new(),
+            line_number: 1,
+            nexus: None,
+            in_trees_block: false,
+            translate: HashMap::new(),
+        }
+    }
+    //the next statement up to and without its ';', with the line it starts on, None at the end of the file
+    //a ';' in a quoted label or a [comment] does not end the statement
+    fn next_statement(&mut self) -> Result<Option<(usize, String)>, ParseError> {
+        self.buffer.clear();
+        let mut quoted = false;
+        let mut depth = 0usize;
+        loop {
+            let scanned = self.buffer.len();
+            let read = self
+                .reader
+                .read_until(b';', &mut self.buffer)
+                .map_err(|source| ParseError::Io {
+                    line_number: self.line_number,
+                    record_id: String::new(),
+                    line: String::new(),
+                    source,
+                })?;
+            if read == 0 {
+                break;
+            }
+            for byte in self.buffer[scanned..].iter() {
+                match byte {
+                    b'\'' if depth == 0 => quoted = !quoted,
+                    b'[' if !quoted => depth += 1,
+                    b']' if !quoted => depth = depth.saturating_sub(1),
+                    _ => (),
+                }
+            }
+            if !quoted && depth == 0 {
+                break;
+            }
+        }
+        if self.buffer.is_empty() {
+            return Ok(None);
+        }
+        let text = String::from_utf8_lossy(&self.buffer).to_string();
+        let leading = text.len() - text.trim_start().len();
+        let start = self.line_number + text[..leading].matches('\n').count();
+        self.line_number += text.matches('\n').count();
+        let mut statement = text.trim().to_string();
+        if statement.ends_with(';') {
+            statement.pop();
+        }
+        Ok(Some((start, statement)))
+    }
+    fn malformed(line_number: usize, record_id: &str, line: &str, message: String) -> ParseError {
+        ParseError::MalformedLine {
+            line_number,
+            record_id: record_id.to_string(),
+            line: line.to_string(),
+            message,
+        }
+    }
+    ///reads the next tree, returns None at the end of the file
+    pub fn read_tree(&mut self) -> Result<Option<Tree>, ParseError> {
+        loop {
+            let Some((line_number, mut statement)) = self.next_statement()? else {
+                return Ok(None);
+            };
+            if self.nexus.is_none() && statement.is_empty() {
+                continue;
+            }
+            let nexus = *self
+                .nexus
+                .get_or_insert_with(|| statement.to_ascii_uppercase().starts_with("#NEXUS"));
+            if !nexus {
+                if statement.is_empty() {
+                    continue;
+                }
+                let tree = Tree::from_newick(&statement)
+                    .map_err(|e| Self::malformed(line_number, "", &statement, e.to_string()))?;
+                return Ok(Some(tree));
+            }
+            if statement
+                .get(..6)
+                .is_some_and(|start| start.eq_ignore_ascii_case("#NEXUS"))
+            {
+                statement = statement[6..].trim().to_string();
+            }
+            let plain = without_comments(&statement);
+            let mut words = plain.split_whitespace();
+            let command = words.next().unwrap_or_default().to_ascii_lowercase();
+            match command.as_str() {
+                "begin" => {
+                    self.in_trees_block = words
+                        .next()
+                        .is_some_and(|b| b.eq_ignore_ascii_case("trees"));
+                }
+                "end" | "endblock" => self.in_trees_block = false,
+                "translate" if self.in_trees_block => {
+                    let pairs = plain.trim_start()["translate".len()..].to_string();
+                    self.translate = parse_translate(&pairs)
+                        .map_err(|e| Self::malformed(line_number, "", &statement, e.to_string()))?;
+                }
+                "tree" | "utree" if self.in_trees_block => {
+                    let (head, newick) = statement.split_once('=').ok_or_else(|| {
+                        Self::malformed(line_number, "", &statement, "tree without '='".to_string())
+                    })?;
+                    let name = without_comments(head)
+                        .split_whitespace()
+                        .nth(1)
+                        .map(|n| n.trim_matches('\'').to_string());
+                    let mut tree = Tree::from_newick(newick).map_err(|e| {
+                        Self::malformed(
+                            line_number,
+                            name.as_deref().unwrap_or_default(),
+                            &statement,
+                            e.to_string(),
+                        )
+                    })?;
+                    for node in tree.nodes.iter_mut().filter(|n| n.is_leaf()) {
+                        if let Some(label) = node.label.as_ref().and_then(|l| self.translate.get(l))
+                        {
+                            node.label = Some(label.clone());
+                        }
+                    }
+                    tree.name = name;
+                    if command == "utree" {
+                        tree.rooted = Some(false);
+                    }
+                    return Ok(Some(tree));
+                }
+                _ => (),
+            }
+        }
+    }
+    ///return an iterator over the trees of the file
+    pub fn trees(self) -> Trees<B> {
+        Trees {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newick() {
+        let text = "(('E. coli K-12':0.1,kleb[&&NHX:S=kleb]:0.2)0.98:0.05,(salm:0.3,vibrio:0.4)n2:0.05);\n(a,b,(c,d));\n";
+        let trees: Vec<Tree> = Reader::new(text.as_bytes())
+            .trees()
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(trees.len(), 2);
+        let first = &trees[0];
+        assert_eq!(
+            first.leaf_labels(),
+            vec!["E. coli K-12", "kleb", "salm", "vibrio"]
+        );
+        assert_eq!(
+            first.nodes[first.nodes[1].children[0]].label.as_deref(),
+            Some("E. coli K-12")
+        );
+        assert_eq!(first.nodes[1].support, Some(0.98));
+        assert_eq!(
+            first.nodes[first.find("n2").unwrap()].branch_length,
+            Some(0.05)
+        );
+        assert_eq!(
+            first.to_newick(),
+            "(('E. coli K-12':0.1,kleb:0.2)0.98:0.05,(salm:0.3,vibrio:0.4)n2:0.05);"
+        );
+        assert_eq!(trees[1].to_newick(), "(a,b,(c,d));");
+        let postorder: Vec<String> = trees[1]
+            .postorder()
+            .into_iter()
+            .filter_map(|i| trees[1].nodes[i].label.clone())
+            .collect();
+        assert_eq!(postorder, vec!["a", "b", "c", "d"]);
+        assert_eq!(*trees[1].postorder().last().unwrap(), trees[1].root);
+        for bad in ["((a,b);", "(a,b));", "(a,b):x;"] {
+            assert!(Tree::from_newick(bad).is_err(), "{}", bad);
+        }
+    }
+    #[test]
+    fn test_nexus() {
+        let text = concat!(
+            "#Nexus\n",
+            "[written by hand; not by a program]\n",
+            "begin taxa;\n  dimensions ntax=3;\nend;\n",
+            "BEGIN TREES;\n",
+            "  Translate\n    1 kleb_rpoB,\n    2 'E. coli',\n    3 salm_rpoB\n  ;\n",
+            "  tree best = [&R] ((1:0.1,2:0.2):0.05,3:0.3);\n",
+            "  tree quoted = ('a;b',[c;d]'e',f);\n",
+            "  tree other = [&U] (1,2,3);\n",
+            "END;\n",
+        );
+        let trees: Vec<Tree> = Reader::new(text.as_bytes())
+            .trees()
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(trees.len(), 3);
+        assert_eq!(trees[0].name.as_deref(), Some("best"));
+        assert_eq!(trees[0].rooted, Some(true));
+        assert_eq!(
+            trees[0].leaf_labels(),
+            vec!["kleb_rpoB", "E. coli", "salm_rpoB"]
+        );
+        assert_eq!(trees[1].leaf_labels(), vec!["a;b", "e", "f"]);
+        assert_eq!(trees[2].rooted, Some(false));
+        let mut written = Vec::new();
+        write_nexus(&mut written, &trees).unwrap();
+        let written = String::from_utf8(written).unwrap();
+        assert!(written
+            .contains("\ttree best = [&R] ((kleb_rpoB:0.1,'E. coli':0.2):0.05,salm_rpoB:0.3);\n"));
+        let again: Vec<Tree> = Reader::new(written.as_bytes())
+            .trees()
+            .map(|t| t.unwrap())
+            .collect();
+        assert_eq!(again, trees);
+        match Reader::new("#NEXUS\nbegin trees;\ntree bad = ((a,b);\nend;\n".as_bytes()).read_tree()
+        {
+            Err(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                ..
+            }) => assert_eq!((line_number, record_id.as_str()), (3, "bad")),
+            other => panic!("expected a malformed tree, got {:?}", other),
+        }
+    }
+    #[test]
+    fn test_reroot_and_distances() {
+        let mut tree = Tree::from_newick("((a:1,b:2)90:1,c:4,(d:1,e:1)80:0.5);").unwrap();
+        let (labels, matrix) = tree.patristic_matrix();
+        assert_eq!(labels, vec!["a", "b", "c", "d", "e"]);
+        assert_eq!(matrix[0][1], 3.0);
+        assert_eq!(matrix[1][2], 7.0);
+        assert_eq!(matrix[1][4], 4.5);
+        //the root moves but the distances do not
+        tree.midpoint_root().unwrap();
+        assert_eq!(tree.nodes[tree.root].children.len(), 2);
+        let ordered = tree.patristic_matrix_for(&labels).unwrap();
+        assert_eq!(ordered, matrix);
+        //b and c are 7 apart, the midpoint is 3.5 from c
+        let c = tree.find("c").unwrap();
+        assert_eq!(tree.distance_to_root(c), 3.5);
+        assert_eq!(tree.nodes[c].parent, Some(tree.root));
+        tree.root_with_outgroup(&["d", "e"]).unwrap();
+        assert_eq!(
+            tree.to_newick(),
+            "((d:1,e:1)80:0.25,((a:1,b:2)90:1,c:4)80:0.25);"
+        );
+        assert_eq!(tree.patristic_matrix_for(&labels).unwrap(), matrix);
+        assert!(tree.root_with_outgroup(&["d", "a", "c", "b", "e"]).is_err());
+        tree.prune(&["c", "b"]).unwrap();
+        assert_eq!(tree.to_newick(), "((d:1,e:1)80:0.25,a:2.25);");
+        assert!(tree.prune(&["x"]).is_err());
+    }
+}
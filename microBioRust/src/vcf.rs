@@ -0,0 +1,812 @@
+//! # VCF reading and variant annotation
+//!
+//! A streaming reader for VCF 4.x, as written by snippy, bcftools or freebayes. The ```##``` meta lines are kept in a ```Header```
+//! (INFO, FORMAT, FILTER and contig definitions with their ```ID```, ```Number```, ```Type``` and ```Description```) with the sample names of the ```#CHROM``` line,
+//! and each data line is a ```Variant``` with its INFO values and the FORMAT values and ```Genotype``` of every sample. Compressed files (gzip, BGZF...) are read transparently.
+//!
+//! ```annotate``` places a variant on the CDS of a ```gbk::Record```, giving the gene, locus tag, codon number and position in the codon,
+//! the reference and alternate codons and amino acids (with the /transl_table of the CDS) and whether the change is synonymous, missense or nonsense.
+//!
+//!```rust
+//! use microBioRust::gbk::Reader as GbkReader;
+//! use microBioRust::vcf::{annotate, Effect, Reader};
+//!
+//! let text = concat!(
+//!     "##fileformat=VCFv4.2\n",
+//!     "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Total read depth\">\n",
+//!     "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n",
+//!     "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tsample1\n",
+//!     "NC_000913\t360\t.\tC\tT\t225.4\tPASS\tDP=42\tGT\t1\n",
+//! );
+//! let mut reader = Reader::new(text.as_bytes());
+//! let variant = reader.read_variant().unwrap().unwrap();
+//! assert_eq!(reader.header().samples, vec!["sample1"]);
+//! assert_eq!(variant.info("DP"), Some("42"));
+//! assert_eq!(variant.genotype(0).unwrap().alleles, vec![Some(1)]);
+//!
+//! let record = GbkReader::from_file("K12_ribo.gbk").unwrap().records().next().unwrap().unwrap();
+//! let annotation = &annotate(&record, &variant).unwrap()[0];
+//! assert_eq!(annotation.locus_tag, "b3304");
+//! assert_eq!((annotation.codon_number, annotation.codon_position), (2, 1));
+//! assert_eq!((annotation.ref_codon.as_str(), annotation.alt_codon.as_str()), ("GAT", "AAT"));
+//! assert_eq!(annotation.effect, Effect::Missense);
+//!```
+
+use crate::compression::open;
+use crate::error::ParseError;
+use crate::gbk::Record;
+use crate::translation::{genetic_code, DEFAULT_TABLE};
+use anyhow::{anyhow, Context};
+use bio::alphabets::dna::complement;
+use std::{fmt, io, path::Path};
+
+///a ```##key=value``` meta line, structured lines such as ```##INFO=<ID=DP,Number=1,...>``` have their fields split out
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct MetaLine {
+    pub key: String,
+    pub value: String,
+    ///the ```key=value``` pairs between the angle brackets, quotes removed, empty for plain lines such as ```##source```
+    pub fields: Vec<(String, String)>,
+}
+
+impl MetaLine {
+    ///reads a meta line without its leading ```##```
+    pub fn parse(text: &str) -> Result<Self, anyhow::Error> {
+        let (key, value) = text
+            .split_once('=')
+            .ok_or(anyhow!("meta line without '='"))?;
+        let mut meta = MetaLine {
+            key: key.to_string(),
+            value: value.to_string(),
+            fields: Vec::new(),
+        };
+        if let Some(inner) = value.strip_prefix('<').and_then(|v| v.strip_suffix('>')) {
+            meta.fields = parse_structured(inner)?;
+        }
+        Ok(meta)
+    }
+    ///the value of a field of a structured line, e.g. ```ID``` or ```Description```
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(key, _)| key == field)
+            .map(|(_, value)| value.as_str())
+    }
+    pub fn id(&self) -> Option<&str> {
+        self.get("ID")
+    }
+}
+
+//splits the fields of a structured meta line, quoted values can hold commas and escaped quotes
+fn parse_structured(text: &str) -> Result<Vec<(String, String)>, anyhow::Error> {
+    let mut fields = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (key, after_key) = rest
+            .split_once('=')
+            .ok_or(anyhow!("field {:?} has no value", rest))?;
+        let (value, after_value) = match after_key.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = None;
+                while let Some((i, c)) = chars.next() {
+                    match c {
+                        '\\' => {
+                            if let Some((_, escaped)) = chars.next() {
+                                value.push(escaped);
+                            }
+                        }
+                        '"' => {
+                            end = Some(i);
+                            break;
+                        }
+                        c => value.push(c),
+                    }
+                }
+                let end = end.ok_or(anyhow!("unterminated value for {:?}", key))?;
+                (value, &quoted[end + 1..])
+            }
+            None => {
+                let end = after_key.find(',').unwrap_or(after_key.len());
+                (after_key[..end].to_string(), &after_key[end..])
+            }
+        };
+        fields.push((key.trim().to_string(), value));
+        rest = after_value.strip_prefix(',').unwrap_or(after_value);
+    }
+    Ok(fields)
+}
+
+///the meta lines and sample names of a file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Header {
+    ///e.g. ```VCFv4.2```
+    pub file_format: String,
+    pub meta: Vec<MetaLine>,
+    pub samples: Vec<String>,
+}
+
+impl Header {
+    ///the meta lines with the given key, e.g. ```INFO```, ```FORMAT```, ```FILTER``` or ```contig```
+    pub fn meta_lines<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a MetaLine> {
+        self.meta.iter().filter(move |meta| meta.key == key)
+    }
+    ///the definition of an INFO key
+    pub fn info(&self, id: &str) -> Option<&MetaLine> {
+        self.meta_lines("INFO").find(|meta| meta.id() == Some(id))
+    }
+    ///the definition of a FORMAT key
+    pub fn format(&self, id: &str) -> Option<&MetaLine> {
+        self.meta_lines("FORMAT").find(|meta| meta.id() == Some(id))
+    }
+    ///the contigs with their length when given
+    pub fn contigs(&self) -> Vec<(&str, Option<u32>)> {
+        self.meta_lines("contig")
+            .filter_map(|meta| {
+                meta.id()
+                    .map(|id| (id, meta.get("length").and_then(|l| l.parse().ok())))
+            })
+            .collect()
+    }
+    ///the column of a sample, counted from 0
+    pub fn sample_index(&self, name: &str) -> Option<usize> {
+        self.samples.iter().position(|sample| sample == name)
+    }
+}
+
+///the called alleles of a sample, 0 is the reference and ```None``` a missing call (```.```), bacterial calls are often haploid
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct Genotype {
+    pub alleles: Vec<Option<usize>>,
+    pub phased: bool,
+}
+
+impl Genotype {
+    ///reads a GT value such as ```0/1```, ```1|0```, ```./.``` or ```1```
+    pub fn parse(text: &str) -> Result<Self, anyhow::Error> {
+        let mut alleles = Vec::new();
+        for allele in text.split(['/', '|']) {
+            alleles.push(match allele {
+                "." => None,
+                number => Some(number.parse::<usize>().map_err(|e| {
+                    anyhow!("bad allele {:?} in genotype {:?}: {}", number, text, e)
+                })?),
+            });
+        }
+        Ok(Genotype {
+            alleles,
+            phased: text.contains('|'),
+        })
+    }
+    ///true when no allele was called
+    pub fn is_missing(&self) -> bool {
+        self.alleles.iter().all(|allele| allele.is_none())
+    }
+    ///true when the called alleles differ
+    pub fn is_heterozygous(&self) -> bool {
+        let called: Vec<usize> = self.alleles.iter().flatten().copied().collect();
+        called.windows(2).any(|pair| pair[0] != pair[1])
+    }
+    ///true when an alternate allele was called
+    pub fn has_alt(&self) -> bool {
+        self.alleles.iter().flatten().any(|allele| *allele > 0)
+    }
+}
+
+impl fmt::Display for Genotype {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let alleles: Vec<String> = self
+            .alleles
+            .iter()
+            .map(|allele| match allele {
+                Some(allele) => allele.to_string(),
+                None => ".".to_string(),
+            })
+            .collect();
+        write!(f, "{}", alleles.join(if self.phased { "|" } else { "/" }))
+    }
+}
+
+///a data line, positions count from 1 as in the file
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Variant {
+    pub chrom: String,
+    pub pos: u32,
+    ///None for ```.```
+    pub id: Option<String>,
+    pub reference: String,
+    ///the alternate alleles, empty for ```.```
+    pub alternates: Vec<String>,
+    ///None for ```.```
+    pub qual: Option<f64>,
+    ///```PASS``` or the failed filters, empty for ```.```
+    pub filters: Vec<String>,
+    ///the INFO entries in file order, flags have no value
+    pub info: Vec<(String, Option<String>)>,
+    pub format: Vec<String>,
+    ///the values of each sample in the order of ```format```
+    pub samples: Vec<Vec<String>>,
+}
+
+impl Variant {
+    ///reads a tab separated data line
+    pub fn from_line(line: &str) -> Result<Self, anyhow::Error> {
+        let columns: Vec<&str> = line.split('\t').collect();
+        if columns.len() < 8 {
+            return Err(anyhow!(
+                "expected at least 8 tab separated columns, found {}",
+                columns.len()
+            ));
+        }
+        let dot_list = |text: &str, separator: char| -> Vec<String> {
+            match text {
+                "." | "" => Vec::new(),
+                text => text.split(separator).map(|v| v.to_string()).collect(),
+            }
+        };
+        let info = dot_list(columns[7], ';')
+            .into_iter()
+            .map(|entry| match entry.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (entry, None),
+            })
+            .collect();
+        Ok(Variant {
+            chrom: columns[0].to_string(),
+            pos: columns[1]
+                .parse()
+                .map_err(|e| anyhow!("bad POS {:?}: {}", columns[1], e))?,
+            id: match columns[2] {
+                "." => None,
+                id => Some(id.to_string()),
+            },
+            reference: columns[3].to_string(),
+            alternates: dot_list(columns[4], ','),
+            qual: match columns[5] {
+                "." => None,
+                qual => Some(
+                    qual.parse()
+                        .map_err(|e| anyhow!("bad QUAL {:?}: {}", qual, e))?,
+                ),
+            },
+            filters: dot_list(columns[6], ';'),
+            info,
+            format: columns
+                .get(8)
+                .map(|format| dot_list(format, ':'))
+                .unwrap_or_default(),
+            samples: columns
+                .iter()
+                .skip(9)
+                .map(|sample| sample.split(':').map(|v| v.to_string()).collect())
+                .collect(),
+        })
+    }
+    ///the value of an INFO key, a flag gives an empty value
+    pub fn info(&self, key: &str) -> Option<&str> {
+        self.info
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_deref().unwrap_or_default())
+    }
+    ///true when the variant passed every filter, or was not filtered
+    pub fn is_pass(&self) -> bool {
+        self.filters.is_empty() || self.filters.iter().all(|f| f == "PASS")
+    }
+    ///the FORMAT value of a sample, counted from 0, trailing values may be left out of the sample column
+    pub fn sample_value(&self, sample: usize, key: &str) -> Option<&str> {
+        let index = self.format.iter().position(|k| k == key)?;
+        self.samples
+            .get(sample)?
+            .get(index)
+            .map(|value| value.as_str())
+    }
+    ///the genotype (GT) of a sample, counted from 0
+    pub fn genotype(&self, sample: usize) -> Option<Genotype> {
+        self.sample_value(sample, "GT")
+            .and_then(|gt| Genotype::parse(gt).ok())
+    }
+    ///the allele with the given number, 0 is the reference
+    pub fn allele(&self, number: usize) -> Option<&str> {
+        match number {
+            0 => Some(&self.reference),
+            n => self.alternates.get(n - 1).map(|a| a.as_str()),
+        }
+    }
+    ///the last reference base covered by the variant
+    pub fn end(&self) -> u32 {
+        self.pos + (self.reference.len() as u32).max(1) - 1
+    }
+}
+
+///the consequence of an alternate allele on a CDS
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Effect {
+    ///the amino acids are unchanged
+    Synonymous,
+    ///an amino acid is changed
+    Missense,
+    ///a codon becomes a stop codon
+    Nonsense,
+    ///the stop codon becomes a sense codon
+    StopLost,
+    ///the start codon is no longer an initiation codon
+    StartLost,
+    ///an insertion or deletion of a multiple of three bases
+    InframeIndel,
+    ///an insertion or deletion changing the reading frame
+    Frameshift,
+}
+
+impl fmt::Display for Effect {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Effect::Synonymous => "synonymous",
+            Effect::Missense => "missense",
+            Effect::Nonsense => "nonsense",
+            Effect::StopLost => "stop_lost",
+            Effect::StartLost => "start_lost",
+            Effect::InframeIndel => "inframe_indel",
+            Effect::Frameshift => "frameshift",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+///an alternate allele placed on a CDS
+#[derive(Debug, Clone, PartialEq)]
+pub struct Annotation {
+    pub allele: String,
+    pub locus_tag: String,
+    pub gene: Option<String>,
+    pub product: Option<String>,
+    ///the first codon changed, counted from 1 at the start of the CDS
+    pub codon_number: u32,
+    ///the position of the first changed base in that codon, 1 to 3
+    pub codon_position: u8,
+    ///the reference and alternate codons of a substitution on the coding strand, several codons for a change spanning codons, empty for indels
+    pub ref_codon: String,
+    pub alt_codon: String,
+    pub ref_aa: String,
+    pub alt_aa: String,
+    pub effect: Effect,
+}
+
+//the offset of a base on the coding strand of a location, counted from 0, None when the location does not cover it
+fn coding_offset(segments: &[(u32, u32, i8)], pos: u32, length: u32) -> Option<u32> {
+    let mut offset = 0;
+    for (start, end, strand) in segments.iter().copied() {
+        //a span such as 4641000..250 runs over the origin of a circular record
+        let (span, within) = match start <= end {
+            true => (end - start + 1, start <= pos && pos <= end),
+            false => (length - start + 1 + end, pos >= start || pos <= end),
+        };
+        if within {
+            let from_start = match pos >= start {
+                true => pos - start,
+                false => length - start + 1 + pos - 1,
+            };
+            return Some(match strand {
+                -1 => offset + span - 1 - from_start,
+                _ => offset + from_start,
+            });
+        }
+        offset += span;
+    }
+    None
+}
+
+//symbolic alleles (<DEL>, *, breakends) have no sequence to place on a CDS
+fn is_symbolic(allele: &str) -> bool {
+    allele.is_empty() || !allele.bytes().all(|b| b.is_ascii_alphabetic())
+}
+
+///places each alternate allele of a variant on the CDS of a record it falls in, a variant between genes gives no annotation
+///the record should be the one named in the CHROM column, an error is returned when the REF bases differ from the record sequence
+pub fn annotate(record: &Record, variant: &Variant) -> Result<Vec<Annotation>, anyhow::Error> {
+    let mut annotations = Vec::new();
+    let sequence = record.sequence.as_bytes();
+    let start = variant.pos as usize;
+    match sequence.get(start.max(1) - 1..variant.end() as usize) {
+        Some(bases) if bases.eq_ignore_ascii_case(variant.reference.as_bytes()) => (),
+        Some(bases) => {
+            return Err(anyhow!(
+                "REF {} at {} does not match {} in {}",
+                variant.reference,
+                variant.pos,
+                String::from_utf8_lossy(bases),
+                record.id
+            ))
+        }
+        None => {
+            return Err(anyhow!(
+                "position {} is past the end of {}",
+                variant.pos,
+                record.id
+            ))
+        }
+    }
+    let length = record.sequence.len() as u32;
+    for (locus_tag, _) in record.cds.attributes.iter() {
+        let Some(location) = record.cds.get_location(locus_tag) else {
+            continue;
+        };
+        let segments = location.segments();
+        //the reference bases of the variant that fall in the CDS, with their coding offsets
+        let covered: Vec<(usize, u32)> = (0..variant.reference.len())
+            .filter_map(|i| {
+                coding_offset(&segments, variant.pos + i as u32, length).map(|offset| (i, offset))
+            })
+            .collect();
+        let Some(first_offset) = covered.iter().map(|(_, offset)| *offset).min() else {
+            continue;
+        };
+        let qualifiers = record.cds.get_qualifiers(locus_tag);
+        let code = genetic_code(
+            qualifiers
+                .and_then(|q| q.transl_table())
+                .unwrap_or(DEFAULT_TABLE),
+        )
+        .ok_or(anyhow!("unknown transl_table for {}", locus_tag))?;
+        let frame = record
+            .cds
+            .get_codon_start(locus_tag)
+            .map(|c| c.saturating_sub(1) as u32)
+            .unwrap_or(0);
+        let Some(first_codon_offset) = first_offset.checked_sub(frame) else {
+            continue;
+        };
+        let coding = location.extract(&record.sequence).to_ascii_uppercase();
+        let complete_start = !location.partial_start();
+        let strand = location.strand();
+        for alternate in variant.alternates.iter() {
+            if is_symbolic(alternate) {
+                continue;
+            }
+            let mut annotation = Annotation {
+                allele: alternate.clone(),
+                locus_tag: locus_tag.clone(),
+                gene: record.cds.get_gene(locus_tag).cloned(),
+                product: record.cds.get_product(locus_tag).cloned(),
+                codon_number: first_codon_offset / 3 + 1,
+                codon_position: (first_codon_offset % 3 + 1) as u8,
+                ref_codon: String::new(),
+                alt_codon: String::new(),
+                ref_aa: String::new(),
+                alt_aa: String::new(),
+                effect: Effect::Synonymous,
+            };
+            if alternate.len() != variant.reference.len() {
+                let difference = alternate.len().abs_diff(variant.reference.len());
+                annotation.effect = match difference % 3 {
+                    0 => Effect::InframeIndel,
+                    _ => Effect::Frameshift,
+                };
+                annotations.push(annotation);
+                continue;
+            }
+            //a substitution, the changed bases are complemented on the reverse strand
+            let mut changed = coding.clone().into_bytes();
+            for (i, offset) in covered.iter().copied() {
+                let base = alternate.as_bytes()[i].to_ascii_uppercase();
+                if let Some(coding_base) = changed.get_mut(offset as usize) {
+                    *coding_base = match strand {
+                        -1 => complement(base),
+                        _ => base,
+                    };
+                }
+            }
+            let codons: Vec<u32> = covered
+                .iter()
+                .filter_map(|(_, offset)| offset.checked_sub(frame).map(|o| o / 3))
+                .collect();
+            let (first, last) = (
+                *codons.iter().min().unwrap_or(&0) as usize,
+                *codons.iter().max().unwrap_or(&0) as usize,
+            );
+            let range =
+                frame as usize + first * 3..(frame as usize + (last + 1) * 3).min(coding.len());
+            annotation.ref_codon = coding.get(range.clone()).unwrap_or_default().to_string();
+            annotation.alt_codon =
+                String::from_utf8_lossy(changed.get(range).unwrap_or_default()).to_string();
+            let translate = |codons: &str| match first == 0 && frame == 0 {
+                true => code.translate_cds(codons, complete_start),
+                false => code.translate(codons),
+            };
+            annotation.ref_aa = translate(&annotation.ref_codon);
+            annotation.alt_aa = translate(&annotation.alt_codon);
+            let starts_lost = first == 0
+                && frame == 0
+                && complete_start
+                && code.is_start(annotation.ref_codon.as_bytes().get(..3).unwrap_or_default())
+                && !code.is_start(annotation.alt_codon.as_bytes().get(..3).unwrap_or_default());
+            let stops = |aa: &str| aa.matches('*').count();
+            annotation.effect = if starts_lost {
+                Effect::StartLost
+            } else if stops(&annotation.alt_aa) > stops(&annotation.ref_aa) {
+                Effect::Nonsense
+            } else if stops(&annotation.alt_aa) < stops(&annotation.ref_aa) {
+                Effect::StopLost
+            } else if annotation.alt_aa == annotation.ref_aa {
+                Effect::Synonymous
+            } else {
+                Effect::Missense
+            };
+            annotations.push(annotation);
+        }
+    }
+    Ok(annotations)
+}
+
+///an iterator over the variants of a file
+#[derive(Debug)]
+pub struct Variants<B> {
+    reader: Reader<B>,
+    error_has_occurred: bool,
+}
+
+impl<B> Iterator for Variants<B>
+where
+    B: io::BufRead,
+{
+    type Item = Result<Variant, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.error_has_occurred {
+            return None;
+        }
+        match self.reader.read_variant() {
+            Ok(Some(variant)) => Some(Ok(variant)),
+            Ok(None) => None,
+            Err(err) => {
+                self.error_has_occurred = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+///per line reader for the file
+#[derive(Debug)]
+pub struct Reader<B> {
+    reader: B,
+    line_buffer: String,
+    line_number: usize,
+    header: Header,
+    header_read: bool,
+}
+
+impl Reader<io::BufReader<Box<dyn io::Read>>> {
+    /// Read VCF from given file path, compressed or not.
+    pub fn from_file<P: AsRef<Path> + std::fmt::Debug>(path: P) -> anyhow::Result<Self> {
+        open(&path)
+            .map(Reader::new)
+            .with_context(|| format!("Failed to read VCF from {:#?}", path))
+    }
+}
+
+impl<R> Reader<io::BufReader<R>>
+where
+    R: io::Read,
+{
+    /// Create a new VCF reader given an instance of `io::Read`
+    pub fn new(reader: R) -> Self {
+        Reader::from_bufread(io::BufReader::new(reader))
+    }
+}
+
+impl<B> Reader<B>
+where
+    B: io::BufRead,
+{
+    pub fn from_bufread(bufreader: B) -> Self {
+        Reader {
+            reader: bufreader,
+            line_buffer: String::new(),
+            line_number: 0,
+            header: Header::default(),
+            header_read: false,
+        }
+    }
+    //reads the next line into the buffer, false at the end of the file
+    fn next_line(&mut self) -> Result<bool, ParseError> {
+        self.line_buffer.clear();
+        let read = self
+            .reader
+            .read_line(&mut self.line_buffer)
+            .map_err(|source| ParseError::Io {
+                line_number: self.line_number + 1,
+                record_id: String::new(),
+                line: String::new(),
+                source,
+            })?;
+        if read > 0 {
+            self.line_number += 1;
+        }
+        Ok(read > 0)
+    }
+    //the error for the line in the buffer
+    fn malformed(&self, message: String) -> ParseError {
+        let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+        ParseError::MalformedLine {
+            line_number: self.line_number,
+            record_id: line.split('\t').next().unwrap_or_default().to_string(),
+            line: line.to_string(),
+            message,
+        }
+    }
+    ///reads the meta lines and the ```#CHROM``` line, done by the first ```read_variant``` when not called before
+    pub fn read_header(&mut self) -> Result<&Header, ParseError> {
+        while !self.header_read {
+            if !self.next_line()? {
+                self.header_read = true;
+                break;
+            }
+            let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+            if let Some(meta) = line.strip_prefix("##") {
+                let meta = MetaLine::parse(meta).map_err(|e| self.malformed(e.to_string()))?;
+                if meta.key == "fileformat" {
+                    self.header.file_format = meta.value.clone();
+                }
+                self.header.meta.push(meta);
+            } else if line.starts_with("#CHROM") {
+                self.header.samples = line.split('\t').skip(9).map(|s| s.to_string()).collect();
+                self.header_read = true;
+            } else if !line.trim().is_empty() {
+                return Err(
+                    self.malformed("expected a ## meta line or the #CHROM line".to_string())
+                );
+            }
+        }
+        Ok(&self.header)
+    }
+    ///the meta lines and sample names, filled in by ```read_header``` or the first ```read_variant```
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+    ///reads the next variant, returns None at the end of the file
+    pub fn read_variant(&mut self) -> Result<Option<Variant>, ParseError> {
+        self.read_header()?;
+        loop {
+            if !self.next_line()? {
+                return Ok(None);
+            }
+            let line = self.line_buffer.trim_end_matches(['\n', '\r']);
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let variant = Variant::from_line(line).map_err(|e| self.malformed(e.to_string()))?;
+            if variant.samples.len() != self.header.samples.len() {
+                return Err(self.malformed(format!(
+                    "{} sample columns for {} samples",
+                    variant.samples.len(),
+                    self.header.samples.len()
+                )));
+            }
+            return Ok(Some(variant));
+        }
+    }
+    ///return an iterator over the variants of the file
+    pub fn variants(self) -> Variants<B> {
+        Variants {
+            reader: self,
+            error_has_occurred: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gbk::Reader as GbkReader;
+
+    const VCF: &str = concat!(
+        "##fileformat=VCFv4.2\n",
+        "##source=snippy\n",
+        "##contig=<ID=NC_000913,length=913>\n",
+        "##INFO=<ID=TYPE,Number=A,Type=String,Description=\"The type of allele, either snp, mnp, ins, del, or complex.\">\n",
+        "##INFO=<ID=DP,Number=1,Type=Integer,Description=\"Total read depth, \\\"raw\\\"\">\n",
+        "##FORMAT=<ID=GT,Number=1,Type=String,Description=\"Genotype\">\n",
+        "##FORMAT=<ID=AO,Number=A,Type=Integer,Description=\"Alternate allele observation count\">\n",
+        "#CHROM\tPOS\tID\tREF\tALT\tQUAL\tFILTER\tINFO\tFORMAT\tisolate1\tisolate2\n",
+        "NC_000913\t5\t.\tC\tT\t120.1\tPASS\tTYPE=snp;DP=30\tGT:AO\t1:29\t0:0\n",
+        "NC_000913\t358\trs1\tA\tG\t300\tPASS\tTYPE=snp;DP=50\tGT:AO\t1:50\t0:1\n",
+        "NC_000913\t357\t.\tT\tA,G\t.\tlowqual\tDP=12;INDEL\tGT\t1/2\t./.\n",
+        "NC_000913\t358\t.\tA\tAT\t80\tPASS\tTYPE=ins\tGT:AO\t1|0\t0\n",
+    );
+
+    fn k12() -> Record {
+        GbkReader::from_file("K12_ribo.gbk")
+            .unwrap()
+            .records()
+            .next()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_read_vcf() {
+        let mut reader = Reader::new(VCF.as_bytes());
+        let header = reader.read_header().unwrap().clone();
+        assert_eq!(header.file_format, "VCFv4.2");
+        assert_eq!(header.samples, vec!["isolate1", "isolate2"]);
+        assert_eq!(header.contigs(), vec![("NC_000913", Some(913))]);
+        assert_eq!(
+            header.info("DP").unwrap().get("Description"),
+            Some("Total read depth, \"raw\"")
+        );
+        assert_eq!(header.format("AO").unwrap().get("Number"), Some("A"));
+        let variants: Vec<Variant> = reader.variants().map(|v| v.unwrap()).collect();
+        assert_eq!(variants.len(), 4);
+        assert_eq!(variants[1].id.as_deref(), Some("rs1"));
+        assert_eq!(variants[1].sample_value(1, "AO"), Some("1"));
+        let third = &variants[2];
+        assert_eq!(third.alternates, vec!["A", "G"]);
+        assert_eq!(third.qual, None);
+        assert!(!third.is_pass());
+        assert_eq!(third.info("INDEL"), Some(""));
+        assert!(third.genotype(0).unwrap().is_heterozygous());
+        assert!(third.genotype(1).unwrap().is_missing());
+        //trailing FORMAT values can be left out
+        assert_eq!(variants[3].sample_value(1, "AO"), None);
+        assert_eq!(variants[3].genotype(0).unwrap().to_string(), "1|0");
+    }
+    #[test]
+    fn test_annotate() {
+        let record = k12();
+        let variants: Vec<Variant> = Reader::new(VCF.as_bytes())
+            .variants()
+            .map(|v| v.unwrap())
+            .collect();
+        //before the first CDS
+        assert!(annotate(&record, &variants[0]).unwrap().is_empty());
+        //third base of codon 2 of rplR on the reverse strand, GAT to GAC
+        let synonymous = annotate(&record, &variants[1]).unwrap();
+        assert_eq!(synonymous.len(), 1);
+        assert_eq!(synonymous[0].gene.as_deref(), Some("rplR"));
+        assert_eq!(
+            (synonymous[0].codon_number, synonymous[0].codon_position),
+            (2, 3)
+        );
+        assert_eq!(synonymous[0].alt_codon, "GAC");
+        assert_eq!(synonymous[0].effect, Effect::Synonymous);
+        //codon 3 AAG becomes TAG or CAG
+        let third = annotate(&record, &variants[2]).unwrap();
+        assert_eq!(third[0].alt_codon, "TAG");
+        assert_eq!(
+            (third[0].ref_aa.as_str(), third[0].alt_aa.as_str()),
+            ("K", "*")
+        );
+        assert_eq!(third[0].effect, Effect::Nonsense);
+        assert_eq!(third[1].alt_aa, "Q");
+        assert_eq!(third[1].effect, Effect::Missense);
+        let insertion = annotate(&record, &variants[3]).unwrap();
+        assert_eq!(insertion[0].effect, Effect::Frameshift);
+        //the start codon ATG at 361..363 on the reverse strand
+        let start = Variant::from_line("NC_000913\t362\t.\tA\tC\t.\t.\t.").unwrap();
+        let start_lost = annotate(&record, &start).unwrap();
+        assert_eq!(start_lost[0].alt_codon, "AGG");
+        assert_eq!(start_lost[0].effect, Effect::StartLost);
+        let wrong_ref = Variant::from_line("NC_000913\t358\t.\tC\tG\t.\t.\t.").unwrap();
+        assert!(annotate(&record, &wrong_ref).is_err());
+    }
+    #[test]
+    fn test_bad_line() {
+        let text = VCF.replace("NC_000913\t358\t.\tA\tAT", "NC_000913\tx\t.\tA\tAT");
+        let mut variants = Reader::new(text.as_bytes()).variants();
+        assert!(variants.nth(2).unwrap().is_ok());
+        match variants.next() {
+            Some(Err(ParseError::MalformedLine {
+                line_number,
+                record_id,
+                ..
+            })) => {
+                assert_eq!(line_number, 12);
+                assert_eq!(record_id, "NC_000913");
+            }
+            other => panic!("expected a malformed line, got {:?}", other),
+        }
+        assert!(variants.next().is_none());
+    }
+}
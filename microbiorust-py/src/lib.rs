@@ -17,6 +17,8 @@
 //!      print(r)
 //!  gbk_to_gff("test_input.gbk")
 //!  
+//!  Input files can be gzip, BGZF, bzip2, xz or zstd compressed (e.g. "genome.gbff.gz", "genome.embl.bz2"), they are decompressed as they are read
+//!  
 //!  Other pyfunctions that can be run include gbk_to_faa, embl_to_faa, gbk_to_gff, embl_to_gff, amino_counts, amino_percentage, hydrophobicity
 //!
 //!  from microbiorust import amino_percentage
@@ -70,7 +72,7 @@ fn gbk_to_faa_count(filename: &str) -> PyResult<usize> {
 
 #[pyfunction]
 fn embl_to_faa(filename: &str) -> PyResult<Vec<String>> {
-    let records = genbank!(&filename);
+    let records = embl!(&filename);
     let mut result = Vec::new();
     for record in records {
         for (k, _v) in &record.cds.attributes {
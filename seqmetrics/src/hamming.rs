@@ -1,4 +1,5 @@
 #[allow(unused_imports)]
+use microBioRust::alignment::Alignment;
 
 //function to calculate the hamming distance between 2 sequences
 pub fn hamming_distance(seq1: &str, seq2: &str) -> usize {
@@ -32,3 +33,11 @@ pub async fn hamming_matrix(sequences: &Vec<String>) -> Result<Vec<Vec<usize>>,
     }
     Ok(distances)
 }
+
+//the hamming distances between the rows of an alignment read with microBioRust::alignment (Clustal, Stockholm, PHYLIP or aligned FASTA)
+//the rows and columns of the matrix follow alignment.ids()
+pub async fn alignment_hamming_matrix(
+    alignment: &Alignment,
+) -> Result<Vec<Vec<usize>>, anyhow::Error> {
+    hamming_matrix(&alignment.sequences()).await
+}
@@ -201,7 +201,7 @@
 //!  use microBioRust_seqmetrics::write_dst_csv::write_distances_csv;
 //!  use tokio::fs::File;
 //!  use std::collections::HashMap;
-//!  use bio::io::fasta;
+//!  use microBioRust::fasta;
 //!  use tokio::io;
 //!  use tokio::io::{AsyncWriteExt, BufWriter};
 //!
@@ -213,11 +213,11 @@
 //!	    println!("gathering records");
 //!            let sequences: Vec<String> = records
 //!	                          .iter()
-//!				  .map(|rec| String::from_utf8_lossy(rec.seq()).to_string())
+//!				  .map(|rec| rec.seq.clone())
 //!				  .collect();
 //!            let ids: Vec<String> = records
 //!	                          .iter()
-//!				  .map(|rec| rec.id().to_string())
+//!				  .map(|rec| rec.id.clone())
 //!				  .collect();
 //!	    println!("gathered ids");
 //!	    let distances = hamming_matrix(&sequences).await?;
@@ -228,9 +228,10 @@
 //!  ```
 
 #![allow(unused_imports)]
-use crate::hamming::hamming_matrix;
+use crate::hamming::{alignment_hamming_matrix, hamming_matrix};
 use crate::write_dst_csv::write_distances_csv;
-use bio::io::fasta;
+use microBioRust::alignment;
+use microBioRust::fasta;
 use microBioRust::gbk::Reader;
 use std::collections::HashMap;
 use std::fs::File;
@@ -783,15 +784,12 @@ mod tests {
     }
     #[tokio::test]
     pub async fn main() -> Result<(), anyhow::Error> {
-        let reader = fasta::Reader::new(File::open("test_hamming.aln")?);
-        let records: Vec<_> = reader.records().collect::<Result<_, _>>()?;
-        let sequences: Vec<String> = records
-            .iter()
-            .map(|rec| String::from_utf8_lossy(rec.seq()).to_string())
-            .collect();
-        let ids: Vec<String> = records.iter().map(|rec| rec.id().to_string()).collect();
-        let distances = hamming_matrix(&sequences).await?;
-        let _ = write_distances_csv(ids, distances, "hamming_dists.csv");
+        let alignment = alignment::Reader::from_file("test_hamming.aln")?
+            .read_alignment()?
+            .ok_or(anyhow::anyhow!("no alignment in test_hamming.aln"))?;
+        let distances = alignment_hamming_matrix(&alignment).await?;
+        assert_eq!(distances.len(), alignment.len());
+        let _ = write_distances_csv(alignment.ids(), distances, "hamming_dists.csv");
         Ok(())
     }
 }